@@ -1,5 +1,4 @@
 use rtracer::*;
-use std::{fs::File, io::Write, path::Path};
 
 /// A projectile.
 #[derive(Debug)]
@@ -50,17 +49,9 @@ fn main() {
         }
     }
 
-    let path = Path::new("chapter02.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(e) => panic!("Could not create {}: {}", display, e),
-        Ok(file) => file,
-    };
-
-    let ppm = c.to_ppm();
-    match file.write_all(ppm.as_bytes()) {
-        Err(e) => panic!("Could not write to {}: {}", display, e),
-        Ok(_) => println!("Successfully wrote to {}", display),
+    let path = "chapter02.ppm";
+    match c.save_ppm(path) {
+        Err(e) => panic!("Could not write to {}: {}", path, e),
+        Ok(_) => println!("Successfully wrote to {}", path),
     }
 }
@@ -1,5 +1,5 @@
+use rtracer::consts::PI;
 use rtracer::*;
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
 
 fn main() {
     let canvas = &mut Canvas::new(200, 200);
@@ -17,23 +17,15 @@ fn main() {
         }
     }
 
-    let path = Path::new("chapter04.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
-
-    let ppm = canvas.to_ppm();
-    match file.write_all(ppm.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
+    let path = "chapter04.ppm";
+    match canvas.save_ppm(path) {
+        Err(why) => panic!("couldn't write to {}: {}", path, why),
+        Ok(_) => println!("successfully wrote to {}", path),
     };
 }
 
-fn clock_hour(hour: f64) -> (usize, usize) {
-    let r = Transformation::new().rotate_y(hour * PI / 6.0).init();
+fn clock_hour(hour: Float) -> (usize, usize) {
+    let r = Transformation::new().then_rotate_y(hour * PI / 6.0).init();
     let hour_point = r * Point::new(0.0, 0.0, 1.0);
 
     let radius = (3.0 / 8.0) * 200.0;
@@ -1,31 +1,31 @@
-use rtracer::{shapes::Sphere, Canvas, Point, Ray, Shape, Transformation, RED};
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
+use rtracer::consts::PI;
+use rtracer::{shapes::Sphere, Canvas, Float, Point, Ray, Shape, Transformation, RED};
 
 fn main() {
     let mut shape = Sphere::new();
     draw_shape(&shape, "ch05_circle.ppm");
 
     // shrink it along the y axis
-    let t = Transformation::new().scaling(1.0, 0.5, 1.0);
+    let t = Transformation::new().then_scaling(1.0, 0.5, 1.0);
     shape.set_transform(t);
     draw_shape(&shape, "ch05_shrink_y.ppm");
 
     // shrink it along the x axis
-    let t = Transformation::new().scaling(0.5, 1.0, 1.0);
+    let t = Transformation::new().then_scaling(0.5, 1.0, 1.0);
     shape.set_transform(t);
     draw_shape(&shape, "ch05_shrink_x.ppm");
 
     // shrink it and rotate it!
     let t = Transformation::new()
-        .scaling(0.5, 1.0, 1.0)
-        .rotate_z(PI / 4.0);
+        .then_scaling(0.5, 1.0, 1.0)
+        .then_rotate_z(PI / 4.0);
     shape.set_transform(t);
     draw_shape(&shape, "ch05_shrink_rotate.ppm");
 
     // shrink it and skew it!
     let t = Transformation::new()
-        .scaling(0.5, 1.0, 1.0)
-        .shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        .then_scaling(0.5, 1.0, 1.0)
+        .then_shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
     shape.set_transform(t);
     draw_shape(&shape, "ch05_shrink_skew.ppm");
 }
@@ -37,7 +37,7 @@ fn draw_shape(shape: &Sphere, file_name: &str) {
     let wall_size = 7.0;
     let canvas_pixels = 400;
 
-    let pixel_size = wall_size / canvas_pixels as f64;
+    let pixel_size = wall_size / canvas_pixels as Float;
 
     let half = wall_size / 2.0;
 
@@ -45,10 +45,10 @@ fn draw_shape(shape: &Sphere, file_name: &str) {
     let color = RED;
 
     for y in 0..canvas_pixels {
-        let world_y = half - pixel_size * y as f64;
+        let world_y = half - pixel_size * y as Float;
 
         for x in 0..canvas_pixels {
-            let world_x = -half + pixel_size * x as f64;
+            let world_x = -half + pixel_size * x as Float;
 
             let position = Point::new(world_x, world_y, wall_z);
 
@@ -61,20 +61,8 @@ fn draw_shape(shape: &Sphere, file_name: &str) {
         }
     }
 
-    write_file(file_name, canvas.to_ppm().as_bytes())
-}
-
-fn write_file(file_name: &str, ppm: &[u8]) {
-    let path = Path::new(file_name);
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
-
-    match file.write_all(ppm) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
-    };
+    match canvas.save_ppm(file_name) {
+        Err(why) => panic!("couldn't write to {}: {}", file_name, why),
+        Ok(_) => println!("successfully wrote to {}", file_name),
+    }
 }
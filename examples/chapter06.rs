@@ -1,5 +1,7 @@
-use rtracer::{shapes::Sphere, Canvas, Point, PointLight, Ray, Shape, Transformation, RGB, WHITE};
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
+use rtracer::consts::PI;
+use rtracer::{
+    shapes::Sphere, Canvas, Float, Point, PointLight, Ray, Shape, Transformation, RGB, WHITE,
+};
 
 fn main() {
     // build world
@@ -13,26 +15,26 @@ fn main() {
     draw_shape(&shape, &light, "ch06_circle.ppm");
 
     // shrink it along the y axis
-    let t = Transformation::new().scaling(1.0, 0.5, 1.0);
+    let t = Transformation::new().then_scaling(1.0, 0.5, 1.0);
     shape.set_transform(t);
     draw_shape(&shape, &light, "ch06_shrink_y.ppm");
 
     // shrink it along the x axis
-    let t = Transformation::new().scaling(0.5, 1.0, 1.0);
+    let t = Transformation::new().then_scaling(0.5, 1.0, 1.0);
     shape.set_transform(t);
     draw_shape(&shape, &light, "ch06_shrink_x.ppm");
 
     // shrink it and rotate it!
     let t = Transformation::new()
-        .scaling(0.5, 1.0, 1.0)
-        .rotate_z(PI / 4.0);
+        .then_scaling(0.5, 1.0, 1.0)
+        .then_rotate_z(PI / 4.0);
     shape.set_transform(t);
     draw_shape(&shape, &light, "ch06_shrink_rotate.ppm");
 
     // shrink it and skew it!
     let t = Transformation::new()
-        .scaling(0.5, 1.0, 1.0)
-        .shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        .then_scaling(0.5, 1.0, 1.0)
+        .then_shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
     shape.set_transform(t);
     draw_shape(&shape, &light, "ch06_shrink_skew.ppm");
 }
@@ -44,17 +46,17 @@ fn draw_shape(shape: &dyn Shape, light: &PointLight, file_name: &str) {
     let wall_size = 7.0;
     let canvas_pixels = 400;
 
-    let pixel_size = wall_size / canvas_pixels as f64;
+    let pixel_size = wall_size / canvas_pixels as Float;
 
     let half = wall_size / 2.0;
 
     let mut canvas = Canvas::new(canvas_pixels, canvas_pixels);
 
     for y in 0..canvas_pixels {
-        let world_y = half - pixel_size * y as f64;
+        let world_y = half - pixel_size * y as Float;
 
         for x in 0..canvas_pixels {
-            let world_x = -half + pixel_size * x as f64;
+            let world_x = -half + pixel_size * x as Float;
 
             let position = Point::new(world_x, world_y, wall_z);
 
@@ -65,30 +67,18 @@ fn draw_shape(shape: &dyn Shape, light: &PointLight, file_name: &str) {
                 let xs = xs.unwrap();
                 let point = r.position(xs[0].t);
                 let normal = xs[0].object.normal_at(point, None);
-                let eye = -r.direction;
+                let eye = -r.direction();
                 let color = xs[0]
                     .object
                     .get_material()
-                    .lightning(shape, *light, point, eye, normal, false);
+                    .lighting(shape, *light, point, eye, normal, false);
                 canvas.write_pixel(x, y, color);
             }
         }
     }
 
-    write_file(file_name, canvas.to_ppm().as_bytes())
-}
-
-fn write_file(file_name: &str, ppm: &[u8]) {
-    let path = Path::new(file_name);
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
-
-    match file.write_all(ppm) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
-    };
+    match canvas.save_ppm(file_name) {
+        Err(why) => panic!("couldn't write to {}: {}", file_name, why),
+        Ok(_) => println!("successfully wrote to {}", file_name),
+    }
 }
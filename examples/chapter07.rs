@@ -1,12 +1,15 @@
+mod common;
+
+use common::TerminalProgress;
+use rtracer::consts::PI;
 use rtracer::*;
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
 
 fn main() {
     // build world
     let mut world = World::new();
 
     let mut floor = Sphere::new();
-    floor.set_transform(Transformation::new().scaling(10.0, 0.01, 10.0));
+    floor.set_transform(Transformation::new().then_scaling(10.0, 0.01, 10.0));
     let mut m = Material::default();
     m.color = RGB::new(1.0, 0.9, 0.9);
     m.specular = 0.0;
@@ -16,10 +19,10 @@ fn main() {
     let mut left_wall = Sphere::new();
     left_wall.set_transform(
         Transformation::new()
-            .scaling(10.0, 0.01, 10.0)
-            .rotate_x(PI / 2.0)
-            .rotate_y(-PI / 4.0)
-            .translation(0.0, 0.0, 5.0),
+            .then_scaling(10.0, 0.01, 10.0)
+            .then_rotate_x(PI / 2.0)
+            .then_rotate_y(-PI / 4.0)
+            .then_translation(0.0, 0.0, 5.0),
     );
     let mut m = Material::default();
     m.color = RGB::new(1.0, 0.9, 0.9);
@@ -30,10 +33,10 @@ fn main() {
     let mut right_wall = Sphere::new();
     right_wall.set_transform(
         Transformation::new()
-            .scaling(10.0, 0.01, 10.0)
-            .rotate_x(PI / 2.0)
-            .rotate_y(PI / 4.0)
-            .translation(0.0, 0.0, 5.0),
+            .then_scaling(10.0, 0.01, 10.0)
+            .then_rotate_x(PI / 2.0)
+            .then_rotate_y(PI / 4.0)
+            .then_translation(0.0, 0.0, 5.0),
     );
     let mut m = Material::default();
     m.color = RGB::new(1.0, 0.9, 0.9);
@@ -42,7 +45,7 @@ fn main() {
     add_object!(world, right_wall);
 
     let mut middle = Sphere::new();
-    middle.set_transform(Transformation::new().translation(-0.5, 1.0, 0.5));
+    middle.set_transform(Transformation::new().then_translation(-0.5, 1.0, 0.5));
     let mut m = Material::default();
     m.color = RGB::new(0.1, 1.0, 0.5);
     m.diffuse = 0.7;
@@ -53,8 +56,8 @@ fn main() {
     let mut right = Sphere::new();
     right.set_transform(
         Transformation::new()
-            .scaling(0.5, 0.5, 0.5)
-            .translation(1.5, 0.5, -0.5),
+            .then_scaling(0.5, 0.5, 0.5)
+            .then_translation(1.5, 0.5, -0.5),
     );
     let mut m = Material::default();
     m.color = RGB::new(0.5, 1.0, 0.1);
@@ -66,8 +69,8 @@ fn main() {
     let mut left = Sphere::new();
     left.set_transform(
         Transformation::new()
-            .scaling(0.33, 0.33, 0.33)
-            .translation(-1.5, 0.33, -0.75),
+            .then_scaling(0.33, 0.33, 0.33)
+            .then_translation(-1.5, 0.33, -0.75),
     );
     let mut m = Material::default();
     m.color = RGB::new(1.0, 0.8, 0.1);
@@ -88,19 +91,11 @@ fn main() {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(&world);
-
-    let path = Path::new("ch07.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
+    let canvas = camera.render_with_progress(&world, &mut TerminalProgress::default());
 
-    let ppm = canvas.to_ppm();
-    match file.write_all(ppm.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
+    let path = "ch07.ppm";
+    match canvas.save_ppm(path) {
+        Err(why) => panic!("couldn't write to {}: {}", path, why),
+        Ok(_) => println!("successfully wrote to {}", path),
     };
 }
@@ -1,8 +1,11 @@
+mod common;
+
+use common::TerminalProgress;
+use rtracer::consts::PI;
 use rtracer::{
     add_object, shapes::Sphere, Camera, Point, PointLight, Shape, Transformation, Vector, World,
     RGB,
 };
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
 
 fn main() {
     let mut world = World::new();
@@ -10,10 +13,10 @@ fn main() {
     let mut right_wall = Sphere::new();
     right_wall.set_transform(
         Transformation::new()
-            .scaling(40.0, 40.0, 40.0)
-            .rotate_x(PI / 2.0)
-            .rotate_y(PI)
-            .translation(0.0, 0.0, 10.0),
+            .then_scaling(40.0, 40.0, 40.0)
+            .then_rotate_x(PI / 2.0)
+            .then_rotate_y(PI)
+            .then_translation(0.0, 0.0, 10.0),
     );
     right_wall.get_material_mut().color = RGB::new(1.0, 0.9, 0.9);
     right_wall.get_material_mut().specular = 0.0;
@@ -22,8 +25,8 @@ fn main() {
     let mut wrist = Sphere::new();
     wrist.set_transform(
         Transformation::new()
-            .scaling(0.5, 0.5, 0.5)
-            .translation(-1.25, 1.5, -2.0),
+            .then_scaling(0.5, 0.5, 0.5)
+            .then_translation(-1.25, 1.5, -2.0),
     );
     wrist.get_material_mut().color = RGB::new(0.1, 1.0, 0.5);
     wrist.get_material_mut().diffuse = 0.7;
@@ -33,8 +36,8 @@ fn main() {
     let mut palm = Sphere::new();
     palm.set_transform(
         Transformation::new()
-            .scaling(0.75, 0.75, 0.75)
-            .translation(-0.5, 2.0, -1.75),
+            .then_scaling(0.75, 0.75, 0.75)
+            .then_translation(-0.5, 2.0, -1.75),
     );
     palm.get_material_mut().color = RGB::new(0.1, 1.0, 0.5);
     palm.get_material_mut().diffuse = 0.7;
@@ -44,8 +47,8 @@ fn main() {
     let mut finger1 = Sphere::new();
     finger1.set_transform(
         Transformation::new()
-            .scaling(1.0, 0.2, 0.1)
-            .translation(0.0, 2.0, -2.5),
+            .then_scaling(1.0, 0.2, 0.1)
+            .then_translation(0.0, 2.0, -2.5),
     );
     finger1.get_material_mut().color = RGB::new(1.0, 0.8, 0.1);
     finger1.get_material_mut().diffuse = 0.7;
@@ -55,8 +58,8 @@ fn main() {
     let mut finger2 = Sphere::new();
     finger2.set_transform(
         Transformation::new()
-            .scaling(1.0, 0.2, 0.1)
-            .translation(0.0, 1.80, -2.5),
+            .then_scaling(1.0, 0.2, 0.1)
+            .then_translation(0.0, 1.80, -2.5),
     );
     finger2.get_material_mut().color = RGB::new(1.0, 0.8, 0.1);
     finger2.get_material_mut().diffuse = 0.7;
@@ -66,8 +69,8 @@ fn main() {
     let mut finger3 = Sphere::new();
     finger3.set_transform(
         Transformation::new()
-            .scaling(0.75, 0.2, 0.1)
-            .translation(0.0, 1.60, -2.5),
+            .then_scaling(0.75, 0.2, 0.1)
+            .then_translation(0.0, 1.60, -2.5),
     );
     finger3.get_material_mut().color = RGB::new(1.0, 0.8, 0.1);
     finger3.get_material_mut().diffuse = 0.7;
@@ -77,9 +80,9 @@ fn main() {
     let mut finger4 = Sphere::new();
     finger4.set_transform(
         Transformation::new()
-            .scaling(0.75, 0.2, 0.1)
-            .rotate_z(PI / 2.0)
-            .translation(-0.75, 2.40, -2.5),
+            .then_scaling(0.75, 0.2, 0.1)
+            .then_rotate_z(PI / 2.0)
+            .then_translation(-0.75, 2.40, -2.5),
     );
     finger4.get_material_mut().color = RGB::new(1.0, 0.8, 0.1);
     finger4.get_material_mut().diffuse = 0.7;
@@ -99,19 +102,11 @@ fn main() {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(&world);
-
-    let path = Path::new("chapter_08.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
+    let canvas = camera.render_with_progress(&world, &mut TerminalProgress::default());
 
-    let ppm = canvas.to_ppm();
-    match file.write_all(ppm.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
+    let path = "chapter_08.ppm";
+    match canvas.save_ppm(path) {
+        Err(why) => panic!("couldn't write to {}: {}", path, why),
+        Ok(_) => println!("successfully wrote to {}", path),
     };
 }
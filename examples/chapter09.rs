@@ -1,8 +1,11 @@
+mod common;
+
+use common::TerminalProgress;
+use rtracer::consts::PI;
 use rtracer::{
     add_object, shapes::Plane, shapes::Shape, shapes::Sphere, Camera, Point, PointLight,
     Transformation, Vector, World, RGB,
 };
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
 
 fn main() {
     let mut world = World::new();
@@ -13,7 +16,7 @@ fn main() {
     add_object!(world, floor);
 
     let mut middle = Sphere::new();
-    middle.set_transform(Transformation::new().translation(-0.5, 1.0, 0.5));
+    middle.set_transform(Transformation::new().then_translation(-0.5, 1.0, 0.5));
     middle.get_material_mut().color = RGB::new(0.1, 1.0, 0.5);
     middle.get_material_mut().diffuse = 0.7;
     middle.get_material_mut().specular = 0.3;
@@ -22,8 +25,8 @@ fn main() {
     let mut right = Sphere::new();
     right.set_transform(
         Transformation::new()
-            .scaling(0.5, 0.5, 0.5)
-            .translation(1.5, 0.5, -0.5),
+            .then_scaling(0.5, 0.5, 0.5)
+            .then_translation(1.5, 0.5, -0.5),
     );
     right.get_material_mut().color = RGB::new(0.5, 1.0, 0.1);
     right.get_material_mut().diffuse = 0.7;
@@ -33,8 +36,8 @@ fn main() {
     let mut left = Sphere::new();
     left.set_transform(
         Transformation::new()
-            .scaling(0.33, 0.33, 0.33)
-            .translation(-1.5, 0.33, -0.75),
+            .then_scaling(0.33, 0.33, 0.33)
+            .then_translation(-1.5, 0.33, -0.75),
     );
     left.get_material_mut().color = RGB::new(1.0, 0.8, 0.1);
     left.get_material_mut().diffuse = 0.7;
@@ -54,19 +57,11 @@ fn main() {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(&world);
-
-    let path = Path::new("ch09.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
+    let canvas = camera.render_with_progress(&world, &mut TerminalProgress::default());
 
-    let ppm = canvas.to_ppm();
-    match file.write_all(ppm.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
+    let path = "ch09.ppm";
+    match canvas.save_ppm(path) {
+        Err(why) => panic!("couldn't write to {}: {}", path, why),
+        Ok(_) => println!("successfully wrote to {}", path),
     };
 }
@@ -1,8 +1,11 @@
+mod common;
+
+use common::TerminalProgress;
+use rtracer::consts::PI;
 use rtracer::{
     add_object, set_pattern, shapes::Plane, shapes::Shape, shapes::Sphere, Camera, Pattern, Point,
     PointLight, Stripes, Transformation, Vector, World, BLUE, GREEN, RED, RGB, WHITE,
 };
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
 
 fn main() {
     let mut world = World::new();
@@ -13,21 +16,21 @@ fn main() {
     let mut pattern = Stripes::stripe_pattern(BLUE, GREEN);
     pattern.set_transform(
         Transformation::new()
-            .scaling(0.5, 0.5, 0.5)
-            .rotate_y(PI / 6.0),
+            .then_scaling(0.5, 0.5, 0.5)
+            .then_rotate_y(PI / 6.0),
     );
     set_pattern!(floor, pattern);
     add_object!(world, floor);
 
     let mut middle = Sphere::new();
-    middle.set_transform(Transformation::new().translation(-0.5, 1.0, 0.5));
+    middle.set_transform(Transformation::new().then_translation(-0.5, 1.0, 0.5));
     middle.get_material_mut().diffuse = 0.7;
     let mut pattern = Stripes::stripe_pattern(RED, WHITE);
     pattern.set_transform(
         Transformation::new()
-            .scaling(0.1, 0.1, 0.1)
-            .rotate_y(PI / 3.0)
-            .rotate_y(PI / 4.0),
+            .then_scaling(0.1, 0.1, 0.1)
+            .then_rotate_y(PI / 3.0)
+            .then_rotate_y(PI / 4.0),
     );
     set_pattern!(middle, pattern);
     add_object!(world, middle);
@@ -35,8 +38,8 @@ fn main() {
     let mut right = Sphere::new();
     right.set_transform(
         Transformation::new()
-            .scaling(0.5, 0.5, 0.5)
-            .translation(1.5, 0.5, -0.5),
+            .then_scaling(0.5, 0.5, 0.5)
+            .then_translation(1.5, 0.5, -0.5),
     );
     right.get_material_mut().color = RGB::new(0.5, 1.0, 0.1);
     right.get_material_mut().diffuse = 0.7;
@@ -46,8 +49,8 @@ fn main() {
     let mut left = Sphere::new();
     left.set_transform(
         Transformation::new()
-            .scaling(0.33, 0.33, 0.33)
-            .translation(-1.5, 0.33, -0.75),
+            .then_scaling(0.33, 0.33, 0.33)
+            .then_translation(-1.5, 0.33, -0.75),
     );
     left.get_material_mut().color = RGB::new(1.0, 0.8, 0.1);
     left.get_material_mut().diffuse = 0.7;
@@ -67,19 +70,11 @@ fn main() {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(&world);
-
-    let path = Path::new("ch10a.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
+    let canvas = camera.render_with_progress(&world, &mut TerminalProgress::default());
 
-    let ppm = canvas.to_ppm();
-    match file.write_all(ppm.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
+    let path = "ch10a.ppm";
+    match canvas.save_ppm(path) {
+        Err(why) => panic!("couldn't write to {}: {}", path, why),
+        Ok(_) => println!("successfully wrote to {}", path),
     };
 }
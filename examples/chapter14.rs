@@ -1,5 +1,8 @@
+mod common;
+
+use common::TerminalProgress;
+use rtracer::consts::PI;
 use rtracer::*;
-use std::{f64::consts::PI, fs::File, io::Write, path::Path};
 
 fn main() {
     let w = &mut World::new();
@@ -7,9 +10,9 @@ fn main() {
     let mut pattern = Checkers::new();
     pattern.set_transform(
         Transformation::new()
-            .scaling(0.1, 0.1, 0.1)
-            .rotate_y(0.174)
-            .translation(10.0, 0.0, 10.0),
+            .then_scaling(0.1, 0.1, 0.1)
+            .then_rotate_y(0.174)
+            .then_translation(10.0, 0.0, 10.0),
     );
 
     let mut floor = Plane::new();
@@ -20,15 +23,15 @@ fn main() {
     add_object!(w, row1);
 
     let mut row3 = hex_row();
-    row3.set_transform(Transformation::new().translation(0.0, 0.0, -1.0));
+    row3.set_transform(Transformation::new().then_translation(0.0, 0.0, -1.0));
     add_object!(w, row3);
 
     let mut row5 = hex_row();
-    row5.set_transform(Transformation::new().translation(0.0, 0.0, -2.0));
+    row5.set_transform(Transformation::new().then_translation(0.0, 0.0, -2.0));
     add_object!(w, row5);
 
     let mut row7 = hex_row();
-    row7.set_transform(Transformation::new().translation(0.0, 0.0, -3.0));
+    row7.set_transform(Transformation::new().then_translation(0.0, 0.0, -3.0));
     add_object!(w, row7);
 
     w.set_light(PointLight::new(
@@ -46,32 +49,24 @@ fn main() {
         Vector::new(0.0, 1.0, 0.0),
     );
 
-    let canvas = camera.render(&w);
-
-    let path = Path::new("ch14.ppm");
-    let display = path.display();
-
-    let mut file = match File::create(&path) {
-        Err(why) => panic!("couldn't create {}: {}", display, why),
-        Ok(file) => file,
-    };
+    let canvas = camera.render_with_progress(&w, &mut TerminalProgress::default());
 
-    let ppm = canvas.to_ppm();
-    match file.write_all(ppm.as_bytes()) {
-        Err(why) => panic!("couldn't write to {}: {}", display, why),
-        Ok(_) => println!("successfully wrote to {}", display),
+    let path = "ch14.ppm";
+    match canvas.save_ppm(path) {
+        Err(why) => panic!("couldn't write to {}: {}", path, why),
+        Ok(_) => println!("successfully wrote to {}", path),
     };
 }
 
 pub fn hex_row() -> Group {
     let mut h1 = hex_in_hex();
-    h1.set_transform(Transformation::new().translation(-1.0, 1.0, 0.0));
+    h1.set_transform(Transformation::new().then_translation(-1.0, 1.0, 0.0));
 
     let mut h2 = hex_in_hex();
-    h2.set_transform(Transformation::new().translation(0.0, 1.0, 0.0));
+    h2.set_transform(Transformation::new().then_translation(0.0, 1.0, 0.0));
 
     let mut h3 = hex_in_hex();
-    h3.set_transform(Transformation::new().translation(1.0, 1.0, 0.0));
+    h3.set_transform(Transformation::new().then_translation(1.0, 1.0, 0.0));
 
     let mut row = Group::new();
     row.add_object(Box::new(h1));
@@ -84,15 +79,15 @@ pub fn hex_in_hex() -> Group {
     let mut hex = hexagon();
     hex.set_transform(
         Transformation::new()
-            .scaling(0.5, 0.5, 0.5)
-            .rotate_x(-PI / 0.08726646),
+            .then_scaling(0.5, 0.5, 0.5)
+            .then_rotate_x(-PI / 0.08726646),
     );
 
     let mut hex2 = hexagon();
     hex2.set_transform(
         Transformation::new()
-            .scaling(0.25, 0.25, 0.25)
-            .rotate_x(-PI / 6.0),
+            .then_scaling(0.25, 0.25, 0.25)
+            .then_rotate_x(-PI / 6.0),
     );
     hex2.material.color = RGB::new(0.0, 1.0, 0.0);
 
@@ -108,7 +103,7 @@ pub fn hexagon() -> Group {
 
     for n in 0..6 {
         let mut side = hexagon_side();
-        side.set_transform(Transformation::new().rotate_y(n as f64 * PI / 3.0));
+        side.set_transform(Transformation::new().then_rotate_y(n as Float * PI / 3.0));
 
         hex.add_object(Box::new(side));
     }
@@ -120,8 +115,8 @@ fn hexagon_corner() -> Sphere {
     let mut corner = Sphere::new();
     corner.set_transform(
         Transformation::new()
-            .scaling(0.25, 0.25, 0.25)
-            .translation(0.0, 0.0, -1.0),
+            .then_scaling(0.25, 0.25, 0.25)
+            .then_translation(0.0, 0.0, -1.0),
     );
 
     corner.get_material_mut().color = RGB::new(1.0, 0.0, 0.0);
@@ -134,10 +129,10 @@ pub fn hexagon_edge() -> Cylinder {
     edge.set_cuts(0.0, 1.0);
     edge.set_transform(
         Transformation::new()
-            .scaling(0.25, 1.0, 0.25)
-            .rotate_z(-PI / 2.0)
-            .rotate_y(-PI / 6.0)
-            .translation(0.0, 0.0, -1.0),
+            .then_scaling(0.25, 1.0, 0.25)
+            .then_rotate_z(-PI / 2.0)
+            .then_rotate_y(-PI / 6.0)
+            .then_translation(0.0, 0.0, -1.0),
     );
     edge.get_material_mut().color = RGB::new(1.0, 0.0, 0.0);
 
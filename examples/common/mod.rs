@@ -0,0 +1,26 @@
+use progress_bar::pb::ProgressBar;
+use progress_bar::{Color, Style};
+use rtracer::ProgressSink;
+use std::time::Duration;
+
+/// Progress bar in the terminal, prints the render time once done.
+#[derive(Default)]
+pub struct TerminalProgress {
+    bar: Option<ProgressBar>,
+}
+
+impl ProgressSink for TerminalProgress {
+    fn progress(&mut self, done: usize, total: usize, elapsed: Duration) {
+        let bar = self.bar.get_or_insert_with(|| {
+            let mut bar = ProgressBar::new(total);
+            bar.set_action("Rendering", Color::Blue, Style::Bold);
+            bar
+        });
+        bar.set_progress(done);
+
+        if done == total {
+            bar.finalize();
+            println!("The render took {:.3} seconds", elapsed.as_secs_f64());
+        }
+    }
+}
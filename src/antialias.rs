@@ -0,0 +1,226 @@
+use crate::*;
+use uuid::Uuid;
+
+/// Per pixel geometry of a render: the visible object and its distance.
+#[derive(Debug, Clone)]
+pub struct GBuffer {
+    /// Width of the buffer.
+    pub width: usize,
+    /// Height of the buffer.
+    pub height: usize,
+    /// Id of the object seen by each pixel, None if the ray hit nothing.
+    pub ids: Vec<Option<Uuid>>,
+    /// Distance along the primary ray, infinite if the ray hit nothing.
+    pub depths: Vec<Float>,
+    /// Normal in world space, the zero vector if the ray hit nothing.
+    pub normals: Vec<Vector>,
+}
+
+impl GBuffer {
+    /// Trace the primary rays of `camera` without shading.
+    /// This is much cheaper than the render itself.
+    pub fn render(camera: &Camera, world: &World) -> Self {
+        let mut ids = Vec::with_capacity(camera.hsize * camera.vsize);
+        let mut depths = Vec::with_capacity(camera.hsize * camera.vsize);
+        let mut normals = Vec::with_capacity(camera.hsize * camera.vsize);
+        for y in 0..camera.vsize {
+            for x in 0..camera.hsize {
+                let ray = camera.ray_for_pixel(x, y);
+                match world.pick(&ray) {
+                    Some((id, t, _, normal)) => {
+                        ids.push(Some(id));
+                        depths.push(t);
+                        normals.push(normal);
+                    }
+                    None => {
+                        ids.push(None);
+                        depths.push(Float::INFINITY);
+                        normals.push(Vector::new(0.0, 0.0, 0.0));
+                    }
+                }
+            }
+        }
+
+        Self {
+            width: camera.hsize,
+            height: camera.vsize,
+            ids,
+            depths,
+            normals,
+        }
+    }
+
+    /// Do the pixels `a` and `b` (as indices) show different surfaces?
+    fn is_edge(&self, a: usize, b: usize, depth_threshold: Float) -> bool {
+        if self.ids[a] != self.ids[b] {
+            return true;
+        }
+        let (da, db) = (self.depths[a], self.depths[b]);
+        da.is_finite() && db.is_finite() && (da - db).abs() > depth_threshold * da.max(db)
+    }
+}
+
+/// Edge aware blur in image space, a cheap alternative to supersampling.
+/// Edges are found from the contrast of the colors and, if given,
+/// from changes of object or depth in a `GBuffer`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fxaa {
+    /// Smallest difference in luma that is treated as an edge.
+    pub contrast_threshold: Float,
+
+    /// Smallest difference in luma relative to the brightest neighbour.
+    pub relative_threshold: Float,
+
+    /// Smallest change in depth, relative to the depth, that is treated as an edge.
+    pub depth_threshold: Float,
+}
+
+impl Default for Fxaa {
+    fn default() -> Self {
+        Self {
+            contrast_threshold: 0.0312,
+            relative_threshold: 0.125,
+            depth_threshold: 0.1,
+        }
+    }
+}
+
+impl Fxaa {
+    /// Smooth the edges of `canvas`.
+    /// The `gbuffer` must have the size of the canvas.
+    pub fn apply(&self, canvas: &Canvas, gbuffer: Option<&GBuffer>) -> Canvas {
+        if let Some(g) = gbuffer {
+            assert!(
+                g.width == canvas.width && g.height == canvas.height,
+                "GBuffer and canvas must have the same size!"
+            );
+        }
+        let mut out = Canvas::new(canvas.width, canvas.height);
+        if canvas.width == 0 || canvas.height == 0 {
+            return out;
+        }
+
+        let luma: Vec<Float> = canvas.pixels().map(RGB::luminance).collect();
+        let index = |x: usize, y: usize| x + y * canvas.width;
+        let at = |i: usize| canvas.pixel_at(i % canvas.width, i / canvas.width);
+        for y in 0..canvas.height {
+            for x in 0..canvas.width {
+                let m = index(x, y);
+                // neighbours are clamped at the borders
+                let n = index(x, y.saturating_sub(1));
+                let s = index(x, (y + 1).min(canvas.height - 1));
+                let w = index(x.saturating_sub(1), y);
+                let e = index((x + 1).min(canvas.width - 1), y);
+
+                let lumas = [luma[m], luma[n], luma[s], luma[w], luma[e]];
+                let max = lumas.iter().cloned().fold(Float::NEG_INFINITY, Float::max);
+                let min = lumas.iter().cloned().fold(Float::INFINITY, Float::min);
+                let mut horizontal = (luma[w] - luma[m]).abs() + (luma[e] - luma[m]).abs();
+                let mut vertical = (luma[n] - luma[m]).abs() + (luma[s] - luma[m]).abs();
+                let mut edge =
+                    max - min >= self.contrast_threshold.max(self.relative_threshold * max);
+
+                if let Some(g) = gbuffer {
+                    let across = |a: usize| g.is_edge(m, a, self.depth_threshold) as u8 as Float;
+                    let geometric_h = across(w) + across(e);
+                    let geometric_v = across(n) + across(s);
+                    edge |= geometric_h + geometric_v > 0.0;
+                    horizontal += geometric_h;
+                    vertical += geometric_v;
+                }
+
+                let color = at(m);
+                if !edge {
+                    out.write_pixel(x, y, color);
+                    continue;
+                }
+                // blend across the edge, i.e. along the larger gradient
+                let (a, b) = if horizontal >= vertical {
+                    (at(w), at(e))
+                } else {
+                    (at(n), at(s))
+                };
+                out.write_pixel(x, y, color * 0.5 + (a + b) * 0.25);
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consts::PI;
+
+    fn vertical_edge(left: RGB, right: RGB) -> Canvas {
+        let mut c = Canvas::new(4, 3);
+        for y in 0..3 {
+            for x in 0..4 {
+                c.write_pixel(x, y, if x < 2 { left } else { right });
+            }
+        }
+        c
+    }
+
+    #[test]
+    fn flat_fxaa() {
+        let c = vertical_edge(RED, RED);
+        let out = Fxaa::default().apply(&c, None);
+
+        assert!(out.pixels().eq(c.pixels()));
+    }
+
+    #[test]
+    fn edge_fxaa() {
+        let c = vertical_edge(BLACK, WHITE);
+        let out = Fxaa::default().apply(&c, None);
+
+        assert_eq!(out.pixel_at(0, 1), BLACK);
+        assert_eq!(out.pixel_at(1, 1), RGB::new(0.25, 0.25, 0.25));
+        assert_eq!(out.pixel_at(2, 1), RGB::new(0.75, 0.75, 0.75));
+        assert_eq!(out.pixel_at(3, 1), WHITE);
+    }
+
+    #[test]
+    fn gbuffer_fxaa() {
+        // too little contrast to be found in the colors alone
+        let left = RGB::new(0.5, 0.5, 0.5);
+        let right = RGB::new(0.52, 0.52, 0.52);
+        let c = vertical_edge(left, right);
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let g = GBuffer {
+            width: 4,
+            height: 3,
+            ids: (0..12)
+                .map(|i| Some(if i % 4 < 2 { a } else { b }))
+                .collect(),
+            depths: vec![1.0; 12],
+            normals: vec![Vector::new(0.0, 0.0, -1.0); 12],
+        };
+
+        assert_eq!(Fxaa::default().apply(&c, None).pixel_at(1, 1), left);
+        assert_eq!(
+            Fxaa::default().apply(&c, Some(&g)).pixel_at(1, 1),
+            RGB::new(0.505, 0.505, 0.505)
+        );
+    }
+
+    #[test]
+    fn render_gbuffer() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let g = GBuffer::render(&c, &w);
+
+        assert_eq!(g.ids[5 + 5 * 11], Some(w.get_object(0).unwrap().id()));
+        assert!(float_eq(g.depths[5 + 5 * 11], 4.0));
+        assert_eq!(g.normals[5 + 5 * 11], Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(g.ids[0], None);
+        assert!(g.depths[0].is_infinite());
+    }
+}
@@ -0,0 +1,318 @@
+use crate::{Float, Matrix, Point, Ray, EPSILON};
+
+/// Axis aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    /// Smallest corner.
+    pub min: Point,
+
+    /// Largest corner.
+    pub max: Point,
+}
+
+impl BoundingBox {
+    /// Create a new box spanned by `min` and `max`.
+    pub fn new(min: Point, max: Point) -> Self {
+        Self { min, max }
+    }
+
+    /// A box containing nothing, grows with `add_point`.
+    pub fn empty() -> Self {
+        Self {
+            min: Point::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            max: Point::new(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+        }
+    }
+
+    /// A box containing everything.
+    pub fn infinite() -> Self {
+        Self {
+            min: Point::new(
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+                Float::NEG_INFINITY,
+            ),
+            max: Point::new(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+        }
+    }
+
+    /// Does the box contain nothing?
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    /// Is the box finite in all directions?
+    pub fn is_finite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y, self.max.z,
+        ]
+        .iter()
+        .all(|c| c.is_finite())
+    }
+
+    /// Grow the box to include `p`.
+    pub fn add_point(&mut self, p: Point) {
+        self.min = Point::new(
+            self.min.x.min(p.x),
+            self.min.y.min(p.y),
+            self.min.z.min(p.z),
+        );
+        self.max = Point::new(
+            self.max.x.max(p.x),
+            self.max.y.max(p.y),
+            self.max.z.max(p.z),
+        );
+    }
+
+    /// Grow the box to include `other`.
+    pub fn merge(&mut self, other: &BoundingBox) {
+        if other.is_empty() {
+            return;
+        }
+        self.add_point(other.min);
+        self.add_point(other.max);
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Point; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Point::new(a.x, a.y, a.z),
+            Point::new(a.x, a.y, b.z),
+            Point::new(a.x, b.y, a.z),
+            Point::new(a.x, b.y, b.z),
+            Point::new(b.x, a.y, a.z),
+            Point::new(b.x, a.y, b.z),
+            Point::new(b.x, b.y, a.z),
+            Point::new(b.x, b.y, b.z),
+        ]
+    }
+
+    /// Distances where `ray` enters and leaves the box, None if it misses.
+    pub fn intersect(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let origin = [ray.origin().x, ray.origin().y, ray.origin().z];
+        let min = [self.min.x, self.min.y, self.min.z];
+        let max = [self.max.x, self.max.y, self.max.z];
+        let inv_direction = ray.inv_direction();
+        let sign = ray.sign();
+
+        let mut tmin = Float::NEG_INFINITY;
+        let mut tmax = Float::INFINITY;
+        for axis in 0..3 {
+            let (near, far) = if sign[axis] {
+                (max[axis], min[axis])
+            } else {
+                (min[axis], max[axis])
+            };
+            let (axis_min, axis_max) = check_axis(origin[axis], inv_direction[axis], near, far);
+            tmin = tmin.max(axis_min);
+            tmax = tmax.min(axis_max);
+        }
+
+        if tmin > tmax {
+            None
+        } else {
+            Some((tmin, tmax))
+        }
+    }
+
+    /// The box containing this box after applying `m`.
+    pub fn transform(&self, m: Matrix) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_finite() {
+            return Self::infinite();
+        }
+
+        let mut b = Self::empty();
+        for corner in self.corners() {
+            b.add_point(m * corner);
+        }
+        b
+    }
+}
+
+/// Entry and exit distance of a ray between the `near` and `far` plane of one axis.
+/// Zero directions are handled explicitly instead of relying on IEEE infinities,
+/// which turn into NaN for origins lying exactly on a face.
+fn check_axis(origin: Float, inv_direction: Float, near: Float, far: Float) -> (Float, Float) {
+    // an empty interval is a miss
+    let miss = (Float::INFINITY, Float::NEG_INFINITY);
+    if origin.is_nan() || inv_direction.is_nan() {
+        return miss;
+    }
+
+    // the ray runs parallel to the slab
+    if inv_direction.is_infinite() {
+        let (min, max) = (near.min(far), near.max(far));
+        return if min <= origin && origin <= max {
+            (Float::NEG_INFINITY, Float::INFINITY)
+        } else {
+            miss
+        };
+    }
+
+    (
+        (near - origin) * inv_direction,
+        (far - origin) * inv_direction,
+    )
+}
+
+/// Sphere enclosing a shape, cheaper to test against than a box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    /// Center of the sphere.
+    pub center: Point,
+
+    /// Radius of the sphere.
+    pub radius: Float,
+}
+
+impl BoundingSphere {
+    /// Create a new bounding sphere.
+    pub fn new(center: Point, radius: Float) -> Self {
+        Self { center, radius }
+    }
+
+    /// The smallest sphere around `bounds`, None if the box is empty or infinite.
+    pub fn from_box(bounds: &BoundingBox) -> Option<Self> {
+        if bounds.is_empty() || !bounds.is_finite() {
+            return None;
+        }
+
+        let center = Point::new(
+            (bounds.min.x + bounds.max.x) / 2.0,
+            (bounds.min.y + bounds.max.y) / 2.0,
+            (bounds.min.z + bounds.max.z) / 2.0,
+        );
+        Some(Self::new(center, (bounds.max - center).magnitude()))
+    }
+
+    /// Does the line of `ray` pass through the sphere?
+    /// The radius is padded by EPSILON, so grazing rays are never rejected.
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        let radius = self.radius + EPSILON;
+        let to_ray = ray.origin() - self.center;
+        let a = ray.direction().dot(ray.direction());
+        let b = ray.direction().dot(to_ray);
+        let c = to_ray.dot(to_ray) - radius * radius;
+
+        b * b - a * c >= 0.0
+    }
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        BoundingBox::empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consts::{FRAC_1_SQRT_2, PI, SQRT_2};
+    use crate::{float_eq, Transformation, Vector};
+
+    #[test]
+    fn empty_bounds() {
+        let b = BoundingBox::empty();
+
+        assert!(b.is_empty());
+        assert!(!b.is_finite());
+    }
+
+    #[test]
+    fn add_point_bounds() {
+        let mut b = BoundingBox::empty();
+        b.add_point(Point::new(-5.0, 2.0, 0.0));
+        b.add_point(Point::new(7.0, 0.0, -3.0));
+
+        assert_eq!(b.min, Point::new(-5.0, 0.0, -3.0));
+        assert_eq!(b.max, Point::new(7.0, 2.0, 0.0));
+    }
+
+    #[test]
+    fn merge_bounds() {
+        let mut a = BoundingBox::new(Point::new(-5.0, -2.0, 0.0), Point::new(7.0, 4.0, 4.0));
+        let b = BoundingBox::new(Point::new(8.0, -7.0, -2.0), Point::new(14.0, 2.0, 8.0));
+        a.merge(&b);
+
+        assert_eq!(a.min, Point::new(-5.0, -7.0, -2.0));
+        assert_eq!(a.max, Point::new(14.0, 4.0, 8.0));
+    }
+
+    #[test]
+    fn transform_bounds() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let m = Transformation::new()
+            .then_rotate_y(PI / 4.0)
+            .then_rotate_x(PI / 4.0)
+            .init();
+        let b2 = b.transform(m);
+
+        assert_eq!(
+            b2.min,
+            Point::new(-SQRT_2, -1.0 - FRAC_1_SQRT_2, -1.0 - FRAC_1_SQRT_2)
+        );
+        assert_eq!(
+            b2.max,
+            Point::new(SQRT_2, 1.0 + FRAC_1_SQRT_2, 1.0 + FRAC_1_SQRT_2)
+        );
+    }
+
+    #[test]
+    fn sphere_from_box_bounds() {
+        let b = BoundingBox::new(Point::new(-1.0, 0.0, -1.0), Point::new(1.0, 2.0, 1.0));
+        let s = BoundingSphere::from_box(&b).expect("The box is finite");
+
+        assert_eq!(s.center, Point::new(0.0, 1.0, 0.0));
+        assert!(float_eq(s.radius, (3.0 as Float).sqrt()));
+        assert!(BoundingSphere::from_box(&BoundingBox::infinite()).is_none());
+        assert!(BoundingSphere::from_box(&BoundingBox::empty()).is_none());
+    }
+
+    #[test]
+    fn sphere_intersects_bounds() {
+        let s = BoundingSphere::new(Point::new(0.0, 0.0, 0.0), 1.0);
+        let hit = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let graze = Ray::new(Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let miss = Ray::new(Point::new(0.0, 1.1, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(s.intersects(&hit));
+        assert!(s.intersects(&graze));
+        assert!(!s.intersects(&miss));
+    }
+
+    #[test]
+    fn intersect_bounds() {
+        let b = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(b.intersect(&r), Some((4.0, 6.0)));
+
+        let r = Ray::new(Point::new(-1.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(b.intersect(&r), Some((4.0, 6.0)));
+
+        let r = Ray::new(Point::new(2.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(b.intersect(&r), None);
+
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 0.0));
+        assert_eq!(b.intersect(&r), None);
+    }
+
+    #[test]
+    fn transform_infinite_bounds() {
+        let b = BoundingBox::new(
+            Point::new(Float::NEG_INFINITY, 0.0, Float::NEG_INFINITY),
+            Point::new(Float::INFINITY, 0.0, Float::INFINITY),
+        );
+        let m = Transformation::new().then_translation(0.0, 1.0, 0.0).init();
+
+        assert!(!b.transform(m).is_finite());
+    }
+}
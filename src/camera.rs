@@ -1,9 +1,210 @@
+use crate::canvas::map_chunks;
+use crate::consts::PI;
 use crate::*;
-use progress_bar::*;
+use std::error::Error;
+use std::fmt;
 // use rayon::prelude::*;
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Receives the progress of a render, e.g. to drive a progress bar.
+/// Closures taking `(done, total, elapsed)` are sinks, `()` ignores the progress.
+pub trait ProgressSink {
+    /// Called after each pixel with the number of rendered pixels,
+    /// the number of pixels in the image and the time since the start.
+    fn progress(&mut self, _done: usize, _total: usize, _elapsed: Duration) {}
+}
+
+impl ProgressSink for () {}
+
+impl<F: FnMut(usize, usize, Duration)> ProgressSink for F {
+    fn progress(&mut self, done: usize, total: usize, elapsed: Duration) {
+        self(done, total, elapsed)
+    }
+}
+
+/// Limits after which a render stops early, None means unlimited.
+/// Rows are the tiles of a render: a started row is always finished.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderLimit {
+    /// Wall-clock time.
+    pub time: Option<Duration>,
+
+    /// Number of primary (camera) rays.
+    pub rays: Option<usize>,
+}
+
+impl RenderLimit {
+    /// Stop after `time` has passed.
+    pub fn time(time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            rays: None,
+        }
+    }
+
+    /// Stop after `rays` primary rays.
+    pub fn rays(rays: usize) -> Self {
+        Self {
+            time: None,
+            rays: Some(rays),
+        }
+    }
+
+    /// Has a limit been reached?
+    fn reached(&self, elapsed: Duration, rays: usize) -> bool {
+        self.time.is_some_and(|t| elapsed >= t) || self.rays.is_some_and(|r| rays >= r)
+    }
+}
+
+/// Time since the start of a render.
+/// wasm32 has no clock without JavaScript, there no time passes.
+struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+}
+
+impl Stopwatch {
+    fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// Stops a render from another thread.
+/// Clones share the same state, so keep one and hand one to the render.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the render to stop after its current row.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Has `cancel` been called on this token or one of its clones?
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A canvas that may have been cut short by a `RenderLimit` or a `CancelToken`.
+#[derive(Debug)]
+pub struct Render {
+    /// The image, rows that were not rendered are black.
+    pub canvas: Canvas,
+
+    /// Number of rendered rows, counted from the top.
+    pub rows: usize,
+
+    /// Was every row rendered?
+    pub complete: bool,
+
+    /// Number of traced camera rays, one per rendered pixel,
+    /// or `lens_samples` per pixel with an aperture.
+    pub rays: usize,
+
+    /// How long the render took, zero on wasm32.
+    pub time: Duration,
+}
+
+/// What a frame was rendered from and how, so an image file can describe
+/// itself, see `Camera::metadata`, `Canvas::to_ppm_with_comments` and
+/// `Canvas::write_png_with_text`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameMetadata {
+    /// Version of this crate.
+    pub version: String,
+
+    /// `World::scene_hash` of the scene.
+    pub scene_hash: u64,
+
+    /// Width and height in pixels.
+    pub size: (usize, usize),
+
+    /// Field of view of the camera in radians.
+    pub field_of_view: Float,
+
+    /// Transformation of the camera.
+    pub transform: Transformation,
+
+    /// Recursion depth for reflected and refracted rays.
+    pub budget: Budget,
+
+    /// Number of traced camera rays.
+    pub rays: usize,
+
+    /// How long the render took.
+    pub time: Duration,
+}
+
+/// One `key: value` line per entry.
+impl fmt::Display for FrameMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows: Vec<String> = self
+            .transform
+            .as_matrix()
+            .to_rows_array()
+            .chunks(4)
+            .map(|row| format!("{:?}", row))
+            .collect();
+        writeln!(f, "rtracer: {}", self.version)?;
+        writeln!(f, "scene: {:016x}", self.scene_hash)?;
+        writeln!(f, "size: {}x{}", self.size.0, self.size.1)?;
+        writeln!(f, "field of view: {}", self.field_of_view)?;
+        writeln!(f, "transform: [{}]", rows.join(", "))?;
+        writeln!(
+            f,
+            "budget: {} reflections, {} refractions",
+            self.budget.reflection, self.budget.refraction
+        )?;
+        writeln!(f, "rays: {}", self.rays)?;
+        write!(f, "render time: {:.3}s", self.time.as_secs_f64())
+    }
+}
+
+/// One canvas per term of the color, see `World::color_terms`.
+/// Adding up all canvases gives the render.
+#[derive(Debug, Clone)]
+pub struct RenderTerms {
+    /// Ambient light, and the background where rays miss.
+    pub ambient: Canvas,
+
+    /// Diffuse light.
+    pub diffuse: Canvas,
+
+    /// Highlights.
+    pub specular: Canvas,
+
+    /// Reflected light.
+    pub reflection: Canvas,
+
+    /// Refracted light.
+    pub refraction: Canvas,
+}
 
 /// Just like a real camera, the virtual camera allows moving around in the scene.
+#[derive(Debug)]
 pub struct Camera {
     /// hsize is the horizontal size (in pixels) of the canvas that the picture will be rendered to.
     pub hsize: usize,
@@ -14,7 +215,7 @@ pub struct Camera {
     /// field_of_view is an angle that describes how much the camera can see.
     /// When the field of view is small, the view will be “zoomed in,”
     /// magnifying a smaller area of the scene.
-    pub field_of_view: f64,
+    pub field_of_view: Float,
 
     /// transform is a matrix describing how the world should be oriented
     /// relative to the camera. This is usually a view transformation like you
@@ -22,20 +223,36 @@ pub struct Camera {
     pub transform: Transformation,
 
     /// pixel_size describes the view of the world by the Camera.
-    pub pixel_size: f64,
+    pub pixel_size: Float,
 
     /// TODO
-    pub half_width: f64,
+    pub half_width: Float,
 
     /// TODO
-    pub half_height: f64,
+    pub half_height: Float,
+
+    /// Recursion depth for reflected and refracted rays.
+    pub budget: Budget,
+
+    /// When to stop rendering early, unlimited by default.
+    pub limit: RenderLimit,
+
+    /// Radius of the lens in world units, 0 (the default) is a pinhole camera
+    /// with everything in focus. A wider lens blurs what is not at `focal_distance`.
+    pub aperture: Float,
+
+    /// Distance from the camera to the plane that is in focus.
+    pub focal_distance: Float,
+
+    /// Rays per pixel spread over the lens, only used with an aperture.
+    pub lens_samples: usize,
 }
 
 impl Camera {
     /// Create a new camera.
-    pub fn new(hsize: usize, vsize: usize, field_of_view: f64) -> Self {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: Float) -> Self {
         let half_view = (field_of_view / 2.0).tan();
-        let aspect = hsize as f64 / vsize as f64;
+        let aspect = hsize as Float / vsize as Float;
 
         let mut half_width = half_view * aspect;
         let mut half_height = half_view;
@@ -45,7 +262,7 @@ impl Camera {
             half_height = half_view / aspect;
         }
 
-        let pixel_size = (half_width * 2.0) / hsize as f64;
+        let pixel_size = (half_width * 2.0) / hsize as Float;
 
         Self {
             hsize,
@@ -55,52 +272,498 @@ impl Camera {
             pixel_size,
             half_width,
             half_height,
+            budget: Budget::default(),
+            limit: RenderLimit::default(),
+            aperture: 0.0,
+            focal_distance: 1.0,
+            lens_samples: 16,
+        }
+    }
+
+    /// A camera with the canvas size scaled by `factor`, e.g. for previews.
+    /// The visible part of the world stays the same, only the pixels grow or shrink.
+    /// Rounding the size may change the aspect ratio a little, the view is then
+    /// fitted to the new size just like `Camera::new` does.
+    pub fn scaled(&self, factor: Float) -> Self {
+        assert!(
+            factor.is_finite() && factor > 0.0,
+            "Scale factor must be positive!"
+        );
+        let hsize = ((self.hsize as Float * factor).round() as usize).max(1);
+        let vsize = ((self.vsize as Float * factor).round() as usize).max(1);
+
+        Self {
+            transform: self.transform,
+            budget: self.budget,
+            limit: self.limit,
+            aperture: self.aperture,
+            focal_distance: self.focal_distance,
+            lens_samples: self.lens_samples,
+            ..Self::new(hsize, vsize, self.field_of_view)
         }
     }
 
     /// Compute a ray that starts at the camera and passes through the indicated (x,y) pixel.
+    /// This is the ray through the center of the lens, see `ray_for_pixel_on_lens`.
     pub fn ray_for_pixel(&self, x: usize, y: usize) -> Ray {
-        let xoffset = (x as f64 + 0.5) * self.pixel_size;
-        let yoffset = (y as f64 + 0.5) * self.pixel_size;
+        self.ray_for_pixel_on_lens(x, y, 0.0, 0.0)
+    }
+
+    /// Compute a ray through the indicated (x,y) pixel that starts at the point
+    /// (u, v) of the unit disk scaled to the lens. All rays of a pixel meet
+    /// at `focal_distance`, so only objects there are sharp.
+    pub fn ray_for_pixel_on_lens(&self, x: usize, y: usize, u: Float, v: Float) -> Ray {
+        let xoffset = (x as Float + 0.5) * self.pixel_size;
+        let yoffset = (y as Float + 0.5) * self.pixel_size;
 
         let world_x = self.half_width - xoffset;
         let world_y = self.half_height - yoffset;
 
         let inv = self
             .transform
-            .init()
-            .inverse(4)
+            .inverse()
             .expect("Camera transform should be invertible!");
-        let pixel = inv * Point::new(world_x, world_y, -1.0);
-        let origin = inv * Point::new(0.0, 0.0, 0.0);
+        // the canvas is at z = -1, the focal plane at z = -focal_distance
+        let d = self.focal_distance;
+        let focus = Point::new(world_x * d, world_y * d, -d);
+        let lens = Point::new(u * self.aperture, v * self.aperture, 0.0);
+        let pixel = inv * focus;
+        let origin = inv * lens;
         let direction = (pixel - origin).normalize();
 
-        Ray { origin, direction }
+        Ray::new(origin, direction)
+    }
+
+    /// Points on the unit disk where the rays of a pixel leave the lens,
+    /// only the center for a pinhole camera.
+    fn lens_points(&self) -> Vec<(Float, Float)> {
+        if self.aperture <= 0.0 {
+            return vec![(0.0, 0.0)];
+        }
+        let samples = self.lens_samples.max(1);
+        (0..samples)
+            .map(|i| {
+                // Hammersley points spread the samples evenly over the disk
+                let u1 = (i as Float + 0.5) / samples as Float;
+                let u2 = (i as u32).reverse_bits() as Float / (u32::MAX as Float + 1.0);
+                let (sin, cos) = (2.0 * PI * u2).sin_cos();
+                (u1.sqrt() * cos, u1.sqrt() * sin)
+            })
+            .collect()
+    }
+
+    /// Pixel coordinates of a point in world space, the inverse of `ray_for_pixel`:
+    /// the center of pixel (x, y) is at (x + 0.5, y + 0.5).
+    /// Points outside of the view give coordinates outside of the canvas,
+    /// points behind the camera give None.
+    pub fn project(&self, point: Point) -> Option<(Float, Float)> {
+        let p = self.transform.init() * point;
+        // the camera looks down -z, the canvas is at z = -1
+        if p.z >= 0.0 {
+            return None;
+        }
+        let world_x = p.x / -p.z;
+        let world_y = p.y / -p.z;
+
+        Some((
+            (self.half_width - world_x) / self.pixel_size,
+            (self.half_height - world_y) / self.pixel_size,
+        ))
+    }
+
+    /// Could any part of `bounds` (in world space) show up in the image?
+    /// Conservative: a box is only rejected if all its corners are outside
+    /// the same plane of the view frustum.
+    pub fn is_visible(&self, bounds: &BoundingBox) -> bool {
+        if bounds.is_empty() {
+            return false;
+        }
+        if !bounds.is_finite() {
+            return true;
+        }
+
+        let view = self.transform.init();
+        let corners = bounds.corners().map(|c| view * c);
+        // the camera looks down -z, the canvas is at z = -1
+        let outside = [
+            corners.iter().all(|c| c.z > 0.0),
+            corners.iter().all(|c| c.x < self.half_width * c.z),
+            corners.iter().all(|c| c.x > -self.half_width * c.z),
+            corners.iter().all(|c| c.y < self.half_height * c.z),
+            corners.iter().all(|c| c.y > -self.half_height * c.z),
+        ];
+
+        !outside.iter().any(|o| *o)
     }
 
     /// Render a view of the given world with the camera.
     pub fn render(&self, world: &World) -> Canvas {
-        init_progress_bar(self.hsize * self.vsize);
-        set_progress_bar_action("Rendering", Color::Blue, Style::Bold);
-        let mut canvas = Canvas::new(self.hsize, self.vsize);
+        self.render_with_progress(world, &mut ())
+    }
+
+    /// Render a view of the given world, reporting the progress to `progress`.
+    pub fn render_with_progress(&self, world: &World, progress: &mut dyn ProgressSink) -> Canvas {
+        self.render_rows(world, progress, None, false).canvas
+    }
+
+    /// Describe a render of `world` with this camera.
+    pub fn metadata(&self, world: &World, render: &Render) -> FrameMetadata {
+        FrameMetadata {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            scene_hash: world.scene_hash(),
+            size: (self.hsize, self.vsize),
+            field_of_view: self.field_of_view,
+            transform: self.transform,
+            budget: self.budget,
+            rays: render.rays,
+            time: render.time,
+        }
+    }
+
+    /// Render a view of the given world with an alpha channel, pixels that
+    /// show no object are transparent, e.g. to composite over a photograph.
+    pub fn render_with_alpha(&self, world: &World) -> Canvas {
+        let mut canvas = self.render(world);
+        let gbuffer = GBuffer::render(self, world);
+        for (i, id) in gbuffer.ids.iter().enumerate() {
+            let alpha = if id.is_some() { 1.0 } else { 0.0 };
+            canvas.set_alpha(i % self.hsize, i / self.hsize, alpha);
+        }
+        canvas
+    }
+
+    /// Render a view of the given world as RGBA bytes, row by row from the top,
+    /// e.g. for the `ImageData` of an HTML canvas.
+    pub fn render_to_rgba8(&self, world: &World) -> Vec<u8> {
+        self.render(world).to_rgba8()
+    }
+
+    /// Render each term of the color to its own canvas,
+    /// e.g. to show what ambient, diffuse or specular light add to an image.
+    pub fn render_terms(&self, world: &World) -> RenderTerms {
+        let canvas = || Canvas::new(self.hsize, self.vsize);
+        let mut terms = RenderTerms {
+            ambient: canvas(),
+            diffuse: canvas(),
+            specular: canvas(),
+            reflection: canvas(),
+            refraction: canvas(),
+        };
 
-        let now = SystemTime::now();
         for y in 0..self.vsize {
             for x in 0..self.hsize {
-                let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(&ray, 5);
+                let color = world.color_terms(&self.ray_for_pixel(x, y), self.budget);
+                terms.ambient.write_pixel(x, y, color.ambient);
+                terms.diffuse.write_pixel(x, y, color.diffuse);
+                terms.specular.write_pixel(x, y, color.specular);
+                terms.reflection.write_pixel(x, y, color.reflection);
+                terms.refraction.write_pixel(x, y, color.refraction);
+            }
+        }
+
+        terms
+    }
+
+    /// Render a view of the given world without reporting progress,
+    /// stopping once `limit` is reached. Useful for previews and CI.
+    pub fn render_partial(&self, world: &World) -> Render {
+        self.render_rows(world, &mut (), None, false)
+    }
 
-                canvas.write_pixel(x, y, color);
-                inc_progress_bar();
+    /// Render a view of the given world until `limit` is reached or `cancel`
+    /// is cancelled, e.g. by the user of an interactive tool.
+    pub fn render_cancellable(
+        &self,
+        world: &World,
+        progress: &mut dyn ProgressSink,
+        cancel: &CancelToken,
+    ) -> Render {
+        self.render_rows(world, progress, Some(cancel), false)
+    }
+
+    /// Render a view of the given world with rows traced on all cores.
+    /// The image is the same as the one of `render`.
+    pub fn render_parallel(&self, world: &World) -> Canvas {
+        self.render_rows(world, &mut (), None, true).canvas
+    }
+
+    fn render_rows(
+        &self,
+        world: &World,
+        progress: &mut dyn ProgressSink,
+        cancel: Option<&CancelToken>,
+        parallel: bool,
+    ) -> Render {
+        let mut canvas = Canvas::new(self.hsize, self.vsize);
+        let start = Stopwatch::start();
+        let total = self.hsize * self.vsize;
+        let lens = self.lens_points();
+        let mut rays = 0;
+        let mut pixels = 0;
+
+        // one row per thread at a time, so a cancelled render stops soon
+        let batch = if parallel {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            1
+        };
+        let render_row = |y: usize| {
+            // the rays of a row share one intersection buffer
+            let row: Vec<Ray> = (0..self.hsize)
+                .flat_map(|x| {
+                    lens.iter()
+                        .map(move |&(u, v)| self.ray_for_pixel_on_lens(x, y, u, v))
+                })
+                .collect();
+            world.color_at_primary_batch(&row, self.budget)
+        };
+
+        for first in (0..self.vsize).step_by(batch) {
+            let cancelled = cancel.is_some_and(CancelToken::is_cancelled);
+            if cancelled || self.limit.reached(start.elapsed(), rays) {
+                return Render {
+                    canvas,
+                    rows: first,
+                    complete: false,
+                    rays,
+                    time: start.elapsed(),
+                };
+            }
+
+            let rows = (first + batch).min(self.vsize) - first;
+            let colors = if rows == 1 {
+                vec![render_row(first)]
+            } else {
+                map_chunks(rows, 1, |r| render_row(first + r.start))
+            };
+            for (y, colors) in (first..).zip(colors) {
+                for (x, samples) in colors.chunks(lens.len()).enumerate() {
+                    let sum = samples.iter().fold(BLACK, |sum, c| sum + *c);
+                    canvas.write_pixel(x, y, sum * (1.0 / lens.len() as Float));
+                    rays += lens.len();
+                    pixels += 1;
+                    progress.progress(pixels, total, start.elapsed());
+                }
             }
         }
-        finalize_progress_bar();
-        match now.elapsed() {
-            Ok(elapsed) => println!("The render took {:.3} seconds", elapsed.as_secs_f64()),
-            Err(why) => eprintln!("Error: {}", why),
+
+        Render {
+            canvas,
+            rows: self.vsize,
+            complete: true,
+            rays,
+            time: start.elapsed(),
         }
+    }
+}
 
-        canvas
+/// Render `world` as seen by `camera` with the defaults of a finished image:
+/// the recursion depth and limit of the camera (5 reflections and refractions
+/// unless changed), rows traced on all cores, edges smoothed by `Fxaa` with
+/// a `GBuffer`, no progress. Use the methods of `Camera` for anything else.
+///
+/// ```
+/// use rtracer::{CameraBuilder, Point, PointLight, Sphere, Vector, WorldBuilder, WHITE};
+///
+/// let (world, camera) = WorldBuilder::new()
+///     .light(PointLight::new(Point::new(-10.0, 10.0, -10.0), WHITE))
+///     .object(Sphere::new())
+///     .camera(CameraBuilder::new(20, 10).look_at(
+///         Point::new(0.0, 0.0, -5.0),
+///         Point::new(0.0, 0.0, 0.0),
+///         Vector::new(0.0, 1.0, 0.0),
+///     ))
+///     .build()
+///     .unwrap();
+/// let canvas = rtracer::render(&world, &camera);
+/// assert_eq!((canvas.width, canvas.height), (20, 10));
+/// ```
+pub fn render(world: &World, camera: &Camera) -> Canvas {
+    let canvas = camera.render_parallel(world);
+    let gbuffer = GBuffer::render(camera, world);
+    Fxaa::default().apply(&canvas, Some(&gbuffer))
+}
+
+/// Reasons a `CameraBuilder` can not build a camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraError {
+    /// The canvas has no pixels.
+    EmptyCanvas,
+
+    /// The field of view must be in (0, π).
+    FieldOfView(Float),
+
+    /// The camera looks at its own position.
+    NoViewDirection,
+
+    /// The up vector is parallel to the view direction, so the roll is unknown.
+    UpParallel,
+
+    /// The radius of the lens must be finite and not negative.
+    Aperture(Float),
+
+    /// The distance in focus must be positive.
+    FocalDistance(Float),
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::EmptyCanvas => write!(f, "Camera canvas has no pixels"),
+            CameraError::FieldOfView(fov) => {
+                write!(f, "Field of view {} is not between 0 and π", fov)
+            }
+            CameraError::NoViewDirection => write!(f, "Camera looks at its own position"),
+            CameraError::UpParallel => {
+                write!(f, "Camera up vector is parallel to the view direction")
+            }
+            CameraError::Aperture(radius) => write!(f, "Aperture {} is not a lens radius", radius),
+            CameraError::FocalDistance(distance) => {
+                write!(f, "Focal distance {} is not positive", distance)
+            }
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+/// Builds a `Camera` from where it is and where it looks at.
+///
+/// ```
+/// use rtracer::{CameraBuilder, Point, Vector};
+///
+/// let camera = CameraBuilder::new(400, 200)
+///     .fov_degrees(60.0)
+///     .look_at(
+///         Point::new(0.0, 1.5, -5.0),
+///         Point::new(0.0, 1.0, 0.0),
+///         Vector::new(0.0, 1.0, 0.0),
+///     )
+///     .build()
+///     .unwrap();
+/// assert_eq!(camera.hsize, 400);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraBuilder {
+    hsize: usize,
+    vsize: usize,
+    field_of_view: Float,
+    from: Point,
+    to: Point,
+    up: Vector,
+    roll: Float,
+    aperture: Float,
+    focal_distance: Option<Float>,
+    lens_samples: usize,
+}
+
+impl CameraBuilder {
+    /// Horizontal field of view of a 50 mm lens on 35 mm film, in radians.
+    pub const NORMAL: Float = 0.6911;
+
+    /// Horizontal field of view of a 24 mm lens on 35 mm film, in radians.
+    pub const WIDE: Float = 1.2870;
+
+    /// Horizontal field of view of a 200 mm lens on 35 mm film, in radians.
+    pub const TELEPHOTO: Float = 0.1795;
+
+    /// A camera at the origin looking down -z with a field of view of π/3.
+    pub fn new(hsize: usize, vsize: usize) -> Self {
+        Self {
+            hsize,
+            vsize,
+            field_of_view: PI / 3.0,
+            from: Point::new(0.0, 0.0, 0.0),
+            to: Point::new(0.0, 0.0, -1.0),
+            up: Vector::new(0.0, 1.0, 0.0),
+            roll: 0.0,
+            aperture: 0.0,
+            focal_distance: None,
+            lens_samples: 16,
+        }
+    }
+
+    /// Field of view along the longer side of the canvas, in radians.
+    pub fn fov(mut self, rad: Float) -> Self {
+        self.field_of_view = rad;
+        self
+    }
+
+    /// Field of view along the longer side of the canvas, in degrees.
+    pub fn fov_degrees(self, deg: Float) -> Self {
+        self.fov(deg.to_radians())
+    }
+
+    /// Field of view of a lens with the focal length `mm` on 35 mm film,
+    /// the longer side of the canvas is the 36 mm side of the film.
+    pub fn focal_length(self, mm: Float) -> Self {
+        self.fov(2.0 * (18.0 / mm).atan())
+    }
+
+    /// Place the camera at `from` looking at `to`, `up` points roughly upwards.
+    pub fn look_at(mut self, from: Point, to: Point, up: Vector) -> Self {
+        self.from = from;
+        self.to = to;
+        self.up = up;
+        self
+    }
+
+    /// Turn the camera around its view direction by `rad` radians.
+    /// Positive angles turn the camera clockwise, so the image turns counterclockwise.
+    pub fn roll(mut self, rad: Float) -> Self {
+        self.roll = rad;
+        self
+    }
+
+    /// Radius of the lens in world units, 0 is a pinhole camera.
+    /// The point the camera looks at is in focus unless `focal_distance` is set.
+    pub fn aperture(mut self, radius: Float) -> Self {
+        self.aperture = radius;
+        self
+    }
+
+    /// Distance from the camera to the plane that is in focus.
+    pub fn focal_distance(mut self, distance: Float) -> Self {
+        self.focal_distance = Some(distance);
+        self
+    }
+
+    /// Rays per pixel spread over the lens, 16 by default.
+    pub fn lens_samples(mut self, samples: usize) -> Self {
+        self.lens_samples = samples;
+        self
+    }
+
+    /// The camera, or why it can't see anything.
+    pub fn build(self) -> Result<Camera, CameraError> {
+        if self.hsize == 0 || self.vsize == 0 {
+            return Err(CameraError::EmptyCanvas);
+        }
+        if !(self.field_of_view > 0.0 && self.field_of_view < PI) {
+            return Err(CameraError::FieldOfView(self.field_of_view));
+        }
+        let forward = self.to - self.from;
+        if forward.magnitude() < EPSILON {
+            return Err(CameraError::NoViewDirection);
+        }
+        if forward.normalize().cross(self.up.normalize()).magnitude() < EPSILON {
+            return Err(CameraError::UpParallel);
+        }
+        if !(self.aperture >= 0.0 && self.aperture.is_finite()) {
+            return Err(CameraError::Aperture(self.aperture));
+        }
+        let focal_distance = self.focal_distance.unwrap_or(forward.magnitude());
+        if !(focal_distance > 0.0 && focal_distance.is_finite()) {
+            return Err(CameraError::FocalDistance(focal_distance));
+        }
+
+        let mut camera = Camera::new(self.hsize, self.vsize, self.field_of_view);
+        camera.transform = Transformation::view_transformation(self.from, self.to, self.up)
+            .then_rotate_z(-self.roll);
+        camera.aperture = self.aperture;
+        camera.focal_distance = focal_distance;
+        camera.lens_samples = self.lens_samples.max(1);
+        Ok(camera)
     }
 }
 
@@ -108,7 +771,6 @@ impl Camera {
 mod test {
     use super::*;
     use crate::{float_eq, Point, Vector, IDENTITY, RGB};
-    use std::f64::consts::PI;
 
     #[test]
     fn construct_camera() {
@@ -142,32 +804,79 @@ mod test {
         let c = Camera::new(201, 101, PI / 2.0);
         let r = c.ray_for_pixel(100, 50);
 
-        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
-        assert_eq!(r.direction, Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(r.origin(), Point::new(0.0, 0.0, 0.0));
+        assert_eq!(r.direction(), Vector::new(0.0, 0.0, -1.0));
     }
     #[test]
     fn ray_corner_canvas_camera() {
         let c = Camera::new(201, 101, PI / 2.0);
         let r = c.ray_for_pixel(0, 0);
 
-        assert_eq!(r.origin, Point::new(0.0, 0.0, 0.0));
-        assert_eq!(r.direction, Vector::new(0.66519, 0.33259, -0.66851));
+        assert_eq!(r.origin(), Point::new(0.0, 0.0, 0.0));
+        assert_eq!(r.direction(), Vector::new(0.66519, 0.33259, -0.66851));
     }
     #[test]
     fn ray_transform_canvas_camera() {
         let mut c = Camera::new(201, 101, PI / 2.0);
         c.transform = Transformation::new()
-            .translation(0.0, -2.0, 5.0)
-            .rotate_y(PI / 4.0);
+            .then_translation(0.0, -2.0, 5.0)
+            .then_rotate_y(PI / 4.0);
         let r = c.ray_for_pixel(100, 50);
 
-        assert_eq!(r.origin, Point::new(0.0, 2.0, -5.0));
+        assert_eq!(r.origin(), Point::new(0.0, 2.0, -5.0));
         assert_eq!(
-            r.direction,
-            Vector::new(2_f64.sqrt() / 2.0, 0.0, -(2_f64.sqrt()) / 2.0)
+            r.direction(),
+            Vector::new(
+                (2.0 as Float).sqrt() / 2.0,
+                0.0,
+                -((2.0 as Float).sqrt()) / 2.0
+            )
         );
     }
 
+    #[test]
+    fn visible_camera() {
+        let mut c = Camera::new(100, 50, PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let unit = |x: Float, y: Float, z: Float| {
+            BoundingBox::new(
+                Point::new(x - 1.0, y - 1.0, z - 1.0),
+                Point::new(x + 1.0, y + 1.0, z + 1.0),
+            )
+        };
+
+        assert!(c.is_visible(&unit(0.0, 0.0, 0.0)));
+        assert!(!c.is_visible(&unit(0.0, 0.0, -10.0)));
+        assert!(!c.is_visible(&unit(10.0, 0.0, 0.0)));
+        assert!(!c.is_visible(&unit(-10.0, 0.0, 0.0)));
+        assert!(c.is_visible(&unit(5.0, 0.0, 0.0)));
+        assert!(!c.is_visible(&unit(0.0, 5.0, 0.0)));
+        assert!(c.is_visible(&BoundingBox::infinite()));
+        assert!(!c.is_visible(&BoundingBox::empty()));
+    }
+
+    #[test]
+    fn project_camera() {
+        let mut c = Camera::new(201, 101, PI / 2.0);
+        c.transform = Transformation::new()
+            .then_translation(0.0, -2.0, 5.0)
+            .then_rotate_y(PI / 4.0);
+
+        for (x, y) in [(0, 0), (100, 50), (200, 100), (17, 83)] {
+            let r = c.ray_for_pixel(x, y);
+            let (px, py) = c.project(r.position(7.0)).unwrap();
+            assert!(float_eq(px, x as Float + 0.5));
+            assert!(float_eq(py, y as Float + 0.5));
+        }
+
+        let r = c.ray_for_pixel(100, 50);
+        assert!(c.project(r.position(-1.0)).is_none());
+    }
+
     #[test]
     fn render_camera() {
         let w = World::default();
@@ -179,5 +888,327 @@ mod test {
         let image = c.render(&w);
 
         assert_eq!(image.pixel_at(5, 5), RGB::new(0.38066, 0.47583, 0.2855));
+        // rows traced on other threads give the same image
+        assert!(c.render_parallel(&w).pixels().eq(image.pixels()));
+    }
+
+    #[test]
+    fn sync_world_camera() {
+        fn shared<T: Send + Sync>() {}
+        shared::<World>();
+        shared::<Camera>();
+    }
+
+    #[test]
+    fn progress_camera() {
+        let w = World::default();
+        let c = Camera::new(4, 3, PI / 2.0);
+        let mut reports = Vec::new();
+        c.render_with_progress(&w, &mut |done, total, _| reports.push((done, total)));
+
+        assert_eq!(reports.len(), 12);
+        assert_eq!(reports[0], (1, 12));
+        assert_eq!(reports[11], (12, 12));
+    }
+
+    #[test]
+    fn render_terms_camera() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let terms = c.render_terms(&w);
+        let sum = &(&(&terms.ambient + &terms.diffuse) + &terms.specular)
+            + &(&terms.reflection + &terms.refraction);
+
+        assert_eq!(sum.pixel_at(5, 5), RGB::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(terms.ambient.pixel_at(5, 5), RGB::new(0.08, 0.1, 0.06));
+        assert_eq!(terms.reflection.pixel_at(5, 5), BLACK);
+    }
+
+    #[test]
+    fn limit_rays_camera() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        c.limit = RenderLimit::rays(60);
+        let render = c.render_partial(&w);
+
+        // the sixth row is started before the limit and finished
+        assert!(!render.complete);
+        assert_eq!(render.rows, 6);
+        assert_eq!(
+            render.canvas.pixel_at(5, 5),
+            RGB::new(0.38066, 0.47583, 0.2855)
+        );
+        assert_eq!(render.canvas.pixel_at(5, 6), BLACK);
+
+        c.limit = RenderLimit::default();
+        let render = c.render_partial(&w);
+        assert!(render.complete);
+        assert_eq!(render.rows, 11);
+    }
+
+    #[test]
+    fn limit_time_camera() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.limit = RenderLimit::time(Duration::ZERO);
+        let render = c.render_partial(&w);
+
+        assert!(!render.complete);
+        assert_eq!(render.rows, 0);
+    }
+
+    #[test]
+    fn cancel_camera() {
+        let w = World::default();
+        let c = Camera::new(11, 11, PI / 2.0);
+        let cancel = CancelToken::new();
+        let token = cancel.clone();
+        // cancel from within the render, once the third row is done
+        let mut progress = |done, _, _| {
+            if done == 33 {
+                token.cancel();
+            }
+        };
+        let render = c.render_cancellable(&w, &mut progress, &cancel);
+
+        assert!(cancel.is_cancelled());
+        assert!(!render.complete);
+        assert_eq!(render.rows, 3);
+
+        let render = c.render_cancellable(&w, &mut (), &CancelToken::new());
+        assert!(render.complete);
+    }
+
+    #[test]
+    fn scaled_camera() {
+        let mut c = Camera::new(300, 150, PI / 2.0);
+        c.transform = Transformation::new()
+            .then_translation(0.0, -2.0, 5.0)
+            .then_rotate_y(PI / 4.0);
+        let s = c.scaled(1.0 / 3.0);
+
+        assert_eq!(s.hsize, 100);
+        assert_eq!(s.vsize, 50);
+        assert!(float_eq(s.pixel_size, 3.0 * c.pixel_size));
+        assert!(float_eq(s.half_width, c.half_width));
+        assert!(float_eq(s.half_height, c.half_height));
+        // each preview pixel covers 3x3 pixels of the full render
+        for (x, y) in [(0, 0), (50, 25), (99, 49)] {
+            let r = s.ray_for_pixel(x, y);
+            let expected = c.ray_for_pixel(3 * x + 1, 3 * y + 1);
+            assert_eq!(r.origin(), expected.origin());
+            assert_eq!(r.direction(), expected.direction());
+        }
+
+        // rounding changes the aspect ratio, the pixels stay square and fill the view
+        let c = Camera::new(301, 100, PI / 2.0);
+        let s = c.scaled(0.5);
+        assert_eq!((s.hsize, s.vsize), (151, 50));
+        assert!(float_eq(
+            s.pixel_size * s.hsize as Float,
+            2.0 * s.half_width
+        ));
+        assert!(float_eq(
+            s.pixel_size * s.vsize as Float,
+            2.0 * s.half_height
+        ));
+    }
+
+    #[test]
+    fn builder_camera() {
+        let from = Point::new(1.0, 3.0, 2.0);
+        let to = Point::new(4.0, -2.0, 8.0);
+        let up = Vector::new(1.0, 1.0, 0.0);
+        let c = CameraBuilder::new(201, 101)
+            .fov_degrees(90.0)
+            .look_at(from, to, up)
+            .build()
+            .unwrap();
+
+        assert!(float_eq(c.field_of_view, PI / 2.0));
+        assert_eq!(
+            c.transform,
+            Transformation::view_transformation(from, to, up)
+        );
+        assert!(float_eq(
+            CameraBuilder::new(1, 1).focal_length(50.0).field_of_view,
+            CameraBuilder::NORMAL
+        ));
+        assert!(float_eq(
+            CameraBuilder::new(1, 1).focal_length(24.0).field_of_view,
+            CameraBuilder::WIDE
+        ));
+        assert!(float_eq(
+            CameraBuilder::new(1, 1).focal_length(200.0).field_of_view,
+            CameraBuilder::TELEPHOTO
+        ));
+    }
+
+    #[test]
+    fn roll_camera() {
+        let c = CameraBuilder::new(101, 101)
+            .look_at(
+                Point::new(0.0, 0.0, -5.0),
+                Point::new(0.0, 0.0, 0.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .roll(PI / 2.0)
+            .build()
+            .unwrap();
+
+        // the image turns counterclockwise, what was above the center is left of it
+        let (x, y) = c.project(Point::new(0.0, 1.0, 0.0)).unwrap();
+        assert!(x < 50.0 && float_eq(y, 50.5));
+    }
+
+    #[test]
+    fn invalid_builder_camera() {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let builder = CameraBuilder::new(10, 10);
+
+        assert_eq!(
+            CameraBuilder::new(0, 10).build().unwrap_err(),
+            CameraError::EmptyCanvas
+        );
+        assert_eq!(
+            builder.fov(PI).build().unwrap_err(),
+            CameraError::FieldOfView(PI)
+        );
+        assert_eq!(
+            builder
+                .look_at(Point::new(1.0, 1.0, 1.0), Point::new(1.0, 1.0, 1.0), up)
+                .build()
+                .unwrap_err(),
+            CameraError::NoViewDirection
+        );
+        assert_eq!(
+            builder
+                .look_at(Point::new(0.0, 5.0, 0.0), Point::new(0.0, 0.0, 0.0), up)
+                .build()
+                .unwrap_err(),
+            CameraError::UpParallel
+        );
+        assert_eq!(
+            builder.aperture(-1.0).build().unwrap_err(),
+            CameraError::Aperture(-1.0)
+        );
+        assert_eq!(
+            builder.focal_distance(0.0).build().unwrap_err(),
+            CameraError::FocalDistance(0.0)
+        );
+    }
+
+    #[test]
+    fn lens_camera() {
+        let c = CameraBuilder::new(11, 11)
+            .fov(PI / 2.0)
+            .look_at(
+                Point::new(0.0, 0.0, 0.0),
+                Point::new(0.0, 0.0, -5.0),
+                Vector::new(0.0, 1.0, 0.0),
+            )
+            .aperture(0.5)
+            .build()
+            .unwrap();
+        assert_eq!(c.focal_distance, 5.0);
+
+        // rays through the same pixel meet on the focal plane
+        let center = c.ray_for_pixel(2, 7);
+        assert_eq!(
+            center.direction(),
+            c.ray_for_pixel_on_lens(2, 7, 0.0, 0.0).direction()
+        );
+        let edge = c.ray_for_pixel_on_lens(2, 7, 1.0, 0.0);
+        assert_eq!(edge.origin(), Point::new(0.5, 0.0, 0.0));
+        let meet = |r: &Ray| r.position(-5.0 / r.direction().z);
+        assert_eq!(meet(&center), meet(&edge));
+    }
+
+    #[test]
+    fn render_lens_camera() {
+        let w = World::default();
+        let builder = CameraBuilder::new(11, 11).fov(PI / 2.0).look_at(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let pinhole = builder.build().unwrap().render(&w);
+
+        // the front of the sphere is in focus
+        let c = builder.aperture(0.05).focal_distance(4.0).build().unwrap();
+        let render = c.render_partial(&w);
+        assert_eq!(render.rays, 11 * 11 * 16);
+        let sharp = render.canvas.pixel_at(5, 5);
+        let expected = pinhole.pixel_at(5, 5);
+        assert!((sharp - expected).luminance().abs() < 0.01);
+
+        // in focus far behind the sphere its edge is blurred
+        let blurred = builder.aperture(0.5).focal_distance(50.0).build().unwrap();
+        let image = blurred.render(&w);
+        // the bright edge of the sphere is mixed with the black background
+        assert!(image.pixel_at(4, 5).luminance() < pinhole.pixel_at(4, 5).luminance() - 0.1);
+    }
+
+    #[test]
+    fn metadata_camera() {
+        let w = World::default();
+        let c = Camera::new(4, 3, PI / 2.0);
+        let render = c.render_partial(&w);
+        assert_eq!(render.rays, 12);
+
+        let meta = c.metadata(&w, &render);
+        assert_eq!(meta.size, (4, 3));
+        assert_eq!(meta.scene_hash, World::default().scene_hash());
+        let text = meta.to_string();
+        assert!(text.starts_with(&format!("rtracer: {}\n", env!("CARGO_PKG_VERSION"))));
+        assert!(text.contains("\nsize: 4x3\n"));
+        assert!(text.contains("\ntransform: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0"));
+        assert!(text.contains("\nbudget: 5 reflections, 5 refractions\n"));
+        assert!(text.contains("\nrays: 12\n"));
+    }
+
+    #[test]
+    fn golden_path_render() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let image = render(&w, &c);
+
+        // the background is kept, the sphere is smoothed
+        let plain = c.render(&w);
+        assert_eq!(image.pixel_at(0, 0), plain.pixel_at(0, 0));
+        assert!(image.pixels().ne(plain.pixels()));
+    }
+
+    #[test]
+    fn alpha_camera() {
+        let w = World::default();
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let image = c.render_with_alpha(&w);
+
+        assert!(image.has_alpha());
+        assert_eq!(image.alpha_at(0, 0), 0.0);
+        assert_eq!(image.alpha_at(5, 5), 1.0);
+        assert_eq!(image.pixel_at(5, 5), RGB::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(image.to_rgba8()[3], 0);
     }
 }
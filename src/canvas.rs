@@ -1,16 +1,197 @@
-use crate::{color::RGB, BLACK};
+use crate::{color::RGB, Exr, Float, BLACK};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::{Add, Mul};
+use std::path::Path;
 
 const MAXIMUM_PPM_LINE_LENGTH: usize = 70;
 
-/// Canvas object
+/// Luminance that auto exposure maps the chosen percentile to.
+pub const MID_GRAY: Float = 0.18;
+
+/// Distribution of the luminance of the pixels of a `Canvas`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// Upper end of the last bin, the lower end of the first is 0.
+    pub max: Float,
+    /// Number of pixels per bin, all bins have the same width.
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// Width of a single bin.
+    pub fn bin_width(&self) -> Float {
+        self.max / self.counts.len() as Float
+    }
+
+    /// Upper end of the bin containing the `p`-th percentile, p in [0,1].
+    pub fn percentile(&self, p: Float) -> Float {
+        let total: usize = self.counts.iter().sum();
+        let target = (p.clamp(0.0, 1.0) * total as Float).ceil() as usize;
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target.max(1) {
+                return (i + 1) as Float * self.bin_width();
+            }
+        }
+
+        self.max
+    }
+}
+
+/// Glyphs of `Canvas::draw_text`, 3x5 pixels with the top row in the highest bits.
+const FONT: [(char, u16); 48] = [
+    ('0', 0b111101101101111),
+    ('1', 0b010110010010111),
+    ('2', 0b111001111100111),
+    ('3', 0b111001111001111),
+    ('4', 0b101101111001001),
+    ('5', 0b111100111001111),
+    ('6', 0b111100111101111),
+    ('7', 0b111001010010010),
+    ('8', 0b111101111101111),
+    ('9', 0b111101111001111),
+    ('A', 0b010101111101101),
+    ('B', 0b110101110101110),
+    ('C', 0b011100100100011),
+    ('D', 0b110101101101110),
+    ('E', 0b111100110100111),
+    ('F', 0b111100110100100),
+    ('G', 0b011100101101011),
+    ('H', 0b101101111101101),
+    ('I', 0b111010010010111),
+    ('J', 0b001001001101010),
+    ('K', 0b101101110101101),
+    ('L', 0b100100100100111),
+    ('M', 0b101111111101101),
+    ('N', 0b110101101101101),
+    ('O', 0b010101101101010),
+    ('P', 0b110101110100100),
+    ('Q', 0b010101101110011),
+    ('R', 0b110101110101101),
+    ('S', 0b011100010001110),
+    ('T', 0b111010010010010),
+    ('U', 0b101101101101111),
+    ('V', 0b101101101101010),
+    ('W', 0b101101111111101),
+    ('X', 0b101101010101101),
+    ('Y', 0b101101010010010),
+    ('Z', 0b111001010100111),
+    (' ', 0b000000000000000),
+    ('.', 0b000000000000010),
+    (',', 0b000000000010100),
+    (':', 0b000010000010000),
+    ('-', 0b000000111000000),
+    ('+', 0b000010111010000),
+    ('/', 0b001001010100100),
+    ('%', 0b101001010100101),
+    ('(', 0b010100100100010),
+    (')', 0b010001001001010),
+    ('=', 0b000111000111000),
+    ('?', 0b110001010000010),
+];
+
+/// Width and height of a glyph of `FONT`.
+const GLYPH_SIZE: (usize, usize) = (3, 5);
+
+/// Reasons a PPM image can not be read.
 #[derive(Debug)]
+pub enum PpmError {
+    /// The input could not be read.
+    Io(io::Error),
+
+    /// The input is not a P3 or P6 image.
+    Format(String),
+}
+
+impl fmt::Display for PpmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PpmError::Io(why) => write!(f, "Could not read PPM: {}", why),
+            PpmError::Format(message) => write!(f, "Invalid PPM: {}", message),
+        }
+    }
+}
+
+impl Error for PpmError {}
+
+impl From<io::Error> for PpmError {
+    fn from(why: io::Error) -> Self {
+        PpmError::Io(why)
+    }
+}
+
+/// Reads the numbers of a PPM, skipping whitespace and comments.
+struct PpmTokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl PpmTokens<'_> {
+    fn skip_space(&mut self) {
+        while let Some(&b) = self.data.get(self.pos) {
+            if b == b'#' {
+                while self.data.get(self.pos).is_some_and(|&b| b != b'\n') {
+                    self.pos += 1;
+                }
+            } else if b.is_ascii_whitespace() {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// The next number, `what` names it in errors.
+    fn number(&mut self, what: &str) -> Result<usize, PpmError> {
+        self.skip_space();
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.data[start..self.pos])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| PpmError::Format(format!("expected {}", what)))
+    }
+}
+
+/// How `Canvas::resize` samples the source pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ResizeFilter {
+    /// Closest source pixel, keeps hard edges, e.g. for pixel art or masks.
+    Nearest,
+    /// Weighted mean of the four closest source pixels.
+    Bilinear,
+}
+
+/// How the linear colors of a canvas are written to 8 bit images.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub enum ColorEncoding {
+    /// Components as they are, dark on screen unless the lights are bright.
+    #[default]
+    Linear,
+    /// Encoded with the sRGB curve like `RGB::to_srgb`, as image viewers expect.
+    Srgb,
+}
+
+/// Canvas object
+#[derive(Debug, Clone)]
 pub struct Canvas {
     /// Width of the Canvas.
     pub width: usize,
     /// Height of the Canvas.
     pub height: usize,
-    /// Pixels of the Canvas. TODO Avoid heap allocations!
-    pub pixels: Vec<RGB>,
+    /// Pixels of the Canvas, row by row from the top. TODO Avoid heap allocations!
+    pixels: Vec<RGB>,
+    /// Coverage of each pixel, from 0 for transparent to 1 for opaque.
+    /// None for opaque canvases, most operations create those.
+    alpha: Option<Vec<Float>>,
+    /// Encoding of the PPM and PNG output.
+    encoding: ColorEncoding,
 }
 
 impl Canvas {
@@ -20,6 +201,8 @@ impl Canvas {
             width,
             height,
             pixels: vec![BLACK; height * width],
+            alpha: None,
+            encoding: ColorEncoding::Linear,
         }
     }
 
@@ -28,47 +211,801 @@ impl Canvas {
         self.pixels[i] = color;
     }
 
+    /// Does the canvas carry an alpha channel?
+    pub fn has_alpha(&self) -> bool {
+        self.alpha.is_some()
+    }
+
+    /// Coverage of the given pixel, 1 on canvases without alpha channel.
+    pub fn alpha_at(&self, x: usize, y: usize) -> Float {
+        let i = x + y * self.width;
+        self.alpha.as_ref().map_or(1.0, |alpha| alpha[i])
+    }
+
+    /// Set the coverage of the given pixel, adding an opaque alpha channel first if needed.
+    pub fn set_alpha(&mut self, x: usize, y: usize, alpha: Float) {
+        let i = x + y * self.width;
+        let len = self.pixels.len();
+        self.alpha.get_or_insert_with(|| vec![1.0; len])[i] = alpha;
+    }
+
+    /// Drop the alpha channel, all pixels become opaque.
+    pub fn remove_alpha(&mut self) {
+        self.alpha = None;
+    }
+
+    /// Encoding of the PPM and PNG output, linear by default.
+    pub fn encoding(&self) -> ColorEncoding {
+        self.encoding
+    }
+
+    /// Encode the PPM and PNG output with `encoding`, e.g. `ColorEncoding::Srgb`
+    /// for images that look right in viewers. EXR files are always linear.
+    pub fn set_encoding(&mut self, encoding: ColorEncoding) {
+        self.encoding = encoding;
+    }
+
+    /// 8 bit components of `color` in the output encoding.
+    fn encode(&self, color: RGB) -> [u8; 3] {
+        match self.encoding {
+            ColorEncoding::Linear => color.to_u8(),
+            ColorEncoding::Srgb => color.to_u8_srgb(),
+        }
+    }
+
+    /// The pixels as RGBA bytes, row by row from the top, clamped and encoded
+    /// like `to_ppm`. Pixels are opaque unless the canvas has an alpha channel.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .enumerate()
+            .flat_map(|(i, p)| {
+                let [r, g, b] = self.encode(*p);
+                let a = self.alpha.as_ref().map_or(255, |alpha| {
+                    (alpha[i].clamp(0.0, 1.0) * 255.0).round() as u8
+                });
+                [r, g, b, a]
+            })
+            .collect()
+    }
+
+    /// Plain PPM of the clamped colors, see `set_encoding`.
     pub fn to_ppm(&self) -> String {
         let mut buffer = ["P3", &format!("{} {}", self.width, self.height), "255"].join("\n");
         buffer.push('\n');
 
-        let mut col_counter = 0;
+        // lines are wrapped within a row, so rows are encoded independently
+        for chunk in self.encode_rows(|y, out| self.ppm_row(y, out)) {
+            buffer += &chunk;
+        }
+        buffer.push('\n');
+
+        buffer
+    }
+
+    /// Write the PPM of `to_ppm` row by row, without holding all of it in memory.
+    pub fn write_ppm<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write!(out, "P3\n{} {}\n255\n", self.width, self.height)?;
+        let mut row = String::new();
         for y in 0..self.height {
-            for x in 0..self.width {
-                let pixel = self.pixel_at(x, y);
+            row.clear();
+            self.ppm_row(y, &mut row);
+            out.write_all(row.as_bytes())?;
+        }
+        out.write_all(b"\n")?;
 
-                for c in pixel.rgb_string_array().iter() {
-                    if col_counter + c.len() + 1 > MAXIMUM_PPM_LINE_LENGTH {
-                        buffer += "\n";
-                        col_counter = 0;
-                    }
-                    if col_counter > 0 {
-                        buffer += " ";
-                    }
-                    buffer += c;
-                    col_counter += c.len() + 1;
+        out.flush()
+    }
+
+    /// Write the image to a PPM file, see `write_ppm`.
+    pub fn save_ppm<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_ppm(BufWriter::new(File::create(path)?))
+    }
+
+    /// Encode the image as PNG with the clamped colors of `to_ppm` and the alpha
+    /// channel, if there is one. Blocks of rows are compressed on all threads,
+    /// the bytes are the same on every machine.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: Write>(&self, out: W) -> io::Result<()> {
+        self.write_png_with_text(out, "")
+    }
+
+    /// PNG of `write_png` with each `key: value` line of `text` as text chunk,
+    /// e.g. the `FrameMetadata` of a render. Lines without a key are stored as `Comment`.
+    #[cfg(feature = "png")]
+    pub fn write_png_with_text<W: Write>(&self, mut out: W, text: &str) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "PNG images must not be empty",
+            ));
+        }
+        let text: Vec<(&str, &str)> = text
+            .lines()
+            .map(|line| match line.split_once(": ") {
+                // keywords are 1 to 79 characters without surrounding spaces
+                Some((key, value)) if !key.is_empty() && key.len() < 80 && key.trim() == key => {
+                    (key, value)
                 }
+                _ => ("Comment", line),
+            })
+            .collect();
+        let png = match self.alpha {
+            Some(_) => crate::png::encode(self.width, self.height, 4, &self.to_rgba8(), &text),
+            None => {
+                let rgb: Vec<u8> = self.pixels.iter().flat_map(|p| self.encode(*p)).collect();
+                crate::png::encode(self.width, self.height, 3, &rgb, &text)
+            }
+        };
+        out.write_all(&png)?;
+        out.flush()
+    }
+
+    /// Write the image to a PNG file, see `write_png`.
+    #[cfg(feature = "png")]
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write the image to a file, the format is picked by the extension:
+    /// `ppm`, `exr` keeping the colors beyond 1.0 and the alpha channel,
+    /// or `png` with alpha channel if the `png` feature is enabled.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("ppm") => self.save_ppm(path),
+            Some("exr") => {
+                let mut exr = Exr::new(self.width, self.height);
+                exr.add_canvas("", self);
+                exr.save(path)
             }
+            #[cfg(feature = "png")]
+            Some("png") => self.save_png(path),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("Unknown image format of {}", path.display()),
+            )),
+        }
+    }
+
+    /// Read a plain (P3) or binary (P6) PPM image, e.g. written by `to_ppm`.
+    /// Components are divided by the maximum value of the file and taken as
+    /// linear, so reading a written canvas gives back the clamped colors.
+    pub fn from_ppm<R: Read>(mut reader: R) -> Result<Canvas, PpmError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        let binary = match data.get(..2) {
+            Some(b"P3") => false,
+            Some(b"P6") => true,
+            _ => return Err(PpmError::Format("expected P3 or P6".to_string())),
+        };
+        let mut tokens = PpmTokens {
+            data: &data,
+            pos: 2,
+        };
+        let width = tokens.number("width")?;
+        let height = tokens.number("height")?;
+        let maxval = tokens.number("maximum value")?;
+        if !(1..=65535).contains(&maxval) {
+            return Err(PpmError::Format(format!(
+                "maximum value {} not in 1..=65535",
+                maxval
+            )));
+        }
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(3))
+            .ok_or_else(|| PpmError::Format("image too large".to_string()))?;
+
+        let mut values = Vec::with_capacity(count.min(data.len()));
+        if binary {
+            // exactly one whitespace separates the header from the pixels
+            let start = tokens.pos + 1;
+            let size = if maxval < 256 { 1 } else { 2 };
+            let raster = data
+                .get(start..)
+                .filter(|r| r.len() >= count * size)
+                .ok_or_else(|| PpmError::Format("unexpected end of pixel data".to_string()))?;
+            values.extend(raster.chunks_exact(size).take(count).map(|v| match v {
+                [b] => usize::from(*b),
+                _ => usize::from(v[0]) << 8 | usize::from(v[1]),
+            }));
+        } else {
+            for _ in 0..count {
+                values.push(tokens.number("pixel value")?);
+            }
+        }
+
+        if let Some(v) = values.iter().find(|v| **v > maxval) {
+            return Err(PpmError::Format(format!(
+                "pixel value {} above the maximum {}",
+                v, maxval
+            )));
+        }
+        let scale = maxval as Float;
+        Ok(Canvas {
+            width,
+            height,
+            pixels: values
+                .chunks_exact(3)
+                .map(|c| RGB::new(c[0] as Float, c[1] as Float, c[2] as Float) * (1.0 / scale))
+                .collect(),
+            alpha: None,
+            encoding: ColorEncoding::Linear,
+        })
+    }
+
+    /// Read a PPM file, see `from_ppm`.
+    pub fn load_ppm<P: AsRef<Path>>(path: P) -> Result<Canvas, PpmError> {
+        Canvas::from_ppm(BufReader::new(File::open(path)?))
+    }
+
+    /// PPM with each line of `comments` as comment in the header,
+    /// e.g. the `FrameMetadata` of a render. Readers skip comments.
+    pub fn to_ppm_with_comments(&self, comments: &str) -> String {
+        let ppm = self.to_ppm();
+        let (magic, rest) = ppm.split_at(3);
+        let mut buffer = String::with_capacity(ppm.len() + comments.len());
+        buffer += magic;
+        for line in comments.lines() {
+            buffer += "# ";
+            buffer += line;
             buffer.push('\n');
-            col_counter = 0;
         }
-        buffer.push('\n');
+        buffer += rest;
 
         buffer
     }
 
+    /// Append row `y` of the PPM body, wrapped at `MAXIMUM_PPM_LINE_LENGTH`.
+    fn ppm_row(&self, y: usize, buffer: &mut String) {
+        let mut col_counter = 0;
+        for x in 0..self.width {
+            let pixel = self.encode(self.pixel_at(x, y));
+
+            for c in pixel.map(|c| c.to_string()).iter() {
+                if col_counter + c.len() + 1 > MAXIMUM_PPM_LINE_LENGTH {
+                    *buffer += "\n";
+                    col_counter = 0;
+                }
+                if col_counter > 0 {
+                    *buffer += " ";
+                }
+                *buffer += c;
+                col_counter += c.len() + 1;
+            }
+        }
+        buffer.push('\n');
+    }
+
+    /// Encode the rows with `encode` in chunks of consecutive rows, one chunk
+    /// per thread. The chunks are returned in order, so the output is the same
+    /// as encoding row by row.
+    fn encode_rows<F: Fn(usize, &mut String) + Sync>(&self, encode: F) -> Vec<String> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        // wasm32 has no threads without JavaScript
+        #[cfg(target_arch = "wasm32")]
+        let threads = 1;
+
+        map_chunks(self.height, self.height.div_ceil(threads).max(1), |rows| {
+            let mut out = String::new();
+            for y in rows {
+                encode(y, &mut out);
+            }
+            out
+        })
+    }
+
+    /// Histogram of the pixel luminance with `bins` bins from 0 to the brightest pixel.
+    /// Negative luminance is counted in the first bin.
+    pub fn luminance_histogram(&self, bins: usize) -> Histogram {
+        assert!(bins > 0, "A histogram needs at least one bin!");
+        let max = self.pixels.iter().map(RGB::luminance).fold(0.0, Float::max);
+        let mut counts = vec![0; bins];
+        for pixel in self.pixels.iter() {
+            let bin = if max > 0.0 {
+                (pixel.luminance() / max * bins as Float).max(0.0) as usize
+            } else {
+                0
+            };
+            counts[bin.min(bins - 1)] += 1;
+        }
+
+        Histogram { max, counts }
+    }
+
+    /// Exposure value (in stops) that maps the `p`-th percentile (p in [0,1]) of the
+    /// pixel luminance to `MID_GRAY`. Scale the colors by `2^ev` before tone mapping.
+    /// A black canvas needs no exposure and returns 0.
+    pub fn auto_exposure(&self, p: Float) -> Float {
+        let mut luminance: Vec<Float> = self.pixels.iter().map(RGB::luminance).collect();
+        if luminance.is_empty() {
+            return 0.0;
+        }
+        luminance.sort_by(Float::total_cmp);
+        let i = (p.clamp(0.0, 1.0) * (luminance.len() - 1) as Float).round() as usize;
+        if luminance[i] <= 0.0 {
+            return 0.0;
+        }
+
+        (MID_GRAY / luminance[i]).log2()
+    }
+
+    /// Pixel-wise minimum of two canvases of the same size.
+    pub fn min(&self, other: &Canvas) -> Canvas {
+        self.zip(other, |a, b| a.min(b))
+    }
+
+    /// Pixel-wise maximum of two canvases of the same size.
+    pub fn max(&self, other: &Canvas) -> Canvas {
+        self.zip(other, |a, b| a.max(b))
+    }
+
+    /// Mean of canvases of the same size, e.g. passes of the same frame
+    /// rendered with different seeds.
+    pub fn average(canvases: &[Canvas]) -> Canvas {
+        let (first, rest) = canvases
+            .split_first()
+            .expect("Cannot average zero canvases!");
+        let sum = rest.iter().fold(first.clone(), |sum, c| &sum + c);
+
+        &sum * (1.0 / canvases.len() as Float)
+    }
+
+    /// Combine two canvases of the same size pixel by pixel.
+    fn zip<F: Fn(RGB, RGB) -> RGB>(&self, other: &Canvas, f: F) -> Canvas {
+        assert!(
+            self.width == other.width && self.height == other.height,
+            "Canvases must have the same size!"
+        );
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self
+                .pixels
+                .iter()
+                .zip(other.pixels.iter())
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+            alpha: None,
+            encoding: self.encoding,
+        }
+    }
+
     /// Return the color at the given pixel.
     pub fn pixel_at(&self, x: usize, y: usize) -> RGB {
         let i = x + y * self.width;
 
         self.pixels[i]
     }
+
+    /// All pixels, row by row from the top.
+    pub fn pixels(&self) -> std::slice::Iter<'_, RGB> {
+        self.pixels.iter()
+    }
+
+    /// All pixels, row by row from the top.
+    pub fn pixels_mut(&mut self) -> std::slice::IterMut<'_, RGB> {
+        self.pixels.iter_mut()
+    }
+
+    /// Each row as slice, from the top.
+    pub fn rows(&self) -> std::slice::Chunks<'_, RGB> {
+        self.pixels.chunks(self.width.max(1))
+    }
+
+    /// Each row as mutable slice, from the top.
+    pub fn rows_mut(&mut self) -> std::slice::ChunksMut<'_, RGB> {
+        self.pixels.chunks_mut(self.width.max(1))
+    }
+
+    /// All pixels with their position, `(x, y, color)`.
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (usize, usize, &RGB)> {
+        let width = self.width;
+        self.pixels
+            .iter()
+            .enumerate()
+            .map(move |(i, p)| (i % width, i / width, p))
+    }
+
+    /// All pixels with their position, `(x, y, color)`.
+    pub fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (usize, usize, &mut RGB)> {
+        let width = self.width;
+        self.pixels
+            .iter_mut()
+            .enumerate()
+            .map(move |(i, p)| (i % width, i / width, p))
+    }
+
+    /// Each row as mutable slice, processed in parallel by rayon,
+    /// e.g. with `.enumerate().for_each(|(y, row)| ...)`.
+    #[cfg(feature = "rayon")]
+    pub fn par_rows_mut(&mut self) -> rayon::slice::ChunksMut<'_, RGB> {
+        use rayon::slice::ParallelSliceMut;
+        self.pixels.par_chunks_mut(self.width.max(1))
+    }
+}
+
+/// Raster drawing, e.g. to overlay labels or tile boundaries on a render.
+/// Coordinates may lie outside of the canvas, only the visible part is drawn.
+impl Canvas {
+    /// Set every pixel to `color`.
+    pub fn fill(&mut self, color: RGB) {
+        self.pixels.fill(color);
+    }
+
+    /// Set a pixel if it lies on the canvas.
+    fn plot(&mut self, x: isize, y: isize, color: RGB) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.write_pixel(x as usize, y as usize, color);
+        }
+    }
+
+    /// Line from (x0, y0) to (x1, y1), both ends included (Bresenham).
+    pub fn draw_line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, color: RGB) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+        loop {
+            self.plot(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Outline of the `width` x `height` rectangle with the top left corner at (x, y).
+    pub fn draw_rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: RGB) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (x1, y1) = (x + width as isize - 1, y + height as isize - 1);
+        self.draw_line(x, y, x1, y, color);
+        self.draw_line(x, y1, x1, y1, color);
+        self.draw_line(x, y, x, y1, color);
+        self.draw_line(x1, y, x1, y1, color);
+    }
+
+    /// Filled `width` x `height` rectangle with the top left corner at (x, y).
+    pub fn fill_rect(&mut self, x: isize, y: isize, width: usize, height: usize, color: RGB) {
+        for py in y..y + height as isize {
+            for px in x..x + width as isize {
+                self.plot(px, py, color);
+            }
+        }
+    }
+
+    /// Outline of the circle around (cx, cy) (midpoint algorithm).
+    pub fn draw_circle(&mut self, cx: isize, cy: isize, radius: usize, color: RGB) {
+        let (mut x, mut y) = (radius as isize, 0);
+        let mut err = 1 - x;
+        while x >= y {
+            for (px, py) in [
+                (x, y),
+                (y, x),
+                (-y, x),
+                (-x, y),
+                (-x, -y),
+                (-y, -x),
+                (y, -x),
+                (x, -y),
+            ] {
+                self.plot(cx + px, cy + py, color);
+            }
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Filled circle around (cx, cy).
+    pub fn fill_circle(&mut self, cx: isize, cy: isize, radius: usize, color: RGB) {
+        let r = radius as isize;
+        for py in -r..=r {
+            for px in -r..=r {
+                if px * px + py * py <= r * r {
+                    self.plot(cx + px, cy + py, color);
+                }
+            }
+        }
+    }
+
+    /// Stamp `text` with the top left corner at (x, y), each glyph pixel becomes
+    /// a `scale` x `scale` block. The built-in font has digits, letters (drawn
+    /// upper case) and a little punctuation, other characters are skipped.
+    /// A line break starts a new line below the first character.
+    pub fn draw_text(&mut self, x: isize, y: isize, text: &str, scale: usize, color: RGB) {
+        let (glyph_width, glyph_height) = GLYPH_SIZE;
+        let scale = scale.max(1) as isize;
+        let (mut pen_x, mut pen_y) = (x, y);
+        for ch in text.chars() {
+            if ch == '\n' {
+                pen_x = x;
+                pen_y += (glyph_height as isize + 1) * scale;
+                continue;
+            }
+            let ch = ch.to_ascii_uppercase();
+            if let Some((_, bits)) = FONT.iter().find(|(c, _)| *c == ch) {
+                for row in 0..glyph_height {
+                    for col in 0..glyph_width {
+                        let bit = (glyph_height - 1 - row) * glyph_width + (glyph_width - 1 - col);
+                        if bits & (1 << bit) != 0 {
+                            self.fill_rect(
+                                pen_x + col as isize * scale,
+                                pen_y + row as isize * scale,
+                                scale as usize,
+                                scale as usize,
+                                color,
+                            );
+                        }
+                    }
+                }
+            }
+            pen_x += (glyph_width as isize + 1) * scale;
+        }
+    }
+}
+
+/// Geometric post-processing, each operation returns a new canvas
+/// and keeps the alpha channel.
+impl Canvas {
+    /// Canvas of the given size with the color of each pixel from `f(x, y)`.
+    fn from_fn<F: Fn(usize, usize) -> RGB>(width: usize, height: usize, f: F) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| f(x, y))
+                .collect(),
+            alpha: None,
+            encoding: ColorEncoding::Linear,
+        }
+    }
+
+    /// Canvas of the given size, each pixel copied from the pixel `f(x, y)`.
+    fn remap<F>(&self, width: usize, height: usize, f: F) -> Canvas
+    where
+        F: Fn(usize, usize) -> (usize, usize),
+    {
+        let positions: Vec<(usize, usize)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| f(x, y))
+            .collect();
+        Canvas {
+            width,
+            height,
+            pixels: positions
+                .iter()
+                .map(|&(x, y)| self.pixel_at(x, y))
+                .collect(),
+            alpha: self.has_alpha().then(|| {
+                positions
+                    .iter()
+                    .map(|&(x, y)| self.alpha_at(x, y))
+                    .collect()
+            }),
+            encoding: self.encoding,
+        }
+    }
+
+    /// The `width` x `height` region with the top left corner at (x, y).
+    /// Panics if the region does not lie on the canvas.
+    pub fn crop(&self, x: usize, y: usize, width: usize, height: usize) -> Canvas {
+        assert!(
+            x + width <= self.width && y + height <= self.height,
+            "Crop must lie on the canvas!"
+        );
+        self.remap(width, height, |px, py| (x + px, y + py))
+    }
+
+    /// Mirror left and right.
+    pub fn flip_horizontal(&self) -> Canvas {
+        self.remap(self.width, self.height, |x, y| (self.width - 1 - x, y))
+    }
+
+    /// Mirror top and bottom.
+    pub fn flip_vertical(&self) -> Canvas {
+        self.remap(self.width, self.height, |x, y| (x, self.height - 1 - y))
+    }
+
+    /// Rotate clockwise by 90 degrees, width and height swap.
+    /// Three turns rotate counterclockwise.
+    pub fn rotate90(&self) -> Canvas {
+        self.remap(self.height, self.width, |x, y| (y, self.height - 1 - x))
+    }
+
+    /// Scale to `width` x `height`, the aspect ratio may change.
+    pub fn resize(&self, width: usize, height: usize, filter: ResizeFilter) -> Canvas {
+        if self.pixels.is_empty() {
+            return Canvas::new(width, height);
+        }
+        let mut out = self.resize_colors(width, height, filter);
+        if let Some(alpha) = &self.alpha {
+            // filter the coverage like a gray image
+            let gray = Canvas {
+                width: self.width,
+                height: self.height,
+                pixels: alpha.iter().map(|a| RGB::new(*a, *a, *a)).collect(),
+                alpha: None,
+                encoding: ColorEncoding::Linear,
+            };
+            let scaled = gray.resize_colors(width, height, filter);
+            out.alpha = Some(scaled.pixels.iter().map(|p| p.red).collect());
+        }
+        out.encoding = self.encoding;
+        out
+    }
+
+    /// The colors of `resize`.
+    fn resize_colors(&self, width: usize, height: usize, filter: ResizeFilter) -> Canvas {
+        let sx = self.width as Float / width as Float;
+        let sy = self.height as Float / height as Float;
+        Canvas::from_fn(width, height, |x, y| {
+            // pixel centers of the target in source pixel coordinates
+            let u = (x as Float + 0.5) * sx - 0.5;
+            let v = (y as Float + 0.5) * sy - 0.5;
+            let max_x = (self.width - 1) as Float;
+            let max_y = (self.height - 1) as Float;
+            match filter {
+                ResizeFilter::Nearest => self.pixel_at(
+                    u.round().clamp(0.0, max_x) as usize,
+                    v.round().clamp(0.0, max_y) as usize,
+                ),
+                ResizeFilter::Bilinear => {
+                    let (u, v) = (u.clamp(0.0, max_x), v.clamp(0.0, max_y));
+                    let (x0, y0) = (u.floor() as usize, v.floor() as usize);
+                    let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+                    let (fx, fy) = (u - x0 as Float, v - y0 as Float);
+                    let top = self.pixel_at(x0, y0) * (1.0 - fx) + self.pixel_at(x1, y0) * fx;
+                    let bottom = self.pixel_at(x0, y1) * (1.0 - fx) + self.pixel_at(x1, y1) * fx;
+                    top * (1.0 - fy) + bottom * fy
+                }
+            }
+        })
+    }
+}
+
+/// Map the ranges of `size` consecutive items out of `len` with `f` on all threads,
+/// the results are returned in order.
+pub(crate) fn map_chunks<T, F>(len: usize, size: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(std::ops::Range<usize>) -> T + Sync,
+{
+    let ranges = (0..len)
+        .step_by(size.max(1))
+        .map(|start| start..(start + size).min(len));
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let ranges: Vec<_> = ranges.collect();
+        let f = &f;
+        std::thread::scope(|scope| {
+            // thread t takes the ranges t, t + threads, ...
+            let handles: Vec<_> = (0..threads.min(ranges.len()))
+                .map(|t| {
+                    let ranges = &ranges;
+                    scope.spawn(move || {
+                        ranges
+                            .iter()
+                            .skip(t)
+                            .step_by(threads)
+                            .map(|r| f(r.clone()))
+                            .collect::<Vec<T>>()
+                    })
+                })
+                .collect();
+            let mut results: Vec<_> = handles
+                .into_iter()
+                // a panic in `f` goes on in the caller with its own message
+                .map(|h| {
+                    h.join()
+                        .unwrap_or_else(|e| std::panic::resume_unwind(e))
+                        .into_iter()
+                })
+                .collect();
+            (0..ranges.len())
+                .map(|i| {
+                    let n = results.len();
+                    results[i % n].next().expect("Every range has a result")
+                })
+                .collect()
+        })
+    }
+
+    // wasm32 has no threads without JavaScript
+    #[cfg(target_arch = "wasm32")]
+    {
+        ranges.map(f).collect()
+    }
+}
+
+/// 8 bit pixels, clamped and encoded like `to_ppm`.
+#[cfg(feature = "image")]
+impl From<&Canvas> for image::RgbImage {
+    fn from(c: &Canvas) -> Self {
+        let bytes = c.pixels.iter().flat_map(|p| c.encode(*p)).collect();
+        image::RgbImage::from_raw(c.width as u32, c.height as u32, bytes)
+            .expect("Buffer should match the canvas size")
+    }
+}
+
+/// 8 bit pixels like `to_rgba8`.
+#[cfg(feature = "image")]
+impl From<&Canvas> for image::RgbaImage {
+    fn from(c: &Canvas) -> Self {
+        image::RgbaImage::from_raw(c.width as u32, c.height as u32, c.to_rgba8())
+            .expect("Buffer should match the canvas size")
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<Canvas> for image::RgbImage {
+    fn from(c: Canvas) -> Self {
+        (&c).into()
+    }
+}
+
+#[cfg(feature = "image")]
+impl Canvas {
+    /// Canvas of an image, the inverse of the conversion to `image::RgbImage`.
+    /// Components are taken as linear, see `RGB::from_u8`.
+    pub fn from_image(img: &image::RgbImage) -> Canvas {
+        Canvas::from_fn(img.width() as usize, img.height() as usize, |x, y| {
+            let [r, g, b] = img.get_pixel(x as u32, y as u32).0;
+            RGB::from_u8(r, g, b)
+        })
+    }
+}
+
+impl Add for &Canvas {
+    type Output = Canvas;
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip(rhs, |a, b| a + b)
+    }
+}
+
+impl Mul<Float> for &Canvas {
+    type Output = Canvas;
+    fn mul(self, rhs: Float) -> Self::Output {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().map(|c| *c * rhs).collect(),
+            alpha: self.alpha.clone(),
+            encoding: self.encoding,
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{BLACK, RED};
+    use crate::{BLACK, RED, WHITE};
 
     #[test]
     fn create_canvas() {
@@ -100,6 +1037,18 @@ mod test {
         c.write_pixel(109, 10, red);
     }
 
+    #[test]
+    fn rgba8_canvas() {
+        let mut c = Canvas::new(2, 2);
+        c.write_pixel(1, 0, RGB::new(1.5, 0.5, 0.0));
+        c.write_pixel(0, 1, RED);
+
+        assert_eq!(
+            c.to_rgba8(),
+            vec![0, 0, 0, 255, 255, 127, 0, 255, 255, 0, 0, 255, 0, 0, 0, 255]
+        );
+    }
+
     #[test]
     fn ppm_construct_canvas() {
         let mut c = Canvas::new(5, 3);
@@ -129,4 +1078,452 @@ mod test {
 
         assert_eq!(ppm, correct);
     }
+
+    #[test]
+    fn luminance_histogram_canvas() {
+        let mut c = Canvas::new(4, 1);
+        c.write_pixel(1, 0, RGB::new(0.5, 0.5, 0.5));
+        c.write_pixel(2, 0, RGB::new(2.0, 2.0, 2.0));
+        c.write_pixel(3, 0, RGB::new(4.0, 4.0, 4.0));
+        let h = c.luminance_histogram(4);
+
+        assert!(crate::float_eq(h.max, 4.0));
+        assert_eq!(h.counts, vec![2, 0, 1, 1]);
+        assert!(crate::float_eq(h.percentile(0.5), 1.0));
+        assert!(crate::float_eq(h.percentile(1.0), 4.0));
+    }
+
+    #[test]
+    fn auto_exposure_canvas() {
+        let mut c = Canvas::new(3, 1);
+        c.write_pixel(0, 0, RGB::new(0.1, 0.1, 0.1));
+        c.write_pixel(1, 0, RGB::new(0.72, 0.72, 0.72));
+        c.write_pixel(2, 0, RGB::new(8.0, 8.0, 8.0));
+
+        // the median is two stops above mid gray
+        assert!(crate::float_eq(c.auto_exposure(0.5), -2.0));
+        assert!(crate::float_eq(Canvas::new(2, 2).auto_exposure(0.5), 0.0));
+    }
+
+    #[test]
+    fn arithmetic_canvas() {
+        let mut a = Canvas::new(2, 1);
+        let mut b = Canvas::new(2, 1);
+        a.write_pixel(0, 0, RGB::new(0.2, 0.5, 1.0));
+        b.write_pixel(0, 0, RGB::new(0.4, 0.1, 1.0));
+        b.write_pixel(1, 0, RED);
+
+        assert_eq!((&a + &b).pixel_at(0, 0), RGB::new(0.6, 0.6, 2.0));
+        assert_eq!((&a * 2.0).pixel_at(0, 0), RGB::new(0.4, 1.0, 2.0));
+        assert_eq!(a.min(&b).pixel_at(0, 0), RGB::new(0.2, 0.1, 1.0));
+        assert_eq!(a.max(&b).pixel_at(0, 0), RGB::new(0.4, 0.5, 1.0));
+        assert_eq!(a.max(&b).pixel_at(1, 0), RED);
+
+        let avg = Canvas::average(&[a, b]);
+        assert_eq!(avg.pixel_at(0, 0), RGB::new(0.3, 0.3, 1.0));
+        assert_eq!(avg.pixel_at(1, 0), RGB::new(0.5, 0.0, 0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn arithmetic_size_canvas() {
+        let _ = &Canvas::new(2, 1) + &Canvas::new(1, 2);
+    }
+
+    #[test]
+    fn ppm_many_rows_canvas() {
+        // a row per chunk on machines with enough cores
+        let mut c = Canvas::new(2, 5);
+        for y in 0..c.height {
+            for x in 0..c.width {
+                c.write_pixel(x, y, RGB::new(x as Float, y as Float / 4.0, 0.5));
+            }
+        }
+
+        let expected = "P3\n2 5\n255\n\
+                        0 0 127 255 0 127\n\
+                        0 63 127 255 63 127\n\
+                        0 127 127 255 127 127\n\
+                        0 191 127 255 191 127\n\
+                        0 255 127 255 255 127\n\n";
+        assert_eq!(c.to_ppm(), expected);
+        assert_eq!(Canvas::new(3, 0).to_ppm(), "P3\n3 0\n255\n\n");
+    }
+
+    /// Rows of the canvas, '#' for `color` and '.' for anything else.
+    fn sketch(c: &Canvas, color: RGB) -> Vec<String> {
+        (0..c.height)
+            .map(|y| {
+                (0..c.width)
+                    .map(|x| if c.pixel_at(x, y) == color { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn draw_shapes_canvas() {
+        let mut c = Canvas::new(5, 5);
+        c.fill(RED);
+        assert!(c.pixels.iter().all(|p| *p == RED));
+
+        let mut c = Canvas::new(5, 5);
+        c.draw_line(0, 0, 4, 2, WHITE);
+        c.draw_rect(-1, 3, 3, 5, WHITE);
+        assert_eq!(
+            sketch(&c, WHITE),
+            vec!["#....", ".##..", "...##", "##...", ".#..."]
+        );
+
+        let mut c = Canvas::new(7, 7);
+        c.draw_circle(3, 3, 2, WHITE);
+        assert_eq!(
+            sketch(&c, WHITE),
+            vec![".......", "..###..", ".#...#.", ".#...#.", ".#...#.", "..###..", "......."]
+        );
+        c.fill_circle(3, 3, 1, RED);
+        assert_eq!(c.pixel_at(3, 2), RED);
+        assert_eq!(c.pixel_at(2, 2), BLACK);
+
+        let mut c = Canvas::new(3, 2);
+        c.fill_rect(1, 0, 10, 1, WHITE);
+        assert_eq!(sketch(&c, WHITE), vec![".##", "..."]);
+    }
+
+    #[test]
+    fn draw_text_canvas() {
+        let mut c = Canvas::new(8, 6);
+        c.draw_text(0, 0, "h1?\u{e9}", 1, WHITE);
+        assert_eq!(
+            sketch(&c, WHITE),
+            vec!["#.#..#..", "#.#.##..", "###..#..", "#.#..#..", "#.#.###.", "........"]
+        );
+
+        // scaled glyphs, the second line starts 6 glyph pixels lower
+        let mut c = Canvas::new(6, 22);
+        c.draw_text(0, 0, "-\n.", 2, WHITE);
+        assert_eq!(c.pixel_at(0, 4), WHITE);
+        assert_eq!(c.pixel_at(5, 5), WHITE);
+        assert_eq!(c.pixel_at(3, 21), WHITE);
+        assert_eq!(c.pixel_at(1, 21), BLACK);
+        assert_eq!(c.pixel_at(2, 12), BLACK);
+    }
+
+    /// 3x2 canvas with distinct gray pixels 0..6, row by row.
+    fn numbered() -> Canvas {
+        let mut c = Canvas::new(3, 2);
+        for (i, p) in c.pixels.iter_mut().enumerate() {
+            let v = i as Float;
+            *p = RGB::new(v, v, v);
+        }
+        c
+    }
+
+    fn numbers(c: &Canvas) -> Vec<Float> {
+        c.pixels.iter().map(|p| p.red).collect()
+    }
+
+    #[test]
+    fn transform_canvas() {
+        let c = numbered();
+        let crop = c.crop(1, 0, 2, 2);
+        assert_eq!((crop.width, crop.height), (2, 2));
+        assert_eq!(numbers(&crop), vec![1.0, 2.0, 4.0, 5.0]);
+        assert_eq!(
+            numbers(&c.flip_horizontal()),
+            vec![2.0, 1.0, 0.0, 5.0, 4.0, 3.0]
+        );
+        assert_eq!(
+            numbers(&c.flip_vertical()),
+            vec![3.0, 4.0, 5.0, 0.0, 1.0, 2.0]
+        );
+
+        let r = c.rotate90();
+        assert_eq!((r.width, r.height), (2, 3));
+        assert_eq!(numbers(&r), vec![3.0, 0.0, 4.0, 1.0, 5.0, 2.0]);
+        let back = r.rotate90().rotate90().rotate90();
+        assert_eq!(numbers(&back), numbers(&c));
+    }
+
+    #[test]
+    #[should_panic]
+    fn crop_outside_canvas() {
+        numbered().crop(2, 0, 2, 1);
+    }
+
+    #[test]
+    fn resize_canvas() {
+        let c = numbered();
+        let same = c.resize(3, 2, ResizeFilter::Bilinear);
+        assert_eq!(numbers(&same), numbers(&c));
+
+        let big = c.resize(6, 4, ResizeFilter::Nearest);
+        assert_eq!(big.pixel_at(5, 3), c.pixel_at(2, 1));
+        assert_eq!(big.pixel_at(0, 0), c.pixel_at(0, 0));
+
+        // halfway between the pixel centers of the rows
+        let row = c.resize(3, 1, ResizeFilter::Bilinear);
+        assert_eq!(numbers(&row), vec![1.5, 2.5, 3.5]);
+        let one = c.resize(1, 1, ResizeFilter::Bilinear);
+        assert_eq!(one.pixel_at(0, 0), RGB::new(2.5, 2.5, 2.5));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_canvas() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(2, 1, RGB::new(1.5, 0.5, -0.5));
+        let img = image::RgbImage::from(&c);
+
+        assert_eq!(img.dimensions(), (3, 2));
+        assert_eq!(img.get_pixel(2, 1).0, [255, 127, 0]);
+        let back = Canvas::from_image(&img);
+        assert_eq!((back.width, back.height), (3, 2));
+        assert_eq!(back.pixel_at(2, 1), RGB::from_u8(255, 127, 0));
+        assert_eq!(image::RgbImage::from(back), img);
+    }
+
+    #[test]
+    fn ppm_comments_canvas() {
+        let c = Canvas::new(2, 1);
+        let ppm = c.to_ppm_with_comments("scene: 1\nrays: 2");
+        assert!(ppm.starts_with("P3\n# scene: 1\n# rays: 2\n2 1\n255\n"));
+        assert!(ppm.ends_with(&c.to_ppm()[3..]));
+    }
+
+    #[test]
+    fn iterate_canvas() {
+        let mut c = numbered();
+        assert_eq!(c.pixels().len(), 6);
+        assert_eq!(c.rows().count(), 2);
+        assert_eq!(c.rows().nth(1).unwrap()[0], RGB::new(3.0, 3.0, 3.0));
+
+        let (x, y, p) = c.enumerate_pixels().nth(4).unwrap();
+        assert_eq!((x, y, *p), (1, 1, RGB::new(4.0, 4.0, 4.0)));
+
+        for (x, y, p) in c.enumerate_pixels_mut() {
+            *p = RGB::new(x as Float, y as Float, 0.0);
+        }
+        assert_eq!(c.pixel_at(2, 1), RGB::new(2.0, 1.0, 0.0));
+        for row in c.rows_mut() {
+            row[0] = WHITE;
+        }
+        *c.pixels_mut().last().unwrap() = RED;
+        assert_eq!(c.pixel_at(0, 1), WHITE);
+        assert_eq!(c.pixel_at(2, 1), RED);
+        assert_eq!(Canvas::new(0, 0).rows().count(), 0);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_rows_canvas() {
+        use rayon::prelude::*;
+
+        let mut c = Canvas::new(3, 4);
+        c.par_rows_mut().enumerate().for_each(|(y, row)| {
+            for p in row {
+                *p = RGB::new(y as Float, 0.0, 0.0);
+            }
+        });
+        assert_eq!(c.pixel_at(2, 3), RGB::new(3.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn save_canvas() {
+        let mut c = Canvas::new(30, 3);
+        c.write_pixel(29, 2, RGB::new(1.0, 0.5, 0.0));
+        let mut out = Vec::new();
+        c.write_ppm(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), c.to_ppm());
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("rtracer_save_{}.PPM", std::process::id()));
+        c.save(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), c.to_ppm());
+        std::fs::remove_file(&path).unwrap();
+
+        let path = dir.join(format!("rtracer_save_{}.exr", std::process::id()));
+        c.save(&path).unwrap();
+        assert!(std::fs::metadata(&path).unwrap().len() > 30 * 3 * 12);
+        std::fs::remove_file(&path).unwrap();
+
+        let err = c.save(dir.join("rtracer_save.gif")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn read_ppm_canvas() {
+        let mut c = Canvas::new(30, 2);
+        c.write_pixel(29, 1, RGB::new(1.0, 0.2, 0.0));
+        c.write_pixel(0, 0, RGB::new(1.5, -0.5, 0.6));
+        let back = Canvas::from_ppm(c.to_ppm_with_comments("a\nb").as_bytes()).unwrap();
+        assert_eq!((back.width, back.height), (30, 2));
+        assert_eq!(back.pixel_at(29, 1), RGB::from_u8(255, 51, 0));
+        assert_eq!(back.pixel_at(0, 0), RGB::from_u8(255, 0, 153));
+        assert_eq!(back.to_ppm(), c.to_ppm());
+
+        // comments and whitespace anywhere in the header, other maximum
+        let plain = "P3# comment\n 2\t1 #two\n\n15\n15 0 0   0 5 15\n";
+        let c = Canvas::from_ppm(plain.as_bytes()).unwrap();
+        assert_eq!(c.pixel_at(0, 0), RED);
+        assert_eq!(c.pixel_at(1, 0), RGB::new(0.0, 1.0 / 3.0, 1.0));
+
+        let mut binary = b"P6\n2 1\n255\n".to_vec();
+        binary.extend_from_slice(&[255, 0, 0, 0, 51, 255]);
+        let c = Canvas::from_ppm(&binary[..]).unwrap();
+        assert_eq!(c.pixel_at(1, 0), RGB::from_u8(0, 51, 255));
+
+        let mut wide = b"P6 1 1 65535\n".to_vec();
+        wide.extend_from_slice(&[0xff, 0xff, 0x80, 0x00, 0, 0]);
+        let c = Canvas::from_ppm(&wide[..]).unwrap();
+        assert_eq!(c.pixel_at(0, 0), RGB::new(1.0, 32768.0 / 65535.0, 0.0));
+    }
+
+    #[test]
+    fn invalid_ppm_canvas() {
+        let invalid = [
+            "P5\n1 1\n255\n0",
+            "P3\n1\n",
+            "P3\n1 1\n0\n0 0 0",
+            "P3\n1 1\n255\n0 0",
+            "P3\n1 1\n255\n0 256 0",
+            "P6\n2 1\n255\n\x01\x02",
+        ];
+        for ppm in invalid {
+            assert!(matches!(
+                Canvas::from_ppm(ppm.as_bytes()),
+                Err(PpmError::Format(_))
+            ));
+        }
+        assert!(matches!(
+            Canvas::load_ppm("/nonexistent/image.ppm"),
+            Err(PpmError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn alpha_canvas() {
+        let mut c = numbered();
+        assert!(!c.has_alpha());
+        assert_eq!(c.alpha_at(1, 1), 1.0);
+        assert_eq!(c.to_rgba8()[3], 255);
+
+        c.set_alpha(2, 0, 0.0);
+        c.set_alpha(0, 1, 0.5);
+        assert!(c.has_alpha());
+        assert_eq!(c.alpha_at(1, 1), 1.0);
+        assert_eq!(&c.to_rgba8()[8..12], &[255, 255, 255, 0]);
+        assert_eq!(c.to_rgba8()[15], 128);
+
+        // geometric operations move the coverage with the colors
+        assert_eq!(c.flip_horizontal().alpha_at(0, 0), 0.0);
+        assert_eq!(c.rotate90().alpha_at(1, 2), 0.0);
+        assert_eq!(c.crop(0, 1, 2, 1).alpha_at(0, 0), 0.5);
+        let half = c.resize(3, 1, ResizeFilter::Bilinear);
+        assert_eq!(half.alpha_at(0, 0), 0.75);
+        assert_eq!((&c * 2.0).alpha_at(2, 0), 0.0);
+
+        c.remove_alpha();
+        assert_eq!(c.alpha_at(2, 0), 1.0);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn png_canvas() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, RED);
+        c.set_alpha(1, 0, 0.0);
+        let img = image::RgbaImage::from(&c);
+        assert_eq!(img.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(img.get_pixel(1, 0).0, [0, 0, 0, 0]);
+
+        let path = std::env::temp_dir().join(format!("rtracer_save_{}.png", std::process::id()));
+        c.save(&path).unwrap();
+        let back = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(back, img);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn large_png_canvas() {
+        // many blocks of rows compressed on different threads
+        let c = Canvas::from_fn(400, 500, |x, y| {
+            RGB::new(
+                x as Float / 400.0,
+                y as Float / 500.0,
+                ((x * y) % 7) as Float / 7.0,
+            )
+        });
+        let mut png = Vec::new();
+        c.write_png(&mut png).unwrap();
+        let mut again = Vec::new();
+        c.write_png(&mut again).unwrap();
+        assert_eq!(png, again);
+
+        let back = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(back, image::RgbImage::from(&c));
+        assert!(Canvas::new(0, 3).write_png(Vec::new()).is_err());
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn png_text_canvas() {
+        let c = Canvas::new(2, 1);
+        let mut png = Vec::new();
+        c.write_png_with_text(&mut png, "scene: 1\nrender time: 2.5s\nno key")
+            .unwrap();
+        for text in [
+            &b"tEXtscene\x001"[..],
+            b"tEXtrender time\x002.5s",
+            b"tEXtComment\x00no key",
+        ] {
+            assert!(png.windows(text.len()).any(|w| w == text));
+        }
+
+        let mut plain = Vec::new();
+        c.write_png(&mut plain).unwrap();
+        assert!(!plain.windows(4).any(|w| w == b"tEXt"));
+    }
+
+    #[test]
+    fn srgb_canvas() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, RGB::new(0.18, 0.5, 1.0));
+        c.set_alpha(1, 0, 0.5);
+        assert_eq!(c.encoding(), ColorEncoding::Linear);
+        assert_eq!(c.to_ppm(), "P3\n2 1\n255\n45 127 255 0 0 0\n\n");
+
+        c.set_encoding(ColorEncoding::Srgb);
+        assert_eq!(c.to_ppm(), "P3\n2 1\n255\n118 188 255 0 0 0\n\n");
+        assert_eq!(c.to_rgba8(), vec![118, 188, 255, 255, 0, 0, 0, 128]);
+        let mut out = Vec::new();
+        c.write_ppm(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), c.to_ppm());
+
+        // derived canvases keep the encoding
+        assert_eq!(c.flip_horizontal().encoding(), ColorEncoding::Srgb);
+        assert_eq!((&c * 2.0).encoding(), ColorEncoding::Srgb);
+        assert_eq!(
+            c.resize(4, 2, ResizeFilter::Nearest).encoding(),
+            ColorEncoding::Srgb
+        );
+    }
+
+    #[test]
+    fn map_chunks_canvas() {
+        let sums = map_chunks(10, 3, |r| r.sum::<usize>());
+        assert_eq!(sums, vec![3, 12, 21, 9]);
+        assert!(map_chunks(0, 3, |r| r.len()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "range 3..6")]
+    fn map_chunks_panic_canvas() {
+        map_chunks(10, 3, |r| {
+            if r.start == 3 {
+                panic!("range {:?}", r);
+            }
+        });
+    }
 }
@@ -1,19 +1,23 @@
-use crate::float_eq;
+use crate::{float_eq, Float};
+use std::error::Error;
+use std::fmt;
 use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
 
 /// RGB color object
 #[derive(Debug, Clone, Copy)]
 pub struct RGB {
     /// Red color grade [0,1]
-    pub red: f64,
+    pub red: Float,
     /// Green color grade [0,1]
-    pub green: f64,
+    pub green: Float,
     /// Blue color grade [0,1]
-    pub blue: f64,
+    pub blue: Float,
 }
 
 impl RGB {
-    pub fn new(red: f64, green: f64, blue: f64) -> Self {
+    /// Create a color from linear components, as used by all lighting math.
+    pub fn new(red: Float, green: Float, blue: Float) -> Self {
         Self { red, green, blue }
     }
 
@@ -24,14 +28,99 @@ impl RGB {
         format!("{} {} {}", c_red, c_green, c_blue)
     }
 
+    /// Create a color from linear 8 bit components, see `from_u8_srgb`
+    /// for colors picked in image editors.
     pub fn from_u8(red: u8, green: u8, blue: u8) -> Self {
         Self {
-            red: red as f64 / 255.0,
-            green: green as f64 / 255.0,
-            blue: blue as f64 / 255.0,
+            red: red as Float / 255.0,
+            green: green as Float / 255.0,
+            blue: blue as Float / 255.0,
         }
     }
 
+    /// Linear 8 bit components, clamped to [0, 255]. The inverse of `from_u8`.
+    pub fn to_u8(&self) -> [u8; 3] {
+        [clamp(self.red), clamp(self.green), clamp(self.blue)]
+    }
+
+    /// Create a color from sRGB encoded 8 bit components, like the colors of
+    /// textures and color pickers. They are decoded to linear components.
+    pub fn from_u8_srgb(red: u8, green: u8, blue: u8) -> Self {
+        let decode = |c: u8| srgb_to_linear(c as Float / 255.0);
+        Self::new(decode(red), decode(green), decode(blue))
+    }
+
+    /// sRGB encoded 8 bit components. The inverse of `from_u8_srgb`.
+    pub fn to_u8_srgb(&self) -> [u8; 3] {
+        let encode = |c: Float| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8;
+        [encode(self.red), encode(self.green), encode(self.blue)]
+    }
+
+    /// Encode the linear components for display with the sRGB transfer curve.
+    /// Negative components become 0, components above 1 follow the curve.
+    pub fn to_srgb(&self) -> Self {
+        let encode = |c: Float| linear_to_srgb(c.max(0.0));
+        Self::new(encode(self.red), encode(self.green), encode(self.blue))
+    }
+
+    /// Decode a color with sRGB encoded components to linear. The inverse of `to_srgb`.
+    pub fn from_srgb(color: RGB) -> Self {
+        let decode = |c: Float| srgb_to_linear(c.max(0.0));
+        Self::new(decode(color.red), decode(color.green), decode(color.blue))
+    }
+
+    /// Color of a black body at `temperature` Kelvin, e.g. 2700 for warm
+    /// light bulbs and 6500 for daylight. Valid from 1000 to 40000 Kelvin.
+    /// The brightest channel is 1, scale the color for the intensity of a light.
+    #[allow(clippy::excessive_precision)] // the fit is kept as published
+    pub fn from_kelvin(temperature: Float) -> Self {
+        // fit of the black body colors by Tanner Helland, in sRGB
+        let t = temperature.clamp(1000.0, 40000.0) / 100.0;
+        let red = if t <= 66.0 {
+            255.0
+        } else {
+            329.698727446 * (t - 60.0).powf(-0.1332047592)
+        };
+        let green = if t <= 66.0 {
+            99.4708025861 * t.ln() - 161.1195681661
+        } else {
+            288.1221695283 * (t - 60.0).powf(-0.0755148492)
+        };
+        let blue = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.5177312231 * (t - 10.0).ln() - 305.0447927307
+        };
+
+        let decode = |c: Float| srgb_to_linear((c / 255.0).clamp(0.0, 1.0));
+        Self::new(decode(red), decode(green), decode(blue))
+    }
+
+    /// Relative luminance (Rec. 709) of a linear color.
+    pub fn luminance(&self) -> Float {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
+
+    /// Component-wise minimum of two colors.
+    pub fn min(&self, other: RGB) -> Self {
+        Self::new(
+            self.red.min(other.red),
+            self.green.min(other.green),
+            self.blue.min(other.blue),
+        )
+    }
+
+    /// Component-wise maximum of two colors.
+    pub fn max(&self, other: RGB) -> Self {
+        Self::new(
+            self.red.max(other.red),
+            self.green.max(other.green),
+            self.blue.max(other.blue),
+        )
+    }
+
     /// Get an array of the  parts of a `Color` as [`u8`] in string format. The
     pub fn rgb_string_array(&self) -> [String; 3] {
         [
@@ -40,10 +129,149 @@ impl RGB {
             format!("{}", clamp(self.blue)),
         ]
     }
+
+    /// Create a color from hue in degrees, saturation and value in [0, 1],
+    /// as in color pickers. Like `from_u8_srgb` the result is decoded to linear,
+    /// so a hue sweep looks even on screen.
+    pub fn from_hsv(hue: Float, saturation: Float, value: Float) -> Self {
+        let s = saturation.clamp(0.0, 1.0);
+        let v = value.clamp(0.0, 1.0);
+        let c = v * s;
+        Self::from_chroma(hue, c, v - c)
+    }
+
+    /// Hue in degrees [0, 360), saturation and value of the sRGB encoded color,
+    /// components are clamped to [0, 1]. The inverse of `from_hsv`, gray has hue 0.
+    pub fn to_hsv(&self) -> (Float, Float, Float) {
+        let (hue, max, min) = self.hue();
+        let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (hue, saturation, max)
+    }
+
+    /// Create a color from hue in degrees, saturation and lightness in [0, 1].
+    /// Like `from_hsv` the result is decoded from sRGB to linear.
+    pub fn from_hsl(hue: Float, saturation: Float, lightness: Float) -> Self {
+        let s = saturation.clamp(0.0, 1.0);
+        let l = lightness.clamp(0.0, 1.0);
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_chroma(hue, c, l - c / 2.0)
+    }
+
+    /// Hue in degrees [0, 360), saturation and lightness of the sRGB encoded
+    /// color, components are clamped to [0, 1]. The inverse of `from_hsl`.
+    pub fn to_hsl(&self) -> (Float, Float, Float) {
+        let (hue, max, min) = self.hue();
+        let lightness = (max + min) / 2.0;
+        let d = max - min;
+        let saturation = if d > 0.0 {
+            d / (1.0 - (2.0 * lightness - 1.0).abs())
+        } else {
+            0.0
+        };
+        (hue, saturation, lightness)
+    }
+
+    /// Parse a hex color like `#aabbcc` or the short form `#abc`, the `#` is
+    /// optional. Hex colors are sRGB encoded, see `from_u8_srgb`.
+    pub fn from_hex(hex: &str) -> Result<Self, ParseColorError> {
+        let digits = hex.trim();
+        let digits = digits.strip_prefix('#').unwrap_or(digits);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ParseColorError::new(format!(
+                "'{}' is not a hex color",
+                hex
+            )));
+        }
+
+        let channel = |i: usize, len: usize| {
+            let v = u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).unwrap_or(0);
+            // #abc is short for #aabbcc
+            if len == 1 {
+                v * 17
+            } else {
+                v
+            }
+        };
+        match digits.len() {
+            3 => Ok(Self::from_u8_srgb(
+                channel(0, 1),
+                channel(1, 1),
+                channel(2, 1),
+            )),
+            6 => Ok(Self::from_u8_srgb(
+                channel(0, 2),
+                channel(1, 2),
+                channel(2, 2),
+            )),
+            n => Err(ParseColorError::new(format!(
+                "expected 3 or 6 hex digits, found {}",
+                n
+            ))),
+        }
+    }
+
+    /// The sRGB encoded color as `#rrggbb`. The inverse of `from_hex`.
+    pub fn to_hex(&self) -> String {
+        let [r, g, b] = self.to_u8_srgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    /// sRGB color from hue, chroma and the offset `m` added to all components.
+    fn from_chroma(hue: Float, c: Float, m: Float) -> Self {
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u8 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let decode = |v: Float| srgb_to_linear((v + m).clamp(0.0, 1.0));
+        Self::new(decode(r), decode(g), decode(b))
+    }
+
+    /// Hue in degrees, largest and smallest component of the sRGB encoded color.
+    fn hue(&self) -> (Float, Float, Float) {
+        let encode = |c: Float| linear_to_srgb(c.clamp(0.0, 1.0));
+        let (r, g, b) = (encode(self.red), encode(self.green), encode(self.blue));
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let d = max - min;
+        let hue = if d <= 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / d).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / d + 2.0)
+        } else {
+            60.0 * ((r - g) / d + 4.0)
+        };
+        (hue, max, min)
+    }
+}
+
+/// Decode an sRGB component to linear.
+fn srgb_to_linear(c: Float) -> Float {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encode a linear component as sRGB.
+fn linear_to_srgb(c: Float) -> Float {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
 }
 
 // clamp function for RGB
-fn clamp(c: f64) -> u8 {
+fn clamp(c: Float) -> u8 {
     let c = c * 255.0;
     if c > 255.0 {
         255u8
@@ -76,9 +304,9 @@ impl Sub for RGB {
     }
 }
 
-impl Mul<f64> for RGB {
+impl Mul<Float> for RGB {
     type Output = Self;
-    fn mul(self, s: f64) -> Self::Output {
+    fn mul(self, s: Float) -> Self::Output {
         Self {
             red: self.red * s,
             green: self.green * s,
@@ -106,6 +334,36 @@ impl PartialEq for RGB {
     }
 }
 
+/// Error returned when parsing a hex color fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseColorError {
+    message: String,
+}
+
+impl ParseColorError {
+    /// Create a new error with a description of the problem.
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl fmt::Display for ParseColorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid color: {}", self.message)
+    }
+}
+
+impl Error for ParseColorError {}
+
+impl FromStr for RGB {
+    type Err = ParseColorError;
+
+    /// Parse a hex color, see `RGB::from_hex`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
 pub const BLACK: RGB = RGB {
     red: 0.0,
     green: 0.0,
@@ -198,4 +456,116 @@ mod test {
 
         assert_eq!(String::from("0 178 255"), c.ppm_clamp());
     }
+
+    #[test]
+    fn luminance_color() {
+        assert!(float_eq(WHITE.luminance(), 1.0));
+        assert!(float_eq(BLACK.luminance(), 0.0));
+        assert!(float_eq(GREEN.luminance(), 0.7152));
+    }
+
+    #[test]
+    fn u8_color() {
+        let c = RGB::from_u8(255, 51, 0);
+        assert_eq!(c, RGB::new(1.0, 0.2, 0.0));
+        assert_eq!(c.to_u8(), [255, 51, 0]);
+        assert_eq!(RGB::new(1.5, 0.5, -0.5).to_u8(), [255, 127, 0]);
+    }
+
+    #[test]
+    fn srgb_u8_color() {
+        // sRGB mid gray is much darker in linear light
+        let gray = RGB::from_u8_srgb(188, 188, 188);
+        assert!(float_eq(gray.red, 0.50289));
+        assert_eq!(gray.to_u8_srgb(), [188, 188, 188]);
+
+        for v in 0..=255 {
+            assert_eq!(RGB::from_u8_srgb(v, v, v).to_u8_srgb(), [v, v, v]);
+        }
+    }
+
+    #[test]
+    fn kelvin_color() {
+        assert_eq!(RGB::from_kelvin(6600.0), WHITE);
+
+        let warm = RGB::from_kelvin(2700.0);
+        assert_eq!(warm.red, 1.0);
+        assert!(warm.blue < warm.green && warm.green < 1.0);
+
+        let cold = RGB::from_kelvin(10000.0);
+        assert_eq!(cold.blue, 1.0);
+        assert!(cold.red < cold.green && cold.green < 1.0);
+
+        assert_eq!(RGB::from_kelvin(500.0), RGB::from_kelvin(1000.0));
+    }
+
+    #[test]
+    fn hsv_color() {
+        assert_eq!(RGB::from_hsv(0.0, 1.0, 1.0), RED);
+        assert_eq!(RGB::from_hsv(120.0, 1.0, 1.0), GREEN);
+        assert_eq!(RGB::from_hsv(-120.0, 1.0, 1.0), BLUE);
+        assert_eq!(RGB::from_hsv(42.0, 0.0, 1.0), WHITE);
+        assert_eq!(RGB::from_hsv(60.0, 1.0, 1.0), RGB::new(1.0, 1.0, 0.0));
+        assert_eq!(BLACK.to_hsv(), (0.0, 0.0, 0.0));
+
+        for (h, s, v) in [(30.0, 0.5, 0.8), (200.0, 0.9, 0.4), (310.0, 0.2, 1.0)] {
+            let (h2, s2, v2) = RGB::from_hsv(h, s, v).to_hsv();
+            assert!(float_eq(h, h2) && float_eq(s, s2) && float_eq(v, v2));
+        }
+    }
+
+    #[test]
+    fn hsl_color() {
+        assert_eq!(RGB::from_hsl(240.0, 1.0, 0.5), BLUE);
+        assert_eq!(RGB::from_hsl(0.0, 1.0, 1.0), WHITE);
+        assert_eq!(RGB::from_hsl(0.0, 1.0, 0.0), BLACK);
+        // half lightness is the middle gray of a color picker, not of linear light
+        assert_eq!(RGB::from_hsl(0.0, 0.0, 0.5).to_u8_srgb(), [128, 128, 128]);
+
+        for (h, s, l) in [(30.0, 0.5, 0.3), (200.0, 0.9, 0.6), (310.0, 0.2, 0.5)] {
+            let (h2, s2, l2) = RGB::from_hsl(h, s, l).to_hsl();
+            assert!(float_eq(h, h2) && float_eq(s, s2) && float_eq(l, l2));
+        }
+    }
+
+    #[test]
+    fn hex_color() {
+        assert_eq!(RGB::from_hex("#ff0000").unwrap(), RED);
+        assert_eq!(RGB::from_hex("00FF00").unwrap(), GREEN);
+        assert_eq!(RGB::from_hex("#00f").unwrap(), BLUE);
+        assert_eq!(
+            RGB::from_hex("#336699").unwrap(),
+            RGB::from_u8_srgb(0x33, 0x66, 0x99)
+        );
+        assert_eq!(
+            "#abc".parse::<RGB>().unwrap(),
+            RGB::from_hex("#aabbcc").unwrap()
+        );
+        assert_eq!(RGB::from_hex("#336699").unwrap().to_hex(), "#336699");
+        assert_eq!(WHITE.to_hex(), "#ffffff");
+
+        assert!(RGB::from_hex("#12345").is_err());
+        assert!(RGB::from_hex("#gg0000").is_err());
+        assert_eq!(
+            RGB::from_hex("").unwrap_err().to_string(),
+            "Invalid color: expected 3 or 6 hex digits, found 0"
+        );
+    }
+
+    #[test]
+    fn srgb_color() {
+        let c = RGB::new(0.2, 0.5, 1.0);
+        assert_eq!(RGB::from_srgb(c.to_srgb()), c);
+        assert_eq!(BLACK.to_srgb(), BLACK);
+        assert_eq!(WHITE.to_srgb(), WHITE);
+        // linear middle gray is displayed much brighter
+        assert!(float_eq(
+            RGB::new(0.18, 0.18, 0.18).to_srgb().red,
+            0.4613561
+        ));
+        assert_eq!(RGB::new(-1.0, 0.0, 0.0).to_srgb(), BLACK);
+        assert!(RGB::new(2.0, 0.0, 0.0).to_srgb().red > 1.0);
+
+        assert_eq!(c.to_u8_srgb(), [124, 188, 255]);
+    }
 }
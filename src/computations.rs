@@ -1,13 +1,16 @@
-use crate::{shapes::Shape, Point, Vector};
+use crate::{shapes::Shape, Float, Material, Point, Vector};
 
 /// A Computation encapsulates some pre-compute information of an intersection and an object.
 pub struct Computation<'a> {
     /// Distance from the origin of a Ray to the intersection.
-    pub t: f64,
+    pub t: Float,
 
     /// The object intersected by a Ray.
     pub object: &'a dyn Shape,
 
+    /// Material of the object at the intersection.
+    pub material: &'a Material,
+
     /// Point in world space where the Intersection occurred.
     pub point: Point,
 
@@ -30,15 +33,26 @@ pub struct Computation<'a> {
     pub reflectv: Vector,
 
     /// Refraction 1.
-    pub n1: f64,
+    pub n1: Float,
 
     /// Refraction 2.
-    pub n2: f64,
+    pub n2: Float,
+
+    /// Refraction 1 per color channel.
+    pub n1_rgb: [Float; 3],
+
+    /// Refraction 2 per color channel.
+    pub n2_rgb: [Float; 3],
 }
 
 impl Computation<'_> {
+    /// Does the refraction at this intersection split the color channels?
+    pub fn is_dispersive(&self) -> bool {
+        self.n1_rgb.iter().any(|n| *n != self.n1) || self.n2_rgb.iter().any(|n| *n != self.n2)
+    }
+
     /// Fresnel effect.
-    pub fn schlick(&self) -> f64 {
+    pub fn schlick(&self) -> Float {
         // find the cosine of the angle between the eye and normal vector
         let mut cos = self.eyev.dot(self.normalv);
 
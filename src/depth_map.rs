@@ -0,0 +1,126 @@
+use crate::consts::PI;
+use crate::*;
+
+/// How rays are spread when rendering a depth map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Rays start at a single point, like a spot light, with the given field of view.
+    Perspective(Float),
+
+    /// Parallel rays, like a directional light, covering a square of the given half size.
+    Orthographic(Float),
+}
+
+/// Render the distance to the nearest surface as seen from `light` looking at `to`.
+/// Each pixel is gray with `distance / far`, pixels without a hit are white.
+/// Useful to debug shadows or to export for other engines via `Canvas::to_ppm`.
+pub fn depth_map(
+    world: &World,
+    light: PointLight,
+    to: Point,
+    size: usize,
+    projection: Projection,
+    far: Float,
+) -> Canvas {
+    let from = light.get_position();
+    let forward = (to - from).normalize();
+    // any up works as long as it is not parallel to the view direction
+    let up = if float_eq(forward.y.abs(), 1.0) {
+        Vector::new(1.0, 0.0, 0.0)
+    } else {
+        Vector::new(0.0, 1.0, 0.0)
+    };
+    let view = Transformation::view_transformation(from, to, up);
+    let inv = view
+        .inverse()
+        .expect("View transform should be invertible!");
+
+    let mut camera = Camera::new(size, size, PI / 2.0);
+    if let Projection::Perspective(field_of_view) = projection {
+        camera = Camera::new(size, size, field_of_view);
+    }
+    camera.transform = view;
+
+    let mut canvas = Canvas::new(size, size);
+    for y in 0..size {
+        for x in 0..size {
+            let ray = match projection {
+                Projection::Perspective(_) => camera.ray_for_pixel(x, y),
+                Projection::Orthographic(half_size) => {
+                    let pixel_size = half_size * 2.0 / size as Float;
+                    let world_x = half_size - (x as Float + 0.5) * pixel_size;
+                    let world_y = half_size - (y as Float + 0.5) * pixel_size;
+                    let origin = inv * Point::new(world_x, world_y, 0.0);
+                    let direction = (inv * Vector::new(0.0, 0.0, -1.0)).normalize();
+                    Ray::new(origin, direction)
+                }
+            };
+
+            let color = match world.pick(&ray) {
+                Some((_, t, _, _)) => {
+                    let depth = (t / far).clamp(0.0, 1.0);
+                    RGB::new(depth, depth, depth)
+                }
+                None => WHITE,
+            };
+            canvas.write_pixel(x, y, color);
+        }
+    }
+
+    canvas
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn perspective_depth_map() {
+        let w = World::default();
+        let light = PointLight::new(Point::new(0.0, 0.0, -5.0), WHITE);
+        let map = depth_map(
+            &w,
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            11,
+            Projection::Perspective(PI / 2.0),
+            10.0,
+        );
+
+        assert_eq!(map.pixel_at(5, 5), RGB::new(0.4, 0.4, 0.4));
+        assert_eq!(map.pixel_at(0, 0), WHITE);
+    }
+
+    #[test]
+    fn orthographic_depth_map() {
+        let w = World::default();
+        let light = PointLight::new(Point::new(0.0, 0.0, -5.0), WHITE);
+        let map = depth_map(
+            &w,
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            11,
+            Projection::Orthographic(2.0),
+            10.0,
+        );
+
+        assert_eq!(map.pixel_at(5, 5), RGB::new(0.4, 0.4, 0.4));
+        assert_eq!(map.pixel_at(0, 0), WHITE);
+    }
+
+    #[test]
+    fn vertical_depth_map() {
+        let w = World::default();
+        let light = PointLight::new(Point::new(0.0, 5.0, 0.0), WHITE);
+        let map = depth_map(
+            &w,
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            11,
+            Projection::Orthographic(2.0),
+            10.0,
+        );
+
+        assert_eq!(map.pixel_at(5, 5), RGB::new(0.4, 0.4, 0.4));
+    }
+}
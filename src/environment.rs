@@ -0,0 +1,506 @@
+use crate::consts::PI;
+use crate::{Float, Vector, BLACK, RGB};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// An equirectangular image of the surroundings of a scene, seen by rays that miss.
+/// The center of the image is looking down -z, the top row is +y.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<RGB>,
+    sampler: EnvironmentSampler,
+}
+
+/// Distribution of the light of an `EnvironmentMap` over the sphere,
+/// to pick directions proportional to the brightness seen in them.
+/// Bright spots like the sun or windows then get most of the samples.
+#[derive(Debug, Clone)]
+pub struct EnvironmentSampler {
+    width: usize,
+    height: usize,
+    /// Cumulative distribution over the rows.
+    rows: Vec<Float>,
+    /// Cumulative distribution over the columns of each row, row by row.
+    columns: Vec<Float>,
+}
+
+impl EnvironmentMap {
+    /// Create a map from `width * height` pixels, row by row from the top.
+    pub fn new(width: usize, height: usize, pixels: Vec<RGB>) -> Self {
+        assert!(
+            width > 0 && height > 0,
+            "Environment map must not be empty!"
+        );
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "Environment map needs one color per pixel!"
+        );
+        let sampler = EnvironmentSampler::new(width, height, &pixels);
+        Self {
+            width,
+            height,
+            pixels,
+            sampler,
+        }
+    }
+
+    /// Importance sampling of the light of the map.
+    pub fn sampler(&self) -> &EnvironmentSampler {
+        &self.sampler
+    }
+
+    /// Width in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Return the color at the given pixel.
+    pub fn pixel_at(&self, x: usize, y: usize) -> RGB {
+        self.pixels[x + y * self.width]
+    }
+
+    /// Position in the image, both in [0,1], that `direction` looks at.
+    pub fn direction_to_uv(direction: Vector) -> (Float, Float) {
+        let d = direction.normalize();
+        let u = 0.5 + d.x.atan2(-d.z) / (2.0 * PI);
+        let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+
+        (u, v)
+    }
+
+    /// Direction that the position (u, v) in the image looks at.
+    pub fn uv_to_direction(u: Float, v: Float) -> Vector {
+        let phi = (u - 0.5) * 2.0 * PI;
+        let theta = v * PI;
+
+        Vector::new(
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        )
+    }
+
+    /// Bilinear filtered color seen in `direction`.
+    /// The image wraps around horizontally and is clamped at the poles.
+    pub fn sample(&self, direction: Vector) -> RGB {
+        let (u, v) = Self::direction_to_uv(direction);
+        // pixel centers are at half integers
+        let x = u * self.width as Float - 0.5;
+        let y = (v * self.height as Float - 0.5).clamp(0.0, (self.height - 1) as Float);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let column = |x: Float| (x as i64).rem_euclid(self.width as i64) as usize;
+        let (x0, x1) = (column(x0), column(x0 + 1.0));
+        let y0 = y0 as usize;
+        let y1 = (y0 + 1).min(self.height - 1);
+
+        let top = self.pixel_at(x0, y0) * (1.0 - fx) + self.pixel_at(x1, y0) * fx;
+        let bottom = self.pixel_at(x0, y1) * (1.0 - fx) + self.pixel_at(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+/// Turn weights into a cumulative distribution ending at 1, None if they are all 0.
+fn cumulative(weights: &[Float]) -> Option<Vec<Float>> {
+    let mut sum = 0.0;
+    let mut cdf: Vec<Float> = weights
+        .iter()
+        .map(|w| {
+            sum += w;
+            sum
+        })
+        .collect();
+    if sum <= 0.0 {
+        return None;
+    }
+    cdf.iter_mut().for_each(|c| *c /= sum);
+
+    Some(cdf)
+}
+
+/// Pick the entry of `cdf` that `u` in [0,1) falls into,
+/// together with the position of `u` inside of the entry.
+fn pick(cdf: &[Float], u: Float) -> (usize, Float) {
+    let i = cdf.partition_point(|c| *c <= u).min(cdf.len() - 1);
+    let start = if i == 0 { 0.0 } else { cdf[i - 1] };
+    let fraction = ((u - start) / (cdf[i] - start)).clamp(0.0, 1.0);
+
+    (i, fraction)
+}
+
+/// Probability of entry `i` in `cdf`.
+fn probability(cdf: &[Float], i: usize) -> Float {
+    if i == 0 {
+        cdf[0]
+    } else {
+        cdf[i] - cdf[i - 1]
+    }
+}
+
+impl EnvironmentSampler {
+    /// Weight each pixel by its luminance and the solid angle it covers.
+    /// A black map is sampled uniformly over the sphere.
+    fn new(width: usize, height: usize, pixels: &[RGB]) -> Self {
+        let solid_angle = |y: usize| ((y as Float + 0.5) / height as Float * PI).sin();
+        let mut weights: Vec<Float> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, p)| p.luminance().max(0.0) * solid_angle(i / width))
+            .collect();
+        if weights.iter().all(|w| *w <= 0.0) {
+            weights = (0..width * height)
+                .map(|i| solid_angle(i / width))
+                .collect();
+        }
+
+        let row_weights: Vec<Float> = weights.chunks(width).map(|r| r.iter().sum()).collect();
+        let rows = cumulative(&row_weights).expect("Weights should not be zero!");
+        let columns = weights
+            .chunks(width)
+            .flat_map(|r| cumulative(r).unwrap_or_else(|| vec![1.0; width]))
+            .collect();
+
+        Self {
+            width,
+            height,
+            rows,
+            columns,
+        }
+    }
+
+    fn row(&self, y: usize) -> &[Float] {
+        &self.columns[y * self.width..(y + 1) * self.width]
+    }
+
+    /// Direction for the two uniform random numbers `u1`, `u2` in [0,1)
+    /// and its probability density per solid angle.
+    pub fn sample(&self, u1: Float, u2: Float) -> (Vector, Float) {
+        let (y, fy) = pick(&self.rows, u1);
+        let (x, fx) = pick(self.row(y), u2);
+        let u = (x as Float + fx) / self.width as Float;
+        let v = (y as Float + fy) / self.height as Float;
+
+        (EnvironmentMap::uv_to_direction(u, v), self.density(x, y, v))
+    }
+
+    /// Probability density per solid angle of sampling `direction`.
+    pub fn pdf(&self, direction: Vector) -> Float {
+        let (u, v) = EnvironmentMap::direction_to_uv(direction);
+        let x = ((u * self.width as Float) as usize).min(self.width - 1);
+        let y = ((v * self.height as Float) as usize).min(self.height - 1);
+
+        self.density(x, y, v)
+    }
+
+    fn density(&self, x: usize, y: usize, v: Float) -> Float {
+        let sin_theta = (v * PI).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let p = probability(&self.rows, y) * probability(self.row(y), x);
+
+        // a pixel covers 2pi/width * pi/height * sin(theta) of the sphere
+        p * (self.width * self.height) as Float / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+/// Errors while loading a Radiance HDR file.
+#[derive(Debug)]
+pub enum HdrError {
+    /// The input could not be read.
+    Io(io::Error),
+
+    /// The file is not a supported RGBE image.
+    Format(String),
+}
+
+impl fmt::Display for HdrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HdrError::Io(why) => write!(f, "Could not read HDR: {}", why),
+            HdrError::Format(message) => write!(f, "Invalid HDR: {}", message),
+        }
+    }
+}
+
+impl Error for HdrError {}
+
+impl From<io::Error> for HdrError {
+    fn from(why: io::Error) -> Self {
+        HdrError::Io(why)
+    }
+}
+
+/// Convert a shared exponent pixel to a color.
+fn rgbe(p: [u8; 4]) -> RGB {
+    if p[3] == 0 {
+        return BLACK;
+    }
+    let f = (2.0 as Float).powi(p[3] as i32 - (128 + 8));
+    RGB::new(
+        (p[0] as Float + 0.5) * f,
+        (p[1] as Float + 0.5) * f,
+        (p[2] as Float + 0.5) * f,
+    )
+}
+
+/// Read one scan line, either flat or with run length encoded channels.
+fn read_scanline(data: &mut &[u8], width: usize) -> Result<Vec<RGB>, String> {
+    let take = |data: &mut &[u8], n: usize| -> Result<Vec<u8>, String> {
+        if data.len() < n {
+            return Err("unexpected end of pixel data".to_string());
+        }
+        let (head, rest) = data.split_at(n);
+        *data = rest;
+        Ok(head.to_vec())
+    };
+
+    let start = take(data, 4)?;
+    let encoded = (8..0x8000).contains(&width) && start[0] == 2 && start[1] == 2 && start[2] < 128;
+    if !encoded {
+        let rest = take(data, (width - 1) * 4)?;
+        return Ok(start
+            .iter()
+            .chain(rest.iter())
+            .copied()
+            .collect::<Vec<u8>>()
+            .chunks(4)
+            .map(|p| rgbe([p[0], p[1], p[2], p[3]]))
+            .collect());
+    }
+
+    if ((start[2] as usize) << 8 | start[3] as usize) != width {
+        return Err("scan line width does not match the image".to_string());
+    }
+    let mut channels: Vec<Vec<u8>> = (0..4).map(|_| Vec::with_capacity(width)).collect();
+    for channel in channels.iter_mut() {
+        while channel.len() < width {
+            let count = take(data, 1)?[0] as usize;
+            if count > 128 {
+                let value = take(data, 1)?[0];
+                channel.extend(std::iter::repeat_n(value, count - 128));
+            } else {
+                if count == 0 {
+                    return Err("run of length zero".to_string());
+                }
+                channel.extend(take(data, count)?);
+            }
+        }
+        if channel.len() > width {
+            return Err("run exceeds the scan line".to_string());
+        }
+    }
+
+    Ok((0..width)
+        .map(|x| {
+            rgbe([
+                channels[0][x],
+                channels[1][x],
+                channels[2][x],
+                channels[3][x],
+            ])
+        })
+        .collect())
+}
+
+/// Parse a Radiance HDR (RGBE) image, e.g. an environment map for `World::set_environment`.
+/// Flat and run length encoded scan lines are supported in the common
+/// `-Y height +X width` orientation.
+pub fn parse_hdr<R: BufRead>(mut reader: R) -> Result<EnvironmentMap, HdrError> {
+    let mut line = Vec::new();
+    let mut read_line = |reader: &mut R| -> Result<String, HdrError> {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Err(HdrError::Format("unexpected end of header".to_string()));
+        }
+        Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+    };
+
+    let magic = read_line(&mut reader)?;
+    if !magic.starts_with("#?") {
+        return Err(HdrError::Format("missing #? signature".to_string()));
+    }
+    loop {
+        let header = read_line(&mut reader)?;
+        if header.is_empty() {
+            break;
+        }
+        if let Some(format) = header.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(HdrError::Format(format!("unsupported format '{}'", format)));
+            }
+        }
+    }
+
+    let resolution = read_line(&mut reader)?;
+    let (height, width) = match resolution.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", h, "+X", w] => match (h.parse::<usize>(), w.parse::<usize>()) {
+            (Ok(h), Ok(w)) if h > 0 && w > 0 => (h, w),
+            _ => return Err(HdrError::Format(format!("invalid size '{}'", resolution))),
+        },
+        _ => {
+            return Err(HdrError::Format(format!(
+                "unsupported orientation '{}'",
+                resolution
+            )))
+        }
+    };
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let mut data = bytes.as_slice();
+    let mut pixels = Vec::with_capacity(width * height);
+    for _ in 0..height {
+        pixels.extend(read_scanline(&mut data, width).map_err(HdrError::Format)?);
+    }
+
+    Ok(EnvironmentMap::new(width, height, pixels))
+}
+
+/// Load a Radiance HDR file from disk, see `parse_hdr`.
+pub fn load_hdr<P: AsRef<Path>>(path: P) -> Result<EnvironmentMap, HdrError> {
+    let file = File::open(path)?;
+    parse_hdr(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BLUE, GREEN, RED, WHITE};
+
+    fn header(width: usize, height: usize) -> Vec<u8> {
+        format!(
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            height, width
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn flat_hdr() {
+        let mut bytes = header(2, 1);
+        bytes.extend_from_slice(&[127, 0, 0, 129, 0, 0, 0, 0]);
+        let map = parse_hdr(bytes.as_slice()).unwrap();
+
+        assert_eq!(map.width(), 2);
+        assert_eq!(map.height(), 1);
+        assert_eq!(
+            map.pixel_at(0, 0),
+            RGB::new(127.5 / 128.0, 0.5 / 128.0, 0.5 / 128.0)
+        );
+        assert_eq!(map.pixel_at(1, 0), BLACK);
+    }
+
+    #[test]
+    fn rle_hdr() {
+        let mut bytes = header(8, 1);
+        bytes.extend_from_slice(&[2, 2, 0, 8]);
+        // red as a run, green as literals, blue and the exponent as runs
+        bytes.extend_from_slice(&[136, 255]);
+        bytes.extend_from_slice(&[8, 0, 1, 2, 3, 4, 5, 6, 7]);
+        bytes.extend_from_slice(&[136, 0]);
+        bytes.extend_from_slice(&[136, 136]);
+        let map = parse_hdr(bytes.as_slice()).unwrap();
+
+        assert_eq!(map.pixel_at(0, 0), RGB::new(255.5, 0.5, 0.5));
+        assert_eq!(map.pixel_at(7, 0), RGB::new(255.5, 7.5, 0.5));
+    }
+
+    #[test]
+    fn invalid_hdr() {
+        let bytes = b"P3\n1 1\n255\n0 0 0\n";
+        assert!(matches!(parse_hdr(&bytes[..]), Err(HdrError::Format(_))));
+
+        let mut bytes = header(2, 2);
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        assert!(matches!(
+            parse_hdr(bytes.as_slice()),
+            Err(HdrError::Format(_))
+        ));
+
+        let bytes = b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n+X 1 -Y 1\n";
+        assert!(matches!(parse_hdr(&bytes[..]), Err(HdrError::Format(_))));
+    }
+
+    #[test]
+    fn direction_environment() {
+        for d in [
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.3, 0.5, 0.8).normalize(),
+        ] {
+            let (u, v) = EnvironmentMap::direction_to_uv(d);
+            assert_eq!(EnvironmentMap::uv_to_direction(u, v), d);
+        }
+        let (u, v) = EnvironmentMap::direction_to_uv(Vector::new(0.0, 0.0, -1.0));
+        assert!(crate::float_eq(u, 0.5));
+        assert!(crate::float_eq(v, 0.5));
+    }
+
+    #[test]
+    fn sample_environment() {
+        // sky on top, four colors around the horizon
+        let map = EnvironmentMap::new(
+            4,
+            2,
+            vec![WHITE, WHITE, WHITE, WHITE, RED, GREEN, BLUE, BLACK],
+        );
+
+        assert_eq!(map.sample(Vector::new(0.0, 1.0, 0.0)), WHITE);
+        // the center of the image is between green and blue
+        assert_eq!(
+            map.sample(Vector::new(0.0, -1.0, -1.0e-9)),
+            RGB::new(0.0, 0.5, 0.5)
+        );
+        // the image wraps around behind the viewer
+        assert_eq!(
+            map.sample(Vector::new(0.0, -1.0, 1.0e-9)),
+            RGB::new(0.5, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn sampler_environment() {
+        // a single bright pixel
+        let mut pixels = vec![BLACK; 8 * 4];
+        pixels[5 + 8] = WHITE;
+        let map = EnvironmentMap::new(8, 4, pixels);
+        let sampler = map.sampler();
+
+        for i in 0..16 {
+            // away from the pixel borders, which round to either side
+            let u1 = (i as Float + 0.5) / 16.0;
+            let u2 = (i as Float * 0.618 + 0.3) % 1.0;
+            let (d, pdf) = sampler.sample(u1, u2);
+            let (u, v) = EnvironmentMap::direction_to_uv(d);
+            assert_eq!(((u * 8.0) as usize, (v * 4.0) as usize), (5, 1));
+            assert!(crate::float_eq(pdf, sampler.pdf(d)));
+            assert!(pdf > 0.0);
+        }
+        assert_eq!(sampler.pdf(Vector::new(0.0, -1.0, -0.1)), 0.0);
+    }
+
+    #[test]
+    fn uniform_sampler_environment() {
+        // black and uniform maps are sampled uniformly over the sphere
+        for color in [BLACK, WHITE] {
+            let map = EnvironmentMap::new(16, 8, vec![color; 16 * 8]);
+            let (d, pdf) = map.sampler().sample(0.5, 0.5);
+
+            assert!(crate::float_eq(d.magnitude(), 1.0));
+            assert!((pdf - 1.0 / (4.0 * PI)).abs() < 0.01);
+        }
+    }
+}
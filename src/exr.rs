@@ -0,0 +1,606 @@
+use crate::{Canvas, Float, GBuffer, RGB};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+/// Magic number at the start of every OpenEXR file.
+const MAGIC: u32 = 20000630;
+
+/// Pixel type of 32 bit floats in OpenEXR.
+const FLOAT: i32 = 2;
+
+/// Version flag of a single part tiled image.
+const TILED: u32 = 0x200;
+
+/// Version flag of deep data.
+const DEEP: u32 = 0x800;
+
+/// A multi-channel OpenEXR image, e.g. the beauty pass with its auxiliary passes.
+/// Written as uncompressed scan lines or tiles of 32 bit floats, which every
+/// compositing tool reads. Channels are named `layer.channel`,
+/// the beauty pass uses the plain `R`, `G` and `B` channels.
+#[derive(Debug, Clone)]
+pub struct Exr {
+    width: usize,
+    height: usize,
+    channels: Vec<(String, Vec<f32>)>,
+    tiles: Option<(usize, usize)>,
+}
+
+impl Exr {
+    /// Create an image without channels.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            channels: Vec::new(),
+            tiles: None,
+        }
+    }
+
+    /// Names of all channels in the order they are stored in the file.
+    pub fn channel_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.channels.iter().map(|(n, _)| n.as_str()).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Add a single channel with one value per pixel, replacing one with the same name.
+    pub fn add_channel(&mut self, name: &str, values: Vec<f32>) {
+        assert!(!name.is_empty(), "Channel name must not be empty!");
+        assert_eq!(
+            values.len(),
+            self.width * self.height,
+            "Channel must have one value per pixel!"
+        );
+        self.channels.retain(|(n, _)| n != name);
+        self.channels.push((name.to_string(), values));
+    }
+
+    /// Add the colors of a canvas as the `R`, `G` and `B` channels of `layer`,
+    /// and its alpha channel as `A`. An empty layer name adds the beauty pass.
+    pub fn add_canvas(&mut self, layer: &str, canvas: &Canvas) {
+        assert!(
+            canvas.width == self.width && canvas.height == self.height,
+            "Canvas must have the size of the image!"
+        );
+        let prefix = if layer.is_empty() {
+            String::new()
+        } else {
+            format!("{}.", layer)
+        };
+        self.add_channel(
+            &format!("{}R", prefix),
+            canvas.pixels().map(|c| to_f32(c.red)).collect(),
+        );
+        self.add_channel(
+            &format!("{}G", prefix),
+            canvas.pixels().map(|c| to_f32(c.green)).collect(),
+        );
+        self.add_channel(
+            &format!("{}B", prefix),
+            canvas.pixels().map(|c| to_f32(c.blue)).collect(),
+        );
+        if canvas.has_alpha() {
+            let alpha = (0..canvas.height)
+                .flat_map(|y| (0..canvas.width).map(move |x| to_f32(canvas.alpha_at(x, y))))
+                .collect();
+            self.add_channel(&format!("{}A", prefix), alpha);
+        }
+    }
+
+    /// Add the passes of a `GBuffer`: `N.X`, `N.Y`, `N.Z` for the normal,
+    /// `Z` for the depth and `id` for the object.
+    /// Objects are numbered from 1 in the order they first show up, 0 is no object.
+    pub fn add_gbuffer(&mut self, gbuffer: &GBuffer) {
+        assert!(
+            gbuffer.width == self.width && gbuffer.height == self.height,
+            "GBuffer must have the size of the image!"
+        );
+        self.add_channel("N.X", gbuffer.normals.iter().map(|n| to_f32(n.x)).collect());
+        self.add_channel("N.Y", gbuffer.normals.iter().map(|n| to_f32(n.y)).collect());
+        self.add_channel("N.Z", gbuffer.normals.iter().map(|n| to_f32(n.z)).collect());
+        self.add_channel("Z", gbuffer.depths.iter().map(|d| to_f32(*d)).collect());
+
+        let mut seen: Vec<Uuid> = Vec::new();
+        let ids = gbuffer
+            .ids
+            .iter()
+            .map(|id| match id {
+                Some(id) => match seen.iter().position(|s| s == id) {
+                    Some(i) => (i + 1) as f32,
+                    None => {
+                        seen.push(*id);
+                        seen.len() as f32
+                    }
+                },
+                None => 0.0,
+            })
+            .collect();
+        self.add_channel("id", ids);
+    }
+
+    /// Write the image as tiles of `width` x `height` pixels instead of scan lines.
+    /// Viewers load only the tiles they show, which pays off for large images.
+    pub fn with_tiles(mut self, width: usize, height: usize) -> Self {
+        assert!(width > 0 && height > 0, "Tiles must not be empty!");
+        self.tiles = Some((width, height));
+        self
+    }
+
+    /// Encode the image as OpenEXR.
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let mut channels: Vec<&(String, Vec<f32>)> = self.channels.iter().collect();
+        channels.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let names: Vec<&str> = channels.iter().map(|(n, _)| n.as_str()).collect();
+
+        // every block is a rectangle of pixels, a whole scan line or a tile
+        let (block_width, block_height) = self.tiles.unwrap_or((self.width, 1));
+        let columns = self.width.div_ceil(block_width);
+        let rows = self.height.div_ceil(block_height);
+
+        let mut header = match self.tiles {
+            Some((width, height)) => {
+                let mut tiles = Vec::new();
+                tiles.extend_from_slice(&(width as u32).to_le_bytes());
+                tiles.extend_from_slice(&(height as u32).to_le_bytes());
+                // one level, rounded down
+                tiles.push(0);
+                let mut header = header(TILED, &names, self.width, self.height);
+                attribute(&mut header, "tiles", "tiledesc", &tiles);
+                header
+            }
+            None => header(0, &names, self.width, self.height),
+        };
+        header.push(0);
+
+        let mut blocks = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let x0 = column * block_width;
+                let y0 = row * block_height;
+                let x1 = (x0 + block_width).min(self.width);
+                let y1 = (y0 + block_height).min(self.height);
+
+                let mut block = Vec::new();
+                if self.tiles.is_some() {
+                    // tile coordinates and level
+                    for v in [column, row, 0, 0] {
+                        block.extend_from_slice(&(v as i32).to_le_bytes());
+                    }
+                } else {
+                    block.extend_from_slice(&(y0 as i32).to_le_bytes());
+                }
+                let size = (x1 - x0) * (y1 - y0) * channels.len() * 4;
+                block.extend_from_slice(&(size as i32).to_le_bytes());
+                for y in y0..y1 {
+                    for (_, values) in channels.iter() {
+                        for v in &values[y * self.width + x0..y * self.width + x1] {
+                            block.extend_from_slice(&v.to_le_bytes());
+                        }
+                    }
+                }
+                blocks.push(block);
+            }
+        }
+
+        write_blocks(&mut out, header, &blocks)
+    }
+
+    /// Write the image to a file, see `write`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// A sample of a deep image: the color and coverage of a surface at its depth.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeepSample {
+    /// Color of the sample, premultiplied by `alpha`.
+    pub color: RGB,
+    /// Coverage of the pixel by the sample.
+    pub alpha: Float,
+    /// Distance along the primary ray.
+    pub depth: Float,
+}
+
+/// A deep OpenEXR image, every pixel keeps any number of samples at their depths
+/// instead of a single flattened color. Compositing tools merge deep images
+/// by depth without holdout mattes. Written as uncompressed scan lines with
+/// the channels `A`, `B`, `G`, `R` and `Z`, the samples of a pixel sorted by depth.
+#[derive(Debug, Clone)]
+pub struct DeepExr {
+    width: usize,
+    height: usize,
+    samples: Vec<Vec<DeepSample>>,
+}
+
+impl DeepExr {
+    /// Create an image without samples.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            samples: vec![Vec::new(); width * height],
+        }
+    }
+
+    /// One sample for every pixel that sees an object, with the color and alpha of
+    /// the beauty pass at the depth of the `GBuffer`. Pixels without an object stay empty.
+    pub fn from_passes(canvas: &Canvas, gbuffer: &GBuffer) -> Self {
+        assert!(
+            canvas.width == gbuffer.width && canvas.height == gbuffer.height,
+            "Canvas and GBuffer must have the same size!"
+        );
+        let mut exr = Self::new(canvas.width, canvas.height);
+        for y in 0..canvas.height {
+            for x in 0..canvas.width {
+                let i = y * canvas.width + x;
+                if gbuffer.ids[i].is_some() {
+                    let alpha = canvas.alpha_at(x, y);
+                    exr.add_sample(
+                        x,
+                        y,
+                        DeepSample {
+                            color: canvas.pixel_at(x, y) * alpha,
+                            alpha,
+                            depth: gbuffer.depths[i],
+                        },
+                    );
+                }
+            }
+        }
+        exr
+    }
+
+    /// Add a sample to the pixel at (x, y).
+    pub fn add_sample(&mut self, x: usize, y: usize, sample: DeepSample) {
+        assert!(
+            x < self.width && y < self.height,
+            "Pixel is outside of the image!"
+        );
+        self.samples[y * self.width + x].push(sample);
+    }
+
+    /// Samples of the pixel at (x, y) in the order they were added.
+    pub fn samples_at(&self, x: usize, y: usize) -> &[DeepSample] {
+        &self.samples[y * self.width + x]
+    }
+
+    /// Encode the image as deep OpenEXR.
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let most = self.samples.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut header = header(DEEP, &["A", "B", "G", "R", "Z"], self.width, self.height);
+        attribute(&mut header, "name", "string", b"deep");
+        attribute(&mut header, "type", "string", b"deepscanline");
+        attribute(&mut header, "version", "int", &1_i32.to_le_bytes());
+        attribute(
+            &mut header,
+            "chunkCount",
+            "int",
+            &(self.height as i32).to_le_bytes(),
+        );
+        attribute(
+            &mut header,
+            "maxSamplesPerPixel",
+            "int",
+            &(most as i32).to_le_bytes(),
+        );
+        header.push(0);
+
+        let mut blocks = Vec::with_capacity(self.height);
+        for y in 0..self.height {
+            let mut line: Vec<DeepSample> = Vec::new();
+            let mut counts = Vec::with_capacity(self.width * 4);
+            for pixel in &self.samples[y * self.width..(y + 1) * self.width] {
+                let start = line.len();
+                line.extend_from_slice(pixel);
+                line[start..].sort_by(|a, b| a.depth.total_cmp(&b.depth));
+                // running total of samples in the line
+                counts.extend_from_slice(&(line.len() as i32).to_le_bytes());
+            }
+
+            let mut data = Vec::with_capacity(line.len() * 5 * 4);
+            let channels: [fn(&DeepSample) -> Float; 5] = [
+                |s| s.alpha,
+                |s| s.color.blue,
+                |s| s.color.green,
+                |s| s.color.red,
+                |s| s.depth,
+            ];
+            for channel in channels {
+                for sample in &line {
+                    data.extend_from_slice(&to_f32(channel(sample)).to_le_bytes());
+                }
+            }
+
+            let mut block = Vec::with_capacity(28 + counts.len() + data.len());
+            block.extend_from_slice(&(y as i32).to_le_bytes());
+            block.extend_from_slice(&(counts.len() as u64).to_le_bytes());
+            // packed and unpacked size of the samples, the same without compression
+            block.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            block.extend_from_slice(&(data.len() as u64).to_le_bytes());
+            block.extend_from_slice(&counts);
+            block.extend_from_slice(&data);
+            blocks.push(block);
+        }
+
+        write_blocks(&mut out, header, &blocks)
+    }
+
+    /// Write the image to a file, see `write`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+}
+
+/// Start a header with the magic number, the version and the attributes every image needs.
+/// The caller adds its own attributes and terminates the header.
+fn header(flags: u32, channels: &[&str], width: usize, height: usize) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC.to_le_bytes());
+    // version 2, single part
+    header.extend_from_slice(&(2 | flags).to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for name in channels {
+        chlist.extend_from_slice(name.as_bytes());
+        chlist.push(0);
+        chlist.extend_from_slice(&FLOAT.to_le_bytes());
+        // linear flag and reserved bytes
+        chlist.extend_from_slice(&[0, 0, 0, 0]);
+        // x and y sampling
+        chlist.extend_from_slice(&1_i32.to_le_bytes());
+        chlist.extend_from_slice(&1_i32.to_le_bytes());
+    }
+    chlist.push(0);
+    attribute(&mut header, "channels", "chlist", &chlist);
+    // no compression
+    attribute(&mut header, "compression", "compression", &[0]);
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    // increasing y
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1_f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1_f32.to_le_bytes(),
+    );
+    header
+}
+
+/// Write the terminated header, the offset table of the blocks and the blocks.
+fn write_blocks<W: Write>(out: &mut W, mut header: Vec<u8>, blocks: &[Vec<u8>]) -> io::Result<()> {
+    let mut offset = (header.len() + blocks.len() * 8) as u64;
+    for block in blocks {
+        header.extend_from_slice(&offset.to_le_bytes());
+        offset += block.len() as u64;
+    }
+    out.write_all(&header)?;
+    for block in blocks {
+        out.write_all(block)?;
+    }
+    out.flush()
+}
+
+/// Append a header attribute.
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// Value stored in a 32 bit float channel.
+#[allow(clippy::unnecessary_cast)] // Float is f32 with the `f32` feature
+fn to_f32(value: Float) -> f32 {
+    value as f32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Float, Vector, RED};
+
+    fn f32_at(bytes: &[u8], i: usize) -> f32 {
+        f32::from_le_bytes(bytes[i..i + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn channels_exr() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(1, 0, RED);
+        let id = Uuid::new_v4();
+        let gbuffer = GBuffer {
+            width: 2,
+            height: 1,
+            ids: vec![None, Some(id)],
+            depths: vec![Float::INFINITY, 2.5],
+            normals: vec![Vector::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)],
+        };
+        let mut exr = Exr::new(2, 1);
+        exr.add_canvas("", &canvas);
+        exr.add_canvas("diffuse", &canvas);
+        exr.add_gbuffer(&gbuffer);
+
+        assert_eq!(
+            exr.channel_names(),
+            vec![
+                "B",
+                "G",
+                "N.X",
+                "N.Y",
+                "N.Z",
+                "R",
+                "Z",
+                "diffuse.B",
+                "diffuse.G",
+                "diffuse.R",
+                "id"
+            ]
+        );
+
+        canvas.set_alpha(0, 0, 0.0);
+        let mut exr = Exr::new(2, 1);
+        exr.add_canvas("", &canvas);
+        assert_eq!(exr.channel_names(), vec!["A", "B", "G", "R"]);
+    }
+
+    #[test]
+    fn write_exr() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.write_pixel(1, 1, RED);
+        let mut exr = Exr::new(2, 2);
+        exr.add_canvas("", &canvas);
+        let mut bytes = Vec::new();
+        exr.write(&mut bytes).unwrap();
+
+        assert_eq!(&bytes[0..4], &[0x76, 0x2f, 0x31, 0x01]);
+        let names = b"B\0\x02\0\0\0";
+        assert!(bytes.windows(names.len()).any(|w| w == names));
+
+        // two lines of 2 pixels * 3 channels * 4 bytes, each with 8 bytes of prefix
+        let last_line = bytes.len() - (8 + 24);
+        let offset_table = last_line - (8 + 24) - 16;
+        let offset = u64::from_le_bytes(
+            bytes[offset_table + 8..offset_table + 16]
+                .try_into()
+                .unwrap(),
+        );
+        assert_eq!(offset as usize, last_line);
+        assert_eq!(&bytes[last_line..last_line + 4], &1_i32.to_le_bytes());
+        // channels are stored B, G, R
+        let r = last_line + 8 + 16;
+        assert_eq!(f32_at(&bytes, r), 0.0);
+        assert_eq!(f32_at(&bytes, r + 4), 1.0);
+        assert_eq!(f32_at(&bytes, last_line + 8 + 4), 0.0);
+    }
+
+    fn i32_at(bytes: &[u8], i: usize) -> i32 {
+        i32::from_le_bytes(bytes[i..i + 4].try_into().unwrap())
+    }
+
+    fn u64_at(bytes: &[u8], i: usize) -> u64 {
+        u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap())
+    }
+
+    /// Offset of the first entry of the offset table, right after the header.
+    fn offset_table(bytes: &[u8]) -> usize {
+        let end = b"screenWindowWidth\0float\0\x04\0\0\0";
+        let mut i = bytes.windows(end.len()).position(|w| w == end).unwrap() + end.len() + 4;
+        // skip the attributes after the standard ones up to the terminating zero
+        while bytes[i] != 0 {
+            let name = bytes[i..].iter().position(|b| *b == 0).unwrap();
+            let kind = bytes[i + name + 1..].iter().position(|b| *b == 0).unwrap();
+            let size = i + name + kind + 2;
+            i = size + 4 + i32_at(bytes, size) as usize;
+        }
+        i + 1
+    }
+
+    #[test]
+    fn tiled_exr() {
+        let mut canvas = Canvas::new(3, 3);
+        canvas.write_pixel(2, 2, RED);
+        let mut exr = Exr::new(3, 3).with_tiles(2, 2);
+        exr.add_canvas("", &canvas);
+        let mut bytes = Vec::new();
+        exr.write(&mut bytes).unwrap();
+
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 0x202);
+        let tiles = b"tiles\0tiledesc\0\x09\0\0\0\x02\0\0\0\x02\0\0\0\0";
+        assert!(bytes.windows(tiles.len()).any(|w| w == tiles));
+
+        // 2x2 tiles, the last one holds the single red pixel
+        let table = offset_table(&bytes);
+        let last = u64_at(&bytes, table + 24) as usize;
+        assert_eq!(
+            (i32_at(&bytes, last), i32_at(&bytes, last + 4)),
+            (1, 1),
+            "tile coordinates"
+        );
+        assert_eq!(i32_at(&bytes, last + 16), 12);
+        assert_eq!(bytes.len(), last + 20 + 12);
+        // channels are stored B, G, R
+        assert_eq!(f32_at(&bytes, last + 20 + 8), 1.0);
+        // the tile to the left of it is 2x1 pixels
+        let left = u64_at(&bytes, table + 16) as usize;
+        assert_eq!(i32_at(&bytes, left + 16), 24);
+        assert_eq!(left + 20 + 24, last);
+    }
+
+    #[test]
+    fn deep_exr() {
+        let mut canvas = Canvas::new(2, 1);
+        canvas.write_pixel(1, 0, RED);
+        let gbuffer = GBuffer {
+            width: 2,
+            height: 1,
+            ids: vec![None, Some(Uuid::new_v4())],
+            depths: vec![Float::INFINITY, 2.5],
+            normals: vec![Vector::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0)],
+        };
+        let mut exr = DeepExr::from_passes(&canvas, &gbuffer);
+        assert!(exr.samples_at(0, 0).is_empty());
+        assert_eq!(exr.samples_at(1, 0)[0].depth, 2.5);
+        let behind = DeepSample {
+            color: RGB::new(0.0, 0.5, 0.0),
+            alpha: 0.5,
+            depth: 4.0,
+        };
+        let front = DeepSample {
+            depth: 1.0,
+            ..behind
+        };
+        exr.add_sample(1, 0, behind);
+        exr.add_sample(1, 0, front);
+
+        let mut bytes = Vec::new();
+        exr.write(&mut bytes).unwrap();
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 0x802);
+        let kind = b"type\0string\0\x0c\0\0\0deepscanline";
+        assert!(bytes.windows(kind.len()).any(|w| w == kind));
+        let most = b"maxSamplesPerPixel\0int\0\x04\0\0\0\x03\0\0\0";
+        assert!(bytes.windows(most.len()).any(|w| w == most));
+
+        let line = u64_at(&bytes, offset_table(&bytes)) as usize;
+        assert_eq!(i32_at(&bytes, line), 0);
+        // two counts, 3 samples of 5 channels
+        assert_eq!(u64_at(&bytes, line + 4), 8);
+        assert_eq!(u64_at(&bytes, line + 12), 60);
+        assert_eq!(u64_at(&bytes, line + 20), 60);
+        assert_eq!(
+            (i32_at(&bytes, line + 28), i32_at(&bytes, line + 32)),
+            (0, 3)
+        );
+        assert_eq!(bytes.len(), line + 36 + 60);
+        // the Z channel comes last, sorted by depth
+        let z = line + 36 + 48;
+        assert_eq!(
+            [
+                f32_at(&bytes, z),
+                f32_at(&bytes, z + 4),
+                f32_at(&bytes, z + 8)
+            ],
+            [1.0, 2.5, 4.0]
+        );
+        // R of the red sample in the middle
+        assert_eq!(f32_at(&bytes, line + 36 + 36 + 4), 1.0);
+    }
+}
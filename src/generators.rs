@@ -0,0 +1,384 @@
+//! Procedural scenes, as demo content and as stress tests for the bounding
+//! volume culling of groups. Every generator returns a `Group` to add to a
+//! world, random layouts are drawn from a `Sampler`, so a seed always gives
+//! the same scene:
+//!
+//! ```
+//! use rtracer::{generators, presets, MaterialJitter, Sampler, Shape, World};
+//!
+//! let jitter = MaterialJitter {
+//!     brightness: 0.3,
+//!     ..MaterialJitter::default()
+//! };
+//! let mut sampler = Sampler::new(3);
+//! let city = generators::city(4, 4, &mut sampler, |s| {
+//!     let mut m = presets::matte();
+//!     jitter.apply(&mut m, s);
+//!     m
+//! });
+//!
+//! let mut w = World::new();
+//! w.add_object(Box::new(city));
+//! ```
+
+use crate::consts::SQRT_2;
+use crate::{
+    shapes::mesh::Face, Cube, Float, Group, Material, Mesh, Point, Sampler, Shape, Transformation,
+};
+
+/// Width of a city block, streets are left between blocks.
+const BLOCK_SIZE: Float = 10.0;
+
+/// Width of the streets of a city.
+const STREET_WIDTH: Float = 4.0;
+
+/// Building as box of the given size standing on the ground at (x, z).
+fn building(x: Float, z: Float, size: (Float, Float, Float), material: Material) -> Cube {
+    let (width, height, depth) = size;
+    let mut c = Cube::new();
+    c.set_transform(
+        Transformation::new()
+            .then_scaling(width / 2.0, height / 2.0, depth / 2.0)
+            .then_translation(x, height / 2.0, z),
+    );
+    c.set_material(material);
+    c
+}
+
+/// Grid of city blocks with four buildings of random height each, standing
+/// on y = 0 and centered at the origin. `material` is asked once per building.
+pub fn city<F>(blocks_x: usize, blocks_z: usize, sampler: &mut Sampler, mut material: F) -> Group
+where
+    F: FnMut(&mut Sampler) -> Material,
+{
+    let pitch = BLOCK_SIZE + STREET_WIDTH;
+    let offset_x = (blocks_x as Float - 1.0) * pitch / 2.0;
+    let offset_z = (blocks_z as Float - 1.0) * pitch / 2.0;
+    let lot = BLOCK_SIZE / 2.0;
+
+    let mut city = Group::new();
+    for bx in 0..blocks_x {
+        for bz in 0..blocks_z {
+            let mut block = Group::new();
+            let (cx, cz) = (
+                bx as Float * pitch - offset_x,
+                bz as Float * pitch - offset_z,
+            );
+            for (lx, lz) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+                let size = (
+                    sampler.range(0.6, 0.9) * lot,
+                    sampler.range(2.0, 12.0),
+                    sampler.range(0.6, 0.9) * lot,
+                );
+                let (x, z) = (cx + lx * lot / 2.0, cz + lz * lot / 2.0);
+                block.add_object(Box::new(building(x, z, size, material(sampler))));
+            }
+            city.add_object(Box::new(block));
+        }
+    }
+    city
+}
+
+/// Width of a maze corridor.
+const CELL_SIZE: Float = 2.0;
+
+/// Thickness of maze walls.
+const WALL_THICKNESS: Float = 0.2;
+
+/// Perfect maze of `width` x `depth` cells, every cell is reachable on exactly
+/// one path. The entrance is west of the first cell at the -x/-z corner and
+/// the exit east of the last cell, walls are 2 units high and stand on y = 0.
+/// `material` is asked once per wall segment.
+pub fn maze<F>(width: usize, depth: usize, sampler: &mut Sampler, mut material: F) -> Group
+where
+    F: FnMut(&mut Sampler) -> Material,
+{
+    // walls along x north of each row, and along z west of each column
+    let mut walls_x = vec![true; width * (depth + 1)];
+    let mut walls_z = vec![true; (width + 1) * depth];
+    let mut visited = vec![false; width * depth];
+
+    // carve by depth-first search with backtracking
+    let mut stack = vec![(0, 0)];
+    if width * depth > 0 {
+        visited[0] = true;
+    }
+    while let Some(&(x, z)) = stack.last() {
+        let mut next = Vec::with_capacity(4);
+        if x > 0 && !visited[z * width + x - 1] {
+            next.push((x - 1, z));
+        }
+        if x + 1 < width && !visited[z * width + x + 1] {
+            next.push((x + 1, z));
+        }
+        if z > 0 && !visited[(z - 1) * width + x] {
+            next.push((x, z - 1));
+        }
+        if z + 1 < depth && !visited[(z + 1) * width + x] {
+            next.push((x, z + 1));
+        }
+        if next.is_empty() {
+            stack.pop();
+            continue;
+        }
+
+        let (nx, nz) = next[(sampler.next_u64() % next.len() as u64) as usize];
+        if nx != x {
+            walls_z[z * (width + 1) + x.max(nx)] = false;
+        } else {
+            walls_x[z.max(nz) * width + x] = false;
+        }
+        visited[nz * width + nx] = true;
+        stack.push((nx, nz));
+    }
+    if width * depth > 0 {
+        walls_z[0] = false;
+        walls_z[(depth - 1) * (width + 1) + width] = false;
+    }
+
+    let origin_x = -(width as Float) * CELL_SIZE / 2.0;
+    let origin_z = -(depth as Float) * CELL_SIZE / 2.0;
+    let length = CELL_SIZE + WALL_THICKNESS;
+    let mut maze = Group::new();
+    for z in 0..=depth {
+        let mut row = Group::new();
+        for x in 0..width {
+            if walls_x[z * width + x] {
+                let (wx, wz) = (
+                    origin_x + (x as Float + 0.5) * CELL_SIZE,
+                    origin_z + z as Float * CELL_SIZE,
+                );
+                let size = (length, CELL_SIZE, WALL_THICKNESS);
+                row.add_object(Box::new(building(wx, wz, size, material(sampler))));
+            }
+        }
+        if z < depth {
+            for x in 0..=width {
+                if walls_z[z * (width + 1) + x] {
+                    let (wx, wz) = (
+                        origin_x + x as Float * CELL_SIZE,
+                        origin_z + (z as Float + 0.5) * CELL_SIZE,
+                    );
+                    let size = (WALL_THICKNESS, CELL_SIZE, length);
+                    row.add_object(Box::new(building(wx, wz, size, material(sampler))));
+                }
+            }
+        }
+        maze.add_object(Box::new(row));
+    }
+    maze
+}
+
+/// Offsets of the 20 of 27 sub cubes of a Menger sponge, in units of their
+/// size, with at most one coordinate in the middle.
+fn menger_offsets() -> Vec<(Float, Float, Float)> {
+    let mut offsets = Vec::with_capacity(20);
+    for x in [-1.0, 0.0, 1.0] {
+        for y in [-1.0, 0.0, 1.0] {
+            for z in [-1.0, 0.0, 1.0] {
+                if [x, y, z].iter().filter(|c: &&Float| **c == 0.0).count() < 2 {
+                    offsets.push((x, y, z));
+                }
+            }
+        }
+    }
+    offsets
+}
+
+/// Part of a sponge with half the edge length `half` around `center`.
+fn menger_part<F>(level: usize, center: Point, half: Float, material: &mut F) -> Box<dyn Shape>
+where
+    F: FnMut() -> Material,
+{
+    if level == 0 {
+        let mut cube = Cube::new();
+        cube.set_transform(
+            Transformation::new()
+                .then_scaling(half, half, half)
+                .then_translation(center.x, center.y, center.z),
+        );
+        cube.set_material(material());
+        return Box::new(cube);
+    }
+
+    let third = half / 3.0;
+    let mut group = Group::new();
+    for (x, y, z) in menger_offsets() {
+        let sub = Point::new(
+            center.x + 2.0 * third * x,
+            center.y + 2.0 * third * y,
+            center.z + 2.0 * third * z,
+        );
+        group.add_object(menger_part(level - 1, sub, third, material));
+    }
+    Box::new(group)
+}
+
+/// Menger sponge filling the cube from -1 to 1, cubes with a third of the
+/// size are cut out of the faces and the center, `level` times.
+/// The `20^level` cubes are nested in groups of 20, `material` is asked once per cube.
+pub fn menger_sponge<F: FnMut() -> Material>(level: usize, mut material: F) -> Group {
+    let mut sponge = Group::new();
+    sponge.add_object(menger_part(
+        level,
+        Point::new(0.0, 0.0, 0.0),
+        1.0,
+        &mut material,
+    ));
+    sponge
+}
+
+/// Square pyramid standing on y = 0 around `center`, with all edges `size` long.
+fn pyramid(center: Point, size: Float, material: Material) -> Mesh {
+    let half = size / 2.0;
+    let (x, y, z) = (center.x, center.y, center.z);
+    let vertices = vec![
+        Point::new(x - half, y, z - half),
+        Point::new(x + half, y, z - half),
+        Point::new(x + half, y, z + half),
+        Point::new(x - half, y, z + half),
+        Point::new(x, y + half * SQRT_2, z),
+    ];
+    let faces = vec![
+        Face::new([0, 1, 2]),
+        Face::new([0, 2, 3]),
+        Face::new([0, 4, 1]),
+        Face::new([1, 4, 2]),
+        Face::new([2, 4, 3]),
+        Face::new([3, 4, 0]),
+    ];
+    let mut mesh = Mesh::new(vertices, Vec::new(), faces);
+    mesh.set_material(material);
+    mesh
+}
+
+/// Part of a Sierpinski pyramid with the base centered at `center`.
+fn sierpinski_part<F>(level: usize, center: Point, size: Float, material: &mut F) -> Box<dyn Shape>
+where
+    F: FnMut() -> Material,
+{
+    if level == 0 {
+        return Box::new(pyramid(center, size, material()));
+    }
+
+    let quarter = size / 4.0;
+    let mut group = Group::new();
+    for (x, y, z) in [
+        (-quarter, 0.0, -quarter),
+        (quarter, 0.0, -quarter),
+        (quarter, 0.0, quarter),
+        (-quarter, 0.0, quarter),
+        (0.0, quarter * SQRT_2, 0.0),
+    ] {
+        let sub = Point::new(center.x + x, center.y + y, center.z + z);
+        group.add_object(sierpinski_part(level - 1, sub, size / 2.0, material));
+    }
+    Box::new(group)
+}
+
+/// Sierpinski pyramid with the base from -1 to 1 on y = 0, every pyramid is
+/// replaced by five of half the size, one at each corner of its base and
+/// one on top, `level` times. The `5^level` pyramids are nested in groups of 5,
+/// `material` is asked once per pyramid.
+pub fn sierpinski_pyramid<F: FnMut() -> Material>(level: usize, mut material: F) -> Group {
+    let mut pyramid = Group::new();
+    pyramid.add_object(sierpinski_part(
+        level,
+        Point::new(0.0, 0.0, 0.0),
+        2.0,
+        &mut material,
+    ));
+    pyramid
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Ray, Vector};
+
+    /// Number of shapes which are not groups.
+    fn leaves(s: &dyn Shape) -> usize {
+        match s.child(0) {
+            Some(_) => (0..).map_while(|i| s.child(i)).map(leaves).sum(),
+            None => 1,
+        }
+    }
+
+    #[test]
+    fn city_generator() {
+        let a = city(3, 2, &mut Sampler::new(5), |_| Material::default());
+        let b = city(3, 2, &mut Sampler::new(5), |_| Material::default());
+        assert_eq!(a.objects().len(), 6);
+        assert_eq!(leaves(&a), 24);
+        assert_eq!(a.bounds(), b.bounds());
+
+        let bounds = a.bounds();
+        assert_eq!(bounds.min.y, 0.0);
+        assert!(bounds.max.y <= 12.0);
+        assert!((bounds.min.x + bounds.max.x).abs() < BLOCK_SIZE / 2.0);
+        assert!(bounds.max.x < 1.5 * (BLOCK_SIZE + STREET_WIDTH));
+    }
+
+    #[test]
+    fn maze_generator() {
+        let (width, depth) = (5, 4);
+        let mut calls = 0;
+        let m = maze(width, depth, &mut Sampler::new(9), |_| {
+            calls += 1;
+            Material::default()
+        });
+
+        // a spanning tree of the cells opens one wall less than there are cells,
+        // entrance and exit open two more
+        let all = width * (depth + 1) + (width + 1) * depth;
+        assert_eq!(leaves(&m), all - (width * depth - 1) - 2);
+        assert_eq!(calls, leaves(&m));
+
+        // the entrance is open, the first cell has a wall to the north
+        let r = Ray::new(Point::new(-10.0, 1.0, -3.0), Vector::new(1.0, 0.0, 0.0));
+        let xs = m.intersect(&r).unwrap_or_default();
+        assert!(xs.iter().all(|x| x.t > 10.0 - 5.0 + CELL_SIZE / 2.0));
+        let r = Ray::new(Point::new(-4.0, 1.0, -10.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = m.intersect(&r).unwrap();
+        assert!(crate::float_eq(
+            xs.iter().map(|x| x.t).fold(Float::INFINITY, Float::min),
+            10.0 - 4.0 - WALL_THICKNESS / 2.0
+        ));
+    }
+
+    #[test]
+    fn menger_generator() {
+        let sponge = menger_sponge(2, Material::default);
+        assert_eq!(leaves(&sponge), 400);
+        assert_eq!(sponge.bounds().max, Point::new(1.0, 1.0, 1.0));
+
+        // the tunnels through the centers of the faces are open
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(sponge.intersect(&r).is_none());
+        let r = Ray::new(Point::new(0.0, 2.0 / 3.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(sponge.intersect(&r).is_none());
+        let r = Ray::new(Point::new(0.9, 0.9, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = sponge.intersect(&r).unwrap();
+        assert!(crate::float_eq(xs[0].t, 4.0));
+        assert_eq!(
+            xs[0].object.normal_at(Point::new(0.9, 0.9, -1.0), None),
+            Vector::new(0.0, 0.0, -1.0)
+        );
+    }
+
+    #[test]
+    fn sierpinski_generator() {
+        let p = sierpinski_pyramid(3, Material::default);
+        assert_eq!(leaves(&p), 125);
+        let bounds = p.bounds();
+        assert_eq!(bounds.min, Point::new(-1.0, 0.0, -1.0));
+        assert_eq!(bounds.max, Point::new(1.0, SQRT_2, 1.0));
+
+        // straight down onto the apex, and through the gap below the top pyramid
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let xs = p.intersect(&r).unwrap();
+        assert!(crate::float_eq(xs[0].t, 5.0 - SQRT_2));
+        let r = Ray::new(Point::new(-5.0, 0.6, 0.0), Vector::new(1.0, 0.0, 0.0));
+        assert!(p.intersect(&r).is_none());
+    }
+}
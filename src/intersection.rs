@@ -1,5 +1,23 @@
 use crate::*;
 
+/// Scenes within this many meters of the origin use the plain epsilon as bias.
+const BIAS_SCALE: Float = 100.0;
+
+/// Offset used to push over_point/under_point off the surface.
+/// It grows with the distance traveled and the magnitude of the coordinates,
+/// since large scenes lose precision and would self-intersect otherwise.
+/// `epsilon` is the bias of a scene near the origin.
+fn surface_bias(t: Float, point: Point, epsilon: Float) -> Float {
+    let magnitude = point
+        .x
+        .abs()
+        .max(point.y.abs())
+        .max(point.z.abs())
+        .max(t.abs());
+    // precision is relative, so far away the bias does not depend on the units
+    epsilon.max(magnitude * EPSILON / BIAS_SCALE)
+}
+
 /// Generic intersection object, which works on all shapes that
 /// implement the 'Shape' trait.
 /// An Intersection existence makes only sense when the underlying
@@ -8,15 +26,57 @@ use crate::*;
 #[derive(Clone, Copy, Debug)]
 pub struct Intersection<'a> {
     /// The t value of the intersection.
-    pub t: f64,
+    pub t: Float,
     /// A reference to the object that was intersected.
     pub object: &'a dyn Shape,
+    /// The shape hit inside of an instanced geometry, `object` is then the instance.
+    pub part: Option<&'a dyn Shape>,
+    /// Index of the mesh face that was hit, with the barycentric weights
+    /// of its second and third corner.
+    pub face: Option<(usize, Float, Float)>,
 }
 
 impl<'a> Intersection<'a> {
     /// Create a new Intersection with a reference to the object.
-    pub fn new(t: f64, object: &'a dyn Shape) -> Self {
-        Self { t, object }
+    pub fn new(t: Float, object: &'a dyn Shape) -> Self {
+        Self {
+            t,
+            object,
+            part: None,
+            face: None,
+        }
+    }
+
+    /// Create a new Intersection with face `index` of a mesh at the
+    /// barycentric weights `u` and `v`.
+    pub fn with_face(t: Float, object: &'a dyn Shape, index: usize, u: Float, v: Float) -> Self {
+        Self {
+            face: Some((index, u, v)),
+            ..Self::new(t, object)
+        }
+    }
+
+    /// Normal in world space at `point` on the surface that was hit.
+    pub fn normal_at(&self, point: Point, w: Option<&World>) -> Vector {
+        self.object.normal_at_hit(self, point, w)
+    }
+
+    /// Material at `point` on the surface that was hit.
+    pub fn material_at(&self, point: Point, w: Option<&World>) -> &'a Material {
+        self.object.material_at_hit(self, point, w)
+    }
+
+    /// Material used for the refractive index of the object containing the ray.
+    fn container_material(&self, r: &Ray, w: Option<&World>) -> &'a Material {
+        match (self.part, self.face) {
+            (None, None) => self.object.get_material(),
+            _ => self.material_at(r.position(self.t), w),
+        }
+    }
+
+    /// Is `other` a hit on the same shape, or on the same part of an instance?
+    pub fn same_shape(&self, other: &Intersection) -> bool {
+        self.object.eq(other.object) && self.part.map(|p| p.id()) == other.part.map(|p| p.id())
     }
 
     pub fn hit(xs: &'a [Intersection]) -> Option<&'a Intersection<'a>> {
@@ -32,7 +92,8 @@ impl<'a> Intersection<'a> {
     ) -> Computation {
         let point = r.position(self.t);
         let eyev = -r.direction();
-        let mut normalv = self.object.normal_at(point, w);
+        let mut normalv = self.normal_at(point, w);
+        let material = self.material_at(point, w);
         let mut inside = false;
 
         if normalv.dot(eyev) < 0.0 {
@@ -40,33 +101,46 @@ impl<'a> Intersection<'a> {
             normalv = -normalv;
         }
 
-        let over_point = point + normalv * EPSILON;
-        let under_point = point - normalv * EPSILON;
+        // offset along the true normal, a bumped one could point into the surface
+        let bias = surface_bias(self.t, point, w.map_or(EPSILON, World::epsilon));
+        let over_point = point + normalv * bias;
+        let under_point = point - normalv * bias;
+        let normalv = material.bump_normal(self.object, point, normalv);
+        let normalv = material.map_normal(self.object, point, normalv, w);
         let reflectv = r.direction().reflect(normalv);
 
         let mut n1 = 0.0;
         let mut n2 = 0.0;
-        let mut container: Vec<&dyn Shape> = Vec::new();
+        let mut n1_rgb = [0.0; 3];
+        let mut n2_rgb = [0.0; 3];
+        let mut container: Vec<&Intersection> = Vec::new();
         for i in xs {
             if i == self {
                 if container.is_empty() {
                     n1 = 1.0;
-                } else if let Some(object) = container.last() {
-                    n1 = object.get_material().refractive_index;
+                    n1_rgb = [1.0; 3];
+                } else if let Some(x) = container.last() {
+                    let m = x.container_material(r, w);
+                    n1 = m.refractive_index;
+                    n1_rgb = [0, 1, 2].map(|c| m.refractive_index_for(c));
                 }
             }
 
-            if container.contains(&i.object) {
-                container = container.into_iter().filter(|o| *o != i.object).collect();
-            } else {
-                container.push(i.object);
+            match container.iter().position(|x| x.same_shape(i)) {
+                Some(index) => {
+                    container.remove(index);
+                }
+                None => container.push(i),
             }
 
             if i == self {
                 if container.is_empty() {
                     n2 = 1.0;
-                } else if let Some(object) = container.last() {
-                    n2 = object.get_material().refractive_index;
+                    n2_rgb = [1.0; 3];
+                } else if let Some(x) = container.last() {
+                    let m = x.container_material(r, w);
+                    n2 = m.refractive_index;
+                    n2_rgb = [0, 1, 2].map(|c| m.refractive_index_for(c));
                 }
 
                 break;
@@ -76,6 +150,7 @@ impl<'a> Intersection<'a> {
         Computation {
             t: self.t,
             object: self.object,
+            material,
             point,
             eyev,
             normalv,
@@ -85,10 +160,52 @@ impl<'a> Intersection<'a> {
             reflectv,
             n1,
             n2,
+            n1_rgb,
+            n2_rgb,
         }
     }
 }
 
+/// Where a ray enters and leaves an object, see `Shape::thickness`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Thickness {
+    /// t where the ray enters the object, 0 if it starts inside.
+    pub entry: Float,
+    /// t where the ray leaves the object.
+    pub exit: Float,
+    /// Distance traveled inside of the object, in world units.
+    pub length: Float,
+}
+
+impl Thickness {
+    /// The first span of `xs` the ray passes in front of its origin.
+    /// Intersections have to be with a single object and alternate
+    /// between entering and leaving it.
+    pub fn from_intersections(ray: &Ray, xs: &[Intersection]) -> Option<Self> {
+        let mut ts: Vec<Float> = xs.iter().map(|i| i.t).collect();
+        ts.sort_unstable_by(|a, b| float_cmp(*a, *b));
+
+        let span = ts.chunks_exact(2).find(|span| span[1] >= 0.0)?;
+        let entry = span[0].max(0.0);
+        let exit = span[1];
+        Some(Self {
+            entry,
+            exit,
+            length: (exit - entry) * ray.direction().magnitude(),
+        })
+    }
+
+    /// Fraction of the light left after passing the object, by Beer-Lambert.
+    /// `absorption` is the absorption coefficient per world unit.
+    pub fn attenuation(&self, absorption: RGB) -> RGB {
+        RGB::new(
+            (-absorption.red * self.length).exp(),
+            (-absorption.green * self.length).exp(),
+            (-absorption.blue * self.length).exp(),
+        )
+    }
+}
+
 impl PartialEq for Intersection<'_> {
     fn eq(&self, other: &Self) -> bool {
         float_eq(self.t, other.t) && self.object.eq(other.object)
@@ -224,7 +341,7 @@ mod test {
     fn hit_offset_point_intersection() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let mut shape = Sphere::new();
-        shape.set_transform(Transformation::new().translation(0.0, 0.0, 1.0));
+        shape.set_transform(Transformation::new().then_translation(0.0, 0.0, 1.0));
         let i = Intersection::new(5.0, &shape);
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
@@ -233,37 +350,90 @@ mod test {
         assert!(comps.point.z > comps.over_point.z);
     }
 
+    #[test]
+    fn hit_offset_large_scene_intersection() {
+        let r = Ray::new(Point::new(0.0, 0.0, -10000.0), Vector::new(0.0, 0.0, 1.0));
+        let mut shape = Sphere::new();
+        shape.set_transform(Transformation::new().then_scaling(5000.0, 5000.0, 5000.0));
+        let i = Intersection::new(5000.0, &shape);
+        let xs = &vec![i];
+        let comps = i.prepare_computations(&r, xs, None);
+
+        assert!(comps.point.z - comps.over_point.z > EPSILON * 10.0);
+        assert!(comps.under_point.z - comps.point.z > EPSILON * 10.0);
+    }
+
+    #[test]
+    fn bias_small_scene_intersection() {
+        assert_eq!(
+            surface_bias(5.0, Point::new(1.0, -2.0, 3.0), EPSILON),
+            EPSILON
+        );
+        assert!(float_eq(
+            surface_bias(1.0, Point::new(0.0, 2000.0, 0.0), EPSILON),
+            EPSILON * 20.0
+        ));
+    }
+
+    #[test]
+    fn bias_units_intersection() {
+        let mut w = World::new();
+        w.set_units(Units::Millimeters);
+        let shape = Sphere::new();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, &shape);
+        let comps = i.prepare_computations(&r, &vec![i], Some(&w));
+
+        assert!(float_eq(
+            comps.point.z - comps.over_point.z,
+            Units::Millimeters.epsilon()
+        ));
+        // far away the bias is relative to the coordinates in any units
+        assert!(float_eq(
+            surface_bias(1.0, Point::new(0.0, 2.0e6, 0.0), 0.1),
+            surface_bias(1.0, Point::new(0.0, 2.0e6, 0.0), EPSILON)
+        ));
+    }
+
     #[test]
     fn precompute_reflect_intersection() {
         let shape = Plane::new();
         let r = Ray::new(
             Point::new(0.0, 1.0, -1.0),
-            Vector::new(0.0, -(2_f64.sqrt() / 2.0), 2_f64.sqrt() / 2.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt() / 2.0),
+                (2.0 as Float).sqrt() / 2.0,
+            ),
         );
-        let i = Intersection::new(2_f64.sqrt(), &shape);
+        let i = Intersection::new((2.0 as Float).sqrt(), &shape);
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
 
         assert_eq!(
             comps.reflectv,
-            Vector::new(0.0, 2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0)
+            Vector::new(
+                0.0,
+                (2.0 as Float).sqrt() / 2.0,
+                (2.0 as Float).sqrt() / 2.0
+            )
         );
     }
 
     #[test]
     fn find_n1_n2_intersection() {
         let mut a = Sphere::glass_sphere();
-        a.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+        a.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
         a.get_material_mut().refractive_index = 1.5;
         let ia1 = Intersection::new(2.0, &a);
         let ia2 = Intersection::new(6.0, &a);
         let mut b = Sphere::glass_sphere();
-        b.set_transform(Transformation::new().translation(0.0, 0.0, -0.25));
+        b.set_transform(Transformation::new().then_translation(0.0, 0.0, -0.25));
         b.get_material_mut().refractive_index = 2.0;
         let ib1 = Intersection::new(2.75, &b);
         let ib2 = Intersection::new(4.75, &b);
         let mut c = Sphere::glass_sphere();
-        c.set_transform(Transformation::new().translation(0.0, 0.0, 0.25));
+        c.set_transform(Transformation::new().then_translation(0.0, 0.0, 0.25));
         c.get_material_mut().refractive_index = 2.5;
         let ic1 = Intersection::new(3.25, &c);
         let ic2 = Intersection::new(5.25, &c);
@@ -289,7 +459,7 @@ mod test {
     fn under_point_intersection() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let mut shape = Sphere::glass_sphere();
-        shape.set_transform(Transformation::new().translation(0.0, 0.0, 1.0));
+        shape.set_transform(Transformation::new().then_translation(0.0, 0.0, 1.0));
         let i = Intersection::new(5.0, &shape);
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
@@ -298,16 +468,34 @@ mod test {
         assert!(comps.point.z < comps.under_point.z);
     }
 
+    #[test]
+    fn find_n1_n2_dispersion_intersection() {
+        let mut a = Sphere::glass_sphere();
+        a.get_material_mut().dispersion = Some([1.51, 1.52, 1.53]);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = vec![Intersection::new(4.0, &a), Intersection::new(6.0, &a)];
+        let entry = xs[0].prepare_computations(&r, &xs, None);
+        let exit = xs[1].prepare_computations(&r, &xs, None);
+
+        assert_eq!(entry.n1, 1.0);
+        assert_eq!(entry.n2, 1.5);
+        assert_eq!(entry.n1_rgb, [1.0; 3]);
+        assert_eq!(entry.n2_rgb, [1.51, 1.52, 1.53]);
+        assert_eq!(exit.n1_rgb, [1.51, 1.52, 1.53]);
+        assert_eq!(exit.n2_rgb, [1.0; 3]);
+        assert!(entry.is_dispersive());
+    }
+
     #[test]
     fn schlick_total_internal_reflection_intersection() {
         let shape = Sphere::glass_sphere();
         let r = Ray::new(
-            Point::new(0.0, 0.0, 2_f64.sqrt() / 2.0),
+            Point::new(0.0, 0.0, (2.0 as Float).sqrt() / 2.0),
             Vector::new(0.0, 1.0, 0.0),
         );
         let xs = vec![
-            Intersection::new(-2_f64.sqrt() / 2.0, &shape),
-            Intersection::new(2_f64.sqrt() / 2.0, &shape),
+            Intersection::new(-(2.0 as Float).sqrt() / 2.0, &shape),
+            Intersection::new((2.0 as Float).sqrt() / 2.0, &shape),
         ];
         let comps = xs[1].prepare_computations(&r, &xs, None);
         let reflectance = comps.schlick();
@@ -339,4 +527,56 @@ mod test {
 
         assert!(float_eq(reflectance, 0.48873));
     }
+
+    #[test]
+    fn thickness_sphere() {
+        let mut s = Sphere::glass();
+        s.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let t = s.thickness(&r).unwrap();
+
+        assert_eq!(t.entry, 3.0);
+        assert_eq!(t.exit, 7.0);
+        assert_eq!(t.length, 4.0);
+        let a = t.attenuation(RGB::new(0.0, 0.5, 1.0));
+        assert_eq!(
+            a,
+            RGB::new(1.0, (-(2.0 as Float)).exp(), (-(4.0 as Float)).exp())
+        );
+    }
+
+    #[test]
+    fn thickness_inside_sphere() {
+        let s = Sphere::new();
+        // the direction is not normalized
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 2.0));
+        let t = s.thickness(&r).unwrap();
+
+        assert_eq!(t.entry, 0.0);
+        assert_eq!(t.exit, 0.5);
+        assert_eq!(t.length, 1.0);
+
+        let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(s.thickness(&r).is_none());
+    }
+
+    #[test]
+    fn normal_mapped_hit() {
+        let mut image = crate::Canvas::new(1, 1);
+        image.fill(crate::RGB::new(0.5, 1.0, 1.0));
+        let mut s = Sphere::new();
+        s.set_material(
+            crate::Material::builder()
+                .normal_map(crate::NormalMap::new(image))
+                .build(),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let i = Intersection::new(4.0, &s);
+        let comps = i.prepare_computations(&r, &vec![i], None);
+
+        // the shading normal is tilted up, the offsets follow the surface
+        assert_eq!(comps.normalv, Vector::new(0.0, 1.0, -1.0).normalize());
+        assert!(comps.over_point.z < -1.0);
+        assert!(crate::float_eq(comps.over_point.y, 0.0));
+    }
 }
@@ -1,14 +1,39 @@
 use std::cmp::Ordering;
 
-pub const EPSILON: f64 = 0.0001;
+/// Scalar type of all coordinates, colors and distances.
+/// Double precision unless the `f32` feature is enabled.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+
+/// Scalar type of all coordinates, colors and distances.
+/// Single precision, selected by the `f32` feature.
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+/// Mathematical constants of the `Float` type.
+pub mod consts {
+    #[cfg(not(feature = "f32"))]
+    pub use std::f64::consts::*;
+
+    #[cfg(feature = "f32")]
+    pub use std::f32::consts::*;
+}
+
+/// Tolerance for comparing floats, coarser in single precision.
+#[cfg(not(feature = "f32"))]
+pub const EPSILON: Float = 0.0001;
+
+/// Tolerance for comparing floats, coarser in single precision.
+#[cfg(feature = "f32")]
+pub const EPSILON: Float = 0.001;
 
 #[inline(always)]
-pub fn float_eq(a: f64, b: f64) -> bool {
+pub fn float_eq(a: Float, b: Float) -> bool {
     (a - b).abs() < EPSILON
 }
 
 #[inline(always)]
-pub fn float_cmp(a: f64, b: f64) -> Ordering {
+pub fn float_cmp(a: Float, b: Float) -> Ordering {
     if float_eq(a, b) {
         Ordering::Equal
     } else if a < b {
@@ -18,6 +43,20 @@ pub fn float_cmp(a: f64, b: f64) -> Ordering {
     }
 }
 
+/// A new id for a shape or pattern.
+#[cfg(not(target_arch = "wasm32"))]
+fn new_id() -> uuid::Uuid {
+    uuid::Uuid::new_v4()
+}
+
+/// A new id for a shape or pattern, counted since there is no randomness.
+#[cfg(target_arch = "wasm32")]
+fn new_id() -> uuid::Uuid {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    uuid::Uuid::from_u64_pair(0, NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
 #[macro_export]
 macro_rules! add_object {
     ($w:expr, $obj:expr) => {
@@ -39,52 +78,165 @@ mod vector;
 pub use crate::vector::Vector;
 
 mod color;
+pub use crate::color::ParseColorError;
 pub use crate::color::RGB;
 pub use crate::color::{BLACK, BLUE, GREEN, RED, WHITE};
 
 mod canvas;
 pub use crate::canvas::Canvas;
+pub use crate::canvas::ColorEncoding;
+pub use crate::canvas::Histogram;
+pub use crate::canvas::PpmError;
+pub use crate::canvas::ResizeFilter;
+pub use crate::canvas::MID_GRAY;
 
 mod matrix;
 pub use crate::matrix::Matrix;
+pub use crate::matrix::ParseMatrixError;
 pub use crate::matrix::IDENTITY;
 
 mod transformations;
+pub use crate::transformations::TransformError;
 pub use crate::transformations::Transformation;
 
+mod quaternion;
+pub use crate::quaternion::Quaternion;
+
+mod sampler;
+pub use crate::sampler::Sampler;
+
+mod noise;
+pub use crate::noise::Noise;
+
+mod units;
+pub use crate::units::Units;
+
+mod bounds;
+pub use crate::bounds::BoundingBox;
+pub use crate::bounds::BoundingSphere;
+
 mod ray;
 pub use crate::ray::Ray;
 
+pub mod uv;
+pub use crate::uv::Uv;
+pub use crate::uv::UvFace;
+
 pub mod shapes;
 pub use crate::shapes::Cone;
+pub use crate::shapes::Csg;
+pub use crate::shapes::CsgOperation;
 pub use crate::shapes::Cube;
 pub use crate::shapes::Cylinder;
+pub use crate::shapes::Frustum;
 pub use crate::shapes::Group;
+pub use crate::shapes::Instance;
+pub use crate::shapes::Mesh;
 pub use crate::shapes::Plane;
+pub use crate::shapes::RoundedBox;
 pub use crate::shapes::Shape;
 pub use crate::shapes::Sphere;
 
 mod intersection;
 pub use crate::intersection::Intersection;
+pub use crate::intersection::Thickness;
 
 mod light;
+pub use crate::light::Falloff;
 pub use crate::light::PointLight;
 
 mod material;
+pub use crate::material::lighting;
+pub use crate::material::lighting_terms;
+pub use crate::material::presets;
+pub use crate::material::LightingContext;
+pub use crate::material::LightingTerms;
 pub use crate::material::Material;
+pub use crate::material::MaterialBuilder;
+pub use crate::material::MaterialJitter;
+pub use crate::material::NormalMap;
+pub use crate::material::ShadingModel;
+
+pub mod shader;
+pub use crate::shader::PathTracer;
+pub use crate::shader::SecondaryRay;
+pub use crate::shader::Shader;
+pub use crate::shader::ShaderClone;
+pub use crate::shader::Shading;
+pub use crate::shader::Toon;
+pub use crate::shader::Whitted;
 
 mod world;
+pub use crate::world::Budget;
+pub use crate::world::ColorTerms;
+pub use crate::world::FrameError;
+pub use crate::world::ObjectFlags;
+pub use crate::world::ObjectHandle;
 pub use crate::world::World;
+pub use crate::world::WorldBuilder;
 
 mod computations;
 pub use crate::computations::Computation;
 
 mod camera;
+pub use crate::camera::render;
 pub use crate::camera::Camera;
+pub use crate::camera::CameraBuilder;
+pub use crate::camera::CameraError;
+pub use crate::camera::CancelToken;
+pub use crate::camera::FrameMetadata;
+pub use crate::camera::ProgressSink;
+pub use crate::camera::Render;
+pub use crate::camera::RenderLimit;
+pub use crate::camera::RenderTerms;
+
+mod antialias;
+pub use crate::antialias::Fxaa;
+pub use crate::antialias::GBuffer;
+
+mod depth_map;
+pub use crate::depth_map::depth_map;
+pub use crate::depth_map::Projection;
+
+pub mod environment;
+pub use crate::environment::load_hdr;
+pub use crate::environment::parse_hdr;
+pub use crate::environment::EnvironmentMap;
+pub use crate::environment::EnvironmentSampler;
+pub use crate::environment::HdrError;
+
+pub mod exr;
+pub use crate::exr::{DeepExr, DeepSample, Exr};
+
+pub mod generators;
+
+#[cfg(feature = "png")]
+mod png;
+
+pub mod library;
+pub use crate::library::load_library;
+pub use crate::library::parse_library;
+pub use crate::library::Library;
+pub use crate::library::LibraryError;
+
+pub mod obj;
+pub use crate::obj::load_obj;
+pub use crate::obj::parse_obj;
+pub use crate::obj::ObjError;
+pub use crate::obj::ObjOptions;
 
 pub mod pattern;
+pub use crate::pattern::Brick;
 pub use crate::pattern::Checkers;
+pub use crate::pattern::ColorRamp;
 pub use crate::pattern::Gradient;
+pub use crate::pattern::GradientMode;
+pub use crate::pattern::Hexagon;
+pub use crate::pattern::Marble;
+pub use crate::pattern::Masked;
 pub use crate::pattern::Pattern;
+pub use crate::pattern::PatternClone;
 pub use crate::pattern::Ring;
 pub use crate::pattern::Stripes;
+pub use crate::pattern::Voronoi;
+pub use crate::pattern::Wood;
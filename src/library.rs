@@ -0,0 +1,468 @@
+use crate::{Float, Material, MaterialBuilder, Point, PointLight, ShadingModel, RGB};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Named materials and lights shared between scenes.
+///
+/// Libraries are written in a subset of TOML, one table per entry:
+///
+/// ```toml
+/// [materials.glass]
+/// color = [0.1, 0.1, 0.1]
+/// transparency = 0.9
+/// refractive_index = 1.5
+/// shading_model = "blinn_phong"
+///
+/// [lights.sun]
+/// position = [-10, 10, -10]
+/// intensity = [1, 1, 1]
+/// ```
+///
+/// Material keys are the fields of `Material` holding numbers, colors
+/// (`[r, g, b]`) and the shading model, patterns and shaders are set in code.
+/// Names and keys may be quoted, e.g. `[materials."glass #2"]`.
+/// Repeating a table or a key is an error.
+#[derive(Debug, Default)]
+pub struct Library {
+    materials: BTreeMap<String, Vec<(String, Value)>>,
+    lights: BTreeMap<String, PointLight>,
+}
+
+/// A value on the right hand side of `key = value`.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(Float),
+    Text(String),
+    Array(Vec<Float>),
+}
+
+impl Value {
+    fn number(&self) -> Result<Float, String> {
+        match self {
+            Value::Number(n) => Ok(*n),
+            _ => Err("expected a number".to_string()),
+        }
+    }
+
+    fn triple(&self) -> Result<[Float; 3], String> {
+        match self {
+            Value::Array(a) if a.len() == 3 => Ok([a[0], a[1], a[2]]),
+            _ => Err("expected an array of 3 numbers".to_string()),
+        }
+    }
+
+    fn color(&self) -> Result<RGB, String> {
+        self.triple().map(|[r, g, b]| RGB::new(r, g, b))
+    }
+}
+
+/// Set the field `key` of a material.
+fn apply(material: &mut Material, key: &str, value: &Value) -> Result<(), String> {
+    match key {
+        "color" => material.color = value.color()?,
+        "ambient" => material.ambient = value.number()?,
+        "diffuse" => material.diffuse = value.number()?,
+        "specular" => material.specular = value.number()?,
+        "shininess" => material.shininess = value.number()?,
+        "reflective" => material.reflective = value.number()?,
+        "transparency" => material.transparency = value.number()?,
+        "refractive_index" => material.refractive_index = value.number()?,
+        "dispersion" => material.dispersion = Some(value.triple()?),
+        "subsurface" => material.subsurface = value.number()?,
+        "scatter_color" => material.scatter_color = value.color()?,
+        "scatter_distance" => material.scatter_distance = value.number()?,
+        "shading_model" => {
+            material.shading_model = match value {
+                Value::Text(t) => match t.as_str() {
+                    "phong" => ShadingModel::Phong,
+                    "blinn_phong" => ShadingModel::BlinnPhong,
+                    "normalized_blinn_phong" => ShadingModel::NormalizedBlinnPhong,
+                    "lambert" => ShadingModel::Lambert,
+                    "pbr" => ShadingModel::Pbr,
+                    "unlit" => ShadingModel::Unlit,
+                    _ => return Err(format!("unknown shading model '{}'", t)),
+                },
+                _ => return Err("expected a string".to_string()),
+            }
+        }
+        _ => return Err(format!("unknown material key '{}'", key)),
+    }
+
+    Ok(())
+}
+
+impl Library {
+    /// Create an empty library.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new material built from the preset `name`.
+    pub fn material(&self, name: &str) -> Option<Material> {
+        let settings = self.materials.get(name)?;
+        let mut material = Material::default();
+        for (key, value) in settings {
+            apply(&mut material, key, value).expect("Settings are checked while parsing!");
+        }
+
+        Some(material)
+    }
+
+    /// Continue changing the preset `name`, e.g. to vary its color.
+    pub fn material_builder(&self, name: &str) -> Option<MaterialBuilder> {
+        self.material(name).map(MaterialBuilder::from)
+    }
+
+    /// The light preset `name`.
+    pub fn light(&self, name: &str) -> Option<PointLight> {
+        self.lights.get(name).copied()
+    }
+
+    /// Names of all material presets in alphabetical order.
+    pub fn material_names(&self) -> Vec<&str> {
+        self.materials.keys().map(|k| k.as_str()).collect()
+    }
+
+    /// Names of all light presets in alphabetical order.
+    pub fn light_names(&self) -> Vec<&str> {
+        self.lights.keys().map(|k| k.as_str()).collect()
+    }
+
+    /// Add the presets of `other`, replacing those with the same name.
+    /// Useful to layer scene specific presets on top of a shared library.
+    pub fn merge(&mut self, other: Library) {
+        self.materials.extend(other.materials);
+        self.lights.extend(other.lights);
+    }
+}
+
+/// Errors while loading a library file.
+#[derive(Debug)]
+pub enum LibraryError {
+    /// The input could not be read.
+    Io(io::Error),
+
+    /// A statement is malformed, `line` starts at 1.
+    Parse { line: usize, message: String },
+
+    /// No material preset has the name.
+    UnknownMaterial(String),
+}
+
+impl fmt::Display for LibraryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LibraryError::Io(why) => write!(f, "Could not read library: {}", why),
+            LibraryError::Parse { line, message } => {
+                write!(f, "Library line {}: {}", line, message)
+            }
+            LibraryError::UnknownMaterial(name) => {
+                write!(f, "Library has no material '{}'", name)
+            }
+        }
+    }
+}
+
+impl Error for LibraryError {}
+
+impl From<io::Error> for LibraryError {
+    fn from(why: io::Error) -> Self {
+        LibraryError::Io(why)
+    }
+}
+
+/// The statement of a line without its comment, `#` in strings is kept.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Parse a bare or quoted key, e.g. a name of a table.
+fn parse_key(key: &str) -> Result<String, String> {
+    let key = key.trim();
+    if let Some(text) = key.strip_prefix('"') {
+        return match text.strip_suffix('"') {
+            Some(text) if !text.contains('"') => Ok(text.to_string()),
+            _ => Err(format!("invalid key {}", key)),
+        };
+    }
+
+    let bare = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if !key.is_empty() && key.chars().all(bare) {
+        Ok(key.to_string())
+    } else {
+        Err(format!("invalid key '{}'", key))
+    }
+}
+
+/// Parse a number, string or array of numbers.
+fn parse_value(value: &str) -> Result<Value, String> {
+    if let Some(text) = value.strip_prefix('"') {
+        return match text.strip_suffix('"') {
+            Some(text) if !text.contains('"') => Ok(Value::Text(text.to_string())),
+            _ => Err(format!("invalid string {}", value)),
+        };
+    }
+    if let Some(items) = value.strip_prefix('[') {
+        let items = items
+            .strip_suffix(']')
+            .ok_or_else(|| format!("unclosed array {}", value))?;
+        return items
+            .split(',')
+            .map(str::trim)
+            .filter(|i| !i.is_empty())
+            .map(|i| i.parse().map_err(|_| format!("invalid number '{}'", i)))
+            .collect::<Result<_, _>>()
+            .map(Value::Array);
+    }
+
+    value
+        .parse()
+        .map(Value::Number)
+        .map_err(|_| format!("invalid value '{}'", value))
+}
+
+/// What the keys of the current table describe.
+enum Section {
+    None,
+    Material(String),
+    Light(String, Option<[Float; 3]>, Option<RGB>),
+}
+
+/// A light is complete once its table ends.
+fn finish(section: &mut Section, library: &mut Library) -> Result<(), String> {
+    if let Section::Light(name, position, intensity) = section {
+        let [x, y, z] = position.ok_or(format!("light '{}' needs a position", name))?;
+        let intensity = intensity.ok_or(format!("light '{}' needs an intensity", name))?;
+        library.lights.insert(
+            name.clone(),
+            PointLight::new(Point::new(x, y, z), intensity),
+        );
+    }
+    *section = Section::None;
+    Ok(())
+}
+
+/// Parse a library of `[materials.<name>]` and `[lights.<name>]` tables.
+/// Lights need a `position` and an `intensity`.
+pub fn parse_library<R: BufRead>(mut reader: R) -> Result<Library, LibraryError> {
+    let mut library = Library::new();
+    let mut section = Section::None;
+
+    let mut line = String::new();
+    let mut number = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        number += 1;
+
+        let statement = strip_comment(&line).trim();
+        let parsed = if statement.is_empty() {
+            Ok(())
+        } else if let Some(table) = statement.strip_prefix('[') {
+            finish(&mut section, &mut library).and_then(|_| {
+                let table = table
+                    .strip_suffix(']')
+                    .ok_or_else(|| format!("unclosed table {}", statement))?;
+                let (kind, name) = table
+                    .trim()
+                    .split_once('.')
+                    .ok_or_else(|| format!("unknown table {}", statement))?;
+                let name = parse_key(name)?;
+                match kind.trim() {
+                    "materials" if library.materials.contains_key(&name) => {
+                        Err(format!("duplicate table {}", statement))
+                    }
+                    "materials" => {
+                        library.materials.insert(name.clone(), Vec::new());
+                        section = Section::Material(name);
+                        Ok(())
+                    }
+                    "lights" if library.lights.contains_key(&name) => {
+                        Err(format!("duplicate table {}", statement))
+                    }
+                    "lights" => {
+                        section = Section::Light(name, None, None);
+                        Ok(())
+                    }
+                    _ => Err(format!("unknown table {}", statement)),
+                }
+            })
+        } else {
+            match statement.split_once('=') {
+                Some((key, value)) => {
+                    let value = parse_value(value.trim());
+                    parse_key(key).and_then(|key| match &mut section {
+                        Section::Material(name) => {
+                            let value = value?;
+                            apply(&mut Material::default(), &key, &value)?;
+                            let settings = library.materials.get_mut(name).expect("Table exists!");
+                            if settings.iter().any(|(k, _)| *k == key) {
+                                return Err(format!("duplicate key '{}'", key));
+                            }
+                            settings.push((key, value));
+                            Ok(())
+                        }
+                        Section::Light(_, position, intensity) => {
+                            let value = value?;
+                            match key.as_str() {
+                                "position" if position.is_some() => {
+                                    Err(format!("duplicate key '{}'", key))
+                                }
+                                "intensity" if intensity.is_some() => {
+                                    Err(format!("duplicate key '{}'", key))
+                                }
+                                "position" => value.triple().map(|p| *position = Some(p)),
+                                "intensity" => value.color().map(|c| *intensity = Some(c)),
+                                _ => Err(format!("unknown light key '{}'", key)),
+                            }
+                        }
+                        Section::None => Err(format!("key '{}' outside of a table", key)),
+                    })
+                }
+                None => Err(format!("expected key = value, found '{}'", statement)),
+            }
+        };
+
+        parsed.map_err(|message| LibraryError::Parse {
+            line: number,
+            message,
+        })?;
+    }
+    finish(&mut section, &mut library).map_err(|message| LibraryError::Parse {
+        line: number,
+        message,
+    })?;
+
+    Ok(library)
+}
+
+/// Load a library file from disk, see `parse_library`.
+pub fn load_library<P: AsRef<Path>>(path: P) -> Result<Library, LibraryError> {
+    let file = File::open(path)?;
+    parse_library(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::WHITE;
+
+    const LIBRARY: &str = "
+# shared look
+[materials.glass]
+color = [0.1, 0.1, 0.1]
+transparency = 0.9  # almost clear
+refractive_index = 1.5
+shading_model = \"blinn_phong\"
+
+[materials.matte]
+specular = 0
+
+[lights.sun]
+position = [-10, 10, -10]
+intensity = [1, 1, 1]
+";
+
+    #[test]
+    fn parse_library_presets() {
+        let library = parse_library(LIBRARY.as_bytes()).unwrap();
+
+        assert_eq!(library.material_names(), vec!["glass", "matte"]);
+        assert_eq!(library.light_names(), vec!["sun"]);
+        let glass = library.material("glass").unwrap();
+        assert_eq!(glass.color, RGB::new(0.1, 0.1, 0.1));
+        assert_eq!(glass.transparency, 0.9);
+        assert_eq!(glass.refractive_index, 1.5);
+        assert_eq!(glass.shading_model, ShadingModel::BlinnPhong);
+        assert_eq!(glass.diffuse, Material::default().diffuse);
+        assert_eq!(library.material("matte").unwrap().specular, 0.0);
+        assert_eq!(
+            library.light("sun").unwrap(),
+            PointLight::new(Point::new(-10.0, 10.0, -10.0), WHITE)
+        );
+        assert!(library.material("missing").is_none());
+    }
+
+    #[test]
+    fn merge_library() {
+        let mut library = parse_library(LIBRARY.as_bytes()).unwrap();
+        let local =
+            parse_library("[materials.glass]\nrefractive_index = 1.3\n".as_bytes()).unwrap();
+        library.merge(local);
+
+        assert_eq!(library.material("glass").unwrap().refractive_index, 1.3);
+        assert_eq!(library.material("glass").unwrap().transparency, 0.0);
+        assert!(library.light("sun").is_some());
+    }
+
+    #[test]
+    fn quoted_library() {
+        let input = "[materials.\"glass #2\"]  # a second glass\n\"refractive_index\" = 1.3\n";
+        let library = parse_library(input.as_bytes()).unwrap();
+
+        assert_eq!(library.material_names(), vec!["glass #2"]);
+        let glass = library.material("glass #2").unwrap();
+        assert_eq!(glass.refractive_index, 1.3);
+        let tinted = library
+            .material_builder("glass #2")
+            .unwrap()
+            .color(WHITE * 0.5);
+        assert_eq!(tinted.build().refractive_index, 1.3);
+    }
+
+    #[test]
+    fn builder_library() {
+        let library = parse_library(LIBRARY.as_bytes()).unwrap();
+        let (world, _) = crate::WorldBuilder::new()
+            .library(library)
+            .object_with_material(crate::Sphere::new(), "glass")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(
+            world.get_object(0).unwrap().get_material().transparency,
+            0.9
+        );
+
+        let missing =
+            crate::WorldBuilder::new().object_with_material(crate::Sphere::new(), "glass");
+        assert!(matches!(missing, Err(LibraryError::UnknownMaterial(_))));
+    }
+
+    #[test]
+    fn invalid_library() {
+        let line = |input: &str| match parse_library(input.as_bytes()) {
+            Err(LibraryError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+
+        assert_eq!(line("ambient = 0.1\n"), 1);
+        assert_eq!(line("[materials.a]\n\nshininess = high\n"), 3);
+        assert_eq!(line("[materials.a]\nshine = 1\n"), 2);
+        assert_eq!(line("[materials.a]\nshading_model = \"toon\"\n"), 2);
+        assert_eq!(line("[materials.a]\ncolor = [1, 0]\n"), 2);
+        assert_eq!(line("[textures.a]\n"), 1);
+        assert_eq!(line("[lights.a]\nposition = [0, 0, 0]\n"), 2);
+        assert_eq!(line("[materials.a]\n[materials.b]\n[materials.a]\n"), 3);
+        assert_eq!(line("[materials.a]\nambient = 1\nambient = 0\n"), 3);
+        let light = "position = [0, 0, 0]\nintensity = [1, 1, 1]\n";
+        assert_eq!(line(&format!("[lights.a]\n{}[lights.a]\n", light)), 4);
+        assert_eq!(line("[materials.\"a]\n"), 1);
+        assert_eq!(line("[materials.a b]\n"), 1);
+        assert_eq!(line("[materials.a]\nshading_model = \"phong # fast\"\n"), 2);
+    }
+}
@@ -1,7 +1,52 @@
-use crate::{Point, RGB};
+use crate::{Float, Point, Units, BLACK, RGB, WHITE};
 use std::fmt::Debug;
 
-/// A PointLight is light with no size, exisiting at a single
+/// How the intensity of a light decreases with the distance `d` to it, in meters.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Falloff {
+    /// Constant intensity at any distance.
+    #[default]
+    None,
+
+    /// Intensity scaled by `1 / (constant + linear * d)`.
+    Linear { constant: Float, linear: Float },
+
+    /// Intensity scaled by `1 / (constant + linear * d + quadratic * d²)`.
+    InverseSquare {
+        constant: Float,
+        linear: Float,
+        quadratic: Float,
+    },
+}
+
+impl Falloff {
+    /// Physically based falloff, the intensity is the one at distance 1.
+    pub fn inverse_square() -> Self {
+        Falloff::InverseSquare {
+            constant: 0.0,
+            linear: 0.0,
+            quadratic: 1.0,
+        }
+    }
+
+    /// Multiplier of the intensity at `distance` from the light.
+    /// Never brighter than the light's intensity.
+    pub fn factor(&self, distance: Float) -> Float {
+        let denominator = match *self {
+            Falloff::None => return 1.0,
+            Falloff::Linear { constant, linear } => constant + linear * distance,
+            Falloff::InverseSquare {
+                constant,
+                linear,
+                quadratic,
+            } => constant + linear * distance + quadratic * distance * distance,
+        };
+
+        (1.0 / denominator).min(1.0)
+    }
+}
+
+/// A PointLight is light with no size, existing at a single
 /// point in space.
 /// It is also defined by its intensity.
 #[derive(Debug, Clone, Copy)]
@@ -11,6 +56,15 @@ pub struct PointLight {
 
     /// The singular position of the light source.
     position: Point,
+
+    /// Multiplier of the diffuse and specular light in the shadow of this light.
+    shadow_color: RGB,
+
+    /// Decrease of the intensity with distance.
+    falloff: Falloff,
+
+    /// Length of a scene unit, the falloff is applied to distances in meters.
+    units: Units,
 }
 
 impl PointLight {
@@ -19,9 +73,55 @@ impl PointLight {
         Self {
             intensity,
             position,
+            shadow_color: BLACK,
+            falloff: Falloff::None,
+            units: Units::Meters,
         }
     }
 
+    /// Let some light of the given color reach shadowed points.
+    /// Not physical, but handy to tint or lighten shadows of a single light.
+    pub fn with_shadow_color(mut self, shadow_color: RGB) -> Self {
+        self.shadow_color = shadow_color;
+        self
+    }
+
+    /// Set how dark the shadows are, 1.0 (the default) blocks all light, 0.0 none.
+    pub fn with_shadow_darkness(self, darkness: Float) -> Self {
+        self.with_shadow_color(WHITE * (1.0 - darkness.clamp(0.0, 1.0)))
+    }
+
+    /// Let the intensity decrease with the distance to the light.
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn get_falloff(&self) -> Falloff {
+        self.falloff
+    }
+
+    /// Set the length of a scene unit, so the falloff behaves the same
+    /// in any units. A `World` with units sets them on its light.
+    pub fn with_units(mut self, units: Units) -> Self {
+        self.units = units;
+        self
+    }
+
+    pub fn get_units(&self) -> Units {
+        self.units
+    }
+
+    /// Intensity of the light arriving at `point`, after the falloff.
+    pub fn intensity_at(&self, point: Point) -> RGB {
+        let distance = self.units.to_meters((self.position - point).magnitude());
+        self.intensity * self.falloff.factor(distance)
+    }
+
+    pub fn get_shadow_color(&self) -> RGB {
+        self.shadow_color
+    }
+
     pub fn get_intensity(&self) -> RGB {
         self.intensity
     }
@@ -33,7 +133,11 @@ impl PointLight {
 
 impl PartialEq for PointLight {
     fn eq(&self, other: &Self) -> bool {
-        self.intensity == other.intensity && self.position == other.position
+        self.intensity == other.intensity
+            && self.position == other.position
+            && self.shadow_color == other.shadow_color
+            && self.falloff == other.falloff
+            && self.units == other.units
     }
 }
 
@@ -52,4 +156,46 @@ mod test {
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
+
+    #[test]
+    fn shadow_color_point_light() {
+        let light = PointLight::new(Point::new(0.0, 0.0, 0.0), WHITE);
+        assert_eq!(light.get_shadow_color(), BLACK);
+
+        let light = light.with_shadow_darkness(0.75);
+        assert_eq!(light.get_shadow_color(), RGB::new(0.25, 0.25, 0.25));
+        let tinted = light.with_shadow_color(RGB::new(0.0, 0.0, 0.3));
+        assert_eq!(tinted.get_shadow_color(), RGB::new(0.0, 0.0, 0.3));
+        assert_ne!(light, tinted);
+    }
+
+    #[test]
+    fn falloff_point_light() {
+        let light = PointLight::new(Point::new(0.0, 0.0, 0.0), WHITE);
+        let far = Point::new(0.0, 4.0, 0.0);
+        assert_eq!(light.intensity_at(far), WHITE);
+
+        let linear = light.with_falloff(Falloff::Linear {
+            constant: 1.0,
+            linear: 0.5,
+        });
+        assert_eq!(
+            linear.intensity_at(far),
+            RGB::new(1.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0)
+        );
+
+        let square = light.with_falloff(Falloff::inverse_square());
+        assert_eq!(square.intensity_at(far), RGB::new(0.0625, 0.0625, 0.0625));
+        // close to the light the intensity is capped
+        assert_eq!(square.intensity_at(Point::new(0.0, 0.1, 0.0)), WHITE);
+        assert_ne!(light, square);
+
+        // 400 cm are the same 4 m
+        let centimeters = square.with_units(Units::Centimeters);
+        let far = Point::new(0.0, 400.0, 0.0);
+        assert_eq!(
+            centimeters.intensity_at(far),
+            RGB::new(0.0625, 0.0625, 0.0625)
+        );
+    }
 }
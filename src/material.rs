@@ -1,34 +1,133 @@
-use crate::{Pattern, Point, PointLight, Shape, Vector, BLACK, RGB, WHITE};
+use crate::consts::PI;
+use crate::{
+    uv, Float, Pattern, Point, PointLight, Shader, Shape, Vector, World, BLACK, RGB, WHITE,
+};
+
+/// The reflection model used to light a Material.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingModel {
+    /// Classic Phong reflection model.
+    #[default]
+    Phong,
+
+    /// Phong with the specular term computed from the half-vector.
+    BlinnPhong,
+
+    /// Blinn-Phong scaled by `(n + 8) / 8`, so the highlight keeps its energy:
+    /// a higher shininess makes it smaller but brighter instead of dimmer.
+    /// This is the `(n + 8) / 8π` normalization for the diffuse term of
+    /// `lighting`, which is not divided by π.
+    NormalizedBlinnPhong,
+
+    /// Diffuse only, no specular highlights.
+    Lambert,
+
+    /// Cook-Torrance microfacet specular (GGX), the roughness is derived from
+    /// `shininess` unless there is a `roughness_map`, and `specular` acts as
+    /// reflectance at normal incidence.
+    Pbr,
+
+    /// The surface color as is, ignoring all lights.
+    Unlit,
+}
+
+/// Smallest shininess of the `shininess_map`, a shininess of 0 lights every
+/// point facing the eye with the full highlight.
+const MIN_SHININESS: Float = 1.0;
+
+/// Smallest roughness of the `roughness_map`, perfectly smooth surfaces
+/// have no highlight at all.
+const MIN_ROUGHNESS: Float = 0.01;
+
+/// Distance between the samples of the bump map slope.
+const BUMP_STEP: Float = 1e-3;
 
 /// A Material encapsulates all the properties of the surface.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
     /// The color.
     pub color: RGB,
 
     /// Parameter in Phong reflection model.
-    pub ambient: f64,
+    pub ambient: Float,
 
     /// Parameter in Phong reflection model.
-    pub diffuse: f64,
+    pub diffuse: Float,
 
     /// Parameter in Phong reflection model.
-    pub specular: f64,
+    pub specular: Float,
 
     /// Parameter in Phong reflection model.
-    pub shinniness: f64,
+    pub shininess: Float,
 
     /// General Pattern of the material
     pub pattern: Option<Box<dyn Pattern>>,
 
-    /// The reflectivness of the material.
-    pub reflective: f64,
+    /// Scales `specular` by the luminance of the pattern at the hit, e.g. to dull
+    /// scratches or the mortar between tiles.
+    pub specular_map: Option<Box<dyn Pattern>>,
+
+    /// Scales `shininess` like `specular_map`, the roughness of `ShadingModel::Pbr`.
+    pub shininess_map: Option<Box<dyn Pattern>>,
+
+    /// Roughness by the luminance of the pattern, from 0 for polished to 1 for
+    /// matte, e.g. for worn spots on metal. Replaces `shininess` and the
+    /// `shininess_map` in all shading models, see `Material::roughness_at`.
+    pub roughness_map: Option<Box<dyn Pattern>>,
+
+    /// Scales `reflective` like `specular_map`, e.g. for puddles on a floor.
+    pub reflective_map: Option<Box<dyn Pattern>>,
+
+    /// Scales `transparency` like `specular_map`, e.g. for frosted letters on glass.
+    pub transparency_map: Option<Box<dyn Pattern>>,
 
-    /// The transperancy of the material.
-    pub transparency: f64,
+    /// Height field tilting the normal, the luminance of the pattern is the height.
+    /// Adds bumps, grooves or grain to the shading without changing the geometry.
+    pub bump_map: Option<Box<dyn Pattern>>,
+
+    /// Height of a luminance of 1 in the `bump_map`, negative values invert it.
+    pub bump_height: Float,
+
+    /// Tilts the normal by an image in the texture coordinates of the shape,
+    /// e.g. a normal map baked from a detailed model. Shapes without texture
+    /// coordinates ignore it.
+    pub normal_map: Option<NormalMap>,
+
+    /// The reflectiveness of the material.
+    pub reflective: Float,
+
+    /// The transparency of the material.
+    pub transparency: Float,
 
     /// Refractive index.
-    pub refractive_index: f64,
+    pub refractive_index: Float,
+
+    /// Refractive index per red, green and blue channel.
+    /// Overrides `refractive_index` for refraction rays, so prisms split light.
+    pub dispersion: Option<[Float; 3]>,
+
+    /// Light given off by the surface itself, added regardless of the lights,
+    /// e.g. for glowing panels and neon tubes. Black for most materials.
+    pub emissive: RGB,
+
+    /// Strength of the subsurface scattering approximation, 0.0 disables it.
+    pub subsurface: Float,
+
+    /// Color the light picks up while travelling below the surface.
+    pub scatter_color: RGB,
+
+    /// How far light wraps around the terminator, as offset of the light·normal cosine.
+    pub scatter_distance: Float,
+
+    /// Reflection model used by `lighting`.
+    pub shading_model: ShadingModel,
+
+    /// Shader overriding the one of the World for this material.
+    pub shader: Option<Box<dyn Shader>>,
+
+    /// Tint the light passing through this (transparent) material on the way
+    /// to other objects with its color, like stained glass.
+    pub shadow_tint: bool,
 }
 
 impl Default for Material {
@@ -38,17 +137,529 @@ impl Default for Material {
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
-            shinniness: 200.0,
+            shininess: 200.0,
             pattern: None,
+            specular_map: None,
+            shininess_map: None,
+            roughness_map: None,
+            reflective_map: None,
+            transparency_map: None,
+            bump_map: None,
+            bump_height: 0.1,
+            normal_map: None,
             reflective: 0.0,
             transparency: 0.0,
             refractive_index: 1.0,
+            dispersion: None,
+            emissive: BLACK,
+            subsurface: 0.0,
+            scatter_color: WHITE,
+            scatter_distance: 0.5,
+            shading_model: ShadingModel::Phong,
+            shader: None,
+            shadow_tint: false,
         }
     }
 }
 
+/// Chainable setters for a `Material`, starting from the default or a preset.
+#[derive(Debug, Default)]
+pub struct MaterialBuilder {
+    material: Material,
+}
+
+impl MaterialBuilder {
+    /// The color of the surface.
+    pub fn color(mut self, color: RGB) -> Self {
+        self.material.color = color;
+        self
+    }
+
+    /// Ambient term of the Phong reflection model.
+    pub fn ambient(mut self, ambient: Float) -> Self {
+        self.material.ambient = ambient;
+        self
+    }
+
+    /// Diffuse term of the Phong reflection model.
+    pub fn diffuse(mut self, diffuse: Float) -> Self {
+        self.material.diffuse = diffuse;
+        self
+    }
+
+    /// Specular term of the Phong reflection model.
+    pub fn specular(mut self, specular: Float) -> Self {
+        self.material.specular = specular;
+        self
+    }
+
+    /// Size of the highlight, larger values make it smaller.
+    pub fn shininess(mut self, shininess: Float) -> Self {
+        self.material.shininess = shininess;
+        self
+    }
+
+    /// Pattern replacing the color, see `Material::pattern`.
+    pub fn pattern(mut self, pattern: Box<dyn Pattern>) -> Self {
+        self.material.pattern = Some(pattern);
+        self
+    }
+
+    /// Specular map, see `Material::specular_map`.
+    pub fn specular_map(mut self, map: Box<dyn Pattern>) -> Self {
+        self.material.specular_map = Some(map);
+        self
+    }
+
+    /// Shininess map, see `Material::shininess_map`.
+    pub fn shininess_map(mut self, map: Box<dyn Pattern>) -> Self {
+        self.material.shininess_map = Some(map);
+        self
+    }
+
+    /// Roughness map, see `Material::roughness_map`.
+    pub fn roughness_map(mut self, map: Box<dyn Pattern>) -> Self {
+        self.material.roughness_map = Some(map);
+        self
+    }
+
+    /// Reflective map, see `Material::reflective_map`.
+    pub fn reflective_map(mut self, map: Box<dyn Pattern>) -> Self {
+        self.material.reflective_map = Some(map);
+        self
+    }
+
+    /// Transparency map, see `Material::transparency_map`.
+    pub fn transparency_map(mut self, map: Box<dyn Pattern>) -> Self {
+        self.material.transparency_map = Some(map);
+        self
+    }
+
+    /// Bump map of the given height, see `Material::bump_map`.
+    pub fn bump_map(mut self, bump_map: Box<dyn Pattern>, height: Float) -> Self {
+        self.material.bump_map = Some(bump_map);
+        self.material.bump_height = height;
+        self
+    }
+
+    /// Normal map, see `Material::normal_map`.
+    pub fn normal_map(mut self, normal_map: NormalMap) -> Self {
+        self.material.normal_map = Some(normal_map);
+        self
+    }
+
+    /// Fraction of the light reflected like a mirror.
+    pub fn reflective(mut self, reflective: Float) -> Self {
+        self.material.reflective = reflective;
+        self
+    }
+
+    /// Fraction of the light passing through the surface.
+    pub fn transparency(mut self, transparency: Float) -> Self {
+        self.material.transparency = transparency;
+        self
+    }
+
+    /// Refractive index, e.g. 1.5 for glass.
+    pub fn refractive_index(mut self, refractive_index: Float) -> Self {
+        self.material.refractive_index = refractive_index;
+        self
+    }
+
+    /// Refractive index per red, green and blue channel.
+    pub fn dispersion(mut self, dispersion: [Float; 3]) -> Self {
+        self.material.dispersion = Some(dispersion);
+        self
+    }
+
+    /// Light given off by the surface, see `Material::emissive`.
+    pub fn emissive(mut self, emissive: RGB) -> Self {
+        self.material.emissive = emissive;
+        self
+    }
+
+    /// Subsurface scattering of the given strength, color and wrap distance.
+    pub fn subsurface(mut self, strength: Float, color: RGB, distance: Float) -> Self {
+        self.material.subsurface = strength;
+        self.material.scatter_color = color;
+        self.material.scatter_distance = distance;
+        self
+    }
+
+    /// Reflection model used by `lighting`.
+    pub fn shading_model(mut self, shading_model: ShadingModel) -> Self {
+        self.material.shading_model = shading_model;
+        self
+    }
+
+    /// Shader overriding the one of the World.
+    pub fn shader(mut self, shader: Box<dyn Shader>) -> Self {
+        self.material.shader = Some(shader);
+        self
+    }
+
+    /// Tint the shadows of this material with its color, see `Material::shadow_tint`.
+    pub fn shadow_tint(mut self, shadow_tint: bool) -> Self {
+        self.material.shadow_tint = shadow_tint;
+        self
+    }
+
+    /// The material.
+    pub fn build(self) -> Material {
+        self.material
+    }
+}
+
+/// Continue changing a material, e.g. one of the `presets`.
+impl From<Material> for MaterialBuilder {
+    fn from(material: Material) -> Self {
+        Self { material }
+    }
+}
+
+/// Everything needed to light a single point on a surface.
+/// New lighting parameters are added here instead of to every caller.
+#[derive(Debug, Clone, Copy)]
+pub struct LightingContext {
+    /// Point in world space being lit.
+    pub point: Point,
+
+    /// Eye Vector of the surface.
+    pub eyev: Vector,
+
+    /// Normal Vector of the surface.
+    pub normalv: Vector,
+
+    /// Position of the light sample.
+    pub light_position: Point,
+
+    /// Intensity of the light sample.
+    pub intensity: RGB,
+
+    /// Fraction of the light that is blocked, 0.0 is fully lit and 1.0 is in full shadow.
+    pub shadow: Float,
+
+    /// Multiplier of the diffuse and specular light of the blocked fraction.
+    pub shadow_color: RGB,
+
+    /// Fraction of the light let through by transparent objects on the way, per channel.
+    pub transmission: RGB,
+
+    /// Ambient light of the scene, scales the ambient term of the material.
+    pub ambient: RGB,
+}
+
+impl LightingContext {
+    /// Create a new context for a point light.
+    pub fn new(light: PointLight, point: Point, eyev: Vector, normalv: Vector) -> Self {
+        Self {
+            point,
+            eyev,
+            normalv,
+            light_position: light.get_position(),
+            intensity: light.intensity_at(point),
+            shadow: 0.0,
+            shadow_color: light.get_shadow_color(),
+            transmission: WHITE,
+            ambient: WHITE,
+        }
+    }
+
+    /// Set the in-shadow fraction.
+    pub fn with_shadow(mut self, shadow: Float) -> Self {
+        self.shadow = shadow;
+        self
+    }
+
+    /// Set the fraction of the light let through by transparent objects.
+    pub fn with_transmission(mut self, transmission: RGB) -> Self {
+        self.transmission = transmission;
+        self
+    }
+
+    /// Set the ambient light of the scene.
+    pub fn with_ambient(mut self, ambient: RGB) -> Self {
+        self.ambient = ambient;
+        self
+    }
+
+    /// Direction from the point to the light source.
+    pub fn lightv(&self) -> Vector {
+        (self.light_position - self.point).normalize()
+    }
+}
+
+/// The parts of the light leaving a surface, see `lighting_terms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightingTerms {
+    /// Light that does not depend on the light source's direction.
+    pub ambient: RGB,
+
+    /// Light scattered equally in all directions, including subsurface scattering.
+    pub diffuse: RGB,
+
+    /// Highlights.
+    pub specular: RGB,
+
+    /// Light given off by the surface, the `emissive` color of the material.
+    pub emission: RGB,
+}
+
+impl LightingTerms {
+    /// Sum of all terms, the result of `lighting`.
+    pub fn total(&self) -> RGB {
+        self.ambient + self.diffuse + self.specular + self.emission
+    }
+}
+
+/// Calculate the lighting of `object` with `material` for the given context.
+pub fn lighting(material: &Material, object: &dyn Shape, ctx: &LightingContext) -> RGB {
+    lighting_terms(material, object, ctx).total()
+}
+
+/// Calculate the lighting like `lighting`, but keep the terms apart,
+/// e.g. to show what each one adds. Unlit surfaces only have an ambient term.
+pub fn lighting_terms(
+    material: &Material,
+    object: &dyn Shape,
+    ctx: &LightingContext,
+) -> LightingTerms {
+    let color = material.color_at(object, ctx.point);
+    if material.shading_model == ShadingModel::Unlit {
+        return LightingTerms {
+            ambient: color,
+            diffuse: BLACK,
+            specular: BLACK,
+            emission: material.emissive,
+        };
+    }
+
+    // combine the surface color with the light's color/intensity
+    let effective_color = color * ctx.intensity;
+    // find the direction to the light source
+    let lightv = ctx.lightv();
+    // compute the ambient contribution
+    let ambient = effective_color * ctx.ambient * material.ambient;
+    // light_dot normal represent the cosine of the angle between the
+    // light vector and the normal vector.
+    // A negative number means the light is on the other side of the surface.
+    let light_dot_normal = lightv.dot(ctx.normalv);
+    let reaching = ctx.transmission * (1.0 - ctx.shadow.clamp(0.0, 1.0));
+    // the light's shadow color reaches the blocked fraction
+    let lit = reaching + ctx.shadow_color * (WHITE - reaching);
+    // light scattered below the surface is not blocked by the object itself,
+    // but by everything else in the way
+    let scatter = material.subsurface_scatter(effective_color, light_dot_normal) * lit;
+    if light_dot_normal <= 0.0 || lit == BLACK {
+        return LightingTerms {
+            ambient,
+            diffuse: scatter,
+            specular: BLACK,
+            emission: material.emissive,
+        };
+    }
+
+    // compute the diffuse contribution
+    let diffuse = effective_color * material.diffuse * light_dot_normal;
+    let highlight = Highlight {
+        specular: material.specular_at(object, ctx.point),
+        shininess: match material.roughness_map {
+            Some(_) => {
+                // the inverse of the mapping in `Highlight::pbr`
+                let roughness = material.roughness_at(object, ctx.point).max(MIN_ROUGHNESS);
+                2.0 / roughness.powi(2) - 2.0
+            }
+            None => material.shininess_at(object, ctx.point),
+        },
+    };
+    let specular = match material.shading_model {
+        ShadingModel::Phong => highlight.phong(ctx, lightv),
+        ShadingModel::BlinnPhong => highlight.blinn(ctx, lightv),
+        ShadingModel::NormalizedBlinnPhong => highlight.normalized_blinn(ctx, lightv),
+        ShadingModel::Pbr => highlight.pbr(ctx, lightv),
+        ShadingModel::Lambert | ShadingModel::Unlit => BLACK,
+    };
+
+    LightingTerms {
+        ambient,
+        diffuse: diffuse * lit + scatter,
+        specular: specular * lit,
+        emission: material.emissive,
+    }
+}
+
 impl Material {
-    /// Calculate the lightning of shape from a Light source.
+    /// Start building a material from the default one.
+    pub fn builder() -> MaterialBuilder {
+        MaterialBuilder::default()
+    }
+
+    /// Clear glass, fully transparent with the refractive index of window glass.
+    pub fn glass() -> Self {
+        Self {
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Self::default()
+        }
+    }
+
+    /// A perfect mirror, all color comes from the reflection.
+    pub fn mirror() -> Self {
+        Self {
+            ambient: 0.0,
+            diffuse: 0.0,
+            reflective: 1.0,
+            ..Self::default()
+        }
+    }
+
+    /// Does the material block all light? Maps only scale `transparency`.
+    pub fn is_opaque(&self) -> bool {
+        self.transparency <= 0.0
+    }
+
+    /// Refractive index for one color channel (0 red, 1 green, 2 blue).
+    pub fn refractive_index_for(&self, channel: usize) -> Float {
+        match self.dispersion {
+            Some(n) => n[channel],
+            None => self.refractive_index,
+        }
+    }
+
+    /// Return the surface color at `position`, taking the pattern into account.
+    pub fn color_at(&self, object: &dyn Shape, position: Point) -> RGB {
+        match self.pattern.as_ref() {
+            Some(pattern) => pattern.pattern_at_shape(object, position),
+            None => self.color,
+        }
+    }
+
+    /// `specular` at `position`, scaled by the `specular_map`.
+    pub fn specular_at(&self, object: &dyn Shape, position: Point) -> Float {
+        mapped(&self.specular_map, self.specular, object, position)
+    }
+
+    /// `shininess` at `position`, scaled by the `shininess_map`.
+    /// Mapped values stay at least `MIN_SHININESS`, so dark texels give a
+    /// broad and dull highlight rather than a flat one.
+    pub fn shininess_at(&self, object: &dyn Shape, position: Point) -> Float {
+        match self.shininess_map {
+            Some(_) => {
+                mapped(&self.shininess_map, self.shininess, object, position).max(MIN_SHININESS)
+            }
+            None => self.shininess,
+        }
+    }
+
+    /// Microfacet roughness at `position`, from 0 for polished to 1 for matte.
+    /// The luminance of the `roughness_map`, without one it is derived from
+    /// the `shininess` like `ShadingModel::Pbr` does.
+    pub fn roughness_at(&self, object: &dyn Shape, position: Point) -> Float {
+        match &self.roughness_map {
+            Some(map) => map
+                .pattern_at_shape(object, position)
+                .luminance()
+                .clamp(0.0, 1.0),
+            None => (2.0 / (self.shininess_at(object, position) + 2.0)).sqrt(),
+        }
+    }
+
+    /// `reflective` at `position`, scaled by the `reflective_map`.
+    pub fn reflective_at(&self, object: &dyn Shape, position: Point) -> Float {
+        mapped(&self.reflective_map, self.reflective, object, position)
+    }
+
+    /// `transparency` at `position`, scaled by the `transparency_map`.
+    pub fn transparency_at(&self, object: &dyn Shape, position: Point) -> Float {
+        mapped(&self.transparency_map, self.transparency, object, position)
+    }
+
+    /// Tilt the world space `normalv` at `position` by the slope of the `bump_map`.
+    /// Without bump map the normal is returned as is.
+    pub fn bump_normal(&self, object: &dyn Shape, position: Point, normalv: Vector) -> Vector {
+        let bump_map = match self.bump_map.as_ref() {
+            Some(bump_map) => bump_map,
+            None => return normalv,
+        };
+
+        // any two tangents work, the height field is sampled in 3D
+        let axis = if normalv.x.abs() < 0.9 {
+            Vector::new(1.0, 0.0, 0.0)
+        } else {
+            Vector::new(0.0, 1.0, 0.0)
+        };
+        let tangent = normalv.cross(axis).normalize();
+        let bitangent = normalv.cross(tangent);
+
+        let height = |p: Point| bump_map.pattern_at_shape(object, p).luminance();
+        let h = height(position);
+        let du = (height(position + tangent * BUMP_STEP) - h) / BUMP_STEP;
+        let dv = (height(position + bitangent * BUMP_STEP) - h) / BUMP_STEP;
+
+        (normalv - (tangent * du + bitangent * dv) * self.bump_height).normalize()
+    }
+
+    /// Tilt the world space `normalv` at `position` by the `normal_map`, along
+    /// the directions in which the texture coordinates of `object` grow.
+    /// Without normal map or texture coordinates the normal is returned as is.
+    pub fn map_normal(
+        &self,
+        object: &dyn Shape,
+        position: Point,
+        normalv: Vector,
+        w: Option<&World>,
+    ) -> Vector {
+        let normal_map = match self.normal_map.as_ref() {
+            Some(normal_map) => normal_map,
+            None => return normalv,
+        };
+
+        let inverse = match w {
+            Some(_) => None,
+            None => Some(
+                object
+                    .get_transform()
+                    .inverse()
+                    .expect("Transform should have an inverse!"),
+            ),
+        };
+        let uv_at = |p: Point| {
+            let local_point = match (w, &inverse) {
+                (Some(w), _) => object.world_to_object(p, w),
+                (None, Some(inverse)) => *inverse * p,
+                (None, None) => p,
+            };
+            object.local_uv_at(local_point)
+        };
+
+        let (uv, (tangent, bitangent)) =
+            match (uv_at(position), uv::tangents(uv_at, position, normalv)) {
+                (Some(uv), Some(frame)) => (uv, frame),
+                _ => return normalv,
+            };
+        let n = normal_map.normal_at(uv);
+
+        (tangent * n.x + bitangent * n.y + normalv * n.z).normalize()
+    }
+
+    /// Calculate the lighting of shape from a Light source.
+    /// Shorthand for [`lighting`] with a fully lit or fully shadowed point.
+    pub fn lighting(
+        &self,
+        object: &dyn Shape,
+        light: PointLight,
+        position: Point,
+        eyev: Vector,
+        normalv: Vector,
+        in_shadow: bool,
+    ) -> RGB {
+        let shadow = if in_shadow { 1.0 } else { 0.0 };
+        let ctx = LightingContext::new(light, position, eyev, normalv).with_shadow(shadow);
+        lighting(self, object, &ctx)
+    }
+
+    /// Misspelled alias of [`Material::lighting`].
+    #[deprecated(note = "use `Material::lighting` instead")]
     pub fn lightning(
         &self,
         object: &dyn Shape,
@@ -58,52 +669,133 @@ impl Material {
         normalv: Vector,
         in_shadow: bool,
     ) -> RGB {
-        let color = match self.pattern.as_ref() {
-            Some(pattern) => pattern.pattern_at_shape(object, position),
-            None => self.color,
-        };
+        self.lighting(object, light, position, eyev, normalv, in_shadow)
+    }
+
+    /// Misspelled getter of [`Material::shininess`].
+    #[deprecated(note = "use the `shininess` field instead")]
+    pub fn shinniness(&self) -> Float {
+        self.shininess
+    }
+
+    /// Misspelled setter of [`Material::shininess`].
+    #[deprecated(note = "use the `shininess` field instead")]
+    pub fn set_shinniness(&mut self, shininess: Float) {
+        self.shininess = shininess;
+    }
+
+    /// Wrap lighting approximation of subsurface scattering.
+    /// Light bleeds past the terminator by `scatter_distance` and is tinted
+    /// with `scatter_color`, which softens wax, skin or jade.
+    fn subsurface_scatter(&self, effective_color: RGB, light_dot_normal: Float) -> RGB {
+        if self.subsurface <= 0.0 {
+            return BLACK;
+        }
+
+        let wrap =
+            ((light_dot_normal + self.scatter_distance) / (1.0 + self.scatter_distance)).max(0.0);
+        let bleed = (wrap - light_dot_normal.max(0.0)).max(0.0);
 
-        // combine the surface color with the light's color/intensity
-        let effective_color = color * light.get_intensity();
-        let diffuse;
-        let specular;
-        // find the direction to the light source
-        let lightv = (light.get_position() - position).normalize();
-        // compute the ambient contribution
-        let ambient = effective_color * self.ambient;
-        // light_dot normal represent the cosine of the angle between the
-        // light vector and the normal vector.
-        // A negative number means the light is on the other side of the surface.
-        let light_dot_normal = lightv.dot(normalv);
-        if light_dot_normal <= 0.0 || in_shadow {
-            diffuse = BLACK;
-            specular = BLACK;
+        effective_color * self.scatter_color * (self.subsurface * bleed)
+    }
+}
+
+/// `value` scaled by the luminance of `map` at `position`, if there is a map.
+fn mapped(
+    map: &Option<Box<dyn Pattern>>,
+    value: Float,
+    object: &dyn Shape,
+    position: Point,
+) -> Float {
+    match map {
+        Some(map) => value * map.pattern_at_shape(object, position).luminance(),
+        None => value,
+    }
+}
+
+/// Specular parameters of a material at the lit point.
+struct Highlight {
+    specular: Float,
+    shininess: Float,
+}
+
+impl Highlight {
+    /// Specular term of the Phong model.
+    fn phong(&self, ctx: &LightingContext, lightv: Vector) -> RGB {
+        // reflect_dot_eye represents the cosine of the angle between the
+        // reflection vector and the eye vector.
+        // A negative number means the light reflects away from the eye.
+        let reflectv = (-lightv).reflect(ctx.normalv);
+        let reflect_dot_eye = reflectv.dot(ctx.eyev);
+
+        if reflect_dot_eye <= 0.0 {
+            BLACK
         } else {
-            // compute the diffuse contribution
-            diffuse = effective_color * self.diffuse * light_dot_normal;
-            // reflect_dot_eye represents the cosine of the angle between the
-            // reflection vector and the eye vector.
-            // A negative number means the light reflects away from the eye.
-            let reflectv = (-lightv).reflect(normalv);
-            let reflect_dot_eye = reflectv.dot(eyev);
-
-            if reflect_dot_eye <= 0.0 {
-                specular = BLACK;
-            } else {
-                // compute the specular contribution
-                let factor = reflect_dot_eye.powf(self.shinniness);
-                specular = light.get_intensity() * self.specular * factor;
-            }
+            let factor = reflect_dot_eye.powf(self.shininess);
+            ctx.intensity * self.specular * factor
+        }
+    }
+
+    /// Specular term of the energy normalized Blinn-Phong model.
+    fn normalized_blinn(&self, ctx: &LightingContext, lightv: Vector) -> RGB {
+        let exponent = self.shininess * 4.0;
+        let normalization = (exponent + 8.0) / 8.0;
+        let light_dot_normal = lightv.dot(ctx.normalv);
+
+        self.blinn(ctx, lightv) * (normalization * light_dot_normal)
+    }
+
+    /// Specular term of the Blinn-Phong model.
+    /// The exponent is scaled by four to roughly match the Phong highlight size.
+    fn blinn(&self, ctx: &LightingContext, lightv: Vector) -> RGB {
+        let halfv = (lightv + ctx.eyev).normalize();
+        let normal_dot_half = ctx.normalv.dot(halfv);
+
+        if normal_dot_half <= 0.0 {
+            BLACK
+        } else {
+            let factor = normal_dot_half.powf(self.shininess * 4.0);
+            ctx.intensity * self.specular * factor
+        }
+    }
+
+    /// Specular term of the Cook-Torrance model with GGX distribution,
+    /// Schlick-Smith geometry and Schlick Fresnel.
+    fn pbr(&self, ctx: &LightingContext, lightv: Vector) -> RGB {
+        let n_dot_l = ctx.normalv.dot(lightv);
+        let n_dot_v = ctx.normalv.dot(ctx.eyev);
+        if n_dot_v <= 0.0 {
+            return BLACK;
         }
 
-        // add the three contributions together to get the final shading
-        return ambient + diffuse + specular;
+        let halfv = (lightv + ctx.eyev).normalize();
+        let n_dot_h = ctx.normalv.dot(halfv).max(0.0);
+        let v_dot_h = ctx.eyev.dot(halfv).max(0.0);
+
+        // map the Phong exponent onto a microfacet roughness
+        let alpha2 = 2.0 / (self.shininess + 2.0);
+        let alpha = alpha2.sqrt();
+
+        let d = alpha2 / (PI * (n_dot_h.powi(2) * (alpha2 - 1.0) + 1.0).powi(2));
+        let k = alpha / 2.0;
+        let g = (n_dot_l / (n_dot_l * (1.0 - k) + k)) * (n_dot_v / (n_dot_v * (1.0 - k) + k));
+        let f = self.specular + (1.0 - self.specular) * (1.0 - v_dot_h).powi(5);
+
+        ctx.intensity * (d * g * f / (4.0 * n_dot_v))
     }
 }
 
+pub mod jitter;
+pub use jitter::MaterialJitter;
+pub mod normal_map;
+pub use normal_map::NormalMap;
+pub mod presets;
+
 #[cfg(test)]
 mod test {
-    use crate::{PointLight, Sphere, Stripes};
+    use crate::{
+        Canvas, Falloff, Gradient, Plane, PointLight, Sampler, Sphere, Stripes, Transformation, RED,
+    };
 
     use super::*;
 
@@ -115,76 +807,84 @@ mod test {
         assert_eq!(m.ambient, 0.1);
         assert_eq!(m.diffuse, 0.9);
         assert_eq!(m.specular, 0.9);
-        assert_eq!(m.shinniness, 200.0);
+        assert_eq!(m.shininess, 200.0);
     }
 
     #[test]
-    fn eye_surface_lightning() {
+    fn eye_surface_lighting() {
         let s = Sphere::new();
         let m = Material::default();
         let position = Point::new(0.0, 0.0, 0.0);
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
-        let result = m.lightning(&s, light, position, eyev, normalv, false);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
 
         assert_eq!(result, RGB::new(1.9, 1.9, 1.9));
     }
 
     #[test]
-    fn eye_45_surface_lightning() {
+    fn eye_45_surface_lighting() {
         let s = Sphere::new();
         let m = Material::default();
         let position = Point::new(0.0, 0.0, 0.0);
-        let eyev = Vector::new(0.0, 2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0);
+        let eyev = Vector::new(
+            0.0,
+            (2.0 as Float).sqrt() / 2.0,
+            (2.0 as Float).sqrt() / 2.0,
+        );
         let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
-        let result = m.lightning(&s, light, position, eyev, normalv, false);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
 
         assert_eq!(result, WHITE);
     }
 
     #[test]
-    fn eye_surface_45_lightning() {
+    fn eye_surface_45_lighting() {
         let s = Sphere::new();
         let m = Material::default();
         let position = Point::new(0.0, 0.0, 0.0);
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::new(Point::new(0.0, 10.0, -10.0), WHITE);
-        let result = m.lightning(&s, light, position, eyev, normalv, false);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
 
         assert_eq!(result, RGB::new(0.7364, 0.7364, 0.7364));
     }
 
     #[test]
-    fn eye_surface_path_lightning() {
+    fn eye_surface_path_lighting() {
         let s = Sphere::new();
         let m = Material::default();
         let position = Point::new(0.0, 0.0, 0.0);
-        let eyev = Vector::new(0.0, -(2_f64.sqrt()) / 2.0, -(2_f64.sqrt()) / 2.0);
+        let eyev = Vector::new(
+            0.0,
+            -((2.0 as Float).sqrt()) / 2.0,
+            -((2.0 as Float).sqrt()) / 2.0,
+        );
         let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::new(Point::new(0.0, 10.0, -10.0), WHITE);
-        let result = m.lightning(&s, light, position, eyev, normalv, false);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
 
         assert_eq!(result, RGB::new(1.6364, 1.6363, 1.6364));
     }
 
     #[test]
-    fn eye_surface_behind_lightning() {
+    fn eye_surface_behind_lighting() {
         let s = Sphere::new();
         let m = Material::default();
         let position = Point::new(0.0, 0.0, 0.0);
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::new(Point::new(0.0, 0.0, 10.0), WHITE);
-        let result = m.lightning(&s, light, position, eyev, normalv, false);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
 
         assert_eq!(result, RGB::new(0.1, 0.1, 0.1));
     }
 
     #[test]
-    fn surface_shadow_lightning() {
+    fn surface_shadow_lighting() {
         let s = Sphere::new();
         let m = Material::default();
         let position = Point::new(0.0, 0.0, 0.0);
@@ -192,13 +892,62 @@ mod test {
         let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
         let in_shadow = true;
-        let result = m.lightning(&s, light, position, eyev, normalv, in_shadow);
+        let result = m.lighting(&s, light, position, eyev, normalv, in_shadow);
 
         assert_eq!(result, RGB::new(0.1, 0.1, 0.1));
     }
 
     #[test]
-    fn pattern_lightning() {
+    fn shadow_color_lighting() {
+        let s = Sphere::new();
+        let m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE).with_shadow_darkness(0.5);
+
+        // half of the diffuse and specular light reaches the shadow
+        let result = m.lighting(&s, light, position, eyev, normalv, true);
+        assert_eq!(result, RGB::new(1.0, 1.0, 1.0));
+
+        let light = light.with_shadow_color(RGB::new(0.0, 0.0, 0.5));
+        let ctx = LightingContext::new(light, position, eyev, normalv).with_shadow(0.5);
+        let result = lighting(&m, &s, &ctx);
+        assert_eq!(result, RGB::new(1.0, 1.0, 1.45));
+    }
+
+    #[test]
+    fn transmission_lighting() {
+        let s = Sphere::new();
+        let m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+
+        // light passing a transparent object on its way to the surface
+        let ctx = LightingContext::new(light, position, eyev, normalv)
+            .with_transmission(RGB::new(0.5, 0.5, 0.0));
+        let result = lighting(&m, &s, &ctx);
+        assert_eq!(result, RGB::new(1.0, 1.0, 0.1));
+    }
+
+    #[test]
+    fn falloff_lighting() {
+        let s = Sphere::new();
+        let m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -2.0), WHITE)
+            .with_falloff(Falloff::inverse_square());
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+
+        assert_eq!(result, RGB::new(0.475, 0.475, 0.475));
+    }
+
+    #[test]
+    fn pattern_lighting() {
         let s = Sphere::new();
         let mut m = Material::default();
         m.pattern = Some(Box::new(Stripes::new()));
@@ -208,13 +957,195 @@ mod test {
         let eyev = Vector::new(0.0, 0.0, -1.0);
         let normalv = Vector::new(0.0, 0.0, -1.0);
         let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
-        let c1 = m.lightning(&s, light, Point::new(0.9, 0.0, 0.0), eyev, normalv, false);
-        let c2 = m.lightning(&s, light, Point::new(1.1, 0.0, 0.0), eyev, normalv, false);
+        let c1 = m.lighting(&s, light, Point::new(0.9, 0.0, 0.0), eyev, normalv, false);
+        let c2 = m.lighting(&s, light, Point::new(1.1, 0.0, 0.0), eyev, normalv, false);
 
         assert_eq!(c1, WHITE);
         assert_eq!(c2, BLACK);
     }
 
+    #[test]
+    fn default_shading_model_material() {
+        let m = Material::default();
+
+        assert_eq!(m.shading_model, ShadingModel::Phong);
+    }
+
+    #[test]
+    fn blinn_phong_lighting() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        m.shading_model = ShadingModel::BlinnPhong;
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+
+        assert_eq!(result, RGB::new(1.9, 1.9, 1.9));
+
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), WHITE);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+
+        assert_eq!(result, RGB::new(0.7364, 0.7364, 0.7364));
+    }
+
+    #[test]
+    fn normalized_blinn_phong_lighting() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        m.shading_model = ShadingModel::NormalizedBlinnPhong;
+        m.shininess = 1.0;
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+
+        assert_eq!(result, RGB::new(2.35, 2.35, 2.35));
+
+        // the peak of a sharper highlight is brighter
+        m.shininess = 200.0;
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+        assert_eq!(result, RGB::new(91.9, 91.9, 91.9));
+    }
+
+    #[test]
+    fn lambert_lighting() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        m.shading_model = ShadingModel::Lambert;
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+
+        assert_eq!(result, WHITE);
+    }
+
+    #[test]
+    fn pbr_lighting() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        m.shading_model = ShadingModel::Pbr;
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+
+        assert_eq!(result, RGB::new(8.23359, 8.23359, 8.23359));
+
+        let light = PointLight::new(Point::new(0.0, 10.0, -10.0), WHITE);
+        let result = m.lighting(&s, light, position, eyev, normalv, false);
+
+        assert_eq!(result, RGB::new(0.76535, 0.76535, 0.76535));
+    }
+
+    #[test]
+    fn unlit_lighting() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        m.shading_model = ShadingModel::Unlit;
+        m.color = RED;
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), WHITE);
+        let result = m.lighting(&s, light, position, eyev, normalv, true);
+
+        assert_eq!(result, RED);
+    }
+
+    #[test]
+    fn context_lighting() {
+        let s = Sphere::new();
+        let m = Material::default();
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let ctx = LightingContext::new(
+            light,
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(0.0, 0.0, -1.0),
+        );
+
+        assert_eq!(lighting(&m, &s, &ctx), RGB::new(1.9, 1.9, 1.9));
+        assert_eq!(
+            lighting(&m, &s, &ctx.with_shadow(1.0)),
+            RGB::new(0.1, 0.1, 0.1)
+        );
+        assert_eq!(
+            lighting(&m, &s, &ctx.with_shadow(0.5)),
+            RGB::new(1.0, 1.0, 1.0)
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_aliases_material() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        m.set_shinniness(10.0);
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+
+        assert_eq!(m.shinniness(), 10.0);
+        assert_eq!(m.shininess, 10.0);
+        assert_eq!(
+            m.lightning(&s, light, position, eyev, normalv, false),
+            m.lighting(&s, light, position, eyev, normalv, false)
+        );
+    }
+
+    #[test]
+    fn subsurface_terminator_lighting() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(10.0, 0.0, 0.0), WHITE);
+
+        assert_eq!(
+            m.lighting(&s, light, position, eyev, normalv, false),
+            RGB::new(0.1, 0.1, 0.1)
+        );
+
+        m.subsurface = 1.0;
+        m.scatter_color = RED;
+        m.scatter_distance = 1.0;
+
+        assert_eq!(
+            m.lighting(&s, light, position, eyev, normalv, false),
+            RGB::new(0.6, 0.1, 0.1)
+        );
+        // no light to scatter behind an occluder
+        assert_eq!(
+            m.lighting(&s, light, position, eyev, normalv, true),
+            RGB::new(0.1, 0.1, 0.1)
+        );
+    }
+
+    #[test]
+    fn subsurface_behind_lighting() {
+        let s = Sphere::new();
+        let mut m = Material::default();
+        m.subsurface = 1.0;
+        m.scatter_distance = 1.0;
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, 10.0), WHITE);
+
+        assert_eq!(
+            m.lighting(&s, light, position, eyev, normalv, false),
+            RGB::new(0.1, 0.1, 0.1)
+        );
+    }
+
     #[test]
     fn default_material_reflect() {
         let m = Material::default();
@@ -229,4 +1160,239 @@ mod test {
         assert_eq!(m.transparency, 0.0);
         assert_eq!(m.refractive_index, 1.0);
     }
+
+    #[test]
+    fn presets_material() {
+        let glass = Material::glass();
+        assert_eq!(glass.transparency, 1.0);
+        assert_eq!(glass.refractive_index, 1.5);
+        assert_eq!(glass.reflective, 0.0);
+
+        let mirror = Material::mirror();
+        assert_eq!(mirror.reflective, 1.0);
+        assert_eq!(mirror.transparency, 0.0);
+        assert_eq!(mirror.diffuse, 0.0);
+    }
+
+    #[test]
+    fn builder_material() {
+        let m = Material::builder()
+            .color(RGB::new(0.2, 0.4, 0.6))
+            .specular(0.3)
+            .transparency(0.5)
+            .refractive_index(1.3)
+            .shading_model(ShadingModel::BlinnPhong)
+            .build();
+
+        assert_eq!(m.color, RGB::new(0.2, 0.4, 0.6));
+        assert_eq!(m.specular, 0.3);
+        assert_eq!(m.transparency, 0.5);
+        assert_eq!(m.refractive_index, 1.3);
+        assert_eq!(m.shading_model, ShadingModel::BlinnPhong);
+        assert_eq!(m.diffuse, Material::default().diffuse);
+
+        let tinted = MaterialBuilder::from(Material::glass())
+            .color(RGB::new(0.0, 0.2, 0.0))
+            .build();
+        assert_eq!(tinted.transparency, 1.0);
+        assert_eq!(tinted.color, RGB::new(0.0, 0.2, 0.0));
+    }
+
+    #[test]
+    fn jitter_material() {
+        let base = presets::rubber();
+        let mut sampler = Sampler::new(1);
+        let mut m = presets::rubber();
+        MaterialJitter::default().apply(&mut m, &mut sampler);
+        assert_eq!(m, base);
+
+        let jitter = MaterialJitter {
+            brightness: 0.1,
+            shininess: 0.5,
+            specular: 2.0,
+            ..MaterialJitter::default()
+        };
+        let mut a = presets::rubber();
+        jitter.apply(&mut a, &mut sampler);
+        assert!(a.color.red != base.color.red);
+        let scale = a.color.red / base.color.red;
+        assert!((0.9..=1.1).contains(&scale));
+        assert!(crate::float_eq(a.color.green, base.color.green * scale));
+        assert!((0.5..=1.5).contains(&(a.shininess / base.shininess)));
+        assert!((0.0..=1.0).contains(&a.specular));
+
+        // the same seed varies the same way
+        let mut b = presets::rubber();
+        jitter.apply(&mut b, &mut Sampler::new(1));
+        let mut c = presets::rubber();
+        jitter.apply(&mut c, &mut Sampler::new(1));
+        assert_eq!(b.color, c.color);
+        assert_eq!(b.shininess, c.shininess);
+    }
+
+    #[test]
+    fn bump_map_material() {
+        let s = Sphere::new();
+        let n = Vector::new(0.0, 0.0, -1.0);
+        let p = Point::new(0.25, 0.0, -1.0);
+        let m = Material::default();
+        assert_eq!(m.bump_normal(&s, p, n), n);
+
+        // brightness rising along x tilts the normal towards -x
+        let ramp = Gradient::gradient_pattern(BLACK, WHITE);
+        let m = Material::builder().bump_map(Box::new(ramp), 0.5).build();
+        let bumped = m.bump_normal(&s, p, n);
+        assert!(crate::float_eq(bumped.magnitude(), 1.0));
+        assert!(bumped.x < 0.0);
+        assert!(crate::float_eq(bumped.y, 0.0));
+
+        let ramp = Gradient::gradient_pattern(BLACK, WHITE);
+        let m = Material::builder().bump_map(Box::new(ramp), -0.5).build();
+        assert!(m.bump_normal(&s, p, n).x > 0.0);
+    }
+
+    #[test]
+    fn black_shininess_map_material() {
+        let s = Sphere::new();
+        let m = Material::builder()
+            .shininess_map(Box::new(Stripes::stripe_pattern(BLACK, BLACK)))
+            .build();
+        let p = Point::new(0.0, 0.0, -1.0);
+        assert_eq!(m.shininess_at(&s, p), MIN_SHININESS);
+
+        // away from the reflection the highlight fades instead of staying at full strength
+        let eyev = Vector::new(0.0, 0.8, -0.6);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let dull = Material::builder().shininess(MIN_SHININESS).build();
+        let lit = m.lighting(&s, light, p, eyev, normalv, false);
+        assert_eq!(lit, dull.lighting(&s, light, p, eyev, normalv, false));
+        assert!(crate::float_eq(lit.red, 0.1 + 0.9 + 0.9 * 0.6));
+    }
+
+    #[test]
+    fn roughness_map_material() {
+        let s = Sphere::new();
+        let m = Material::builder()
+            .shading_model(ShadingModel::Pbr)
+            .roughness_map(Box::new(Stripes::stripe_pattern(BLACK, WHITE)))
+            .build();
+        let a = Point::new(0.5, 0.0, -0.8);
+        let b = Point::new(-0.5, 0.0, -0.8);
+        assert_eq!(m.roughness_at(&s, a), 0.0);
+        assert_eq!(m.roughness_at(&s, b), 1.0);
+
+        // a polished spot has a small and bright highlight, a matte one a dim one
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let polished = m.lighting(&s, light, a, eyev, normalv, false);
+        let matte = m.lighting(&s, light, b, eyev, normalv, false);
+        assert!(polished.red > matte.red);
+        assert!(polished.red.is_finite());
+
+        // the map replaces the shininess in the other models too
+        let phong = Material::builder()
+            .shininess(1.0)
+            .roughness_map(Box::new(Stripes::stripe_pattern(BLACK, WHITE)))
+            .build();
+        let blunt = Material::builder().shininess(1.0).build();
+        let eyev = Vector::new(0.0, 0.6, -0.8);
+        assert!(
+            phong.lighting(&s, light, a, eyev, normalv, false).red
+                < blunt.lighting(&s, light, a, eyev, normalv, false).red
+        );
+    }
+
+    #[test]
+    fn normal_map_material() {
+        let tilted = |color: RGB| {
+            let mut image = Canvas::new(2, 2);
+            image.fill(color);
+            Material::builder()
+                .normal_map(NormalMap::new(image))
+                .build()
+        };
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let p = Point::new(0.3, 0.0, 0.6);
+        let plane = Plane::new();
+        assert_eq!(Material::default().map_normal(&plane, p, up, None), up);
+
+        // flat map
+        let m = tilted(RGB::new(0.5, 0.5, 1.0));
+        assert_eq!(m.map_normal(&plane, p, up, None), up);
+
+        // towards growing u, which is +x on a plane
+        let m = tilted(RGB::new(1.0, 0.5, 1.0));
+        let n = m.map_normal(&plane, p, up, None);
+        assert_eq!(n, Vector::new(1.0, 1.0, 0.0).normalize());
+
+        // towards growing v, up to the north pole of a scaled sphere
+        let m = tilted(RGB::new(0.5, 1.0, 1.0));
+        let mut s = Sphere::new();
+        s.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
+        let front = Vector::new(0.0, 0.0, -1.0);
+        let n = m.map_normal(&s, Point::new(0.0, 0.0, -2.0), front, None);
+        assert_eq!(n, Vector::new(0.0, 1.0, -1.0).normalize());
+
+        // no texture coordinates at the poles
+        let top = Point::new(0.0, 2.0, 0.0);
+        assert_eq!(m.map_normal(&s, top, up, None), up);
+    }
+
+    #[test]
+    fn emissive_lighting() {
+        let s = Sphere::new();
+        let m = Material::builder()
+            .emissive(RGB::new(0.5, 0.25, 0.0))
+            .build();
+        let position = Point::new(0.0, 0.0, 0.0);
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+
+        let lit = m.lighting(&s, light, position, eyev, normalv, false);
+        assert_eq!(lit, RGB::new(2.4, 2.15, 1.9));
+        // the glow does not need light
+        let shadowed = m.lighting(&s, light, position, eyev, normalv, true);
+        assert_eq!(shadowed, RGB::new(0.6, 0.35, 0.1));
+        let behind = PointLight::new(Point::new(0.0, 0.0, 10.0), WHITE);
+        let ctx = LightingContext::new(behind, position, eyev, normalv);
+        assert_eq!(lighting_terms(&m, &s, &ctx).emission, m.emissive);
+    }
+
+    #[test]
+    fn property_maps_material() {
+        let s = Sphere::new();
+        let m = Material::builder()
+            .specular(0.8)
+            .reflective(0.5)
+            .specular_map(Box::new(Stripes::stripe_pattern(WHITE, BLACK)))
+            .reflective_map(Box::new(Stripes::stripe_pattern(BLACK, WHITE)))
+            .build();
+        let a = Point::new(0.5, 0.0, 0.0);
+        let b = Point::new(-0.5, 0.0, 0.0);
+
+        assert_eq!(m.specular_at(&s, a), 0.8);
+        assert_eq!(m.specular_at(&s, b), 0.0);
+        assert_eq!(m.reflective_at(&s, a), 0.0);
+        assert_eq!(m.reflective_at(&s, b), 0.5);
+        assert_eq!(m.shininess_at(&s, b), m.shininess);
+        assert_eq!(m.transparency_at(&s, b), 0.0);
+
+        assert!(crate::float_eq(
+            m.roughness_at(&s, b),
+            (2.0 / (m.shininess + 2.0)).sqrt()
+        ));
+
+        // no highlight where the specular map is black
+        let eyev = Vector::new(0.0, 0.0, -1.0);
+        let normalv = Vector::new(0.0, 0.0, -1.0);
+        let light = PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE);
+        let lit_a = m.lighting(&s, light, a, eyev, normalv, false);
+        let lit_b = m.lighting(&s, light, b, eyev, normalv, false);
+        let dull = Material::builder().specular(0.0).build();
+        assert_eq!(lit_b, dull.lighting(&s, light, b, eyev, normalv, false));
+        assert!(lit_a.red > lit_b.red);
+    }
 }
@@ -0,0 +1,61 @@
+use crate::{Float, Material, Sampler, RGB};
+
+/// Random variation of materials, so a field of objects sharing one base
+/// material doesn't look artificially uniform:
+///
+/// ```
+/// use rtracer::{presets, MaterialJitter, Sampler};
+///
+/// let jitter = MaterialJitter {
+///     brightness: 0.2,
+///     shininess: 0.5,
+///     ..MaterialJitter::default()
+/// };
+/// let mut sampler = Sampler::new(42);
+/// let materials: Vec<_> = (0..10)
+///     .map(|_| {
+///         let mut m = presets::rubber();
+///         jitter.apply(&mut m, &mut sampler);
+///         m
+///     })
+///     .collect();
+/// assert_ne!(materials[0].color, materials[1].color);
+/// ```
+///
+/// Each range is the largest change in either direction, zero keeps the parameter.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct MaterialJitter {
+    /// Relative change of the color as a whole, 0.1 is up to ±10%.
+    pub brightness: Float,
+
+    /// Change of each color channel on its own, which varies the hue.
+    pub tint: Float,
+
+    /// Relative change of the shininess, i.e. of the roughness.
+    pub shininess: Float,
+
+    /// Change of the specular parameter.
+    pub specular: Float,
+
+    /// Change of the reflectiveness.
+    pub reflective: Float,
+}
+
+impl MaterialJitter {
+    /// Perturb `material` within the ranges, drawing numbers from `sampler`.
+    /// Parameters stay in their valid ranges.
+    pub fn apply(&self, material: &mut Material, sampler: &mut Sampler) {
+        let mut offset = |range: Float| sampler.range(-range, range);
+
+        let scale = 1.0 + offset(self.brightness);
+        let c = material.color;
+        material.color = RGB::new(
+            (c.red * scale + offset(self.tint)).max(0.0),
+            (c.green * scale + offset(self.tint)).max(0.0),
+            (c.blue * scale + offset(self.tint)).max(0.0),
+        );
+        material.shininess = (material.shininess * (1.0 + offset(self.shininess))).max(0.0);
+        material.specular = (material.specular + offset(self.specular)).clamp(0.0, 1.0);
+        material.reflective = (material.reflective + offset(self.reflective)).clamp(0.0, 1.0);
+    }
+}
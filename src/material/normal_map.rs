@@ -0,0 +1,126 @@
+use crate::{Canvas, Float, Uv, Vector};
+
+/// Tangent space normal map, the red, green and blue channels of each pixel
+/// are the x, y and z of the normal from 0 to 1, the usual bluish images:
+///
+/// ```
+/// use rtracer::{Canvas, Material, NormalMap, RGB};
+///
+/// let mut image = Canvas::new(2, 2);
+/// image.fill(RGB::new(0.5, 0.5, 1.0));
+/// let m = Material::builder()
+///     .normal_map(NormalMap::new(image).with_strength(0.5))
+///     .build();
+/// assert!(m.normal_map.is_some());
+/// ```
+///
+/// x points towards growing `u`, y towards growing `v` and z along the normal
+/// of the surface. The image covers the unit square of the texture coordinates
+/// with `v` going up, so the top row is at `v = 1`. It repeats outside of it.
+#[derive(Debug, Clone)]
+pub struct NormalMap {
+    /// The normals, stored as colors.
+    image: Canvas,
+
+    /// Scale of the tilt, 0 flattens the map and 1 keeps it as is.
+    strength: Float,
+}
+
+impl NormalMap {
+    /// Create a normal map of the image at full strength.
+    pub fn new(image: Canvas) -> Self {
+        Self {
+            image,
+            strength: 1.0,
+        }
+    }
+
+    /// Scale the tilt of the normals by `strength`.
+    pub fn with_strength(mut self, strength: Float) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    /// Scale of the tilt.
+    pub fn strength(&self) -> Float {
+        self.strength
+    }
+
+    /// Unit normal in tangent space at the texture coordinate, bilinearly
+    /// interpolated between the pixels.
+    pub fn normal_at(&self, uv: Uv) -> Vector {
+        let (width, height) = (self.image.width, self.image.height);
+        let x = uv.u.rem_euclid(1.0) * width as Float - 0.5;
+        let y = (1.0 - uv.v.rem_euclid(1.0)) * height as Float - 0.5;
+
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let wrap = |i: Float, n: usize| (i as isize).rem_euclid(n as isize) as usize;
+        let pixel = |dx: Float, dy: Float| {
+            let c = self
+                .image
+                .pixel_at(wrap(x0 + dx, width), wrap(y0 + dy, height));
+            Vector::new(c.red, c.green, c.blue)
+        };
+
+        let top = pixel(0.0, 0.0) * (1.0 - fx) + pixel(1.0, 0.0) * fx;
+        let bottom = pixel(0.0, 1.0) * (1.0 - fx) + pixel(1.0, 1.0) * fx;
+        let c = top * (1.0 - fy) + bottom * fy;
+
+        Vector::new(
+            (2.0 * c.x - 1.0) * self.strength,
+            (2.0 * c.y - 1.0) * self.strength,
+            (2.0 * c.z - 1.0).max(0.0),
+        )
+        .normalize()
+    }
+}
+
+impl PartialEq for NormalMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.strength == other.strength
+            && self.image.width == other.image.width
+            && self.image.height == other.image.height
+            && self.image.pixels().eq(other.image.pixels())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{UvFace, RGB};
+
+    #[test]
+    fn flat_normal_map() {
+        let mut image = Canvas::new(4, 4);
+        image.fill(RGB::new(0.5, 0.5, 1.0));
+        let map = NormalMap::new(image);
+
+        for (u, v) in [(0.0, 0.0), (0.3, 0.9), (1.7, -0.2)] {
+            let n = map.normal_at(Uv::new(UvFace::Side, u, v));
+            assert_eq!(n, Vector::new(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn tilted_normal_map() {
+        // left half tilted towards +u, right half towards +v
+        let mut image = Canvas::new(2, 1);
+        image.write_pixel(0, 0, RGB::new(1.0, 0.5, 1.0));
+        image.write_pixel(1, 0, RGB::new(0.5, 1.0, 1.0));
+        let map = NormalMap::new(image.clone());
+
+        let n = map.normal_at(Uv::new(UvFace::Side, 0.25, 0.5));
+        assert_eq!(n, Vector::new(1.0, 0.0, 1.0).normalize());
+        let n = map.normal_at(Uv::new(UvFace::Side, 0.75, 0.5));
+        assert_eq!(n, Vector::new(0.0, 1.0, 1.0).normalize());
+        // halfway, blended between both pixels
+        let n = map.normal_at(Uv::new(UvFace::Side, 0.5, 0.5));
+        assert_eq!(n, Vector::new(0.5, 0.5, 1.0).normalize());
+
+        let half = NormalMap::new(image).with_strength(0.5);
+        let n = half.normal_at(Uv::new(UvFace::Side, 0.25, 0.5));
+        assert_eq!(n, Vector::new(0.5, 0.0, 1.0).normalize());
+        assert_ne!(half, map);
+    }
+}
@@ -0,0 +1,99 @@
+//! Tuned materials for common looks, change them further with `MaterialBuilder`:
+//!
+//! ```
+//! use rtracer::{presets, MaterialBuilder, RGB};
+//!
+//! let red_rubber = MaterialBuilder::from(presets::rubber())
+//!     .color(RGB::new(0.6, 0.05, 0.05))
+//!     .build();
+//! assert_eq!(red_rubber.shininess, presets::rubber().shininess);
+//! ```
+
+use crate::{Material, ShadingModel, RGB};
+
+/// Glass with reflections, lets most light through and shows highlights.
+pub fn glass() -> Material {
+    Material::builder()
+        .color(RGB::new(0.1, 0.1, 0.1))
+        .ambient(0.0)
+        .diffuse(0.1)
+        .specular(1.0)
+        .shininess(300.0)
+        .reflective(0.9)
+        .transparency(0.9)
+        .refractive_index(1.5)
+        .build()
+}
+
+/// A perfect mirror with a sharp highlight.
+pub fn mirror() -> Material {
+    Material::builder()
+        .ambient(0.0)
+        .diffuse(0.0)
+        .specular(1.0)
+        .shininess(300.0)
+        .reflective(1.0)
+        .build()
+}
+
+/// Polished metal, a slightly blue reflection over a dim base color.
+pub fn chrome() -> Material {
+    Material::builder()
+        .color(RGB::new(0.8, 0.8, 0.85))
+        .ambient(0.05)
+        .diffuse(0.2)
+        .specular(1.0)
+        .shininess(400.0)
+        .reflective(0.7)
+        .shading_model(ShadingModel::BlinnPhong)
+        .build()
+}
+
+/// Dark rubber with a broad, faint highlight.
+pub fn rubber() -> Material {
+    Material::builder()
+        .color(RGB::new(0.1, 0.1, 0.1))
+        .diffuse(0.9)
+        .specular(0.1)
+        .shininess(10.0)
+        .build()
+}
+
+/// A perfectly diffuse surface without highlights.
+pub fn matte() -> Material {
+    Material::builder()
+        .specular(0.0)
+        .shading_model(ShadingModel::Lambert)
+        .build()
+}
+
+/// Clear water, tinting the light passing through it.
+pub fn water() -> Material {
+    Material::builder()
+        .color(RGB::new(0.0, 0.1, 0.15))
+        .ambient(0.0)
+        .diffuse(0.1)
+        .specular(1.0)
+        .shininess(300.0)
+        .reflective(0.5)
+        .transparency(0.9)
+        .refractive_index(1.333)
+        .shadow_tint(true)
+        .build()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn presets() {
+        for m in [glass(), mirror(), chrome(), rubber(), matte(), water()] {
+            assert!(m.ambient + m.diffuse <= 1.0);
+            assert!(m.refractive_index >= 1.0);
+            assert!(m.reflective <= 1.0 && m.transparency <= 1.0);
+        }
+        assert_eq!(water().refractive_index, 1.333);
+        assert_eq!(matte().shading_model, ShadingModel::Lambert);
+    }
+}
@@ -1,13 +1,15 @@
-use crate::{float_eq, Point, Vector};
+use crate::{float_eq, Float, Point, Vector};
 use std::{
+    error::Error,
     fmt,
-    ops::{Index, IndexMut, Mul},
+    ops::{Add, Div, Index, IndexMut, Mul, Sub},
+    str::FromStr,
 };
 
 /// Matrix 4x4 implementation (rows first).
 #[derive(Default, Debug, Clone, Copy)]
 pub struct Matrix {
-    pub data: [[f64; 4]; 4],
+    pub data: [[Float; 4]; 4],
 }
 
 pub const IDENTITY: Matrix = Matrix {
@@ -21,7 +23,7 @@ pub const IDENTITY: Matrix = Matrix {
 
 impl Matrix {
     /// Create a new 4x4 Matrix.
-    pub fn new(data: [[f64; 4]; 4]) -> Self {
+    pub fn new(data: [[Float; 4]; 4]) -> Self {
         Self { data }
     }
 
@@ -39,8 +41,8 @@ impl Matrix {
     /// Calculate the determinant of a matrix.
     ///
     /// 's': describes the matrix dimensions.
-    fn determinant(&self, s: usize) -> f64 {
-        let mut det = 0_f64;
+    fn determinant(&self, s: usize) -> Float {
+        let mut det: Float = 0.0;
 
         if s == 2 {
             det = self[0][0] * self[1][1] - self[0][1] * self[1][0];
@@ -52,6 +54,11 @@ impl Matrix {
         det
     }
 
+    /// Calculate the determinant of the whole 4x4 matrix.
+    pub fn determinant_4x4(&self) -> Float {
+        self.determinant(4)
+    }
+
     /// Return a submatrix from the matrix with the given col,row removed.
     fn sub_matrix(&self, r: usize, c: usize) -> Self {
         let mut ret = Matrix::default();
@@ -65,12 +72,12 @@ impl Matrix {
     }
 
     /// Calculate the minor.
-    fn minor(&self, r: usize, c: usize, s: usize) -> f64 {
+    fn minor(&self, r: usize, c: usize, s: usize) -> Float {
         self.sub_matrix(r, c).determinant(s)
     }
 
     /// Calculate the cofactor.
-    fn cofactor(&self, r: usize, c: usize, s: usize) -> f64 {
+    fn cofactor(&self, r: usize, c: usize, s: usize) -> Float {
         let mut minor = self.minor(r, c, s);
         if (r + c) % 2 == 1 {
             minor *= -1.0
@@ -79,8 +86,10 @@ impl Matrix {
     }
 
     /// Check if a Matrix is invertible.
+    /// A NaN or infinite determinant is not, dividing by it poisons the inverse.
     fn is_invertible(&self, s: usize) -> bool {
-        !(self.determinant(s) == 0.0)
+        let det = self.determinant(s);
+        det.is_finite() && det != 0.0
     }
 
     /// Calculate the inverse of a Matrix.
@@ -101,9 +110,108 @@ impl Matrix {
     }
 
     /// Return raw data
-    pub fn get_data(&self) -> [[f64; 4]; 4] {
+    pub fn get_data(&self) -> [[Float; 4]; 4] {
         self.data
     }
+
+    /// Element at row `r` and column `c`, None if out of range.
+    pub fn get(&self, r: usize, c: usize) -> Option<Float> {
+        self.data.get(r)?.get(c).copied()
+    }
+
+    /// Set the element at row `r` and column `c` and return the old one.
+    /// Returns None and leaves the matrix unchanged if out of range.
+    pub fn set(&mut self, r: usize, c: usize, value: Float) -> Option<Float> {
+        let element = self.data.get_mut(r)?.get_mut(c)?;
+        Some(std::mem::replace(element, value))
+    }
+
+    /// Iterate over the rows from top to bottom.
+    pub fn rows(&self) -> impl Iterator<Item = [Float; 4]> + '_ {
+        self.data.iter().copied()
+    }
+
+    /// Iterate over the columns from left to right.
+    pub fn columns(&self) -> impl Iterator<Item = [Float; 4]> + '_ {
+        (0..4).map(move |c| self.data.map(|row| row[c]))
+    }
+
+    /// Iterate over all elements row by row.
+    pub fn elements(&self) -> impl Iterator<Item = Float> + '_ {
+        self.data.iter().flatten().copied()
+    }
+
+    /// Create a matrix from elements listed column by column,
+    /// the layout of GPU shaders, glam and nalgebra.
+    pub fn from_cols_array(cols: [Float; 16]) -> Self {
+        let mut data = [[0.0; 4]; 4];
+        for (i, v) in cols.into_iter().enumerate() {
+            data[i % 4][i / 4] = v;
+        }
+        Self { data }
+    }
+
+    /// The elements column by column, see `from_cols_array`.
+    pub fn to_cols_array(&self) -> [Float; 16] {
+        std::array::from_fn(|i| self.data[i % 4][i / 4])
+    }
+
+    /// Create a matrix from elements listed row by row.
+    pub fn from_rows_array(rows: [Float; 16]) -> Self {
+        Self {
+            data: std::array::from_fn(|r| std::array::from_fn(|c| rows[r * 4 + c])),
+        }
+    }
+
+    /// The elements row by row, see `from_rows_array`.
+    pub fn to_rows_array(&self) -> [Float; 16] {
+        std::array::from_fn(|i| self.data[i / 4][i % 4])
+    }
+
+    /// Sum of the diagonal.
+    pub fn trace(&self) -> Float {
+        (0..4).map(|i| self.data[i][i]).sum()
+    }
+
+    /// Is the matrix the identity, within `EPSILON`?
+    pub fn is_identity(&self) -> bool {
+        *self == IDENTITY
+    }
+
+    /// Is the bottom row `[0, 0, 0, 1]`, within `EPSILON`?
+    /// Translation, rotation, scaling and shearing all are affine.
+    pub fn is_affine(&self) -> bool {
+        self.data[3]
+            .iter()
+            .zip([0.0, 0.0, 0.0, 1.0])
+            .all(|(a, b)| float_eq(*a, b))
+    }
+
+    /// Apply `f` to every element.
+    fn map<F: Fn(Float) -> Float>(&self, f: F) -> Self {
+        Self::new(self.data.map(|row| row.map(&f)))
+    }
+
+    /// Combine two matrices element by element.
+    fn zip<F: Fn(Float, Float) -> Float>(&self, other: &Self, f: F) -> Self {
+        let mut data = self.data;
+        for (r, row) in data.iter_mut().enumerate() {
+            for (c, v) in row.iter_mut().enumerate() {
+                *v = f(*v, other.data[r][c]);
+            }
+        }
+        Self::new(data)
+    }
+}
+
+/// Panic with the offending index instead of a bare bounds check.
+fn check_index(r: usize, c: usize) {
+    assert!(
+        r < 4 && c < 4,
+        "Matrix index ({}, {}) out of range, a matrix has 4 rows and 4 columns!",
+        r,
+        c
+    );
 }
 
 impl Mul for Matrix {
@@ -123,6 +231,79 @@ impl Mul for Matrix {
     }
 }
 
+#[cfg(feature = "glam")]
+impl From<glam::DMat4> for Matrix {
+    fn from(m: glam::DMat4) -> Self {
+        Self::from_cols_array(m.to_cols_array().map(|v| v as Float))
+    }
+}
+
+#[cfg(feature = "glam")]
+impl From<Matrix> for glam::DMat4 {
+    fn from(m: Matrix) -> Self {
+        glam::DMat4::from_cols_array(&m.to_cols_array().map(f64::from))
+    }
+}
+
+#[cfg(feature = "glam")]
+impl From<glam::Mat4> for Matrix {
+    fn from(m: glam::Mat4) -> Self {
+        m.as_dmat4().into()
+    }
+}
+
+/// Single precision for the GPU, loses precision unless the `f32` feature is on.
+#[cfg(feature = "glam")]
+impl From<Matrix> for glam::Mat4 {
+    fn from(m: Matrix) -> Self {
+        glam::DMat4::from(m).as_mat4()
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl From<nalgebra::Matrix4<Float>> for Matrix {
+    fn from(m: nalgebra::Matrix4<Float>) -> Self {
+        let mut cols = [0.0; 16];
+        cols.copy_from_slice(m.as_slice());
+        Self::from_cols_array(cols)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl From<Matrix> for nalgebra::Matrix4<Float> {
+    fn from(m: Matrix) -> Self {
+        nalgebra::Matrix4::from_column_slice(&m.to_cols_array())
+    }
+}
+
+impl Mul<Float> for Matrix {
+    type Output = Self;
+    fn mul(self, rhs: Float) -> Self::Output {
+        self.map(|v| v * rhs)
+    }
+}
+
+impl Div<Float> for Matrix {
+    type Output = Self;
+    fn div(self, rhs: Float) -> Self::Output {
+        self.map(|v| v / rhs)
+    }
+}
+
+impl Add for Matrix {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip(&rhs, |a, b| a + b)
+    }
+}
+
+impl Sub for Matrix {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.zip(&rhs, |a, b| a - b)
+    }
+}
+
 impl Mul<Vector> for Matrix {
     type Output = Vector;
     fn mul(self, rhs: Vector) -> Self::Output {
@@ -164,19 +345,37 @@ impl Mul<Point> for Matrix {
 }
 
 impl Index<usize> for Matrix {
-    type Output = [f64; 4];
+    type Output = [Float; 4];
 
     fn index(&self, index: usize) -> &Self::Output {
+        check_index(index, 0);
         &self.data[index]
     }
 }
 
 impl IndexMut<usize> for Matrix {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        check_index(index, 0);
         &mut self.data[index]
     }
 }
 
+impl Index<(usize, usize)> for Matrix {
+    type Output = Float;
+
+    fn index(&self, (r, c): (usize, usize)) -> &Self::Output {
+        check_index(r, c);
+        &self.data[r][c]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (r, c): (usize, usize)) -> &mut Self::Output {
+        check_index(r, c);
+        &mut self.data[r][c]
+    }
+}
+
 impl PartialEq for Matrix {
     fn eq(&self, rhs: &Self) -> bool {
         for r in 0..4 {
@@ -191,34 +390,89 @@ impl PartialEq for Matrix {
 }
 
 impl fmt::Display for Matrix {
+    /// One row per line, the columns are aligned at the decimal point.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[0][0]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[0][1]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[0][2]))?;
-        writeln!(f, "{0:>10}", format!("{0:.5}", self.data[0][3]))?;
-
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[1][0]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[1][1]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[1][2]))?;
-        writeln!(f, "{0:>10}", format!("{0:.5}", self.data[1][3]))?;
-
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[2][0]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[2][1]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[2][2]))?;
-        writeln!(f, "{0:>10}", format!("{0:.5}", self.data[2][3]))?;
-
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[3][0]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[3][1]))?;
-        write!(f, "{0:>10}", format!("{0:.5}", self.data[3][2]))?;
-        writeln!(f, "{0:>10}", format!("{0:.5}", self.data[3][3]))?;
+        // avoid printing -0.00000 for tiny negative values
+        let cells = self.data.map(|row| {
+            row.map(|v| {
+                let s = format!("{:.5}", v);
+                if s.trim_start_matches('-')
+                    .chars()
+                    .all(|c| c == '0' || c == '.')
+                {
+                    s.trim_start_matches('-').to_string()
+                } else {
+                    s
+                }
+            })
+        });
+        let width = cells.iter().flatten().map(|c| c.len()).max().unwrap_or(0);
 
+        for row in cells.iter() {
+            let line: Vec<String> = row.iter().map(|c| format!("{:>width$}", c)).collect();
+            writeln!(f, "{}", line.join(" "))?;
+        }
         Ok(())
     }
 }
 
+/// Error returned when parsing a Matrix or Transformation fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMatrixError {
+    message: String,
+}
+
+impl ParseMatrixError {
+    /// Create a new error with a description of the problem.
+    pub fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl fmt::Display for ParseMatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid matrix: {}", self.message)
+    }
+}
+
+impl Error for ParseMatrixError {}
+
+impl FromStr for Matrix {
+    type Err = ParseMatrixError;
+
+    /// Parse 16 numbers in row order, separated by whitespace, commas or
+    /// semicolons. Brackets are ignored, so `[[1, 0, ...], ...]` and the
+    /// output of Display both work.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values: Vec<Float> = s
+            .split(|c: char| c.is_whitespace() || ",;[]".contains(c))
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse()
+                    .map_err(|_| ParseMatrixError::new(format!("'{}' is not a number", v)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        if values.len() != 16 {
+            return Err(ParseMatrixError::new(format!(
+                "expected 16 numbers, found {}",
+                values.len()
+            )));
+        }
+
+        let mut m = Matrix::default();
+        for (i, v) in values.into_iter().enumerate() {
+            m[i / 4][i % 4] = v;
+        }
+        Ok(m)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    #[cfg(any(feature = "glam", feature = "nalgebra"))]
+    use crate::Transformation;
 
     #[test]
     fn init_matrix2() {
@@ -522,6 +776,14 @@ mod test {
         assert!(!a.is_invertible(4));
     }
 
+    #[test]
+    fn is_invertible_nan_matrix() {
+        let mut a = Matrix::new(IDENTITY.get_data());
+        a[1][2] = Float::NAN;
+        assert!(!a.is_invertible(4));
+        assert!(a.inverse(4).is_none());
+    }
+
     #[test]
     fn inverse_1_matrix() {
         let a = Matrix::new([
@@ -602,4 +864,147 @@ mod test {
 
         assert_eq!(c * b.inverse(4).unwrap(), a);
     }
+
+    #[test]
+    fn display_matrix() {
+        let m = Matrix::new([
+            [1.0, -2.5, 0.0, -0.000001],
+            [-100.0, 0.5, 3.0, 4.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let expected = [
+            "   1.00000   -2.50000    0.00000    0.00000",
+            "-100.00000    0.50000    3.00000    4.00000",
+            "   0.00000    0.00000    1.00000    0.00000",
+            "   0.00000    0.00000    0.00000    1.00000",
+        ]
+        .map(|row| row.to_string() + "\n")
+        .concat();
+
+        assert_eq!(m.to_string(), expected);
+    }
+
+    #[test]
+    fn parse_matrix() {
+        let m: Matrix = "[[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12], [13, 14, 15, 16]]"
+            .parse()
+            .unwrap();
+
+        assert_eq!(m[1][2], 7.0);
+        assert_eq!(m[3][0], 13.0);
+        assert_eq!(m.to_string().parse::<Matrix>(), Ok(m));
+        assert!("1 2 3".parse::<Matrix>().is_err());
+        assert!("1 2 3 x 5 6 7 8 9 10 11 12 13 14 15 16"
+            .parse::<Matrix>()
+            .is_err());
+    }
+
+    #[test]
+    fn element_access_matrix() {
+        let mut m = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ]);
+
+        assert_eq!(m.get(1, 2), Some(7.0));
+        assert_eq!(m.get(4, 0), None);
+        assert_eq!(m.get(0, 4), None);
+        assert_eq!(m.set(1, 2, -7.0), Some(7.0));
+        assert_eq!(m.set(0, 9, 1.0), None);
+        assert_eq!(m[(1, 2)], -7.0);
+        m[(3, 3)] = 0.0;
+        assert_eq!(m[3][3], 0.0);
+
+        assert_eq!(m.rows().nth(2), Some([9.0, 10.0, 11.0, 12.0]));
+        assert_eq!(m.columns().nth(1), Some([2.0, 6.0, 10.0, 14.0]));
+        assert_eq!(m.elements().count(), 16);
+        assert_eq!(m.elements().nth(4), Some(5.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix index (5, 0) out of range")]
+    fn index_out_of_range_matrix() {
+        let _ = IDENTITY[5][0];
+    }
+
+    #[test]
+    #[should_panic(expected = "Matrix index (0, 4) out of range")]
+    fn tuple_index_out_of_range_matrix() {
+        let _ = IDENTITY[(0, 4)];
+    }
+
+    #[test]
+    fn arithmetic_matrix() {
+        let a = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 8.0, 7.0, 6.0],
+            [5.0, 4.0, 3.0, 2.0],
+        ]);
+
+        assert_eq!((a * 2.0)[(1, 2)], 14.0);
+        assert_eq!((a / 2.0)[(3, 0)], 2.5);
+        assert_eq!(a + IDENTITY - a, IDENTITY);
+        assert_eq!((a + a)[(2, 1)], 16.0);
+        assert_eq!(a.trace(), 16.0);
+        assert_eq!(IDENTITY.trace(), 4.0);
+    }
+
+    #[test]
+    fn predicates_matrix() {
+        assert!(IDENTITY.is_identity());
+        assert!(IDENTITY.is_affine());
+
+        let mut m = IDENTITY;
+        m[(0, 3)] = 5.0;
+        assert!(!m.is_identity());
+        assert!(m.is_affine());
+        m[(3, 0)] = 1.0;
+        assert!(!m.is_affine());
+    }
+
+    #[test]
+    fn layout_matrix() {
+        let m = Matrix::new([
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ]);
+        let cols = m.to_cols_array();
+
+        assert_eq!(&cols[..4], &[1.0, 5.0, 9.0, 13.0]);
+        assert_eq!(Matrix::from_cols_array(cols), m);
+        assert_eq!(m.to_rows_array()[4], 5.0);
+        assert_eq!(Matrix::from_rows_array(m.to_rows_array()), m);
+        assert_eq!(Matrix::from_rows_array(cols), m.transpose());
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn glam_matrix() {
+        let m = Transformation::new()
+            .then_translation(1.0, 2.0, 3.0)
+            .as_matrix();
+        let g: glam::DMat4 = m.into();
+
+        assert_eq!(g.w_axis, glam::DVec4::new(1.0, 2.0, 3.0, 1.0));
+        assert_eq!(Matrix::from(g), m);
+        assert_eq!(Matrix::from(glam::Mat4::from(m)), m);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn nalgebra_matrix() {
+        let m = Transformation::new()
+            .then_translation(1.0, 2.0, 3.0)
+            .as_matrix();
+        let n: nalgebra::Matrix4<Float> = m.into();
+
+        assert_eq!(n[(0, 3)], 1.0);
+        assert_eq!(Matrix::from(n), m);
+    }
 }
@@ -0,0 +1,340 @@
+use crate::consts::PI;
+use crate::shapes::mesh::Face;
+use crate::{Float, Material, Mesh, Point, Vector};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+/// Options for loading OBJ files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObjOptions {
+    /// Compute smooth normals for faces without `vn` data.
+    /// Faces meeting at a larger angle (radians) keep a hard edge.
+    /// None keeps such faces flat shaded.
+    pub crease_angle: Option<Float>,
+}
+
+impl Default for ObjOptions {
+    fn default() -> Self {
+        Self {
+            crease_angle: Some(PI / 3.0),
+        }
+    }
+}
+
+/// Errors while loading an OBJ file.
+#[derive(Debug)]
+pub enum ObjError {
+    /// The input could not be read.
+    Io(io::Error),
+
+    /// A statement is malformed, `line` starts at 1.
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io(why) => write!(f, "Could not read OBJ: {}", why),
+            ObjError::Parse { line, message } => write!(f, "OBJ line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(why: io::Error) -> Self {
+        ObjError::Io(why)
+    }
+}
+
+/// Turn an OBJ index into a list index.
+/// Positive indices start at 1, negative ones count back from the last element.
+fn resolve(index: &str, len: usize) -> Result<usize, String> {
+    let i: i64 = index
+        .parse()
+        .map_err(|_| format!("invalid index '{}'", index))?;
+    let resolved = if i > 0 { i - 1 } else { len as i64 + i };
+
+    if i == 0 || resolved < 0 || resolved >= len as i64 {
+        Err(format!("index {} out of range, {} defined", i, len))
+    } else {
+        Ok(resolved as usize)
+    }
+}
+
+/// One face corner `v`, `v/vt`, `v/vt/vn` or `v//vn`.
+#[derive(Debug, Clone, Copy)]
+struct Corner {
+    vertex: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+/// Lists read so far, used to resolve the indices of a face.
+struct Lists {
+    vertices: Vec<Point>,
+    normals: Vec<Vector>,
+    uvs: Vec<[Float; 2]>,
+}
+
+impl Lists {
+    fn corner(&self, corner: &str) -> Result<Corner, String> {
+        let mut parts = corner.split('/');
+        let vertex = resolve(parts.next().unwrap_or_default(), self.vertices.len())?;
+        let uv = match parts.next() {
+            Some(t) if !t.is_empty() => Some(resolve(t, self.uvs.len())?),
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(n) if !n.is_empty() => Some(resolve(n, self.normals.len())?),
+            _ => None,
+        };
+
+        Ok(Corner { vertex, uv, normal })
+    }
+}
+
+/// Parse `count` floats, OBJ allows optional trailing values (e.g. `w`).
+fn parse_floats<'a>(
+    values: impl Iterator<Item = &'a str>,
+    count: usize,
+) -> Result<Vec<Float>, String> {
+    let values: Vec<Float> = values
+        .map(|v| v.parse().map_err(|_| format!("invalid number '{}'", v)))
+        .collect::<Result<_, _>>()?;
+
+    if values.len() < count {
+        Err(format!(
+            "expected {} numbers, found {}",
+            count,
+            values.len()
+        ))
+    } else {
+        Ok(values)
+    }
+}
+
+/// Parse the vertices, normals, texture coordinates and faces of an OBJ file into one mesh.
+/// The input is read line by line, so large files are never held in memory as a whole.
+/// Polygons are split into a fan of triangles, unknown statements are ignored.
+/// Each `usemtl` name becomes a material group of the mesh with a default material.
+pub fn parse_obj<R: BufRead>(mut reader: R, options: ObjOptions) -> Result<Mesh, ObjError> {
+    let mut lists = Lists {
+        vertices: Vec::new(),
+        normals: Vec::new(),
+        uvs: Vec::new(),
+    };
+    let mut faces = Vec::new();
+    // names of the `usemtl` groups and the one faces currently belong to
+    let mut groups: Vec<String> = Vec::new();
+    let mut group = None;
+
+    let mut line = String::new();
+    let mut number = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        number += 1;
+
+        let mut tokens = line.split_whitespace();
+        let parsed = match tokens.next() {
+            Some("v") => parse_floats(tokens, 3).map(|v| {
+                lists.vertices.push(Point::new(v[0], v[1], v[2]));
+            }),
+            Some("vn") => parse_floats(tokens, 3).map(|v| {
+                lists.normals.push(Vector::new(v[0], v[1], v[2]));
+            }),
+            // the v coordinate is optional and defaults to 0
+            Some("vt") => parse_floats(tokens, 1).map(|v| {
+                lists.uvs.push([v[0], v.get(1).copied().unwrap_or(0.0)]);
+            }),
+            Some("f") => tokens
+                .map(|c| lists.corner(c))
+                .collect::<Result<Vec<Corner>, String>>()
+                .and_then(|corners| {
+                    if corners.len() < 3 {
+                        return Err(format!("face needs 3 corners, found {}", corners.len()));
+                    }
+
+                    for i in 1..corners.len() - 1 {
+                        let [a, b, c] = [corners[0], corners[i], corners[i + 1]];
+                        faces.push(Face {
+                            vertices: [a.vertex, b.vertex, c.vertex],
+                            normals: a
+                                .normal
+                                .zip(b.normal)
+                                .zip(c.normal)
+                                .map(|((a, b), c)| [a, b, c]),
+                            uvs: a.uv.zip(b.uv).zip(c.uv).map(|((a, b), c)| [a, b, c]),
+                            material: group,
+                        });
+                    }
+                    Ok(())
+                }),
+            Some("usemtl") => match tokens.next() {
+                Some(name) => {
+                    group = match groups.iter().position(|g| g == name) {
+                        Some(i) => Some(i),
+                        None => {
+                            groups.push(name.to_string());
+                            Some(groups.len() - 1)
+                        }
+                    };
+                    Ok(())
+                }
+                None => Err("usemtl needs a material name".to_string()),
+            },
+            _ => Ok(()),
+        };
+
+        parsed.map_err(|message| ObjError::Parse {
+            line: number,
+            message,
+        })?;
+    }
+
+    let mut mesh = Mesh::new(lists.vertices, lists.normals, faces);
+    mesh.set_uvs(lists.uvs);
+    // the materials themselves are set by the caller via `material_group_mut`
+    for name in &groups {
+        mesh.add_material_group(name, Material::default());
+    }
+    if let Some(angle) = options.crease_angle {
+        mesh.smooth_normals(angle);
+    }
+    Ok(mesh)
+}
+
+/// Load an OBJ file from disk, see `parse_obj`.
+pub fn load_obj<P: AsRef<Path>>(path: P, options: ObjOptions) -> Result<Mesh, ObjError> {
+    let file = File::open(path)?;
+    parse_obj(BufReader::new(file), options)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Shape, Uv, UvFace};
+
+    fn parse(input: &str, options: ObjOptions) -> Mesh {
+        parse_obj(input.as_bytes(), options).expect("Input is valid OBJ!")
+    }
+
+    #[test]
+    fn ignore_lines_obj() {
+        let input = "There was a young lady named Bright\nwho traveled much faster than light.\n";
+        let m = parse(input, ObjOptions::default());
+
+        assert!(m.vertices().is_empty());
+        assert!(m.faces().is_empty());
+    }
+
+    #[test]
+    fn vertices_faces_obj() {
+        let input = "v -1 1 0\nv -1.0000 0.5000 0.0000\nv 1 0 0\nv 1 1 0\n\nf 1 2 3\nf 1 3 4\n";
+        let m = parse(input, ObjOptions { crease_angle: None });
+
+        assert_eq!(m.vertices()[1], Point::new(-1.0, 0.5, 0.0));
+        assert_eq!(m.faces(), &[Face::new([0, 1, 2]), Face::new([0, 2, 3])]);
+    }
+
+    #[test]
+    fn polygon_obj() {
+        let input = "v -1 1 0\nv -1 0 0\nv 1 0 0\nv 1 1 0\nv 0 2 0\nf 1 2 3 4 5\n";
+        let m = parse(input, ObjOptions { crease_angle: None });
+
+        assert_eq!(
+            m.faces(),
+            &[
+                Face::new([0, 1, 2]),
+                Face::new([0, 2, 3]),
+                Face::new([0, 3, 4])
+            ]
+        );
+    }
+
+    #[test]
+    fn normals_obj() {
+        let input = "v 0 1 0\nv -1 0 0\nv 1 0 0\nvn -1 0 0\nvn 1 2 3\nf 1//2 2//1 3//2\n";
+        let m = parse(input, ObjOptions::default());
+
+        assert_eq!(m.normals()[1], Vector::new(1.0, 2.0, 3.0));
+        assert_eq!(m.faces()[0].normals, Some([1, 0, 1]));
+    }
+
+    #[test]
+    fn negative_indices_obj() {
+        let input = "v 0 1 0\nv -1 0 0\nv 1 0 0\nvn 0 0 1\nf -3//-1 -2//-1 -1//-1\n";
+        let m = parse(input, ObjOptions::default());
+
+        assert_eq!(m.faces()[0].vertices, [0, 1, 2]);
+        assert_eq!(m.faces()[0].normals, Some([0, 0, 0]));
+    }
+
+    #[test]
+    fn texture_coordinates_obj() {
+        let input = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0.5\nf 1/1 2/2 3/3\n";
+        let m = parse(input, ObjOptions { crease_angle: None });
+
+        assert_eq!(m.uvs(), &[[0.0, 0.0], [1.0, 0.0], [0.5, 0.0]]);
+        assert_eq!(m.faces()[0].uvs, Some([0, 1, 2]));
+        assert_eq!(
+            m.local_uv_at(Point::new(0.5, 0.0, 0.0)),
+            Some(Uv::new(UvFace::Side, 0.5, 0.0))
+        );
+    }
+
+    #[test]
+    fn material_groups_obj() {
+        let input = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\nusemtl red\nf 1 2 4\nusemtl blue\nf 1 3 4\nusemtl red\nf 2 3 4\n";
+        let m = parse(input, ObjOptions::default());
+
+        assert_eq!(m.material_groups().collect::<Vec<_>>(), vec!["red", "blue"]);
+        let groups: Vec<Option<usize>> = m.faces().iter().map(|f| f.material).collect();
+        assert_eq!(groups, vec![None, Some(0), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn errors_obj() {
+        let data = vec![
+            ("v 1 2 3\nv 1 x 3\n", 2),
+            ("v 1 2\n", 1),
+            ("v 0 0 0\nv 1 0 0\nf 1 2 3\n", 3),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 -4\n", 5),
+            ("v 0 0 0\nf 1 0 1\n", 2),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2\n", 4),
+            ("usemtl\n", 1),
+        ];
+        for (input, expected) in data {
+            match parse_obj(input.as_bytes(), ObjOptions::default()) {
+                Err(ObjError::Parse { line, .. }) => assert_eq!(line, expected),
+                other => panic!("Expected a parse error, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_obj() {
+        let input = "v 0 0 0\nv 1 0 0\nv 0 0 -1\nv 0 1 0\nf 1 2 3\nf 1 2 4\n";
+        let flat = parse(input, ObjOptions { crease_angle: None });
+        let smooth = parse(
+            input,
+            ObjOptions {
+                crease_angle: Some(PI),
+            },
+        );
+        let p = Point::new(0.5, 0.0, 0.0);
+
+        assert!(flat.faces().iter().all(|f| f.normals.is_none()));
+        assert_eq!(
+            smooth.local_normal_at(p),
+            Vector::new(0.0, 1.0, 1.0).normalize()
+        );
+    }
+}
@@ -3,7 +3,8 @@ use std::fmt::Debug;
 use uuid::Uuid;
 
 /// This traits describes all patterns.
-pub trait Pattern: Debug {
+/// Patterns are `Clone`, so materials holding them can be copied.
+pub trait Pattern: Debug + Send + Sync + PatternClone {
     /// Used for comparing patterns.
     fn id(&self) -> Uuid;
 
@@ -11,16 +12,26 @@ pub trait Pattern: Debug {
     fn pattern_at_shape(&self, shape: &dyn Shape, point: Point) -> RGB {
         let object_point = shape
             .get_transform()
-            .init()
-            .inverse(4)
+            .inverse()
             .expect("Object transform should be invertible")
             * point;
+        let pattern_point = shape
+            .pattern_transform(self.get_transform())
+            .inverse()
+            .expect("Pattern transform should be invertible")
+            * object_point;
+
+        self.pattern_at(pattern_point)
+    }
+
+    /// Color at a point in the space of an enclosing pattern,
+    /// used by patterns that combine other patterns.
+    fn pattern_at_nested(&self, point: Point) -> RGB {
         let pattern_point = self
             .get_transform()
-            .init()
-            .inverse(4)
+            .inverse()
             .expect("Pattern transform should be invertible")
-            * object_point;
+            * point;
 
         self.pattern_at(pattern_point)
     }
@@ -41,13 +52,46 @@ impl PartialEq for Box<dyn Pattern> {
     }
 }
 
+/// Copies boxed patterns, implemented for every pattern that is `Clone`.
+pub trait PatternClone {
+    /// A boxed copy of the pattern, with the same id.
+    fn clone_box(&self) -> Box<dyn Pattern>;
+}
+
+impl<T: 'static + Pattern + Clone> PatternClone for T {
+    fn clone_box(&self) -> Box<dyn Pattern> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Pattern> {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_box()
+    }
+}
+
 pub mod stripes;
 pub use stripes::Stripes;
 pub mod gradient;
 pub use gradient::Gradient;
+pub use gradient::GradientMode;
 pub mod ring;
 pub use ring::Ring;
 pub mod checkers;
 pub use checkers::Checkers;
+pub mod masked;
+pub use masked::Masked;
+pub mod ramp;
+pub use ramp::ColorRamp;
+pub mod marble;
+pub use marble::Marble;
+pub mod wood;
+pub use wood::Wood;
+pub mod brick;
+pub use brick::Brick;
+pub mod hexagon;
+pub use hexagon::Hexagon;
+pub mod voronoi;
+pub use voronoi::Voronoi;
 pub mod test_pattern;
 pub use test_pattern::TestPattern;
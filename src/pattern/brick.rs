@@ -0,0 +1,106 @@
+use crate::*;
+use uuid::Uuid;
+
+/// Brick wall in the xy plane: courses of bricks stacked along y, every other
+/// course shifted by half a brick, with mortar in between. Constant along z.
+#[derive(Debug, Clone, Copy)]
+pub struct Brick {
+    /// Id.
+    uuid: Uuid,
+
+    /// Color of the bricks.
+    brick: RGB,
+
+    /// Color of the joints.
+    mortar: RGB,
+
+    /// Size of a brick including one joint, along x and y.
+    size: (Float, Float),
+
+    /// Width of the joints.
+    mortar_width: Float,
+
+    /// Transformation matrix.
+    transform: Transformation,
+}
+
+impl Brick {
+    /// Red bricks of 0.25 x 0.075 units with light gray mortar.
+    pub fn new() -> Self {
+        Self::brick_pattern(RGB::new(0.55, 0.2, 0.12), RGB::new(0.8, 0.8, 0.75))
+    }
+
+    pub fn brick_pattern(brick: RGB, mortar: RGB) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            brick,
+            mortar,
+            size: (0.25, 0.075),
+            mortar_width: 0.01,
+            transform: Transformation::new(),
+        }
+    }
+
+    /// Size of a brick including one joint and the width of the joints.
+    pub fn with_size(mut self, width: Float, height: Float, mortar_width: Float) -> Self {
+        self.size = (width, height);
+        self.mortar_width = mortar_width;
+        self
+    }
+}
+
+impl Default for Brick {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pattern for Brick {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn pattern_at(&self, point: Point) -> RGB {
+        let (width, height) = self.size;
+        let course = (point.y / height).floor();
+        let shift = if float_eq(course.rem_euclid(2.0), 1.0) {
+            width / 2.0
+        } else {
+            0.0
+        };
+        let x = (point.x + shift).rem_euclid(width);
+        let y = point.y.rem_euclid(height);
+        if x < self.mortar_width || y < self.mortar_width {
+            self.mortar
+        } else {
+            self.brick
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn courses_brick() {
+        let pattern = Brick::brick_pattern(RED, WHITE).with_size(1.0, 0.5, 0.1);
+
+        assert_eq!(pattern.pattern_at(Point::new(0.5, 0.25, 0.0)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(0.05, 0.25, 3.0)), WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(0.5, 0.05, 0.0)), WHITE);
+        assert_eq!(pattern.pattern_at(Point::new(-0.5, -0.25, 0.0)), WHITE);
+        // the next course is shifted by half a brick
+        assert_eq!(pattern.pattern_at(Point::new(0.05, 0.75, 0.0)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(0.55, 0.75, 0.0)), WHITE);
+    }
+}
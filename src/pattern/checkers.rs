@@ -20,7 +20,7 @@ pub struct Checkers {
 impl Checkers {
     pub fn new() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             a: WHITE,
             b: BLACK,
             transform: Transformation::new(),
@@ -29,7 +29,7 @@ impl Checkers {
 
     pub fn checkers_pattern(a: RGB, b: RGB) -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             a,
             b,
             transform: Transformation::new(),
@@ -47,6 +47,7 @@ impl Pattern for Checkers {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
@@ -1,7 +1,19 @@
 use crate::*;
 use uuid::Uuid;
 
-/// Gradient pattern.
+/// What a `Gradient` does beyond its end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GradientMode {
+    /// Keep the color of the nearest end.
+    Clamp,
+    /// Start over, which jumps back to the first color at every end.
+    #[default]
+    Repeat,
+    /// Run back and forth, without seams.
+    Mirror,
+}
+
+/// Gradient pattern, from `a` at `start` to `b` at `start + direction`.
 #[derive(Debug, Clone, Copy)]
 pub struct Gradient {
     /// Id.
@@ -13,6 +25,15 @@ pub struct Gradient {
     /// Color 2.
     b: RGB,
 
+    /// Where the gradient starts with color 1.
+    start: Point,
+
+    /// Offset from the start to where the gradient reaches color 2.
+    direction: Vector,
+
+    /// Edge behavior.
+    mode: GradientMode,
+
     /// Transformation matrix.
     transform: Transformation,
 }
@@ -20,21 +41,45 @@ pub struct Gradient {
 impl Gradient {
     pub fn new() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             a: WHITE,
             b: BLACK,
+            start: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(1.0, 0.0, 0.0),
+            mode: GradientMode::Repeat,
             transform: Transformation::new(),
         }
     }
 
     pub fn gradient_pattern(a: RGB, b: RGB) -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             a,
             b,
+            start: Point::new(0.0, 0.0, 0.0),
+            direction: Vector::new(1.0, 0.0, 0.0),
+            mode: GradientMode::Repeat,
             transform: Transformation::new(),
         }
     }
+
+    /// Run from `start` to `start + direction` instead of along one unit of x.
+    /// Panics if `direction` is the zero vector.
+    pub fn with_axis(mut self, start: Point, direction: Vector) -> Self {
+        assert!(
+            direction.magnitude() > 0.0,
+            "Gradient direction must not be zero!"
+        );
+        self.start = start;
+        self.direction = direction;
+        self
+    }
+
+    /// Set the edge behavior, `GradientMode::Repeat` by default.
+    pub fn with_mode(mut self, mode: GradientMode) -> Self {
+        self.mode = mode;
+        self
+    }
 }
 
 impl Pattern for Gradient {
@@ -47,12 +92,18 @@ impl Pattern for Gradient {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
     fn pattern_at(&self, point: Point) -> RGB {
         let distance = self.b - self.a;
-        let fraction = point.x - point.x.floor();
+        let t = (point - self.start).dot(self.direction) / self.direction.dot(self.direction);
+        let fraction = match self.mode {
+            GradientMode::Clamp => t.clamp(0.0, 1.0),
+            GradientMode::Repeat => t - t.floor(),
+            GradientMode::Mirror => 1.0 - (t.rem_euclid(2.0) - 1.0).abs(),
+        };
 
         self.a + distance * fraction
     }
@@ -80,4 +131,40 @@ mod test {
             RGB::new(0.25, 0.25, 0.25)
         );
     }
+
+    #[test]
+    fn gradient_axis_pattern() {
+        let pattern = Gradient::gradient_pattern(WHITE, BLACK)
+            .with_axis(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, 4.0, 0.0));
+
+        assert_eq!(pattern.pattern_at(Point::new(7.0, 1.0, 0.0)), WHITE);
+        assert_eq!(
+            pattern.pattern_at(Point::new(0.0, 2.0, 3.0)),
+            RGB::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.pattern_at(Point::new(0.0, 4.0, 0.0)),
+            RGB::new(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn gradient_mode_pattern() {
+        let gray = |v| RGB::new(v, v, v);
+        let repeat = Gradient::gradient_pattern(BLACK, WHITE);
+        let clamp = repeat.with_mode(GradientMode::Clamp);
+        let mirror = repeat.with_mode(GradientMode::Mirror);
+
+        for (x, r, c, m) in [
+            (-0.25, 0.75, 0.0, 0.25),
+            (0.25, 0.25, 0.25, 0.25),
+            (1.25, 0.25, 1.0, 0.75),
+            (2.25, 0.25, 1.0, 0.25),
+        ] {
+            let p = Point::new(x, 0.0, 0.0);
+            assert_eq!(repeat.pattern_at(p), gray(r));
+            assert_eq!(clamp.pattern_at(p), gray(c));
+            assert_eq!(mirror.pattern_at(p), gray(m));
+        }
+    }
 }
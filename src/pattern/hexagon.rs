@@ -0,0 +1,99 @@
+use crate::*;
+use uuid::Uuid;
+
+/// Tiling of hexagons in the xz plane, like floor tiles. Three colors are
+/// enough so that no two neighboring tiles share one. Constant along y.
+#[derive(Debug, Clone, Copy)]
+pub struct Hexagon {
+    /// Id.
+    uuid: Uuid,
+
+    /// Colors of the tiles.
+    colors: [RGB; 3],
+
+    /// Transformation matrix.
+    transform: Transformation,
+}
+
+impl Hexagon {
+    pub fn new() -> Self {
+        Self::hexagon_pattern(WHITE, RGB::new(0.5, 0.5, 0.5), BLACK)
+    }
+
+    /// Hexagons with corners one unit from their center, flat sides facing ±z.
+    pub fn hexagon_pattern(a: RGB, b: RGB, c: RGB) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            colors: [a, b, c],
+            transform: Transformation::new(),
+        }
+    }
+
+    /// Axial coordinates of the tile containing the point (x, z).
+    fn tile(x: Float, z: Float) -> (i64, i64) {
+        let sqrt3 = (3.0 as Float).sqrt();
+        let q = x * 2.0 / 3.0;
+        let r = -x / 3.0 + z * sqrt3 / 3.0;
+        let s = -q - r;
+
+        // round to the closest hexagon center in cube coordinates
+        let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+        if dq > dr && dq > ds {
+            rq = -rr - rs;
+        } else if dr > ds {
+            rr = -rq - rs;
+        }
+        (rq as i64, rr as i64)
+    }
+}
+
+impl Default for Hexagon {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pattern for Hexagon {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn pattern_at(&self, point: Point) -> RGB {
+        let (q, r) = Self::tile(point.x, point.z);
+        // neighbors differ by 1 or 2 in q - r
+        self.colors[(q - r).rem_euclid(3) as usize]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tiles_hexagon() {
+        let pattern = Hexagon::hexagon_pattern(RED, GREEN, BLUE);
+        let sqrt3 = (3.0 as Float).sqrt();
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 5.0, 0.0)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(0.9, 0.0, 0.0)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.8)), RED);
+
+        // the six neighbors around the origin
+        let center = pattern.pattern_at(Point::new(0.0, 0.0, 0.0));
+        for i in 0..6 {
+            let angle = consts::PI / 6.0 + i as Float * consts::PI / 3.0;
+            let p = Point::new(sqrt3 * angle.cos(), 0.0, sqrt3 * angle.sin());
+            assert_ne!(pattern.pattern_at(p), center);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::consts::PI;
+use crate::*;
+use uuid::Uuid;
+
+/// Marble: stripes along x with veins distorted by turbulence,
+/// colored by a ramp from the stripe centers (0) to the veins (1).
+#[derive(Debug, Clone)]
+pub struct Marble {
+    /// Id.
+    uuid: Uuid,
+
+    /// Colors from the stone to the veins.
+    ramp: ColorRamp,
+
+    /// Source of the turbulence.
+    noise: Noise,
+
+    /// Veins per unit along x.
+    frequency: Float,
+
+    /// How far the veins wander.
+    turbulence: Float,
+
+    /// Detail of the turbulence.
+    octaves: usize,
+
+    /// Transformation matrix.
+    transform: Transformation,
+}
+
+impl Marble {
+    /// White marble with dark gray veins.
+    pub fn new() -> Self {
+        Self::marble_pattern(ColorRamp::new(vec![
+            (0.0, WHITE),
+            (0.7, RGB::new(0.85, 0.85, 0.82)),
+            (1.0, RGB::new(0.2, 0.2, 0.22)),
+        ]))
+    }
+
+    pub fn marble_pattern(ramp: ColorRamp) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            ramp,
+            noise: Noise::default(),
+            frequency: 1.0,
+            turbulence: 4.0,
+            octaves: 5,
+            transform: Transformation::new(),
+        }
+    }
+
+    /// Use the noise of `seed`, for slabs that don't look alike.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.noise = Noise::new(seed);
+        self
+    }
+
+    /// Veins per unit, how far they wander and with how many octaves of detail.
+    pub fn with_veins(mut self, frequency: Float, turbulence: Float, octaves: usize) -> Self {
+        self.frequency = frequency;
+        self.turbulence = turbulence;
+        self.octaves = octaves;
+        self
+    }
+}
+
+impl Default for Marble {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pattern for Marble {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn pattern_at(&self, point: Point) -> RGB {
+        let phase =
+            point.x * self.frequency + self.turbulence * self.noise.turbulence(point, self.octaves);
+        // sharp veins where the sine crosses zero
+        let vein = 1.0 - (phase * PI).sin().abs();
+        self.ramp.color_at(vein)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn veins_marble() {
+        let plain = Marble::marble_pattern(ColorRamp::linear(BLACK, WHITE)).with_veins(1.0, 0.0, 0);
+
+        // without turbulence the veins are straight, at integer x
+        assert_eq!(plain.pattern_at(Point::new(2.0, 5.0, -3.0)), WHITE);
+        assert_eq!(plain.pattern_at(Point::new(0.5, 1.0, 0.0)), BLACK);
+
+        let a = Marble::new().with_seed(9);
+        let b = Marble::new().with_seed(9);
+        let p = Point::new(0.3, 0.7, -1.2);
+        assert_eq!(a.pattern_at(p), b.pattern_at(p));
+        assert_ne!(a.pattern_at(p), plain.with_veins(1.0, 4.0, 5).pattern_at(p));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::*;
+use uuid::Uuid;
+
+/// Blend of two patterns, weighted by the brightness of a third.
+/// Where the mask is black `a` shows, where it is white `b`,
+/// e.g. moss (b) on rock (a) wherever a noise or gradient mask is bright.
+#[derive(Debug, Clone)]
+pub struct Masked {
+    /// Id.
+    uuid: Uuid,
+
+    /// Pattern where the mask is black.
+    a: Box<dyn Pattern>,
+
+    /// Pattern where the mask is white.
+    b: Box<dyn Pattern>,
+
+    /// Blend weight, the average of its channels clamped to [0,1].
+    mask: Box<dyn Pattern>,
+
+    /// Transformation matrix.
+    transform: Transformation,
+}
+
+impl Masked {
+    pub fn masked_pattern(
+        a: Box<dyn Pattern>,
+        b: Box<dyn Pattern>,
+        mask: Box<dyn Pattern>,
+    ) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            a,
+            b,
+            mask,
+            transform: Transformation::new(),
+        }
+    }
+}
+
+impl Pattern for Masked {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn pattern_at(&self, point: Point) -> RGB {
+        let m = self.mask.pattern_at_nested(point);
+        let weight = ((m.red + m.green + m.blue) / 3.0).clamp(0.0, 1.0);
+        if weight <= 0.0 {
+            return self.a.pattern_at_nested(point);
+        }
+        if weight >= 1.0 {
+            return self.b.pattern_at_nested(point);
+        }
+
+        self.a.pattern_at_nested(point) * (1.0 - weight) + self.b.pattern_at_nested(point) * weight
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn solid(c: RGB) -> Box<dyn Pattern> {
+        Box::new(Stripes::stripe_pattern(c, c))
+    }
+
+    #[test]
+    fn blend_masked_pattern() {
+        let mask = Gradient::gradient_pattern(BLACK, WHITE);
+        let pattern = Masked::masked_pattern(solid(RED), solid(BLUE), Box::new(mask));
+
+        assert_eq!(pattern.pattern_at(Point::new(0.0, 0.0, 0.0)), RED);
+        assert_eq!(
+            pattern.pattern_at(Point::new(0.25, 0.0, 0.0)),
+            RGB::new(0.75, 0.0, 0.25)
+        );
+        assert_eq!(
+            pattern.pattern_at(Point::new(0.5, 0.0, 0.0)),
+            RGB::new(0.5, 0.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn transform_masked_pattern() {
+        // every sub pattern keeps its own transformation
+        let mut mask = Stripes::stripe_pattern(BLACK, WHITE);
+        mask.set_transform(Transformation::new().then_scaling(2.0, 1.0, 1.0));
+        let mut b = Stripes::stripe_pattern(GREEN, BLUE);
+        b.set_transform(Transformation::new().then_translation(1.0, 0.0, 0.0));
+        let pattern = Masked::masked_pattern(solid(RED), Box::new(b), Box::new(mask));
+
+        assert_eq!(pattern.pattern_at(Point::new(1.5, 0.0, 0.0)), RED);
+        assert_eq!(pattern.pattern_at(Point::new(2.5, 0.0, 0.0)), BLUE);
+        assert_eq!(pattern.pattern_at(Point::new(3.5, 0.0, 0.0)), GREEN);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::*;
+
+/// Colors at positions in [0, 1], blended linearly in between,
+/// e.g. to color the values of noise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorRamp {
+    /// Positions and colors, sorted by position.
+    stops: Vec<(Float, RGB)>,
+}
+
+impl ColorRamp {
+    /// Create a ramp from `(position, color)` stops in any order.
+    /// Panics without stops.
+    pub fn new(mut stops: Vec<(Float, RGB)>) -> Self {
+        assert!(!stops.is_empty(), "Color ramp needs a stop!");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { stops }
+    }
+
+    /// From `a` at 0 to `b` at 1.
+    pub fn linear(a: RGB, b: RGB) -> Self {
+        Self::new(vec![(0.0, a), (1.0, b)])
+    }
+
+    /// Color at `t`, the first or last color beyond the stops.
+    pub fn color_at(&self, t: Float) -> RGB {
+        let upper = self.stops.partition_point(|(p, _)| *p <= t);
+        if upper == 0 {
+            return self.stops[0].1;
+        }
+        if upper == self.stops.len() {
+            return self.stops[upper - 1].1;
+        }
+
+        let (p0, c0) = self.stops[upper - 1];
+        let (p1, c1) = self.stops[upper];
+        c0 + (c1 - c0) * ((t - p0) / (p1 - p0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn color_ramp() {
+        let ramp = ColorRamp::new(vec![(1.0, BLUE), (0.0, RED), (0.5, GREEN)]);
+
+        assert_eq!(ramp.color_at(-1.0), RED);
+        assert_eq!(ramp.color_at(0.0), RED);
+        assert_eq!(ramp.color_at(0.25), RGB::new(0.5, 0.5, 0.0));
+        assert_eq!(ramp.color_at(0.5), GREEN);
+        assert_eq!(ramp.color_at(0.75), RGB::new(0.0, 0.5, 0.5));
+        assert_eq!(ramp.color_at(2.0), BLUE);
+        assert_eq!(ColorRamp::linear(RED, RED).color_at(0.3), RED);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{float_eq, Pattern, Point, Transformation, BLACK, RGB, WHITE};
+use crate::{float_eq, Pattern, Point, Transformation, Vector, BLACK, RGB, WHITE};
 use uuid::Uuid;
 
 /// This generates stripes for any Shape.
@@ -13,6 +13,9 @@ pub struct Stripes {
     /// Color 2.
     pub b: RGB,
 
+    /// Unit vector the colors alternate along, the x axis by default.
+    pub direction: Vector,
+
     /// Transformation matrix.
     pub transform: Transformation,
 }
@@ -26,16 +29,31 @@ impl Stripes {
     /// Generate a Stripe Pattern with given RGBs.
     pub fn stripe_pattern(a: RGB, b: RGB) -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             a,
             b,
+            direction: Vector::new(1.0, 0.0, 0.0),
             transform: Transformation::new(),
         }
     }
 
+    /// Alternate the colors along `direction` instead of the x axis,
+    /// e.g. the y axis for vertical stripes on a wall.
+    /// Panics if `direction` is the zero vector.
+    pub fn with_direction(mut self, direction: Vector) -> Self {
+        assert!(
+            direction.magnitude() > 0.0,
+            "Stripe direction must not be zero!"
+        );
+        self.direction = direction.normalize();
+        self
+    }
+
     /// Give back the RGB value of the Stripe at point.
     pub fn stripe_at(&self, point: Point) -> RGB {
-        if float_eq(point.x.floor() % 2.0, 0.0) {
+        let d = self.direction;
+        let u = point.x * d.x + point.y * d.y + point.z * d.z;
+        if float_eq(u.floor() % 2.0, 0.0) {
             self.a
         } else {
             self.b
@@ -46,9 +64,10 @@ impl Stripes {
 impl Default for Stripes {
     fn default() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             a: WHITE,
             b: BLACK,
+            direction: Vector::new(1.0, 0.0, 0.0),
             transform: Transformation::default(),
         }
     }
@@ -70,6 +89,7 @@ impl Pattern for Stripes {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
@@ -127,4 +147,26 @@ mod test {
         assert_eq!(pattern.stripe_at(Point::new(-1.0, 0.0, 0.0)), BLACK);
         assert_eq!(pattern.stripe_at(Point::new(-1.1, 0.0, 0.0)), WHITE);
     }
+
+    #[test]
+    fn direction_stripe() {
+        let pattern = Stripes::new().with_direction(Vector::new(0.0, 2.0, 0.0));
+
+        assert_eq!(pattern.direction, Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(pattern.stripe_at(Point::new(5.0, 0.5, 0.0)), WHITE);
+        assert_eq!(pattern.stripe_at(Point::new(5.0, 1.5, 0.0)), BLACK);
+        assert_eq!(pattern.stripe_at(Point::new(0.0, -0.5, 3.0)), BLACK);
+
+        // diagonal stripes are one unit wide across
+        let diagonal = Stripes::new().with_direction(Vector::new(1.0, 0.0, 1.0));
+        assert_eq!(diagonal.stripe_at(Point::new(0.5, 0.0, 0.5)), WHITE);
+        assert_eq!(diagonal.stripe_at(Point::new(1.0, 0.0, 1.0)), BLACK);
+        assert_eq!(diagonal.stripe_at(Point::new(1.0, 0.0, -1.0)), WHITE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_direction_stripe() {
+        Stripes::new().with_direction(Vector::new(0.0, 0.0, 0.0));
+    }
 }
@@ -24,6 +24,7 @@ impl Pattern for TestPattern {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
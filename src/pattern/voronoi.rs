@@ -0,0 +1,154 @@
+use crate::*;
+use uuid::Uuid;
+
+/// Cellular pattern: space is split into cells around one random point per
+/// unit cube, each cell with a random color or one picked from a palette.
+#[derive(Debug, Clone)]
+pub struct Voronoi {
+    /// Id.
+    uuid: Uuid,
+
+    /// Seed of the cell points and colors.
+    seed: u64,
+
+    /// Colors to pick from, random colors if empty.
+    palette: Vec<RGB>,
+
+    /// Transformation matrix.
+    transform: Transformation,
+}
+
+impl Voronoi {
+    pub fn new() -> Self {
+        Self::voronoi_pattern(0)
+    }
+
+    /// Cells with random colors, equal seeds give equal cells.
+    pub fn voronoi_pattern(seed: u64) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            seed,
+            palette: Vec::new(),
+            transform: Transformation::new(),
+        }
+    }
+
+    /// Pick the cell colors from `palette`, e.g. a few shades of stone.
+    pub fn with_palette(mut self, palette: Vec<RGB>) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Random numbers of the unit cube at `cell`.
+    fn sampler(&self, cell: [i64; 3]) -> Sampler {
+        let hash = cell
+            .iter()
+            .zip([
+                0x9e37_79b9_7f4a_7c15,
+                0xc2b2_ae3d_27d4_eb4f,
+                0x1656_67b1_9e37_79f9,
+            ])
+            .fold(self.seed, |h, (c, k)| (h ^ *c as u64).wrapping_mul(k));
+        Sampler::new(hash)
+    }
+
+    /// The unit cube whose point is closest to `point`.
+    fn cell_at(&self, point: Point) -> [i64; 3] {
+        let base = [point.x, point.y, point.z].map(|c| c.floor() as i64);
+        let mut closest = (Float::INFINITY, base);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let cell = [base[0] + dx, base[1] + dy, base[2] + dz];
+                    let mut sampler = self.sampler(cell);
+                    let center = Point::new(
+                        cell[0] as Float + sampler.next_float(),
+                        cell[1] as Float + sampler.next_float(),
+                        cell[2] as Float + sampler.next_float(),
+                    );
+                    let distance = (center - point).magnitude();
+                    if distance < closest.0 {
+                        closest = (distance, cell);
+                    }
+                }
+            }
+        }
+        closest.1
+    }
+}
+
+impl Default for Voronoi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pattern for Voronoi {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn pattern_at(&self, point: Point) -> RGB {
+        let mut sampler = self.sampler(self.cell_at(point));
+        // skip the numbers of the cell point
+        for _ in 0..3 {
+            sampler.next_u64();
+        }
+
+        if self.palette.is_empty() {
+            RGB::new(
+                sampler.next_float(),
+                sampler.next_float(),
+                sampler.next_float(),
+            )
+        } else {
+            let i = sampler.next_u64() % self.palette.len() as u64;
+            self.palette[i as usize]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cells_voronoi() {
+        let pattern = Voronoi::voronoi_pattern(5);
+        let p = Point::new(0.3, 1.7, -2.2);
+
+        // the point of a cell lies in the cell
+        let cell = pattern.cell_at(p);
+        let mut sampler = pattern.sampler(cell);
+        let center = Point::new(
+            cell[0] as Float + sampler.next_float(),
+            cell[1] as Float + sampler.next_float(),
+            cell[2] as Float + sampler.next_float(),
+        );
+        assert_eq!(pattern.pattern_at(center), pattern.pattern_at(p));
+        assert_eq!(
+            pattern.pattern_at(p),
+            Voronoi::voronoi_pattern(5).pattern_at(p)
+        );
+
+        let colors: Vec<RGB> = (0..20)
+            .map(|i| pattern.pattern_at(Point::new(i as Float * 0.7, 0.0, 0.0)))
+            .collect();
+        assert!(colors.iter().any(|c| *c != colors[0]));
+
+        let palette = pattern.with_palette(vec![RED, BLUE]);
+        for i in 0..20 {
+            let c = palette.pattern_at(Point::new(0.0, i as Float * 0.7, 0.0));
+            assert!(c == RED || c == BLUE);
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use crate::*;
+use uuid::Uuid;
+
+/// Wood: rings around the y axis, wobbled by noise, colored by a ramp
+/// from the start (0) to the end (1) of each ring.
+#[derive(Debug, Clone)]
+pub struct Wood {
+    /// Id.
+    uuid: Uuid,
+
+    /// Colors across one ring.
+    ramp: ColorRamp,
+
+    /// Source of the wobble.
+    noise: Noise,
+
+    /// Rings per unit of radius.
+    rings: Float,
+
+    /// How far the rings wobble, in rings.
+    wobble: Float,
+
+    /// Transformation matrix.
+    transform: Transformation,
+}
+
+impl Wood {
+    /// Light wood with darker late wood at the end of each ring.
+    pub fn new() -> Self {
+        Self::wood_pattern(ColorRamp::new(vec![
+            (0.0, RGB::new(0.85, 0.6, 0.35)),
+            (0.7, RGB::new(0.75, 0.5, 0.28)),
+            (1.0, RGB::new(0.45, 0.27, 0.13)),
+        ]))
+    }
+
+    pub fn wood_pattern(ramp: ColorRamp) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            ramp,
+            noise: Noise::default(),
+            rings: 4.0,
+            wobble: 0.5,
+            transform: Transformation::new(),
+        }
+    }
+
+    /// Use the noise of `seed`, for boards that don't look alike.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.noise = Noise::new(seed);
+        self
+    }
+
+    /// Rings per unit of radius and how far, in rings, they wobble.
+    pub fn with_rings(mut self, rings: Float, wobble: Float) -> Self {
+        self.rings = rings;
+        self.wobble = wobble;
+        self
+    }
+}
+
+impl Default for Wood {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pattern for Wood {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn pattern_at(&self, point: Point) -> RGB {
+        let radius = (point.x.powi(2) + point.z.powi(2)).sqrt();
+        let ring = radius * self.rings + self.wobble * self.noise.noise(point);
+        self.ramp.color_at(ring - ring.floor())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rings_wood() {
+        let plain = Wood::wood_pattern(ColorRamp::linear(BLACK, WHITE)).with_rings(2.0, 0.0);
+
+        // without wobble the rings are circles around the y axis
+        let gray = plain.pattern_at(Point::new(0.25, 0.0, 0.0));
+        assert_eq!(gray, RGB::new(0.5, 0.5, 0.5));
+        assert_eq!(plain.pattern_at(Point::new(0.0, 7.0, 0.25)), gray);
+        assert_eq!(plain.pattern_at(Point::new(0.6, -1.0, 0.8)), BLACK);
+
+        let a = Wood::new().with_seed(2);
+        let p = Point::new(0.3, 0.7, -1.2);
+        assert_eq!(a.pattern_at(p), Wood::new().with_seed(2).pattern_at(p));
+        assert_ne!(a.pattern_at(p), Wood::new().with_seed(3).pattern_at(p));
+    }
+}
@@ -0,0 +1,247 @@
+//! PNG encoder that filters and compresses blocks of rows in parallel.
+//! The blocks do not depend on the number of threads, so every machine
+//! writes the same bytes for the same image.
+
+use crate::canvas::map_chunks;
+use miniz_oxide::deflate::core::{
+    compress_to_output, create_comp_flags_from_zip_params, CompressorOxide, TDEFLFlush, TDEFLStatus,
+};
+
+/// Signature at the start of every PNG file.
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Rows are compressed in blocks of at least this many bytes.
+const BLOCK_SIZE: usize = 1 << 18;
+
+/// Encode 8 bit pixels with `channels` components, 3 for RGB or 4 for RGBA,
+/// row by row from the top. Every `(keyword, text)` pair is stored as text chunk.
+pub(crate) fn encode(
+    width: usize,
+    height: usize,
+    channels: usize,
+    pixels: &[u8],
+    text: &[(&str, &str)],
+) -> Vec<u8> {
+    assert!(width > 0 && height > 0, "PNG images must not be empty!");
+    assert_eq!(pixels.len(), width * height * channels);
+
+    let mut png = SIGNATURE.to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    let color_type = if channels == 4 { 6 } else { 2 };
+    // 8 bits, deflate, adaptive filters, no interlacing
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+    chunk(&mut png, b"IHDR", &header);
+
+    for (keyword, text) in text {
+        let mut data = keyword.as_bytes().to_vec();
+        data.push(0);
+        data.extend_from_slice(text.as_bytes());
+        chunk(&mut png, b"tEXt", &data);
+    }
+
+    let stride = width * channels;
+    let rows = BLOCK_SIZE.div_ceil(stride);
+    let blocks = map_chunks(height, rows, |range| {
+        let mut filtered = Vec::with_capacity(range.len() * (stride + 1));
+        for y in range.clone() {
+            let row = &pixels[y * stride..(y + 1) * stride];
+            let above = (y > 0).then(|| &pixels[(y - 1) * stride..y * stride]);
+            filter(row, above, channels, &mut filtered);
+        }
+
+        // every block but the last ends on a byte boundary, so they can be joined
+        let flush = if range.end == height {
+            TDEFLFlush::Finish
+        } else {
+            TDEFLFlush::Sync
+        };
+        let mut compressor = CompressorOxide::new(create_comp_flags_from_zip_params(6, 0, 0));
+        let mut deflated = Vec::new();
+        let (status, _) = compress_to_output(&mut compressor, &filtered, flush, |bytes| {
+            deflated.extend_from_slice(bytes);
+            true
+        });
+        assert!(
+            matches!(status, TDEFLStatus::Okay | TDEFLStatus::Done),
+            "Deflating a block of rows failed!"
+        );
+        (deflated, adler32(&filtered), filtered.len())
+    });
+
+    // zlib stream with the default compression
+    let mut data = vec![0x78, 0x9c];
+    let mut checksum = 1;
+    for (deflated, adler, len) in blocks {
+        data.extend_from_slice(&deflated);
+        checksum = adler32_combine(checksum, adler, len);
+    }
+    data.extend_from_slice(&checksum.to_be_bytes());
+    chunk(&mut png, b"IDAT", &data);
+    chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+/// Append the row with the filter that gives the smallest sum of absolute
+/// differences, the usual heuristic for photos and renders.
+fn filter(row: &[u8], above: Option<&[u8]>, bpp: usize, out: &mut Vec<u8>) {
+    let up = |i: usize| above.map_or(0, |a| a[i]);
+    let left = |i: usize| if i >= bpp { row[i - bpp] } else { 0 };
+    let upper_left = |i: usize| if i >= bpp { up(i - bpp) } else { 0 };
+
+    let predict = |kind: u8, i: usize| -> u8 {
+        match kind {
+            0 => 0,
+            1 => left(i),
+            2 => up(i),
+            3 => ((left(i) as u16 + up(i) as u16) / 2) as u8,
+            _ => paeth(left(i), up(i), upper_left(i)),
+        }
+    };
+    let cost = |kind: u8| -> u64 {
+        (0..row.len())
+            .map(|i| (row[i].wrapping_sub(predict(kind, i)) as i8).unsigned_abs() as u64)
+            .sum()
+    };
+
+    let best = (0..5).min_by_key(|&kind| cost(kind)).unwrap_or(0);
+    out.push(best);
+    out.extend((0..row.len()).map(|i| row[i].wrapping_sub(predict(best, i))));
+}
+
+/// The neighbour closest to `a + b - c`.
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Append a chunk with its length and checksum.
+fn chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// CRC-32 of the chunk type and data.
+fn crc32(bytes: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0; 256];
+        let mut n = 0;
+        while n < 256 {
+            let mut c = n as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 == 1 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[n] = c;
+            n += 1;
+        }
+        table
+    };
+
+    !bytes.iter().fold(!0, |crc, b| {
+        TABLE[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Largest prime below 2^16, the modulus of Adler-32.
+const ADLER_BASE: u32 = 65521;
+
+/// Adler-32 checksum of the uncompressed data.
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1, 0);
+    // sums of 5552 bytes can't overflow before they are reduced
+    for block in bytes.chunks(5552) {
+        for byte in block {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= ADLER_BASE;
+        b %= ADLER_BASE;
+    }
+    (b << 16) | a
+}
+
+/// Adler-32 of two blocks joined, from the checksums of both and the length of the second.
+fn adler32_combine(first: u32, second: u32, len: usize) -> u32 {
+    let rem = (len % ADLER_BASE as usize) as u32;
+    let a1 = first & 0xffff;
+    let b1 = first >> 16;
+    let a2 = second & 0xffff;
+    let b2 = second >> 16;
+
+    let a = (a1 + a2 + ADLER_BASE - 1) % ADLER_BASE;
+    let b = (rem * a1 % ADLER_BASE + b1 + b2 + ADLER_BASE - rem) % ADLER_BASE;
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checksums_png() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let data: Vec<u8> = (0..20000).map(|i| (i * 7 % 251) as u8).collect();
+        let (first, second) = data.split_at(12345);
+        assert_eq!(
+            adler32_combine(adler32(first), adler32(second), second.len()),
+            adler32(&data)
+        );
+        assert_eq!(
+            adler32_combine(1, adler32(&data), data.len()),
+            adler32(&data)
+        );
+    }
+
+    #[test]
+    fn blocks_png() {
+        // several blocks of rows, each filtered and compressed on its own
+        let (width, height) = (300, 700);
+        let pixels: Vec<u8> = (0..width * height * 3)
+            .map(|i| ((i / 3) % width ^ (i / 3) / width) as u8)
+            .collect();
+        let png = encode(width, height, 3, &pixels, &[("Software", "rtracer")]);
+
+        assert_eq!(&png[..8], &SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        let text = b"tEXtSoftware\0rtracer";
+        assert!(png.windows(text.len()).any(|w| w == text));
+        assert_eq!(
+            png,
+            encode(width, height, 3, &pixels, &[("Software", "rtracer")])
+        );
+
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap();
+        let len = u32::from_be_bytes(png[idat - 4..idat].try_into().unwrap()) as usize;
+        let zlib = &png[idat + 4..idat + 4 + len];
+        let raw = miniz_oxide::inflate::decompress_to_vec_zlib(zlib).unwrap();
+        assert_eq!(raw.len(), height * (width * 3 + 1));
+        assert!(raw.len() > 2 * BLOCK_SIZE);
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{float_eq, Vector};
+use crate::{float_eq, Float, Vector};
 use std::{
     fmt,
     ops::{Add, Neg, Sub},
@@ -8,16 +8,16 @@ use std::{
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Point {
     /// Distance from origin along the X axis.
-    pub x: f64,
+    pub x: Float,
     /// Distance from origin along the Y axis.
-    pub y: f64,
+    pub y: Float,
     /// Distance from origin along the Z axis.
-    pub z: f64,
+    pub z: Float,
 }
 
 impl Point {
     /// Creates a Point in space.
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
         Self { x, y, z }
     }
 }
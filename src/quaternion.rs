@@ -0,0 +1,284 @@
+use crate::{float_eq, Float, Matrix, Transformation, Vector, EPSILON};
+use std::ops::Mul;
+
+/// A rotation as unit quaternion `w + xi + yj + zk`.
+/// Unlike Euler angles, rotations interpolate smoothly with `slerp`.
+#[derive(Debug, Copy, Clone)]
+pub struct Quaternion {
+    /// Real part, the cosine of half the rotation angle.
+    pub w: Float,
+    /// Imaginary part along the X axis.
+    pub x: Float,
+    /// Imaginary part along the Y axis.
+    pub y: Float,
+    /// Imaginary part along the Z axis.
+    pub z: Float,
+}
+
+impl Quaternion {
+    /// Create a quaternion, normalize it to use it as rotation.
+    pub fn new(w: Float, x: Float, y: Float, z: Float) -> Self {
+        Self { w, x, y, z }
+    }
+
+    /// No rotation at all.
+    pub fn identity() -> Self {
+        Self::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    /// Rotation around `axis` by `rad` radians, like `Transformation::rotate_axis`.
+    pub fn from_axis_angle(axis: Vector, rad: Float) -> Self {
+        let axis = axis.normalize();
+        let (s, c) = (rad / 2.0).sin_cos();
+        Self::new(c, axis.x * s, axis.y * s, axis.z * s)
+    }
+
+    /// Rotation part of a transformation, scaling applied before the rotation is divided out.
+    /// Translation and shearing are ignored.
+    pub fn from_transformation(t: &Transformation) -> Self {
+        let m = t.as_matrix();
+        let mut r = [[0.0; 3]; 3];
+        for c in 0..3 {
+            let scale = Vector::new(m[0][c], m[1][c], m[2][c]).magnitude();
+            for (row, rr) in r.iter_mut().enumerate() {
+                rr[c] = m[row][c] / scale;
+            }
+        }
+
+        // pick the largest component to divide by, for numerical stability
+        let trace = r[0][0] + r[1][1] + r[2][2];
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new(
+                s / 4.0,
+                (r[2][1] - r[1][2]) / s,
+                (r[0][2] - r[2][0]) / s,
+                (r[1][0] - r[0][1]) / s,
+            )
+        } else if r[0][0] > r[1][1] && r[0][0] > r[2][2] {
+            let s = (1.0 + r[0][0] - r[1][1] - r[2][2]).sqrt() * 2.0;
+            Self::new(
+                (r[2][1] - r[1][2]) / s,
+                s / 4.0,
+                (r[0][1] + r[1][0]) / s,
+                (r[0][2] + r[2][0]) / s,
+            )
+        } else if r[1][1] > r[2][2] {
+            let s = (1.0 + r[1][1] - r[0][0] - r[2][2]).sqrt() * 2.0;
+            Self::new(
+                (r[0][2] - r[2][0]) / s,
+                (r[0][1] + r[1][0]) / s,
+                s / 4.0,
+                (r[1][2] + r[2][1]) / s,
+            )
+        } else {
+            let s = (1.0 + r[2][2] - r[0][0] - r[1][1]).sqrt() * 2.0;
+            Self::new(
+                (r[1][0] - r[0][1]) / s,
+                (r[0][2] + r[2][0]) / s,
+                (r[1][2] + r[2][1]) / s,
+                s / 4.0,
+            )
+        };
+
+        q.normalize()
+    }
+
+    /// The rotation as transformation.
+    pub fn to_transformation(&self) -> Transformation {
+        let Self { w, x, y, z } = self.normalize();
+        Transformation::from_matrix(Matrix::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ]))
+    }
+
+    /// Axis and angle in radians of the rotation, the angle is in [0, 2π].
+    /// Without rotation the axis is arbitrary, the x axis.
+    pub fn to_axis_angle(&self) -> (Vector, Float) {
+        let q = self.normalize();
+        let angle = 2.0 * q.w.clamp(-1.0, 1.0).acos();
+        let axis = Vector::new(q.x, q.y, q.z);
+        if axis.magnitude() < EPSILON {
+            return (Vector::new(1.0, 0.0, 0.0), angle);
+        }
+
+        (axis.normalize(), angle)
+    }
+
+    /// Length of the quaternion, 1 for rotations.
+    pub fn magnitude(&self) -> Float {
+        self.dot(*self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        let mag = self.magnitude();
+        Self::new(self.w / mag, self.x / mag, self.y / mag, self.z / mag)
+    }
+
+    /// Calculate the dot product of two quaternions.
+    pub fn dot(&self, other: Self) -> Float {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// The opposite rotation of a unit quaternion.
+    pub fn conjugate(&self) -> Self {
+        Self::new(self.w, -self.x, -self.y, -self.z)
+    }
+
+    /// Rotate a vector.
+    pub fn rotate(&self, v: Vector) -> Vector {
+        let p = Self::new(0.0, v.x, v.y, v.z);
+        let r = *self * p * self.conjugate();
+        Vector::new(r.x, r.y, r.z)
+    }
+
+    /// Spherical linear interpolation, `t` = 0 is `self` and 1 is `other`.
+    /// Turns at constant speed along the shorter way.
+    pub fn slerp(&self, other: Self, t: Float) -> Self {
+        let a = self.normalize();
+        let mut b = other.normalize();
+        let mut cos = a.dot(b);
+        if cos < 0.0 {
+            b = Self::new(-b.w, -b.x, -b.y, -b.z);
+            cos = -cos;
+        }
+
+        // nearly the same rotation, the sine below vanishes
+        let (wa, wb) = if cos > 0.9995 {
+            (1.0 - t, t)
+        } else {
+            let theta = cos.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+
+        Self::new(
+            wa * a.w + wb * b.w,
+            wa * a.x + wb * b.x,
+            wa * a.y + wb * b.y,
+            wa * a.z + wb * b.z,
+        )
+        .normalize()
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+/// Hamilton product, `a * b` rotates by `b` first and then by `a`.
+impl Mul for Quaternion {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        )
+    }
+}
+
+/// Compares the components, `q` and `-q` are the same rotation but not equal.
+impl PartialEq for Quaternion {
+    fn eq(&self, other: &Self) -> bool {
+        float_eq(self.w, other.w)
+            && float_eq(self.x, other.x)
+            && float_eq(self.y, other.y)
+            && float_eq(self.z, other.z)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consts::PI;
+    use crate::Point;
+
+    #[test]
+    fn axis_angle_quaternion() {
+        let axis = Vector::new(1.0, -2.0, 0.5);
+        let q = Quaternion::from_axis_angle(axis, PI / 3.0);
+
+        assert!(crate::float_eq(q.magnitude(), 1.0));
+        assert_eq!(
+            q.to_transformation(),
+            Transformation::new().then_rotate_axis(axis, PI / 3.0)
+        );
+        assert_eq!(
+            q.rotate(Vector::new(0.0, 1.0, 3.0)),
+            q.to_transformation() * Vector::new(0.0, 1.0, 3.0)
+        );
+        assert_eq!(q * q.conjugate(), Quaternion::identity());
+    }
+
+    #[test]
+    fn transformation_quaternion() {
+        // every branch of the extraction
+        for rad in [0.3, PI - 0.1] {
+            for axis in [
+                Vector::new(1.0, 0.1, 0.2),
+                Vector::new(0.1, 1.0, 0.2),
+                Vector::new(0.1, 0.2, 1.0),
+            ] {
+                let q = Quaternion::from_axis_angle(axis, rad);
+                let t = q.to_transformation() * Transformation::new().then_scaling(2.0, 3.0, 4.0);
+                let back = Quaternion::from_transformation(&t);
+                assert!(crate::float_eq(back.dot(q).abs(), 1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn compose_quaternion() {
+        let a = Quaternion::from_axis_angle(Vector::new(1.0, 0.0, 0.0), PI / 2.0);
+        let b = Quaternion::from_axis_angle(Vector::new(0.0, 1.0, 0.0), PI / 2.0);
+        let t = Transformation::new()
+            .then_rotate_y(PI / 2.0)
+            .then_rotate_x(PI / 2.0);
+
+        assert_eq!((a * b).to_transformation(), t);
+        assert_eq!(
+            (a * b).to_transformation() * Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn slerp_quaternion() {
+        let axis = Vector::new(0.0, 0.0, 1.0);
+        let a = Quaternion::identity();
+        let b = Quaternion::from_axis_angle(axis, PI / 2.0);
+
+        assert_eq!(a.slerp(b, 0.0), a);
+        assert_eq!(a.slerp(b, 1.0), b);
+        assert_eq!(a.slerp(b, 0.5), Quaternion::from_axis_angle(axis, PI / 4.0));
+        // -b is the same rotation, slerp still takes the short way
+        let neg = Quaternion::new(-b.w, -b.x, -b.y, -b.z);
+        assert_eq!(
+            a.slerp(neg, 0.5),
+            Quaternion::from_axis_angle(axis, PI / 4.0)
+        );
+        assert_eq!(a.slerp(a, 0.3), a);
+    }
+}
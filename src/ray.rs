@@ -1,41 +1,76 @@
-use crate::{Matrix, Point, Vector};
+use crate::{Float, Matrix, Point, Vector};
 
 /// Ray implementation.
 /// Each ray created by your ray tracer will have a starting point
 /// called the origin, and a vector called the direction which says
 /// where it points.
-#[derive(Debug, Clone, Copy, Default)]
+/// The reciprocal of the direction is cached at construction,
+/// so a ray can't be changed, create a new one instead.
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     /// Starting point.
-    pub origin: Point,
+    origin: Point,
     /// Direction from origin.
-    pub direction: Vector,
+    direction: Vector,
+    /// Componentwise reciprocal of the direction, infinite for zero components.
+    inv_direction: [Float; 3],
+    /// Is the direction component negative?
+    sign: [bool; 3],
 }
 
 impl Ray {
     /// Create a new Ray.
-    pub fn new(origin: Point, direction: Vector) -> Self {
-        Self { origin, direction }
+    pub const fn new(origin: Point, direction: Vector) -> Self {
+        let inv_direction = [1.0 / direction.x, 1.0 / direction.y, 1.0 / direction.z];
+        Self {
+            origin,
+            direction,
+            inv_direction,
+            // 1 / -0.0 is -inf, so -0.0 counts as negative
+            sign: [
+                inv_direction[0] < 0.0,
+                inv_direction[1] < 0.0,
+                inv_direction[2] < 0.0,
+            ],
+        }
     }
 
     /// This function should compute the point at the given distance
     /// 't' along the ray.
-    pub fn position(&self, t: f64) -> Point {
+    pub fn position(&self, t: Float) -> Point {
         self.origin + self.direction * t
     }
 
     /// Transform a ray s.t. on object level we ge a new ray.
     pub fn transform(&self, m: Matrix) -> Self {
-        Self {
-            origin: m * self.origin,
-            direction: m * self.direction,
-        }
+        Self::new(m * self.origin, m * self.direction)
+    }
+
+    /// Get the origin of the Ray.
+    pub fn origin(&self) -> Point {
+        self.origin
     }
 
     /// Get the direction of the Ray.
     pub fn direction(&self) -> Vector {
         self.direction
     }
+
+    /// Get the reciprocal of the direction per axis.
+    pub fn inv_direction(&self) -> [Float; 3] {
+        self.inv_direction
+    }
+
+    /// Get per axis whether the direction is negative.
+    pub fn sign(&self) -> [bool; 3] {
+        self.sign
+    }
+}
+
+impl Default for Ray {
+    fn default() -> Self {
+        Ray::new(Point::default(), Vector::default())
+    }
 }
 
 #[cfg(test)]
@@ -67,7 +102,7 @@ mod test {
     #[test]
     fn translate_ray() {
         let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0));
-        let m = Transformation::new().translation(3.0, 4.0, 5.0).init();
+        let m = Transformation::new().then_translation(3.0, 4.0, 5.0).init();
         let r2 = r.transform(m);
 
         assert_eq!(r2.origin, Point::new(4.0, 6.0, 8.0));
@@ -77,10 +112,24 @@ mod test {
     #[test]
     fn scale_ray() {
         let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(0.0, 1.0, 0.0));
-        let m = Transformation::new().scaling(2.0, 3.0, 4.0).init();
+        let m = Transformation::new().then_scaling(2.0, 3.0, 4.0).init();
         let r2 = r.transform(m);
 
         assert_eq!(r2.origin, Point::new(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, Vector::new(0.0, 3.0, 0.0));
     }
+
+    #[test]
+    fn inv_direction_ray() {
+        let r = Ray::new(Point::new(1.0, 2.0, 3.0), Vector::new(2.0, -4.0, 0.0));
+
+        assert_eq!(r.inv_direction(), [0.5, -0.25, Float::INFINITY]);
+        assert_eq!(r.sign(), [false, true, false]);
+
+        let m = Transformation::new().then_scaling(2.0, 1.0, 1.0).init();
+        assert_eq!(
+            r.transform(m).inv_direction(),
+            [0.25, -0.25, Float::INFINITY]
+        );
+    }
 }
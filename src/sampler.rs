@@ -0,0 +1,60 @@
+use crate::Float;
+
+/// Deterministic pseudo random numbers (SplitMix64), so procedurally
+/// generated scenes look the same for the same seed on every platform.
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    state: u64,
+}
+
+impl Sampler {
+    /// Create a sampler, equal seeds give equal sequences.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Next number of the sequence, uniform over all `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    pub fn next_float(&mut self) -> Float {
+        // as many bits as the mantissa holds, so 1.0 is never reached
+        let bits = Float::MANTISSA_DIGITS;
+        (self.next_u64() >> (64 - bits)) as Float / (1u64 << bits) as Float
+    }
+
+    /// Uniform in [min, max).
+    pub fn range(&mut self, min: Float, max: Float) -> Float {
+        min + (max - min) * self.next_float()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeded_sampler() {
+        let mut a = Sampler::new(7);
+        let mut b = Sampler::new(7);
+        let mut c = Sampler::new(8);
+        let xs: Vec<u64> = (0..4).map(|_| a.next_u64()).collect();
+        assert_eq!(xs, (0..4).map(|_| b.next_u64()).collect::<Vec<_>>());
+        assert_ne!(xs, (0..4).map(|_| c.next_u64()).collect::<Vec<_>>());
+
+        let mut sum = 0.0;
+        for _ in 0..1000 {
+            let x = a.range(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&x));
+            sum += x;
+        }
+        // the mean is close to 0.5
+        assert!((sum / 1000.0 - 0.5).abs() < 0.2);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::*;
+use std::fmt::Debug;
+use uuid::Uuid;
+
+/// A secondary ray spawned while shading a hit, e.g. a reflection.
+#[derive(Debug, Clone, Copy)]
+pub struct SecondaryRay {
+    /// The ray to trace.
+    pub ray: Ray,
+
+    /// Factor applied to the color the ray returns.
+    pub weight: RGB,
+
+    /// Budget left for rays spawned where this ray hits.
+    pub remaining: Budget,
+
+    /// Object the ray starts on, see `World::set_self_exclusion`.
+    pub origin: Option<Uuid>,
+}
+
+/// Result of shading one hit without tracing further rays.
+#[derive(Debug, Clone)]
+pub struct Shading {
+    /// Light leaving the surface directly.
+    pub color: RGB,
+
+    /// Rays whose weighted colors are added to `color`.
+    pub secondary: Vec<SecondaryRay>,
+}
+
+/// A Shader decides which color a ray returns when it hits a surface.
+/// The World hands every hit over to its shader, so custom integrators
+/// (toon, ambient-only, ...) can be plugged in without touching World.
+/// Shaders are `Clone`, so materials holding them can be copied.
+pub trait Shader: Debug + Send + Sync + ShaderClone {
+    /// Compute the color at the intersection described by `comps`.
+    /// `remaining` is the recursion budget for secondary rays.
+    fn shade_hit(&self, world: &World, comps: &Computation, remaining: Budget) -> RGB;
+
+    /// Shade the hit but hand secondary rays back instead of tracing them.
+    /// The World traces them with an explicit stack, so deep reflections do
+    /// not grow the call stack. The default reports no secondary rays and
+    /// uses `shade_hit` for the color.
+    fn shade(&self, world: &World, comps: &Computation, remaining: Budget) -> Shading {
+        Shading {
+            color: self.shade_hit(world, comps, remaining),
+            secondary: Vec::new(),
+        }
+    }
+}
+
+impl PartialEq for Box<dyn Shader> {
+    /// Shaders carry no id, two boxes are equal if they point to the same shader.
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(
+            self.as_ref() as *const dyn Shader as *const u8,
+            other.as_ref() as *const dyn Shader as *const u8,
+        )
+    }
+}
+
+/// Copies boxed shaders, implemented for every shader that is `Clone`.
+pub trait ShaderClone {
+    /// A boxed copy of the shader.
+    fn clone_box(&self) -> Box<dyn Shader>;
+}
+
+impl<T: 'static + Shader + Clone> ShaderClone for T {
+    fn clone_box(&self) -> Box<dyn Shader> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Shader> {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_box()
+    }
+}
+
+pub mod whitted;
+pub use whitted::Whitted;
+pub mod toon;
+pub use toon::Toon;
+pub mod path_tracer;
+pub use path_tracer::PathTracer;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Only returns the ambient part of the surface color.
+    #[derive(Debug, Clone)]
+    struct AmbientOnly;
+
+    impl Shader for AmbientOnly {
+        fn shade_hit(&self, _world: &World, comps: &Computation, _remaining: Budget) -> RGB {
+            let m = comps.material;
+            m.color * m.ambient
+        }
+    }
+
+    #[test]
+    fn default_shader_world() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let shape = w.get_object(0).expect("Default world has two shapes!");
+        let i = Intersection::new(4.0, shape);
+        let xs = &vec![i];
+        let comps = i.prepare_computations(&r, xs, None);
+
+        assert_eq!(
+            Whitted.shade_hit(&w, &comps, Budget::uniform(0)),
+            w.shade_hit(&comps, Budget::uniform(0))
+        );
+    }
+
+    #[test]
+    fn secondary_rays_whitted() {
+        let mut w = World::default();
+        let mut m = Material::default();
+        m.reflective = 0.5;
+        let mut shape = Plane::new();
+        shape.set_material(m);
+        shape.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
+        add_object!(w, shape);
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -3.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt()) / 2.0,
+                (2.0 as Float).sqrt() / 2.0,
+            ),
+        );
+        let shape = w.get_object(2).expect("The plane was added!");
+        let i = Intersection::new((2.0 as Float).sqrt(), shape);
+        let xs = &vec![i];
+        let comps = i.prepare_computations(&r, xs, None);
+        let shading = Whitted.shade(&w, &comps, Budget::uniform(5));
+
+        assert_eq!(shading.secondary.len(), 1);
+        assert_eq!(shading.secondary[0].weight, RGB::new(0.5, 0.5, 0.5));
+        assert_eq!(shading.secondary[0].remaining.reflection, 4);
+        assert_eq!(shading.secondary[0].ray.direction(), comps.reflectv);
+        assert_eq!(shading.secondary[0].origin, Some(shape.id()));
+        assert!(Whitted
+            .shade(&w, &comps, Budget::uniform(0))
+            .secondary
+            .is_empty());
+    }
+
+    #[test]
+    fn deep_reflection_world() {
+        // two parallel mirrors and a budget far beyond any call stack
+        let mut w = World::new();
+        w.set_light(PointLight::new(Point::new(0.0, 0.0, 0.0), WHITE));
+        for y in [-1.0, 1.0] {
+            let mut m = Material::default();
+            m.reflective = 0.5;
+            let mut p = Plane::new();
+            p.set_material(m);
+            p.set_transform(Transformation::new().then_translation(0.0, y, 0.0));
+            add_object!(w, p);
+        }
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        let c = w.color_at(&r, Budget::uniform(5_000));
+
+        assert!(c.red > 0.0 && c.red.is_finite());
+    }
+
+    #[test]
+    fn custom_shader_world() {
+        let mut w = World::default();
+        w.set_shader(Box::new(AmbientOnly));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let c = w.color_at(&r, Budget::uniform(5));
+
+        assert_eq!(c, RGB::new(0.08, 0.1, 0.06));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::consts::PI;
+use crate::*;
+
+/// Whitted shading plus the light bounced between diffuse surfaces, estimated
+/// with random paths. Emissive materials light the objects around them this
+/// way, so glowing panels and neon tubes act as area lights and a scene may do
+/// without a point light:
+///
+/// ```
+/// use rtracer::{PathTracer, World};
+///
+/// let mut w = World::default();
+/// w.set_shader(Box::new(PathTracer::new(64, 3)));
+/// ```
+///
+/// The bounced light replaces the ambient term of the surfaces along the
+/// paths, only the surface hit by the ray itself adds its ambient color, and
+/// only with a point light. The estimate is noisy, more `samples` trade
+/// time for less noise. Paths are seeded by the hit point, so a scene renders
+/// the same every time.
+#[derive(Debug, Clone, Copy)]
+pub struct PathTracer {
+    /// Paths started at every hit.
+    pub samples: usize,
+
+    /// Surfaces each path bounces off at most.
+    pub bounces: usize,
+}
+
+impl PathTracer {
+    /// Create a path tracer with `samples` paths of up to `bounces` surfaces per hit.
+    pub fn new(samples: usize, bounces: usize) -> Self {
+        Self { samples, bounces }
+    }
+
+    /// Light leaving the surface towards the eye without bouncing: the
+    /// emission and, if the world has one, the lit color of the point light
+    /// with `ambient` light of the scene.
+    fn direct(world: &World, comps: &Computation, ambient: RGB) -> RGB {
+        match world.get_light() {
+            Some(light) => {
+                let ctx = LightingContext::new(light, comps.over_point, comps.eyev, comps.normalv)
+                    .with_transmission(world.hit_transmission(comps))
+                    .with_ambient(ambient);
+                lighting(comps.material, comps.object, &ctx)
+            }
+            None => comps.material.emissive,
+        }
+    }
+
+    /// Fraction of the light reaching the surface that it scatters diffusely.
+    fn albedo(comps: &Computation) -> RGB {
+        comps.material.color_at(comps.object, comps.over_point) * comps.material.diffuse
+    }
+
+    /// Light reaching the hit from other surfaces, averaged over the paths.
+    fn indirect(&self, world: &World, comps: &Computation) -> RGB {
+        let albedo = Self::albedo(comps);
+        if self.samples == 0 || self.bounces == 0 || albedo == BLACK {
+            return BLACK;
+        }
+
+        let mut sampler = Sampler::new(seed(comps.over_point));
+        let mut sum = BLACK;
+        for _ in 0..self.samples {
+            sum = sum + self.path(world, comps.over_point, comps.normalv, &mut sampler);
+        }
+        albedo * sum * (1.0 / self.samples as Float)
+    }
+
+    /// Light arriving at `point` from a random direction around `normal`,
+    /// followed over up to `bounces` surfaces. The paths stand in for the
+    /// ambient light, so the surfaces on the way add none.
+    fn path(&self, world: &World, point: Point, normal: Vector, sampler: &mut Sampler) -> RGB {
+        let mut ray = Ray::new(point, cosine_direction(normal, sampler));
+        let mut weight = WHITE;
+        let mut color = BLACK;
+        let mut xs = Vec::new();
+
+        for bounce in 1..=self.bounces {
+            world.intersect_world_into(&ray, &mut xs);
+            let hit = match Intersection::hit(&xs) {
+                Some(hit) => hit,
+                None => return color + world.background(&ray) * weight,
+            };
+            let comps = hit.prepare_computations(&ray, &xs, Some(world));
+            color = color + Self::direct(world, &comps, BLACK) * weight;
+
+            weight = weight * Self::albedo(&comps);
+            if bounce == self.bounces || weight == BLACK {
+                break;
+            }
+            ray = Ray::new(comps.over_point, cosine_direction(comps.normalv, sampler));
+        }
+
+        color
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        PathTracer::new(64, 3)
+    }
+}
+
+impl Shader for PathTracer {
+    fn shade_hit(&self, world: &World, comps: &Computation, remaining: Budget) -> RGB {
+        world.trace_shading(self.shade(world, comps, remaining))
+    }
+
+    fn shade(&self, world: &World, comps: &Computation, remaining: Budget) -> Shading {
+        Shading {
+            color: Self::direct(
+                world,
+                comps,
+                world.ambient_at(comps.over_point, comps.normalv),
+            ) + self.indirect(world, comps),
+            secondary: Whitted::secondary_rays(comps, remaining),
+        }
+    }
+}
+
+/// Random direction around `normal`, more likely the closer it is to the
+/// normal, like the light a diffuse surface receives.
+fn cosine_direction(normal: Vector, sampler: &mut Sampler) -> Vector {
+    let axis = if normal.x.abs() < 0.9 {
+        Vector::new(1.0, 0.0, 0.0)
+    } else {
+        Vector::new(0.0, 1.0, 0.0)
+    };
+    let tangent = normal.cross(axis).normalize();
+    let bitangent = normal.cross(tangent);
+
+    let r = sampler.next_float().sqrt();
+    let phi = 2.0 * PI * sampler.next_float();
+    let z = (1.0 - r * r).max(0.0).sqrt();
+    (tangent * (r * phi.cos()) + bitangent * (r * phi.sin()) + normal * z).normalize()
+}
+
+/// Seed of the paths started at `point`.
+#[allow(clippy::unnecessary_cast)] // Float is f32 with the `f32` feature
+fn seed(point: Point) -> u64 {
+    [point.x, point.y, point.z]
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, c| {
+            (hash ^ (*c as f64).to_bits()).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A white floor without point light and an emissive panel above it.
+    fn panel_world(emissive: RGB) -> World {
+        let mut w = World::new();
+        let mut floor = Plane::new();
+        floor.get_material_mut().ambient = 0.0;
+        add_object!(w, floor);
+
+        let mut panel = Cube::new();
+        panel.set_transform(
+            Transformation::new()
+                .then_scaling(1.0, 0.1, 1.0)
+                .then_translation(0.0, 2.0, 0.0),
+        );
+        let m = panel.get_material_mut();
+        m.color = BLACK;
+        m.emissive = emissive;
+        add_object!(w, panel);
+        w
+    }
+
+    fn floor_color(w: &World, x: Float) -> RGB {
+        let r = Ray::new(
+            Point::new(x, 1.0, -1.0),
+            Vector::new(0.0, -1.0, 1.0).normalize(),
+        );
+        w.color_at(&r, Budget::uniform(2))
+    }
+
+    #[test]
+    fn cosine_direction_path_tracer() {
+        let normal = Vector::new(0.0, 0.0, -1.0);
+        let mut sampler = Sampler::new(7);
+        let mut sum = 0.0;
+        for _ in 0..4096 {
+            let d = cosine_direction(normal, &mut sampler);
+            assert!(float_eq(d.magnitude(), 1.0));
+            assert!(d.dot(normal) >= 0.0);
+            sum += d.dot(normal);
+        }
+        // the mean cosine of a cosine weighted hemisphere is 2/3
+        assert!((sum / 4096.0 - 2.0 / 3.0).abs() < 0.02);
+    }
+
+    #[test]
+    fn emissive_light_path_tracer() {
+        let mut w = panel_world(RGB::new(4.0, 4.0, 4.0));
+        w.set_shader(Box::new(PathTracer::new(256, 2)));
+
+        // the floor below the panel is lit without any point light
+        let below = floor_color(&w, 0.0);
+        assert!(below.red > 0.1);
+        assert!(float_eq(below.red, below.green) && float_eq(below.red, below.blue));
+        // and darker far away from it
+        assert!(floor_color(&w, 10.0).red < below.red / 4.0);
+        // the same paths every time
+        assert_eq!(floor_color(&w, 0.0), below);
+
+        let mut dark = panel_world(BLACK);
+        dark.set_shader(Box::new(PathTracer::new(256, 2)));
+        assert_eq!(floor_color(&dark, 0.0), BLACK);
+    }
+
+    #[test]
+    fn point_light_path_tracer() {
+        // with a point light and no bounces it shades like Whitted
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let whitted = w.color_at(&r, Budget::uniform(5));
+        w.set_shader(Box::new(PathTracer::new(16, 0)));
+        assert_eq!(w.color_at(&r, Budget::uniform(5)), whitted);
+
+        // bounces only add light
+        w.set_shader(Box::new(PathTracer::new(16, 2)));
+        let c = w.color_at(&r, Budget::uniform(5));
+        assert!(c.red >= whitted.red && c.green >= whitted.green);
+    }
+
+    #[test]
+    fn ambient_path_tracer() {
+        // the light below the floor reaches neither the floor nor the ceiling
+        let mut w = World::new();
+        w.set_light(PointLight::new(Point::new(0.0, -10.0, 0.0), WHITE));
+        add_object!(w, Plane::new());
+        let mut ceiling = Plane::new();
+        ceiling.set_transform(Transformation::new().then_translation(0.0, 2.0, 0.0));
+        add_object!(w, ceiling);
+
+        // only the ambient color of the floor itself, none bounced off the ceiling
+        w.set_shader(Box::new(PathTracer::new(16, 0)));
+        let ambient = floor_color(&w, 0.0);
+        assert!(ambient.red > 0.0);
+        w.set_shader(Box::new(PathTracer::new(16, 2)));
+        assert_eq!(floor_color(&w, 0.0), ambient);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::*;
+
+/// Non-photorealistic toon (cel) shader.
+/// Diffuse lighting is quantized into a few flat bands, highlights are
+/// either on or off and silhouettes are darkened.
+#[derive(Debug, Clone, Copy)]
+pub struct Toon {
+    /// Number of flat diffuse bands, 0 disables the quantization.
+    pub bands: usize,
+
+    /// Surfaces whose normal·eye cosine falls below this value are drawn as edge.
+    pub edge_threshold: Float,
+
+    /// Color of the silhouette edges.
+    pub edge_color: RGB,
+}
+
+impl Toon {
+    /// Create a new toon shader with black edges.
+    pub fn new(bands: usize, edge_threshold: Float) -> Self {
+        Self {
+            bands,
+            edge_threshold,
+            edge_color: BLACK,
+        }
+    }
+
+    /// Snap a cosine in (0,1] to the upper edge of its band.
+    fn quantize(&self, cos: Float) -> Float {
+        if self.bands == 0 {
+            cos
+        } else {
+            let bands = self.bands as Float;
+            (cos * bands).ceil() / bands
+        }
+    }
+}
+
+impl Default for Toon {
+    fn default() -> Self {
+        Toon::new(3, 0.2)
+    }
+}
+
+impl Shader for Toon {
+    fn shade_hit(&self, world: &World, comps: &Computation, _remaining: Budget) -> RGB {
+        // silhouette edges are seen at grazing angles
+        if comps.eyev.dot(comps.normalv) < self.edge_threshold {
+            return self.edge_color;
+        }
+
+        let light = world.get_light().expect("World has no light!");
+        let material = comps.material;
+        let intensity = light.intensity_at(comps.over_point);
+        let color = material.color_at(comps.object, comps.over_point) * intensity;
+        let ambient = color * world.ambient_at(comps.over_point, comps.normalv) * material.ambient
+            + material.emissive;
+
+        let lightv = (light.get_position() - comps.over_point).normalize();
+        let light_dot_normal = lightv.dot(comps.normalv);
+        if light_dot_normal <= 0.0 {
+            return ambient;
+        }
+        let lit = if world.is_hit_shadowed(comps) {
+            light.get_shadow_color()
+        } else {
+            WHITE
+        };
+        if lit == BLACK {
+            return ambient;
+        }
+
+        let diffuse = color * material.diffuse * self.quantize(light_dot_normal);
+
+        // the highlight is either fully on or off
+        let reflect_dot_eye = (-lightv).reflect(comps.normalv).dot(comps.eyev);
+        let shininess = material.shininess_at(comps.object, comps.over_point);
+        let specular = if reflect_dot_eye > 0.0 && reflect_dot_eye.powf(shininess) > 0.5 {
+            intensity * material.specular_at(comps.object, comps.over_point)
+        } else {
+            BLACK
+        };
+
+        ambient + (diffuse + specular) * lit
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quantize_toon() {
+        let t = Toon::new(4, 0.2);
+
+        assert!(float_eq(t.quantize(0.1), 0.25));
+        assert!(float_eq(t.quantize(0.25), 0.25));
+        assert!(float_eq(t.quantize(0.6), 0.75));
+        assert!(float_eq(t.quantize(1.0), 1.0));
+        assert!(float_eq(Toon::new(0, 0.2).quantize(0.6), 0.6));
+    }
+
+    #[test]
+    fn banded_toon() {
+        let mut w = World::default();
+        w.set_shader(Box::new(Toon::default()));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let c = w.color_at(&r, Budget::uniform(5));
+
+        assert_eq!(c, RGB::new(0.45333, 0.56667, 0.34));
+    }
+
+    #[test]
+    fn edge_toon() {
+        let mut w = World::default();
+        w.set_shader(Box::new(Toon::default()));
+        let r = Ray::new(Point::new(0.0, 0.99, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let c = w.color_at(&r, Budget::uniform(5));
+
+        assert_eq!(c, BLACK);
+    }
+
+    #[test]
+    fn material_toon() {
+        let mut w = World::default();
+        {
+            let s = w.get_object_mut(0).expect("Default world has two shapes!");
+            s.get_material_mut().shader = Some(Box::new(Toon::default()));
+        }
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let c = w.color_at(&r, Budget::uniform(5));
+
+        assert_eq!(c, RGB::new(0.45333, 0.56667, 0.34));
+    }
+}
@@ -0,0 +1,77 @@
+use crate::*;
+
+/// The default shader: Phong surface lighting plus Whitted-style
+/// reflection and refraction with the Fresnel effect.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Whitted;
+
+impl Shader for Whitted {
+    fn shade_hit(&self, world: &World, comps: &Computation, remaining: Budget) -> RGB {
+        world.trace_shading(self.shade(world, comps, remaining))
+    }
+
+    fn shade(&self, world: &World, comps: &Computation, remaining: Budget) -> Shading {
+        let light = world.get_light().expect("World has no light!");
+        let ctx = LightingContext::new(light, comps.over_point, comps.eyev, comps.normalv)
+            .with_transmission(world.hit_transmission(comps))
+            .with_ambient(world.ambient_at(comps.over_point, comps.normalv));
+        let color = lighting(comps.material, comps.object, &ctx);
+
+        Shading {
+            color,
+            secondary: Self::secondary_rays(comps, remaining),
+        }
+    }
+}
+
+impl Whitted {
+    /// Reflected and refracted rays of the hit, weighted by the Fresnel effect.
+    pub(crate) fn secondary_rays(comps: &Computation, remaining: Budget) -> Vec<SecondaryRay> {
+        let reflective = comps.material.reflective_at(comps.object, comps.over_point);
+        let transparency = comps
+            .material
+            .transparency_at(comps.object, comps.over_point);
+        let (reflect_weight, refract_weight) = if reflective > 0.0 && transparency > 0.0 {
+            let reflectance = comps.schlick();
+            (reflectance, 1.0 - reflectance)
+        } else {
+            (1.0, 1.0)
+        };
+
+        let mut secondary = Vec::new();
+        if !float_eq(reflective, 0.0) && remaining.reflection > 0 {
+            secondary.push(SecondaryRay {
+                ray: Ray::new(comps.over_point, comps.reflectv),
+                weight: WHITE * (reflective * reflect_weight),
+                remaining: remaining.reflected(),
+                origin: Some(comps.object.id()),
+            });
+        }
+
+        if !float_eq(transparency, 0.0) && remaining.refraction > 0 {
+            let weight = WHITE * (transparency * refract_weight);
+            if comps.is_dispersive() {
+                // every channel bends by its own index and is traced separately
+                for (c, mask) in [RED, GREEN, BLUE].into_iter().enumerate() {
+                    if let Some(ray) = World::refract_ray(comps, comps.n1_rgb[c], comps.n2_rgb[c]) {
+                        secondary.push(SecondaryRay {
+                            ray,
+                            weight: weight * mask,
+                            remaining: remaining.refracted(),
+                            origin: Some(comps.object.id()),
+                        });
+                    }
+                }
+            } else if let Some(ray) = World::refract_ray(comps, comps.n1, comps.n2) {
+                secondary.push(SecondaryRay {
+                    ray,
+                    weight,
+                    remaining: remaining.refracted(),
+                    origin: Some(comps.object.id()),
+                });
+            }
+        }
+
+        secondary
+    }
+}
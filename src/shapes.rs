@@ -3,7 +3,7 @@ use std::fmt::Debug;
 use uuid::Uuid;
 
 /// Common trait among all shapes.
-pub trait Shape: 'static + Debug {
+pub trait Shape: 'static + Debug + Send + Sync {
     /// Every shape has a unique id in the world.
     fn id(&self) -> Uuid;
 
@@ -21,12 +21,38 @@ pub trait Shape: 'static + Debug {
     /// Set the material of a shape
     fn set_material(&mut self, m: Material);
 
+    /// Set the material while declaring a shape, e.g. for `WorldBuilder`.
+    fn with_material(mut self, m: Material) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_material(m);
+        self
+    }
+
     /// Every shape has an internal transformation matrix
     fn get_transform(&self) -> Transformation;
 
-    /// Set the transformation of any shape
+    /// Set the transformation of any shape.
+    /// Panics if the transformation is degenerate, see `try_set_transform`.
     fn set_transform(&mut self, t: Transformation);
 
+    /// Set the transformation while declaring a shape, e.g. for `WorldBuilder`.
+    fn with_transform(mut self, t: Transformation) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_transform(t);
+        self
+    }
+
+    /// Set the transformation of any shape, if it is not degenerate.
+    fn try_set_transform(&mut self, t: Transformation) -> Result<(), TransformError> {
+        t.validate()?;
+        self.set_transform(t);
+        Ok(())
+    }
+
     /// Get parent id of an `object`
     fn parent_id(&self) -> Option<Uuid>;
 
@@ -38,17 +64,100 @@ pub trait Shape: 'static + Debug {
         None
     }
 
+    /// Description of the shape for `World::scene_hash`: its type, parameters,
+    /// transformation and material including patterns and maps, without ids,
+    /// so equal scenes are described equally in every run. Containers
+    /// describe their children by their fingerprints.
+    fn fingerprint(&self) -> String {
+        without_ids(&format!("{:?}", self))
+    }
+
+    /// If the object is a container then get its child at `index`.
+    /// The world walks the children to hand out handles for them.
+    fn child(&self, _index: usize) -> Option<&dyn Shape> {
+        None
+    }
+
+    /// Mutable version of `child`.
+    fn child_mut(&mut self, _index: usize) -> Option<&mut dyn Shape> {
+        None
+    }
+
+    /// Containers caching the bounds of their children recompute them.
+    /// Changes through `child_mut` already do so on their own.
+    fn update_bounds(&mut self) {}
+
+    /// Bounding box of the shape in object space.
+    /// Shapes without a finite extent are unbounded.
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::infinite()
+    }
+
+    /// Bounding box of the shape in the space of its parent.
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(self.get_transform().init())
+    }
+
+    /// Sphere around the shape in object space, tested before `local_intersect`.
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        BoundingSphere::from_box(&self.bounds())
+    }
+
     /// A ray _can_ intersect a shape.
     /// This returns a collection of unit time(s) 't',
-    /// when the ray intersects the shape.
-    fn intersect(&self, ray: &Ray) -> Option<Vec<Intersection>> {
+    /// when the ray intersects the shape, in ascending order.
+    fn intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        let mut xs = Vec::new();
+        self.intersect_into(ray, &mut xs);
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        (!xs.is_empty()).then_some(xs)
+    }
+
+    /// Push the intersections of the ray into `xs`, in no particular order.
+    /// Same as `intersect`, but the caller reuses the buffer for many rays.
+    fn intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
         let local_ray = ray.transform(
             self.get_transform()
-                .init()
-                .inverse(4)
-                .expect("The transformation matrix should invertible!"),
+                .inverse()
+                .expect("The transformation matrix should invertible!")
+                .as_matrix(),
         );
-        self.local_intersect(&local_ray)
+
+        if let Some(sphere) = self.bounding_sphere() {
+            if !sphere.intersects(&local_ray) {
+                return;
+            }
+        }
+
+        self.local_intersect_into(&local_ray, xs)
+    }
+
+    /// Push the intersections of a ray in object space into `xs`.
+    /// The default appends the result of `local_intersect`,
+    /// shapes on the hot path push without allocating.
+    fn local_intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if let Some(mut local) = self.local_intersect(ray) {
+            xs.append(&mut local);
+        }
+    }
+
+    /// Does the ray hit the shape at a t in `[0, max_t)`?
+    /// Shadow rays only need to know this, containers stop at the first blocker.
+    fn intersects_before(&self, ray: &Ray, max_t: Float) -> bool {
+        self.intersect(ray)
+            .is_some_and(|xs| xs.iter().any(|x| x.t >= 0.0 && x.t < max_t))
+    }
+
+    /// Does the shape block all light, so shadow rays can stop at it?
+    /// Shapes with several materials check all of them.
+    fn is_opaque(&self) -> bool {
+        self.get_material().is_opaque()
+    }
+
+    /// Where the ray enters and leaves the shape, and how far it travels inside.
+    /// Only meaningful for closed shapes, e.g. for absorption in glass.
+    fn thickness(&self, ray: &Ray) -> Option<Thickness> {
+        Thickness::from_intersections(ray, &self.intersect(ray)?)
     }
 
     /// Perform the actual intersection of the ray.
@@ -65,9 +174,9 @@ pub trait Shape: 'static + Debug {
             None => {
                 let inv = self
                     .get_transform()
-                    .init()
-                    .inverse(4)
-                    .expect("Transform should have an inverse!");
+                    .inverse()
+                    .expect("Transform should have an inverse!")
+                    .as_matrix();
                 let local_point = inv * point;
                 let local_normal = self.local_normal_at(local_point);
                 (inv.transpose() * local_normal).normalize()
@@ -76,33 +185,91 @@ pub trait Shape: 'static + Debug {
     }
 
     fn world_to_object(&self, point: Point, w: &World) -> Point {
-        let object_point = match self.parent_id() {
-            Some(id) => {
-                let parent = w.get_object_by_id(id).expect("Shape not found!");
-                parent.world_to_object(point, w)
-            }
-            None => point,
-        };
-
-        self.get_transform().init().inverse(4).unwrap() * object_point
+        match w.handle(self.id()) {
+            Some(handle) => w.world_to_object(handle, point),
+            None => self.get_transform().inverse().unwrap() * point,
+        }
     }
 
     /// Compute the local normal.
     fn local_normal_at(&self, point: Point) -> Vector;
 
+    /// Material at a point in world space.
+    /// Shapes with a single material ignore the point.
+    fn material_at(&self, _point: Point, _w: Option<&World>) -> &Material {
+        self.get_material()
+    }
+
+    /// Normal in world space at `point` on the surface hit by `hit`.
+    /// Meshes and instances look up what they stored in the intersection.
+    fn normal_at_hit(&self, _hit: &Intersection, point: Point, w: Option<&World>) -> Vector {
+        self.normal_at(point, w)
+    }
+
+    /// Material at a point in world space on the surface hit by `hit`.
+    fn material_at_hit<'a>(
+        &'a self,
+        _hit: &Intersection<'a>,
+        point: Point,
+        w: Option<&World>,
+    ) -> &'a Material {
+        self.material_at(point, w)
+    }
+
+    /// Transformation from pattern space into object space, for a pattern
+    /// transformed by `pattern`. Instances replace or extend it.
+    fn pattern_transform(&self, pattern: Transformation) -> Transformation {
+        pattern
+    }
+
+    /// Texture coordinate of a point in object space.
+    /// Shapes without a texture mapping return None.
+    fn local_uv_at(&self, _point: Point) -> Option<Uv> {
+        None
+    }
+
     /// Calculate the normal in world space.
     fn normal_to_world(&self, normal: Vector, w: &World) -> Vector {
-        let world_normal =
-            (self.get_transform().init().inverse(4).unwrap().transpose() * normal).normalize();
+        match w.handle(self.id()) {
+            Some(handle) => w.normal_to_world(handle, normal),
+            None => (self
+                .get_transform()
+                .inverse()
+                .unwrap()
+                .as_matrix()
+                .transpose()
+                * normal)
+                .normalize(),
+        }
+    }
+}
 
-        match self.parent_id() {
-            Some(id) => {
-                let parent = w.get_object_by_id(id).expect("Shape not found!");
-                parent.normal_to_world(world_normal, w)
-            }
-            None => world_normal,
+/// `text` with every hyphenated uuid replaced by `-`.
+pub(crate) fn without_ids(text: &str) -> String {
+    let is_id = |bytes: &[u8]| {
+        bytes.len() >= 36
+            && bytes[..36].iter().enumerate().all(|(i, b)| match i {
+                8 | 13 | 18 | 23 => *b == b'-',
+                _ => b.is_ascii_hexdigit(),
+            })
+    };
+
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if is_id(&bytes[i..]) {
+            out.push_str(&text[start..i]);
+            out.push('-');
+            i += 36;
+            start = i;
+        } else {
+            i += 1;
         }
     }
+    out.push_str(&text[start..]);
+    out
 }
 
 impl PartialEq for dyn Shape {
@@ -124,3 +291,14 @@ pub mod cone;
 pub use cone::Cone;
 pub mod group;
 pub use group::Group;
+pub mod instance;
+pub use instance::Instance;
+pub mod rounded_box;
+pub use rounded_box::RoundedBox;
+pub mod frustum;
+pub use frustum::Frustum;
+pub mod mesh;
+pub use mesh::Mesh;
+pub mod csg;
+pub use csg::{Csg, CsgOperation};
+pub mod sdf;
@@ -1,5 +1,4 @@
 use crate::*;
-use std::f64::{INFINITY, NEG_INFINITY};
 use uuid::Uuid;
 
 /// Cone.
@@ -15,10 +14,10 @@ pub struct Cone {
     material: Material,
 
     /// Minimum of cylinder.
-    minimum: f64,
+    minimum: Float,
 
     /// Maximum of cylinder.
-    maximum: f64,
+    maximum: Float,
 
     /// Is the cylinder closed.
     closed: bool,
@@ -31,21 +30,35 @@ impl Cone {
     /// Create a new sphere.
     pub fn new() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             transform: Transformation::new(),
             material: Material::default(),
-            minimum: NEG_INFINITY,
-            maximum: INFINITY,
+            minimum: Float::NEG_INFINITY,
+            maximum: Float::INFINITY,
             closed: false,
             parent: None,
         }
     }
 
+    /// A cone of `Material::glass`.
+    pub fn glass() -> Self {
+        let mut s = Self::new();
+        s.material = Material::glass();
+        s
+    }
+
+    /// A cone of `Material::mirror`.
+    pub fn mirror() -> Self {
+        let mut s = Self::new();
+        s.material = Material::mirror();
+        s
+    }
+
     pub fn set_color(&mut self, color: RGB) {
         self.material.color = color;
     }
 
-    pub fn set_cuts(&mut self, min: f64, max: f64) {
+    pub fn set_cuts(&mut self, min: Float, max: Float) {
         self.minimum = min;
         self.maximum = max;
     }
@@ -56,10 +69,10 @@ impl Cone {
 
     /// checks to see if the intersection at `t` is within a radius
     /// of 1 (the radius of your cylinders) from the y axis.
-    fn check_cap(ray: &Ray, t: f64) -> bool {
-        let x = ray.origin.x + t * ray.direction.x;
-        let z = ray.origin.z + t * ray.direction.z;
-        let y = ray.origin.y + t * ray.direction.y;
+    fn check_cap(ray: &Ray, t: Float) -> bool {
+        let x = ray.origin().x + t * ray.direction().x;
+        let z = ray.origin().z + t * ray.direction().z;
+        let y = ray.origin().y + t * ray.direction().y;
 
         x.powi(2) + z.powi(2) <= y.abs()
     }
@@ -69,20 +82,20 @@ impl Cone {
 
         // caps only matter if the cone is closed, and might possibly be
         // intersected by the ray.
-        if !self.closed || float_eq(ray.direction.y, 0.0) {
+        if !self.closed || float_eq(ray.direction().y, 0.0) {
             return None;
         }
 
         // check for an intersection with the lower end cap by intersecting
         // the ray with the plane at y=cyl.minimum
-        let t = (self.minimum - ray.origin.y) / ray.direction.y;
+        let t = (self.minimum - ray.origin().y) / ray.direction().y;
         if Self::check_cap(ray, t) {
             xs.push(Intersection::new(t, self));
         }
 
         // check for an intersection with the upper end cap by intersecting
         // the ray with the plane at y=cyl.maximum
-        let t = (self.maximum - ray.origin.y) / ray.direction.y;
+        let t = (self.maximum - ray.origin().y) / ray.direction().y;
         if Self::check_cap(ray, t) {
             xs.push(Intersection::new(t, self));
         }
@@ -125,18 +138,27 @@ impl Shape for Cone {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
+    fn bounds(&self) -> BoundingBox {
+        let limit = self.minimum.abs().max(self.maximum.abs());
+        BoundingBox::new(
+            Point::new(-limit, self.minimum, -limit),
+            Point::new(limit, self.maximum, limit),
+        )
+    }
+
     fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection>> {
         let mut xs: Vec<Intersection> = Vec::new();
 
-        let a = ray.direction.x.powi(2) - ray.direction.y.powi(2) + ray.direction.z.powi(2);
+        let a = ray.direction().x.powi(2) - ray.direction().y.powi(2) + ray.direction().z.powi(2);
 
-        let b = 2.0 * ray.origin.x * ray.direction.x - 2.0 * ray.origin.y * ray.direction.y
-            + 2.0 * ray.origin.z * ray.direction.z;
+        let b = 2.0 * ray.origin().x * ray.direction().x - 2.0 * ray.origin().y * ray.direction().y
+            + 2.0 * ray.origin().z * ray.direction().z;
 
-        let c = ray.origin.x.powi(2) - ray.origin.y.powi(2) + ray.origin.z.powi(2);
+        let c = ray.origin().x.powi(2) - ray.origin().y.powi(2) + ray.origin().z.powi(2);
 
         if float_eq(a, 0.0) && float_eq(b, 0.0) {
             return None;
@@ -146,10 +168,12 @@ impl Shape for Cone {
             xs.push(Intersection::new(-c / (2.0 * b), self));
         }
 
+        // a grazing ray can round to a slightly negative discriminant
         let disc = b.powi(2) - 4.0 * a * c;
-        if disc < 0.0 {
+        if disc < -EPSILON {
             return None;
         }
+        let disc = disc.max(0.0);
 
         let mut t = (
             (-b - disc.sqrt()) / (2.0 * a),
@@ -160,12 +184,12 @@ impl Shape for Cone {
             t = (t.1, t.0);
         }
 
-        let y0 = ray.origin.y + t.0 * ray.direction.y;
+        let y0 = ray.origin().y + t.0 * ray.direction().y;
         if self.minimum < y0 && y0 < self.maximum {
             xs.push(Intersection::new(t.0, self));
         }
 
-        let y1 = ray.origin.y + t.1 * ray.direction.y;
+        let y1 = ray.origin().y + t.1 * ray.direction().y;
         if self.minimum < y1 && y1 < self.maximum {
             xs.push(Intersection::new(t.1, self))
         }
@@ -198,6 +222,20 @@ impl Shape for Cone {
             Vector::new(point.x, y, point.z)
         }
     }
+
+    fn local_uv_at(&self, point: Point) -> Option<Uv> {
+        let dist = point.x.powi(2) + point.z.powi(2);
+
+        // the radius of a cap equals its distance from the apex
+        let uv = if dist < self.maximum.powi(2) && point.y >= self.maximum - EPSILON {
+            uv::cap_map(point, self.maximum.abs(), UvFace::Top)
+        } else if dist < self.minimum.powi(2) && point.y <= self.minimum + EPSILON {
+            uv::cap_map(point, self.minimum.abs(), UvFace::Bottom)
+        } else {
+            uv::cylindrical_map(point, self.minimum, self.maximum)
+        };
+        Some(uv)
+    }
 }
 
 impl PartialEq for Cone {
@@ -282,6 +320,23 @@ mod test {
         }
     }
 
+    #[test]
+    fn uv_cone() {
+        let mut c = Cone::new();
+        c.minimum = -2.0;
+        c.maximum = 1.0;
+        c.closed = true;
+
+        let side = c.local_uv_at(Point::new(0.0, -0.5, -0.5)).unwrap();
+        assert_eq!(side, Uv::new(UvFace::Side, 0.0, 0.5));
+
+        let top = c.local_uv_at(Point::new(0.5, 1.0, 0.0)).unwrap();
+        assert_eq!(top, Uv::new(UvFace::Top, 0.75, 0.5));
+
+        let bottom = c.local_uv_at(Point::new(1.0, -2.0, 1.0)).unwrap();
+        assert_eq!(bottom, Uv::new(UvFace::Bottom, 0.75, 0.75));
+    }
+
     #[test]
     fn normal_cone() {
         let cone = Cone::new();
@@ -289,7 +344,7 @@ mod test {
             (Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 0.0)),
             (
                 Point::new(1.0, 1.0, 1.0),
-                Vector::new(1.0, -2_f64.sqrt(), 1.0),
+                Vector::new(1.0, -(2.0 as Float).sqrt(), 1.0),
             ),
             (Point::new(-1.0, -1.0, 0.0), Vector::new(-1.0, 1.0, 0.0)),
         ];
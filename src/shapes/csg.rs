@@ -0,0 +1,371 @@
+use crate::*;
+use uuid::Uuid;
+
+/// How the two operands of a `Csg` are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Everything inside either operand.
+    Union,
+    /// Everything inside both operands.
+    Intersection,
+    /// Everything inside the left but not the right operand.
+    Difference,
+}
+
+impl CsgOperation {
+    /// Is a point inside the result, given whether it is inside each operand?
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Constructive solid geometry, combines two closed shapes into one.
+#[derive(Debug)]
+pub struct Csg {
+    id: Uuid,
+    parent_id: Option<Uuid>,
+    transform: Transformation,
+    material: Material,
+    operation: CsgOperation,
+    left: Box<dyn Shape>,
+    right: Box<dyn Shape>,
+}
+
+impl Csg {
+    /// Combine `left` and `right`, both become children of the new shape.
+    pub fn new(
+        operation: CsgOperation,
+        mut left: Box<dyn Shape>,
+        mut right: Box<dyn Shape>,
+    ) -> Self {
+        let id = crate::new_id();
+        left.set_parent_id(id);
+        right.set_parent_id(id);
+
+        Csg {
+            id,
+            parent_id: None,
+            transform: Transformation::new(),
+            material: Material::default(),
+            operation,
+            left,
+            right,
+        }
+    }
+
+    pub fn operation(&self) -> CsgOperation {
+        self.operation
+    }
+
+    pub fn left(&self) -> &dyn Shape {
+        self.left.as_ref()
+    }
+
+    pub fn right(&self) -> &dyn Shape {
+        self.right.as_ref()
+    }
+
+    /// Is `shape` the left operand or part of it?
+    fn in_left(&self, shape: &dyn Shape) -> bool {
+        shape.id() == self.left.id() || self.left.get_object_by_id(shape.id()).is_some()
+    }
+
+    /// Keep the intersections on the surface of the combined shape.
+    /// `xs` must be sorted. Hits at the same distance, e.g. of coplanar faces,
+    /// are handled together: the surface is only crossed if the combined
+    /// inside state changes, which avoids speckles where operands touch.
+    /// The left operand wins ties, so the result is deterministic.
+    pub fn filter_intersections<'a>(&self, xs: &[Intersection<'a>]) -> Vec<Intersection<'a>> {
+        let mut result = Vec::new();
+        let (mut in_left, mut in_right) = (false, false);
+
+        let mut i = 0;
+        while i < xs.len() {
+            let before = self.operation.contains(in_left, in_right);
+
+            let mut j = i;
+            let mut chosen: Option<Intersection> = None;
+            while j < xs.len() && float_eq(xs[j].t, xs[i].t) {
+                if self.in_left(xs[j].object) {
+                    in_left = !in_left;
+                    if chosen.is_none_or(|c| !self.in_left(c.object)) {
+                        chosen = Some(xs[j]);
+                    }
+                } else {
+                    in_right = !in_right;
+                    chosen = chosen.or(Some(xs[j]));
+                }
+                j += 1;
+            }
+
+            if before != self.operation.contains(in_left, in_right) {
+                if let Some(hit) = chosen {
+                    result.push(hit);
+                }
+            }
+            i = j;
+        }
+
+        result
+    }
+}
+
+impl Shape for Csg {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    fn parent_id(&self) -> Option<Uuid> {
+        self.parent_id
+    }
+
+    fn set_parent_id(&mut self, id: Uuid) {
+        self.parent_id = Some(id);
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, transform: Transformation) {
+        transform.assert_valid();
+        self.transform = transform;
+    }
+
+    fn fingerprint(&self) -> String {
+        let own = format!(
+            "Csg {:?} {:?} {:?}",
+            self.operation, self.transform, self.material
+        );
+        format!(
+            "{} {} {}",
+            shapes::without_ids(&own),
+            self.left.fingerprint(),
+            self.right.fingerprint()
+        )
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn child(&self, index: usize) -> Option<&dyn Shape> {
+        match index {
+            0 => Some(self.left.as_ref()),
+            1 => Some(self.right.as_ref()),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, index: usize) -> Option<&mut dyn Shape> {
+        match index {
+            0 => Some(self.left.as_mut()),
+            1 => Some(self.right.as_mut()),
+            _ => None,
+        }
+    }
+
+    fn get_object_by_id(&self, id: Uuid) -> Option<&dyn Shape> {
+        for s in [&self.left, &self.right] {
+            if s.id() == id {
+                return Some(s.as_ref());
+            }
+            if let Some(c) = s.get_object_by_id(id) {
+                return Some(c);
+            }
+        }
+
+        None
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut b = self.left.parent_space_bounds();
+        // a difference never reaches outside its left operand
+        if self.operation != CsgOperation::Difference {
+            b.merge(&self.right.parent_space_bounds());
+        }
+        b
+    }
+
+    fn local_intersect<'a>(&'a self, ray: &Ray) -> Option<Vec<Intersection<'a>>> {
+        let mut xs: Vec<Intersection> = Vec::new();
+        for o in [&self.left, &self.right] {
+            if let Some(oxs) = o.intersect(ray) {
+                xs.extend(oxs);
+            }
+        }
+        // stable, so the left operand comes first among equal distances
+        xs.sort_by(|a, b| a.t.total_cmp(&b.t));
+
+        let xs = self.filter_intersections(&xs);
+        if xs.is_empty() {
+            None
+        } else {
+            Some(xs)
+        }
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.left.is_opaque() && self.right.is_opaque()
+    }
+
+    fn local_normal_at(&self, _point: Point) -> Vector {
+        panic!("Should not be called!")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new()),
+            Box::new(Cube::new()),
+        );
+
+        assert_eq!(c.operation(), CsgOperation::Union);
+        assert_eq!(c.left().parent_id(), Some(c.id()));
+        assert_eq!(c.right().parent_id(), Some(c.id()));
+    }
+
+    #[test]
+    fn contains_csg() {
+        let data = vec![
+            (CsgOperation::Union, [false, true, true, true]),
+            (CsgOperation::Intersection, [false, false, false, true]),
+            (CsgOperation::Difference, [false, false, true, false]),
+        ];
+        for (op, expected) in data {
+            let inside = [(false, false), (false, true), (true, false), (true, true)]
+                .map(|(l, r)| op.contains(l, r));
+            assert_eq!(inside, expected);
+        }
+    }
+
+    #[test]
+    fn filter_csg() {
+        let data = vec![
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+        for (op, x0, x1) in data {
+            let c = Csg::new(op, Box::new(Sphere::new()), Box::new(Cube::new()));
+            let xs = [
+                Intersection::new(1.0, c.left()),
+                Intersection::new(2.0, c.right()),
+                Intersection::new(3.0, c.left()),
+                Intersection::new(4.0, c.right()),
+            ];
+            let result = c.filter_intersections(&xs);
+
+            assert_eq!(result.len(), 2);
+            assert_eq!(result[0], xs[x0]);
+            assert_eq!(result[1], xs[x1]);
+        }
+    }
+
+    #[test]
+    fn miss_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Sphere::new()),
+            Box::new(Cube::new()),
+        );
+        let r = Ray::new(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(c.local_intersect(&r).is_none());
+    }
+
+    #[test]
+    fn hit_csg() {
+        let mut s2 = Sphere::new();
+        s2.set_transform(Transformation::new().then_translation(0.0, 0.0, 0.5));
+        let c = Csg::new(CsgOperation::Union, Box::new(Sphere::new()), Box::new(s2));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = c.local_intersect(&r).expect("Ray hits both spheres!");
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.0));
+        assert_eq!(xs[0].object.id(), c.left().id());
+        assert!(float_eq(xs[1].t, 6.5));
+        assert_eq!(xs[1].object.id(), c.right().id());
+    }
+
+    #[test]
+    fn coplanar_difference_csg() {
+        // a square hole of identical height through a cube
+        let mut hole = Cube::new();
+        hole.set_transform(Transformation::new().then_scaling(0.5, 1.0, 0.5));
+        let c = Csg::new(
+            CsgOperation::Difference,
+            Box::new(Cube::new()),
+            Box::new(hole),
+        );
+
+        let through = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert!(c.local_intersect(&through).is_none());
+
+        let beside = Ray::new(Point::new(0.75, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let xs = c
+            .local_intersect(&beside)
+            .expect("Ray hits the solid part!");
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.0));
+        assert!(float_eq(xs[1].t, 6.0));
+    }
+
+    #[test]
+    fn coplanar_union_csg() {
+        let c = Csg::new(
+            CsgOperation::Union,
+            Box::new(Cube::new()),
+            Box::new(Cube::new()),
+        );
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = c.local_intersect(&r).expect("Ray hits the cubes!");
+
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object.id(), c.left().id());
+        assert_eq!(xs[1].object.id(), c.left().id());
+    }
+
+    #[test]
+    fn mesh_csg() {
+        let input = "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+                     v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+                     f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 4 8 7 3\nf 1 5 8 4\nf 2 3 7 6\n";
+        let mesh = parse_obj(input.as_bytes(), ObjOptions { crease_angle: None })
+            .expect("Input is a valid cube!");
+        let mut hole = Cube::new();
+        hole.set_transform(Transformation::new().then_scaling(0.5, 1.0, 0.5));
+        let c = Csg::new(CsgOperation::Difference, Box::new(mesh), Box::new(hole));
+
+        // straight down the hole and across the diagonals of the mesh faces
+        let through = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert!(c.local_intersect(&through).is_none());
+
+        let diagonal = Ray::new(Point::new(0.75, 5.0, 0.75), Vector::new(0.0, -1.0, 0.0));
+        let xs = c
+            .local_intersect(&diagonal)
+            .expect("Ray hits the solid part!");
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.0));
+        assert!(float_eq(xs[1].t, 6.0));
+    }
+}
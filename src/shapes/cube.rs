@@ -15,35 +15,52 @@ pub struct Cube {
 
     /// Parent id
     parent: Option<Uuid>,
+
+    /// Smallest corner of the box.
+    minimum: Point,
+
+    /// Largest corner of the box.
+    maximum: Point,
 }
 
 impl Cube {
     /// Create a new sphere.
     pub fn new() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             transform: Transformation::new(),
             material: Material::default(),
             parent: None,
+            minimum: Point::new(-1.0, -1.0, -1.0),
+            maximum: Point::new(1.0, 1.0, 1.0),
         }
     }
 
-    pub fn set_color(&mut self, color: RGB) {
-        self.material.color = color;
+    /// A cube of `Material::glass`.
+    pub fn glass() -> Self {
+        let mut s = Self::new();
+        s.material = Material::glass();
+        s
     }
-}
 
-fn check_axis(origin: f64, direction: f64) -> (f64, f64) {
-    let tmin_numerator = -1.0 - origin;
-    let tmax_numerator = 1.0 - origin;
+    /// A cube of `Material::mirror`.
+    pub fn mirror() -> Self {
+        let mut s = Self::new();
+        s.material = Material::mirror();
+        s
+    }
 
-    let tmin = tmin_numerator / direction;
-    let tmax = tmax_numerator / direction;
+    /// Create a box spanning from `min` to `max` without any transformation.
+    /// Handy to render bounding boxes for debugging.
+    pub fn from_bounds(min: Point, max: Point) -> Self {
+        let mut cube = Self::new();
+        cube.minimum = Point::new(min.x.min(max.x), min.y.min(max.y), min.z.min(max.z));
+        cube.maximum = Point::new(min.x.max(max.x), min.y.max(max.y), min.z.max(max.z));
+        cube
+    }
 
-    if tmin > tmax {
-        (tmax, tmin)
-    } else {
-        (tmin, tmax)
+    pub fn set_color(&mut self, color: RGB) {
+        self.material.color = color;
     }
 }
 
@@ -77,45 +94,59 @@ impl Shape for Cube {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(self.minimum, self.maximum)
+    }
+
     fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection>> {
-        let (xtmin, xtmax) = check_axis(ray.origin.x, ray.direction.x);
-        let (ytmin, ytmax) = check_axis(ray.origin.y, ray.direction.y);
-        let (ztmin, ztmax) = check_axis(ray.origin.z, ray.direction.z);
-
-        let min_values = [xtmin, ytmin, ztmin];
-        let tmin = min_values.iter().max_by(|x, y| float_cmp(**x, **y));
-        let max_values = [xtmax, ytmax, ztmax];
-        let tmax = max_values.iter().min_by(|x, y| float_cmp(**x, **y));
-
-        let tmin = *tmin.unwrap();
-        let tmax = *tmax.unwrap();
-
-        if tmin > tmax {
-            None
-        } else {
-            Some(vec![
-                Intersection::new(tmin, self),
-                Intersection::new(tmax, self),
-            ])
+        let mut xs = Vec::with_capacity(2);
+        self.local_intersect_into(ray, &mut xs);
+        (!xs.is_empty()).then_some(xs)
+    }
+
+    fn local_intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if let Some((tmin, tmax)) = self.bounds().intersect(ray) {
+            xs.push(Intersection::new(tmin, self));
+            xs.push(Intersection::new(tmax, self));
         }
     }
 
     fn local_normal_at(&self, point: Point) -> Vector {
-        let max_values = [point.x.abs(), point.y.abs(), point.z.abs()];
-        let maxc = max_values.iter().max_by(|x, y| float_cmp(**x, **y));
-
-        let maxc = *maxc.unwrap();
+        // the face closest to the point wins, ties go to x before y before z
+        let faces = [
+            (
+                (point.x - self.minimum.x).abs(),
+                Vector::new(-1.0, 0.0, 0.0),
+            ),
+            ((self.maximum.x - point.x).abs(), Vector::new(1.0, 0.0, 0.0)),
+            (
+                (point.y - self.minimum.y).abs(),
+                Vector::new(0.0, -1.0, 0.0),
+            ),
+            ((self.maximum.y - point.y).abs(), Vector::new(0.0, 1.0, 0.0)),
+            (
+                (point.z - self.minimum.z).abs(),
+                Vector::new(0.0, 0.0, -1.0),
+            ),
+            ((self.maximum.z - point.z).abs(), Vector::new(0.0, 0.0, 1.0)),
+        ];
 
-        if (maxc - point.x.abs()).abs() < EPSILON {
-            Vector::new(point.x, 0.0, 0.0)
-        } else if (maxc - point.y.abs()).abs() < EPSILON {
-            Vector::new(0.0, point.y, 0.0)
-        } else {
-            Vector::new(0.0, 0.0, point.z)
+        let mut normal = faces[0];
+        for face in faces.iter().skip(1) {
+            if face.0 < normal.0 - EPSILON {
+                normal = *face;
+            }
         }
+        normal.1
+    }
+
+    fn local_uv_at(&self, point: Point) -> Option<Uv> {
+        let normal = self.local_normal_at(point);
+        Some(uv::box_map(point, normal, self.minimum, self.maximum))
     }
 }
 
@@ -191,6 +222,81 @@ mod test {
         }
     }
 
+    #[test]
+    fn ray_bounds_cube() {
+        let c = Cube::from_bounds(Point::new(1.0, 0.0, 2.0), Point::new(-1.0, 4.0, 6.0));
+        let r = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = c.intersect(&r).expect("The ray passes through the box");
+
+        assert!(float_eq(xs[0].t, 2.0));
+        assert!(float_eq(xs[1].t, 6.0));
+
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(c.intersect(&r).is_none());
+    }
+
+    #[test]
+    fn normal_bounds_cube() {
+        let c = Cube::from_bounds(Point::new(-1.0, 0.0, 2.0), Point::new(1.0, 4.0, 6.0));
+        let data = vec![
+            (Point::new(1.0, 2.0, 3.0), Vector::new(1.0, 0.0, 0.0)),
+            (Point::new(-1.0, 2.0, 3.0), Vector::new(-1.0, 0.0, 0.0)),
+            (Point::new(0.5, 4.0, 3.0), Vector::new(0.0, 1.0, 0.0)),
+            (Point::new(0.5, 0.0, 3.0), Vector::new(0.0, -1.0, 0.0)),
+            (Point::new(0.5, 2.0, 6.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(0.5, 2.0, 2.0), Vector::new(0.0, 0.0, -1.0)),
+        ];
+        for rec in data {
+            assert_eq!(c.local_normal_at(rec.0), rec.1);
+        }
+        assert_eq!(
+            c.bounds(),
+            BoundingBox::new(Point::new(-1.0, 0.0, 2.0), Point::new(1.0, 4.0, 6.0))
+        );
+    }
+
+    #[test]
+    fn bounding_sphere_cube() {
+        let c = Cube::new();
+        let s = c.bounding_sphere().expect("A cube is bounded");
+
+        assert_eq!(s.center, Point::new(0.0, 0.0, 0.0));
+        assert!(float_eq(s.radius, (3.0 as Float).sqrt()));
+    }
+
+    #[test]
+    fn ray_parallel_face_cube() {
+        let c = Cube::new();
+        let data = vec![
+            (Point::new(1.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(-1.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(0.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            (Point::new(1.0, 1.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+        ];
+        for rec in data {
+            let xs = c.local_intersect(&Ray::new(rec.0, rec.1));
+            assert!(xs.is_some());
+            let xs = xs.unwrap();
+
+            assert!(float_eq(xs[0].t, 4.0));
+            assert!(float_eq(xs[1].t, 6.0));
+        }
+
+        let r = Ray::new(Point::new(1.0001, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(c.local_intersect(&r).is_none());
+    }
+
+    #[test]
+    fn ray_nan_cube() {
+        let c = Cube::new();
+        let r = Ray::new(
+            Point::new(Float::NAN, 0.0, -5.0),
+            Vector::new(0.0, 0.0, 1.0),
+        );
+
+        assert!(c.local_intersect(&r).is_none());
+    }
+
     #[test]
     fn normal_cube() {
         let c = Cube::new();
@@ -210,4 +316,16 @@ mod test {
             assert_eq!(rec.1, normal);
         }
     }
+
+    #[test]
+    fn uv_cube() {
+        let c = Cube::from_bounds(Point::new(0.0, 0.0, 0.0), Point::new(4.0, 2.0, 2.0));
+
+        let front = c.local_uv_at(Point::new(1.0, 0.5, 2.0)).unwrap();
+        assert_eq!(front, Uv::new(UvFace::Side, 0.25, 0.25));
+        let top = c.local_uv_at(Point::new(3.0, 2.0, 0.5)).unwrap();
+        assert_eq!(top, Uv::new(UvFace::Top, 0.75, 0.75));
+        let left = c.local_uv_at(Point::new(0.0, 1.0, 1.5)).unwrap();
+        assert_eq!(left, Uv::new(UvFace::Side, 0.75, 0.5));
+    }
 }
@@ -1,5 +1,4 @@
 use crate::*;
-use std::f64::{INFINITY, NEG_INFINITY};
 use uuid::Uuid;
 
 /// Cube.
@@ -15,10 +14,10 @@ pub struct Cylinder {
     material: Material,
 
     /// Minimum of cylinder.
-    minimum: f64,
+    minimum: Float,
 
     /// Maximum of cylinder.
-    maximum: f64,
+    maximum: Float,
 
     /// Is the cylinder closed.
     closed: bool,
@@ -31,21 +30,35 @@ impl Cylinder {
     /// Create a new sphere.
     pub fn new() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             transform: Transformation::new(),
             material: Material::default(),
-            minimum: NEG_INFINITY,
-            maximum: INFINITY,
+            minimum: Float::NEG_INFINITY,
+            maximum: Float::INFINITY,
             closed: false,
             parent: None,
         }
     }
 
+    /// A cylinder of `Material::glass`.
+    pub fn glass() -> Self {
+        let mut s = Self::new();
+        s.material = Material::glass();
+        s
+    }
+
+    /// A cylinder of `Material::mirror`.
+    pub fn mirror() -> Self {
+        let mut s = Self::new();
+        s.material = Material::mirror();
+        s
+    }
+
     pub fn set_color(&mut self, color: RGB) {
         self.material.color = color;
     }
 
-    pub fn set_cuts(&mut self, min: f64, max: f64) {
+    pub fn set_cuts(&mut self, min: Float, max: Float) {
         self.minimum = min;
         self.maximum = max;
     }
@@ -56,11 +69,12 @@ impl Cylinder {
 
     /// checks to see if the intersection at `t` is within a radius
     /// of 1 (the radius of your cylinders) from the y axis.
-    fn check_cap(ray: &Ray, t: f64) -> bool {
-        let x = ray.origin.x + t * ray.direction.x;
-        let z = ray.origin.z + t * ray.direction.z;
+    fn check_cap(ray: &Ray, t: Float) -> bool {
+        let x = ray.origin().x + t * ray.direction().x;
+        let z = ray.origin().z + t * ray.direction().z;
 
-        (x.powi(2) + z.powi(2)) <= 1.0
+        // hits on the rim may round to just outside
+        (x.powi(2) + z.powi(2)) <= 1.0 + EPSILON
     }
 
     fn intersect_caps(&self, ray: &Ray) -> Option<Vec<Intersection>> {
@@ -68,20 +82,20 @@ impl Cylinder {
 
         // caps only matter if the cylinder is closed, and might possibly be
         // intersected by the ray.
-        if !self.closed || float_eq(ray.direction.y, 0.0) {
+        if !self.closed || float_eq(ray.direction().y, 0.0) {
             return None;
         }
 
         // check for an intersection with the lower end cap by intersecting
         // the ray with the plane at y=cyl.minimum
-        let t = (self.minimum - ray.origin.y) / ray.direction.y;
+        let t = (self.minimum - ray.origin().y) / ray.direction().y;
         if Self::check_cap(ray, t) {
             xs.push(Intersection::new(t, self));
         }
 
         // check for an intersection with the upper end cap by intersecting
         // the ray with the plane at y=cyl.maximum
-        let t = (self.maximum - ray.origin.y) / ray.direction.y;
+        let t = (self.maximum - ray.origin().y) / ray.direction().y;
         if Self::check_cap(ray, t) {
             xs.push(Intersection::new(t, self));
         }
@@ -124,18 +138,26 @@ impl Shape for Cylinder {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(-1.0, self.minimum, -1.0),
+            Point::new(1.0, self.maximum, 1.0),
+        )
+    }
+
     fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection>> {
-        let a = ray.direction.x.powi(2) + ray.direction.z.powi(2);
+        let a = ray.direction().x.powi(2) + ray.direction().z.powi(2);
 
         if float_eq(a, 0.0) {
             return self.intersect_caps(ray);
         }
 
-        let b = 2.0 * ray.origin.x * ray.direction.x + 2.0 * ray.origin.z * ray.direction.z;
-        let c = ray.origin.x.powi(2) + ray.origin.z.powi(2) - 1.0;
+        let b = 2.0 * ray.origin().x * ray.direction().x + 2.0 * ray.origin().z * ray.direction().z;
+        let c = ray.origin().x.powi(2) + ray.origin().z.powi(2) - 1.0;
         let disc = b.powi(2) - 4.0 * a * c;
 
         if disc < 0.0 {
@@ -150,12 +172,12 @@ impl Shape for Cylinder {
 
         let mut xs: Vec<Intersection> = Vec::new();
 
-        let y0 = ray.origin.y + t0 * ray.direction.y;
+        let y0 = ray.origin().y + t0 * ray.direction().y;
         if self.minimum < y0 && y0 < self.maximum {
             xs.push(Intersection::new(t0, self));
         }
 
-        let y1 = ray.origin.y + t1 * ray.direction.y;
+        let y1 = ray.origin().y + t1 * ray.direction().y;
         if self.minimum < y1 && y1 < self.maximum {
             xs.push(Intersection::new(t1, self));
         }
@@ -184,6 +206,19 @@ impl Shape for Cylinder {
             Vector::new(point.x, 0.0, point.z)
         }
     }
+
+    fn local_uv_at(&self, point: Point) -> Option<Uv> {
+        let dist = point.x.powi(2) + point.z.powi(2);
+
+        let uv = if dist < 1.0 && point.y >= self.maximum - EPSILON {
+            uv::cap_map(point, 1.0, UvFace::Top)
+        } else if dist < 1.0 && point.y <= self.minimum + EPSILON {
+            uv::cap_map(point, 1.0, UvFace::Bottom)
+        } else {
+            uv::cylindrical_map(point, self.minimum, self.maximum)
+        };
+        Some(uv)
+    }
 }
 
 impl PartialEq for Cylinder {
@@ -259,8 +294,8 @@ mod test {
     fn default_cylinder() {
         let cyl = Cylinder::new();
 
-        assert_eq!(cyl.minimum, NEG_INFINITY);
-        assert_eq!(cyl.maximum, INFINITY);
+        assert_eq!(cyl.minimum, Float::NEG_INFINITY);
+        assert_eq!(cyl.maximum, Float::INFINITY);
     }
 
     #[test]
@@ -354,4 +389,21 @@ mod test {
             assert_eq!(rec.1, n);
         }
     }
+
+    #[test]
+    fn uv_cylinder() {
+        let mut c = Cylinder::new();
+        c.minimum = 1.0;
+        c.maximum = 2.0;
+        c.closed = true;
+
+        let side = c.local_uv_at(Point::new(1.0, 1.5, 0.0)).unwrap();
+        assert_eq!(side, Uv::new(UvFace::Side, 0.25, 0.5));
+
+        let top = c.local_uv_at(Point::new(0.5, 2.0, 0.0)).unwrap();
+        assert_eq!(top, Uv::new(UvFace::Top, 0.75, 0.5));
+
+        let bottom = c.local_uv_at(Point::new(0.0, 1.0, 0.5)).unwrap();
+        assert_eq!(bottom, Uv::new(UvFace::Bottom, 0.5, 0.75));
+    }
 }
@@ -0,0 +1,202 @@
+use crate::{
+    shapes::{sdf, Shape},
+    BoundingBox, Float, Intersection, Material, Point, Ray, Transformation, Vector, RGB,
+};
+use uuid::Uuid;
+
+/// A closed cone cut at both ends, spanning y in [0,1].
+/// The rims can be rounded with a corner radius.
+#[derive(Debug)]
+pub struct Frustum {
+    /// Unique id.
+    uuid: Uuid,
+
+    /// Transformation matrix
+    transform: Transformation,
+
+    /// The material of a frustum
+    material: Material,
+
+    /// Radius of the cap at y=0.
+    bottom_radius: Float,
+
+    /// Radius of the cap at y=1.
+    top_radius: Float,
+
+    /// Radius of the rounded rims.
+    corner_radius: Float,
+
+    /// Parent id
+    parent: Option<Uuid>,
+}
+
+impl Frustum {
+    /// Create a new frustum with sharp rims.
+    pub fn new(bottom_radius: Float, top_radius: Float) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            transform: Transformation::new(),
+            material: Material::default(),
+            bottom_radius: bottom_radius.max(0.0),
+            top_radius: top_radius.max(0.0),
+            corner_radius: 0.0,
+            parent: None,
+        }
+    }
+
+    pub fn set_color(&mut self, color: RGB) {
+        self.material.color = color;
+    }
+
+    /// Round the rims, `radius` is clamped to half the height.
+    pub fn set_corner_radius(&mut self, radius: Float) {
+        self.corner_radius = radius.clamp(0.0, 0.5);
+    }
+
+    /// Signed distance from `p` to the surface, negative inside.
+    fn sdf(&self, p: Point) -> Float {
+        let r = self.corner_radius;
+        let h = 0.5 - r;
+        let r1 = (self.bottom_radius - r).max(0.0);
+        let r2 = (self.top_radius - r).max(0.0);
+
+        // work in the plane through the axis, centered halfway up
+        let (qx, qy) = ((p.x.powi(2) + p.z.powi(2)).sqrt(), p.y - 0.5);
+        let cap = if qy < 0.0 { r1 } else { r2 };
+        let (cax, cay) = (qx - qx.min(cap), qy.abs() - h);
+
+        let (k2x, k2y) = (r2 - r1, 2.0 * h);
+        let k2_len = k2x.powi(2) + k2y.powi(2);
+        let s = if k2_len > 0.0 {
+            (((r2 - qx) * k2x + (h - qy) * k2y) / k2_len).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let (cbx, cby) = (qx - r2 + k2x * s, qy - h + k2y * s);
+
+        let sign = if cbx < 0.0 && cay < 0.0 { -1.0 } else { 1.0 };
+        let dist = (cax.powi(2) + cay.powi(2)).min(cbx.powi(2) + cby.powi(2));
+        sign * dist.sqrt() - r
+    }
+}
+
+impl Shape for Frustum {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn parent_id(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent_id(&mut self, id: Uuid) {
+        self.parent = Some(id);
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let limit = self.bottom_radius.max(self.top_radius);
+        BoundingBox::new(
+            Point::new(-limit, 0.0, -limit),
+            Point::new(limit, 1.0, limit),
+        )
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        let xs: Vec<Intersection> = sdf::march(|p| self.sdf(p), ray, &self.bounds())
+            .into_iter()
+            .map(|t| Intersection::new(t, self))
+            .collect();
+
+        if xs.is_empty() {
+            None
+        } else {
+            Some(xs)
+        }
+    }
+
+    fn local_normal_at(&self, point: Point) -> Vector {
+        sdf::gradient(|p| self.sdf(p), point)
+    }
+}
+
+impl PartialEq for Frustum {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::float_eq;
+
+    #[test]
+    fn intersect_frustum() {
+        let f = Frustum::new(1.0, 0.5);
+        let side = Ray::new(Point::new(0.0, 0.5, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let top = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let miss = Ray::new(Point::new(0.0, 0.9, -0.7), Vector::new(1.0, 0.0, 0.0));
+
+        let xs = f.local_intersect(&side).expect("Ray hits the side!");
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.25));
+        assert!(float_eq(xs[1].t, 5.75));
+
+        let xs = f.local_intersect(&top).expect("Ray hits the caps!");
+        assert!(float_eq(xs[0].t, 4.0));
+        assert!(float_eq(xs[1].t, 5.0));
+
+        assert!(f.local_intersect(&miss).is_none());
+    }
+
+    #[test]
+    fn normal_frustum() {
+        let f = Frustum::new(1.0, 1.0);
+
+        assert_eq!(
+            f.local_normal_at(Point::new(0.0, 1.0, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            f.local_normal_at(Point::new(0.0, 0.0, 0.0)),
+            Vector::new(0.0, -1.0, 0.0)
+        );
+        assert_eq!(
+            f.local_normal_at(Point::new(1.0, 0.5, 0.0)),
+            Vector::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn rounded_frustum() {
+        let mut f = Frustum::new(1.0, 1.0);
+        f.set_corner_radius(0.25);
+        let rim = Ray::new(Point::new(0.95, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let xs = f.local_intersect(&rim).expect("Ray hits the rounded rim!");
+
+        // circle of radius 0.25 around x=0.75, y=0.75
+        let dy = (0.0625 as Float - 0.04).sqrt();
+        assert!(float_eq(xs[0].t, 5.0 - 0.75 - dy));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::*;
+use std::sync::OnceLock;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -7,23 +8,40 @@ pub struct Group {
     parent_id: Option<Uuid>,
     pub transform: Transformation,
     pub material: Material,
-    pub objects: Vec<Box<dyn Shape>>,
+    objects: Vec<Box<dyn Shape>>,
+    /// Bounds of all children, rays missing them skip the group.
+    /// Computed on first use and dropped whenever a child may change.
+    bounds: OnceLock<BoundingBox>,
 }
 
 impl Group {
     pub fn new() -> Group {
         Group {
-            id: Uuid::new_v4(),
+            id: crate::new_id(),
             parent_id: None,
             transform: Transformation::new(),
             material: Material::default(),
             objects: Vec::new(),
+            bounds: OnceLock::new(),
         }
     }
 
     pub fn add_object(&mut self, mut shape: Box<dyn Shape>) {
         shape.set_parent_id(self.id);
         self.objects.push(shape);
+        self.bounds = OnceLock::new();
+    }
+
+    /// The children of the group.
+    pub fn objects(&self) -> &[Box<dyn Shape>] {
+        &self.objects
+    }
+
+    /// Can the ray in group space hit any child?
+    /// Unbounded children, like planes, can't be culled.
+    fn may_hit(&self, ray: &Ray) -> bool {
+        let bounds = self.bounds();
+        !bounds.is_finite() || bounds.intersect(ray).is_some()
     }
 
     pub fn get_object(&self, index: usize) -> Option<&dyn Shape> {
@@ -52,9 +70,19 @@ impl Shape for Group {
     }
 
     fn set_transform(&mut self, transform: Transformation) {
+        transform.assert_valid();
         self.transform = transform;
     }
 
+    fn fingerprint(&self) -> String {
+        let own = format!("Group {:?} {:?}", self.transform, self.material);
+        self.objects
+            .iter()
+            .fold(shapes::without_ids(&own), |text, child| {
+                text + " " + &child.fingerprint()
+            })
+    }
+
     fn get_material(&self) -> &Material {
         &self.material
     }
@@ -67,6 +95,22 @@ impl Shape for Group {
         self.material = material;
     }
 
+    fn child(&self, index: usize) -> Option<&dyn Shape> {
+        self.objects.get(index).map(|o| o.as_ref())
+    }
+
+    /// The child may be changed through the reference,
+    /// so the bounds are computed again on their next use.
+    fn child_mut(&mut self, index: usize) -> Option<&mut dyn Shape> {
+        self.bounds = OnceLock::new();
+        self.objects.get_mut(index).map(|o| o.as_mut())
+    }
+
+    /// Recompute the bounds of the children.
+    fn update_bounds(&mut self) {
+        self.bounds = OnceLock::new();
+    }
+
     fn get_object_by_id(&self, id: Uuid) -> Option<&dyn Shape> {
         let mut shape = None;
         for s in &self.objects {
@@ -83,16 +127,24 @@ impl Shape for Group {
         shape
     }
 
+    fn bounds(&self) -> BoundingBox {
+        *self.bounds.get_or_init(|| {
+            let mut bounds = BoundingBox::empty();
+            for o in &self.objects {
+                bounds.merge(&o.parent_space_bounds());
+            }
+            bounds
+        })
+    }
+
+    /// The bounding box is tighter than a sphere, groups test it instead.
+    fn bounding_sphere(&self) -> Option<BoundingSphere> {
+        None
+    }
+
     fn local_intersect<'a>(&'a self, ray: &Ray) -> Option<Vec<Intersection<'a>>> {
         let mut xs: Vec<Intersection> = Vec::new();
-
-        for o in &self.objects {
-            if let Some(oxs) = o.intersect(ray) {
-                for ox in oxs {
-                    xs.push(ox);
-                }
-            }
-        }
+        self.local_intersect_into(ray, &mut xs);
 
         if xs.is_empty() {
             None
@@ -102,6 +154,34 @@ impl Shape for Group {
         }
     }
 
+    fn local_intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if !self.may_hit(ray) {
+            return;
+        }
+        for o in &self.objects {
+            o.intersect_into(ray, xs);
+        }
+    }
+
+    fn intersects_before(&self, ray: &Ray, max_t: Float) -> bool {
+        // t is the same along the transformed ray
+        let local_ray = ray.transform(
+            self.transform
+                .inverse()
+                .expect("The transformation matrix should invertible!")
+                .as_matrix(),
+        );
+        self.may_hit(&local_ray)
+            && self
+                .objects
+                .iter()
+                .any(|o| o.intersects_before(&local_ray, max_t))
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.objects.iter().all(|o| o.is_opaque())
+    }
+
     fn local_normal_at(&self, _point: Point) -> Vector {
         panic!("Should not be called!")
     }
@@ -121,7 +201,7 @@ mod tests {
     fn create_group() {
         let g = Group::new();
 
-        assert!(g.objects.is_empty());
+        assert!(g.objects().is_empty());
         assert_eq!(g.transform, Transformation::new());
     }
 
@@ -134,8 +214,8 @@ mod tests {
         s.set_parent_id(g.id);
         g.add_object(Box::new(s));
 
-        assert!(!g.objects.is_empty());
-        assert_eq!(g.objects[0].parent_id().unwrap(), g.id());
+        assert!(!g.objects().is_empty());
+        assert_eq!(g.objects()[0].parent_id().unwrap(), g.id());
     }
 
     #[test]
@@ -155,11 +235,11 @@ mod tests {
         let s1_id = s1.id();
 
         let mut s2 = Sphere::new();
-        s2.set_transform(Transformation::new().translation(0.0, 0.0, -3.0));
+        s2.set_transform(Transformation::new().then_translation(0.0, 0.0, -3.0));
         let s2_id = s2.id();
 
         let mut s3 = Sphere::new();
-        s3.set_transform(Transformation::new().translation(5.0, 0.0, 0.0));
+        s3.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
 
         g.add_object(Box::new(s1));
         g.add_object(Box::new(s2));
@@ -178,10 +258,10 @@ mod tests {
     #[test]
     fn intersecting_transformed_group() {
         let mut g = Group::new();
-        g.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+        g.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
 
         let mut s = Sphere::new();
-        s.set_transform(Transformation::new().translation(5.0, 0.0, 0.0));
+        s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
 
         g.add_object(Box::new(s));
 
@@ -190,4 +270,51 @@ mod tests {
         let xs = g.intersect(&r).unwrap();
         assert_eq!(xs.len(), 2);
     }
+
+    #[test]
+    fn cached_bounds_group() {
+        let mut g = Group::new();
+        let mut s = Sphere::new();
+        s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
+        g.add_object(Box::new(s));
+
+        assert_eq!(g.bounds().min, Point::new(4.0, -1.0, -1.0));
+        assert_eq!(g.bounds().max, Point::new(6.0, 1.0, 1.0));
+
+        let miss = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(g.intersect(&miss).is_none());
+        assert!(!g.intersects_before(&miss, Float::INFINITY));
+
+        g.child_mut(0).unwrap().set_transform(Transformation::new());
+        assert_eq!(g.bounds().min, Point::new(-1.0, -1.0, -1.0));
+        assert_eq!(g.intersect(&miss).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn moved_child_group() {
+        let mut inner = Group::new();
+        inner.add_object(Box::new(Sphere::new()));
+        let mut g = Group::new();
+        g.add_object(Box::new(inner));
+
+        let r = Ray::new(Point::new(10.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(g.intersect(&r).is_none());
+
+        // moved two levels down, without updating the bounds
+        g.child_mut(0)
+            .and_then(|inner| inner.child_mut(0))
+            .unwrap()
+            .set_transform(Transformation::new().then_translation(10.0, 0.0, 0.0));
+        assert_eq!(g.intersect(&r).unwrap().len(), 2);
+        assert!(g.intersects_before(&r, Float::INFINITY));
+    }
+
+    #[test]
+    fn unbounded_group() {
+        let mut g = Group::new();
+        g.add_object(Box::new(Plane::new()));
+
+        let r = Ray::new(Point::new(100.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(g.intersect(&r).unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,399 @@
+use crate::{
+    shapes::Shape, BoundingBox, Intersection, Material, Point, Ray, Transformation, Uv, Vector,
+    World,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A placement of shared geometry with its own transformation.
+/// Many instances of one mesh don't copy its triangles.
+///
+/// Hits on the geometry are reported as hits on the instance, with the shape
+/// hit inside of the geometry as `part`, so groups and csg can be instanced.
+/// It shades with the material of the part, unless the instance overrides the
+/// material or just the transformation of its pattern, e.g. to vary the tint of trees.
+/// An instance inside of an instanced group shades as a single shape,
+/// its own geometry has to be a leaf.
+#[derive(Debug)]
+pub struct Instance {
+    /// Unique id.
+    uuid: Uuid,
+
+    /// Transformation matrix, applied on top of the one of the geometry.
+    transform: Transformation,
+
+    /// Material replacing the one of the geometry.
+    material: Option<Material>,
+
+    /// Pattern transformation replacing the one of the material.
+    pattern_transform: Option<Transformation>,
+
+    /// Parent id
+    parent: Option<Uuid>,
+
+    /// The shared geometry.
+    geometry: Arc<dyn Shape>,
+
+    /// Transformation from the space of the parent of each shape in the
+    /// geometry into the space of the instance, by id.
+    parts: HashMap<Uuid, Transformation>,
+}
+
+impl Instance {
+    /// Create a new instance of `geometry` without overrides.
+    pub fn new(geometry: Arc<dyn Shape>) -> Self {
+        let mut parts = HashMap::new();
+        Self::add_parts(geometry.as_ref(), Transformation::new(), &mut parts);
+        Self {
+            uuid: crate::new_id(),
+            transform: Transformation::new(),
+            material: None,
+            pattern_transform: None,
+            parent: None,
+            geometry,
+            parts,
+        }
+    }
+
+    /// Record the leaves below `shape`, whose parent space is placed by `to_instance`.
+    fn add_parts(
+        shape: &dyn Shape,
+        to_instance: Transformation,
+        parts: &mut HashMap<Uuid, Transformation>,
+    ) {
+        if shape.child(0).is_none() {
+            parts.insert(shape.id(), to_instance);
+            return;
+        }
+        let to_instance = to_instance * shape.get_transform();
+        for child in (0..).map_while(|i| shape.child(i)) {
+            Self::add_parts(child, to_instance, parts);
+        }
+    }
+
+    /// Transformation from the space of the instance into the parent space of `part`.
+    fn to_part(&self, part: &dyn Shape) -> Transformation {
+        self.parts
+            .get(&part.id())
+            .and_then(|t| t.inverse().ok())
+            .unwrap_or_default()
+    }
+
+    /// A point in world space in the space of the instance.
+    fn to_local(&self, point: Point, w: Option<&World>) -> Point {
+        match w {
+            Some(w) => self.world_to_object(point, w),
+            None => {
+                self.get_transform()
+                    .inverse()
+                    .expect("Transform should have an inverse!")
+                    * point
+            }
+        }
+    }
+
+    /// The shared geometry.
+    pub fn geometry(&self) -> &Arc<dyn Shape> {
+        &self.geometry
+    }
+
+    /// Whether the instance has its own material.
+    pub fn overrides_material(&self) -> bool {
+        self.material.is_some()
+    }
+
+    /// Go back to the material of the geometry.
+    pub fn reset_material(&mut self) {
+        self.material = None;
+    }
+
+    /// Transform the pattern of the material, whichever material is used.
+    /// Panics if the transformation is degenerate.
+    pub fn set_pattern_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.pattern_transform = Some(t);
+    }
+
+    /// Go back to the pattern transformation of the material.
+    pub fn reset_pattern_transform(&mut self) {
+        self.pattern_transform = None;
+    }
+}
+
+impl Shape for Instance {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn parent_id(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent_id(&mut self, id: Uuid) {
+        self.parent = Some(id);
+    }
+
+    fn get_material(&self) -> &Material {
+        match &self.material {
+            Some(m) => m,
+            None => self.geometry.get_material(),
+        }
+    }
+
+    /// The material of the geometry is shared, only an override can be changed.
+    /// Without one the instance starts overriding with a copy of `get_material`.
+    fn get_material_mut(&mut self) -> &mut Material {
+        let geometry = &self.geometry;
+        self.material
+            .get_or_insert_with(|| geometry.get_material().clone())
+    }
+
+    fn fingerprint(&self) -> String {
+        // the parts are a map in no particular order, the geometry has them all
+        let overrides = format!(
+            "Instance {:?} {:?} {:?}",
+            self.transform, self.material, self.pattern_transform
+        );
+        format!(
+            "{} {}",
+            crate::shapes::without_ids(&overrides),
+            self.geometry.fingerprint()
+        )
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = Some(m);
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.geometry.parent_space_bounds()
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        let mut xs = Vec::new();
+        self.local_intersect_into(ray, &mut xs);
+        (!xs.is_empty()).then_some(xs)
+    }
+
+    fn local_intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        let start = xs.len();
+        self.geometry.intersect_into(ray, xs);
+        for x in &mut xs[start..] {
+            x.part = Some(x.object);
+            x.object = self;
+        }
+    }
+
+    fn is_opaque(&self) -> bool {
+        match &self.material {
+            Some(m) => m.is_opaque(),
+            None => self.geometry.is_opaque(),
+        }
+    }
+
+    fn local_normal_at(&self, point: Point) -> Vector {
+        self.geometry.normal_at(point, None)
+    }
+
+    fn material_at(&self, point: Point, w: Option<&World>) -> &Material {
+        match &self.material {
+            Some(m) => m,
+            None => self.geometry.material_at(self.to_local(point, w), None),
+        }
+    }
+
+    fn normal_at_hit(&self, hit: &Intersection, point: Point, w: Option<&World>) -> Vector {
+        let part = match hit.part {
+            Some(part) => part,
+            None => return self.normal_at(point, w),
+        };
+        let to_part = self.to_part(part);
+        let part_hit = Intersection {
+            object: part,
+            part: None,
+            ..*hit
+        };
+        let part_normal = part.normal_at_hit(&part_hit, to_part * self.to_local(point, w), None);
+        let local_normal = (to_part.as_matrix().transpose() * part_normal).normalize();
+        match w {
+            Some(w) => self.normal_to_world(local_normal, w),
+            None => (self
+                .get_transform()
+                .inverse()
+                .expect("Transform should have an inverse!")
+                .as_matrix()
+                .transpose()
+                * local_normal)
+                .normalize(),
+        }
+    }
+
+    fn material_at_hit<'a>(
+        &'a self,
+        hit: &Intersection<'a>,
+        point: Point,
+        w: Option<&World>,
+    ) -> &'a Material {
+        match (&self.material, hit.part) {
+            (Some(m), _) => m,
+            (None, Some(part)) => {
+                let part_hit = Intersection {
+                    object: part,
+                    part: None,
+                    ..*hit
+                };
+                let part_point = self.to_part(part) * self.to_local(point, w);
+                part.material_at_hit(&part_hit, part_point, None)
+            }
+            (None, None) => self.material_at(point, w),
+        }
+    }
+
+    /// Patterns are placed in the object space of the geometry.
+    fn pattern_transform(&self, pattern: Transformation) -> Transformation {
+        self.geometry.get_transform() * self.pattern_transform.unwrap_or(pattern)
+    }
+
+    fn local_uv_at(&self, point: Point) -> Option<Uv> {
+        let inv = self.geometry.get_transform().inverse().ok()?;
+        self.geometry.local_uv_at(inv * point)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{consts::PI, Camera, Group, PointLight, Sphere, Stripes, BLACK, BLUE, RED, WHITE};
+
+    #[test]
+    fn shared_geometry_instance() {
+        let mut sphere = Sphere::new();
+        sphere.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
+        let geometry: Arc<dyn Shape> = Arc::new(sphere);
+
+        let a = Instance::new(geometry.clone());
+        let mut b = Instance::new(geometry.clone());
+        b.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
+        b.set_material(Material {
+            color: RED,
+            ..Material::default()
+        });
+        assert_eq!(Arc::strong_count(&geometry), 3);
+        assert_ne!(a.get_material().color, RED);
+
+        let r = Ray::new(Point::new(5.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(a.intersect(&r).is_none());
+        let xs = b.intersect(&r).unwrap();
+        assert_eq!(xs.len(), 2);
+        assert!(crate::float_eq(xs[0].t, 3.0));
+        assert_eq!(xs[0].object.id(), b.id());
+        assert_eq!(xs[0].object.get_material().color, RED);
+
+        let n = b.normal_at(Point::new(5.0, 0.0, -2.0), None);
+        assert_eq!(n, Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(b.bounds().max, Point::new(2.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn overrides_instance() {
+        let mut sphere = Sphere::new();
+        sphere.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
+        sphere.get_material_mut().pattern = Some(Box::new(Stripes::stripe_pattern(WHITE, BLACK)));
+        let geometry: Arc<dyn Shape> = Arc::new(sphere);
+
+        let mut a = Instance::new(geometry.clone());
+        a.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
+        assert!(!a.overrides_material());
+        assert_eq!(a.get_material(), geometry.get_material());
+
+        // stripes are one unit wide in the space of the geometry
+        let p = Point::new(8.0, 0.0, 0.0);
+        assert_eq!(a.material_at(p, None).color_at(&a, p), BLACK);
+        let m = geometry.get_material();
+        a.set_pattern_transform(Transformation::new().then_scaling(2.0, 1.0, 1.0));
+        assert_eq!(m.color_at(&a, p), WHITE);
+        a.reset_pattern_transform();
+
+        a.set_material(Material::default());
+        a.get_material_mut().color = RED;
+        assert!(a.overrides_material());
+        assert_eq!(a.material_at(p, None).color, RED);
+        assert_eq!(a.material_at(p, None).color_at(&a, p), RED);
+        assert!(geometry.get_material().pattern.is_some());
+        a.reset_material();
+        assert_eq!(a.get_material(), geometry.get_material());
+    }
+
+    #[test]
+    fn shared_material_instance() {
+        let mut sphere = Sphere::new();
+        sphere.get_material_mut().pattern = Some(Box::new(Stripes::stripe_pattern(WHITE, BLACK)));
+        let geometry: Arc<dyn Shape> = Arc::new(sphere);
+        let mut a = Instance::new(geometry.clone());
+
+        // the instance changes its own copy, the geometry keeps its material
+        a.get_material_mut().ambient = 0.5;
+        assert!(a.overrides_material());
+        assert_eq!(a.get_material().ambient, 0.5);
+        assert_eq!(a.get_material().pattern, geometry.get_material().pattern);
+        assert_eq!(geometry.get_material().ambient, Material::default().ambient);
+
+        let p = Point::new(0.5, 0.0, 0.0);
+        assert_eq!(a.material_at(p, None).color_at(&a, p), WHITE);
+    }
+
+    #[test]
+    fn instance_group() {
+        let mut group = Group::new();
+        group.set_transform(Transformation::new().then_scaling(0.5, 0.5, 0.5));
+        let mut left = Sphere::new();
+        left.set_transform(Transformation::new().then_translation(-2.0, 0.0, 0.0));
+        left.get_material_mut().color = RED;
+        let mut right = Sphere::new();
+        right.set_transform(Transformation::new().then_translation(2.0, 0.0, 0.0));
+        right.get_material_mut().color = BLUE;
+        group.add_object(Box::new(left));
+        group.add_object(Box::new(right));
+        let geometry: Arc<dyn Shape> = Arc::new(group);
+
+        // turned around, the red sphere is on the right
+        let mut a = Instance::new(geometry);
+        a.set_transform(Transformation::new().then_rotate_y(PI));
+        let r = Ray::new(Point::new(1.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = a.intersect(&r).unwrap();
+        let hit = Intersection::hit(&xs).unwrap();
+        assert_eq!(hit.object.id(), a.id());
+        assert!(crate::float_eq(hit.t, 4.5));
+        let p = r.position(hit.t);
+        assert_eq!(hit.normal_at(p, None), Vector::new(0.0, 0.0, -1.0));
+        assert_eq!(hit.material_at(p, None).color, RED);
+
+        let mut w = World::new();
+        w.set_light(PointLight::new(Point::new(-10.0, 10.0, -10.0), WHITE));
+        crate::add_object!(w, a);
+        let mut c = Camera::new(11, 11, PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        let image = c.render(&w);
+
+        // lit beyond the ambient term, so the normals face the light
+        let red = image.pixel_at(6, 5);
+        assert!(red.red > 0.2 && red.blue < 0.01);
+        let blue = image.pixel_at(4, 5);
+        assert!(blue.blue > 0.2 && blue.red < 0.01);
+        assert_eq!(image.pixel_at(5, 5), BLACK);
+    }
+}
@@ -0,0 +1,805 @@
+use crate::{
+    float_eq, shapes::Shape, BoundingBox, Float, Intersection, Material, Pattern, Point, Ray,
+    Transformation, Uv, UvFace, Vector, World, EPSILON, RGB,
+};
+use uuid::Uuid;
+
+/// One triangle of a mesh, given by indices into the vertex and normal lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    /// Corners of the triangle, counter clockwise seen from the front.
+    pub vertices: [usize; 3],
+
+    /// Normal of each corner, flat shaded if None.
+    pub normals: Option<[usize; 3]>,
+
+    /// Texture coordinate of each corner.
+    pub uvs: Option<[usize; 3]>,
+
+    /// Material group of the face, the mesh material if None.
+    pub material: Option<usize>,
+}
+
+impl Face {
+    /// Create a new flat shaded face without texture coordinates.
+    pub fn new(vertices: [usize; 3]) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+            material: None,
+        }
+    }
+}
+
+/// Faces per leaf of the hierarchy.
+const LEAF_FACES: usize = 4;
+
+/// Node of the bounding volume hierarchy over the faces of a mesh.
+#[derive(Debug, Clone, Copy)]
+enum MeshNode {
+    /// Faces `order[first..first + count]`.
+    Leaf {
+        bounds: BoundingBox,
+        first: usize,
+        count: usize,
+    },
+    /// Two children, the nodes at `first` and `first + 1`.
+    Inner { bounds: BoundingBox, first: usize },
+}
+
+/// A triangle mesh, all faces share one transformation and material.
+#[derive(Debug)]
+pub struct Mesh {
+    /// Unique id.
+    uuid: Uuid,
+
+    /// Transformation matrix
+    transform: Transformation,
+
+    /// The material of a mesh
+    material: Material,
+
+    /// Named materials faces can refer to.
+    groups: Vec<(String, Material)>,
+
+    /// Parent id
+    parent: Option<Uuid>,
+
+    /// Vertex positions.
+    vertices: Vec<Point>,
+
+    /// Vertex normals referenced by the faces.
+    normals: Vec<Vector>,
+
+    /// Texture coordinates referenced by the faces.
+    uvs: Vec<[Float; 2]>,
+
+    /// Triangles.
+    faces: Vec<Face>,
+
+    /// Cached bounding box of all vertices.
+    bounds: BoundingBox,
+
+    /// Face indices, sorted so the leaves of the hierarchy cover a range each.
+    order: Vec<usize>,
+
+    /// Hierarchy over the faces, rays only test the faces of leaves they hit.
+    nodes: Vec<MeshNode>,
+}
+
+impl Mesh {
+    /// Create a new mesh, faces must only reference existing vertices and normals.
+    pub fn new(vertices: Vec<Point>, normals: Vec<Vector>, faces: Vec<Face>) -> Self {
+        let mut bounds = BoundingBox::empty();
+        for v in &vertices {
+            bounds.add_point(*v);
+        }
+
+        let mut mesh = Self {
+            uuid: crate::new_id(),
+            transform: Transformation::new(),
+            material: Material::default(),
+            groups: Vec::new(),
+            parent: None,
+            vertices,
+            normals,
+            uvs: Vec::new(),
+            faces,
+            bounds,
+            order: Vec::new(),
+            nodes: Vec::new(),
+        };
+        mesh.build_hierarchy();
+        mesh
+    }
+
+    /// Rebuild the hierarchy over the faces, needed after moving vertices.
+    fn build_hierarchy(&mut self) {
+        let centroids: Vec<Point> = self
+            .faces
+            .iter()
+            .map(|f| {
+                let [p1, p2, p3] = f.vertices.map(|i| self.vertices[i]);
+                p1 + ((p2 - p1) + (p3 - p1)) / 3.0
+            })
+            .collect();
+        self.order = (0..self.faces.len()).collect();
+        self.nodes = vec![MeshNode::Leaf {
+            bounds: BoundingBox::empty(),
+            first: 0,
+            count: 0,
+        }];
+        self.build_node(0, 0, self.faces.len(), &centroids);
+    }
+
+    /// Split the faces `order[start..end]` at the median of their centroids
+    /// along the longest axis, until few enough are left for a leaf.
+    fn build_node(&mut self, node: usize, start: usize, end: usize, centroids: &[Point]) {
+        let mut bounds = BoundingBox::empty();
+        let mut centers = BoundingBox::empty();
+        for &i in &self.order[start..end] {
+            for v in self.faces[i].vertices {
+                bounds.add_point(self.vertices[v]);
+            }
+            centers.add_point(centroids[i]);
+        }
+        // flat faces have flat boxes, rays at their edges must not slip past
+        if !bounds.is_empty() {
+            let pad = Vector::new(EPSILON, EPSILON, EPSILON);
+            bounds = BoundingBox::new(bounds.min - pad, bounds.max + pad);
+        }
+
+        if end - start <= LEAF_FACES {
+            self.nodes[node] = MeshNode::Leaf {
+                bounds,
+                first: start,
+                count: end - start,
+            };
+            return;
+        }
+
+        let extent = centers.max - centers.min;
+        let key = |p: Point| {
+            if extent.x >= extent.y && extent.x >= extent.z {
+                p.x
+            } else if extent.y >= extent.z {
+                p.y
+            } else {
+                p.z
+            }
+        };
+        let mid = (start + end) / 2;
+        self.order[start..end].select_nth_unstable_by(mid - start, |a, b| {
+            key(centroids[*a]).total_cmp(&key(centroids[*b]))
+        });
+
+        let first = self.nodes.len();
+        self.nodes.push(self.nodes[node]);
+        self.nodes.push(self.nodes[node]);
+        self.nodes[node] = MeshNode::Inner { bounds, first };
+        self.build_node(first, start, mid, centroids);
+        self.build_node(first + 1, mid, end, centroids);
+    }
+
+    /// Set the texture coordinates referenced by the faces.
+    /// Panics if a face references a texture coordinate past the end of `uvs`.
+    pub fn set_uvs(&mut self, uvs: Vec<[Float; 2]>) {
+        let mut referenced = self.faces.iter().filter_map(|f| f.uvs).flatten();
+        if let Some(i) = referenced.find(|&i| i >= uvs.len()) {
+            panic!(
+                "A face references texture coordinate {}, but there are only {}!",
+                i,
+                uvs.len()
+            );
+        }
+        self.uvs = uvs;
+    }
+
+    pub fn set_color(&mut self, color: RGB) {
+        self.material.color = color;
+    }
+
+    /// Add a named material faces can refer to by the returned index.
+    /// A group with the same name is replaced.
+    pub fn add_material_group(&mut self, name: &str, material: Material) -> usize {
+        match self.groups.iter().position(|(n, _)| n == name) {
+            Some(i) => {
+                self.groups[i].1 = material;
+                i
+            }
+            None => {
+                self.groups.push((name.to_string(), material));
+                self.groups.len() - 1
+            }
+        }
+    }
+
+    /// Get the material of the group `name`.
+    pub fn material_group_mut(&mut self, name: &str) -> Option<&mut Material> {
+        self.groups
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, m)| m)
+    }
+
+    /// Names of all material groups, in the order of their index.
+    pub fn material_groups(&self) -> impl Iterator<Item = &str> {
+        self.groups.iter().map(|(n, _)| n.as_str())
+    }
+
+    /// Material of the face at a point in object space.
+    pub fn local_material_at(&self, point: Point) -> &Material {
+        match self.face_at(point) {
+            Some((face, _, _)) => self.face_material(face),
+            None => &self.material,
+        }
+    }
+
+    /// Material of `face`, the one of its group or of the mesh.
+    fn face_material(&self, face: &Face) -> &Material {
+        face.material
+            .and_then(|i| self.groups.get(i))
+            .map_or(&self.material, |(_, m)| m)
+    }
+
+    /// Normal of `face` in object space at the barycentric weights `u` and `v`.
+    fn face_normal_at(&self, face: &Face, u: Float, v: Float) -> Vector {
+        match face.normals {
+            Some(normals) => {
+                let [n1, n2, n3] = normals.map(|i| self.normals[i]);
+                (n1 * (1.0 - u - v) + n2 * u + n3 * v).normalize()
+            }
+            None => self.face_normal(face).normalize(),
+        }
+    }
+
+    /// Vertex positions.
+    pub fn vertices(&self) -> &[Point] {
+        &self.vertices
+    }
+
+    /// Vertex normals.
+    pub fn normals(&self) -> &[Vector] {
+        &self.normals
+    }
+
+    /// Texture coordinates.
+    pub fn uvs(&self) -> &[[Float; 2]] {
+        &self.uvs
+    }
+
+    /// Triangles.
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    /// Normal of a face scaled by twice its area.
+    fn face_normal(&self, face: &Face) -> Vector {
+        let [p1, p2, p3] = face.vertices.map(|i| self.vertices[i]);
+        (p2 - p1).cross(p3 - p1)
+    }
+
+    /// Give every flat shaded face area weighted vertex normals.
+    /// Only faces meeting within `crease_angle` (radians) are averaged,
+    /// sharper edges stay hard.
+    pub fn smooth_normals(&mut self, crease_angle: Float) {
+        let face_normals: Vec<Vector> = self.faces.iter().map(|f| self.face_normal(f)).collect();
+        let mut adjacent: Vec<Vec<usize>> = vec![Vec::new(); self.vertices.len()];
+        for (i, face) in self.faces.iter().enumerate() {
+            for v in face.vertices {
+                adjacent[v].push(i);
+            }
+        }
+
+        let min_cos = crease_angle.cos();
+        for i in 0..self.faces.len() {
+            if self.faces[i].normals.is_some() || face_normals[i].magnitude() == 0.0 {
+                continue;
+            }
+
+            let own = face_normals[i].normalize();
+            let mut corners = [0; 3];
+            for (corner, v) in self.faces[i].vertices.iter().enumerate() {
+                let mut sum = Vector::new(0.0, 0.0, 0.0);
+                for &other in &adjacent[*v] {
+                    let n = face_normals[other];
+                    if n.magnitude() > 0.0 && own.dot(n.normalize()) >= min_cos - EPSILON {
+                        sum = sum + n;
+                    }
+                }
+
+                corners[corner] = self.normals.len();
+                self.normals.push(sum.normalize());
+            }
+            self.faces[i].normals = Some(corners);
+        }
+    }
+
+    /// Move every vertex along its area weighted normal by `height` of its position.
+    /// Normals are recomputed afterwards with the given `crease_angle`,
+    /// since imported normals no longer match the displaced surface.
+    pub fn displace_by<F: Fn(Point) -> Float>(&mut self, height: F, crease_angle: Float) {
+        let mut vertex_normals = vec![Vector::new(0.0, 0.0, 0.0); self.vertices.len()];
+        for face in &self.faces {
+            let n = self.face_normal(face);
+            for v in face.vertices {
+                vertex_normals[v] = vertex_normals[v] + n;
+            }
+        }
+
+        let mut bounds = BoundingBox::empty();
+        for (v, n) in self.vertices.iter_mut().zip(vertex_normals) {
+            // vertices not used by any face have no direction to move in
+            if n.magnitude() > 0.0 {
+                *v = *v + n.normalize() * height(*v);
+            }
+            bounds.add_point(*v);
+        }
+        self.bounds = bounds;
+        self.build_hierarchy();
+
+        self.normals.clear();
+        for face in self.faces.iter_mut() {
+            face.normals = None;
+        }
+        self.smooth_normals(crease_angle);
+    }
+
+    /// Displace the vertices by the brightness of `pattern` times `scale`,
+    /// e.g. a gradient for slopes or a checkers pattern for embossing.
+    pub fn displace(&mut self, pattern: &dyn Pattern, scale: Float, crease_angle: Float) {
+        let inv = pattern
+            .get_transform()
+            .inverse()
+            .expect("Pattern transform should be invertible");
+
+        self.displace_by(
+            |p| {
+                let c = pattern.pattern_at(inv * p);
+                (c.red + c.green + c.blue) / 3.0 * scale
+            },
+            crease_angle,
+        );
+    }
+
+    /// Distance where `ray` hits `face` (Möller–Trumbore), with the
+    /// barycentric weights of the second and third corner.
+    fn intersect_face(&self, face: &Face, ray: &Ray) -> Option<(Float, Float, Float)> {
+        let [p1, p2, p3] = face.vertices.map(|i| self.vertices[i]);
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+
+        let dir_cross_e2 = ray.direction().cross(e2);
+        let det = e1.dot(dir_cross_e2);
+        if det.abs() < Float::EPSILON {
+            return None;
+        }
+
+        let f = 1.0 / det;
+        let p1_to_origin = ray.origin() - p1;
+        let u = f * p1_to_origin.dot(dir_cross_e2);
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let origin_cross_e1 = p1_to_origin.cross(e1);
+        let v = f * ray.direction().dot(origin_cross_e1);
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        Some((f * e2.dot(origin_cross_e1), u, v))
+    }
+
+    /// The face closest to `point` together with the barycentric weights of
+    /// its second and third corner. Searches all faces, hits know their face.
+    fn face_at(&self, point: Point) -> Option<(&Face, Float, Float)> {
+        let mut best: Option<(&Face, Float, Float)> = None;
+        let mut best_distance = Float::INFINITY;
+
+        for face in &self.faces {
+            let [p1, p2, p3] = face.vertices.map(|i| self.vertices[i]);
+            let (e1, e2, w) = (p2 - p1, p3 - p1, point - p1);
+            let (d00, d01, d11) = (e1.dot(e1), e1.dot(e2), e2.dot(e2));
+            let denom = d00 * d11 - d01 * d01;
+            if denom == 0.0 {
+                continue;
+            }
+
+            let (d20, d21) = (w.dot(e1), w.dot(e2));
+            let mut u = ((d11 * d20 - d01 * d21) / denom).max(0.0);
+            let mut v = ((d00 * d21 - d01 * d20) / denom).max(0.0);
+            if u + v > 1.0 {
+                let sum = u + v;
+                u /= sum;
+                v /= sum;
+            }
+
+            let distance = (point - (p1 + e1 * u + e2 * v)).magnitude();
+            if distance < best_distance {
+                best = Some((face, u, v));
+                best_distance = distance;
+            }
+        }
+
+        best
+    }
+}
+
+impl Shape for Mesh {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn parent_id(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent_id(&mut self, id: Uuid) {
+        self.parent = Some(id);
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        let mut hits: Vec<(Intersection, bool)> = Vec::new();
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            match self.nodes[node] {
+                MeshNode::Inner { bounds, first } => {
+                    if bounds.intersect(ray).is_some() {
+                        stack.extend([first, first + 1]);
+                    }
+                }
+                MeshNode::Leaf {
+                    bounds,
+                    first,
+                    count,
+                } => {
+                    if count == 0 || bounds.intersect(ray).is_none() {
+                        continue;
+                    }
+                    for &i in &self.order[first..first + count] {
+                        let face = &self.faces[i];
+                        if let Some((t, u, v)) = self.intersect_face(face, ray) {
+                            let front = self.face_normal(face).dot(ray.direction()) < 0.0;
+                            hits.push((Intersection::with_face(t, self, i, u, v), front));
+                        }
+                    }
+                }
+            }
+        }
+        hits.sort_by(|a, b| a.0.t.total_cmp(&b.0.t));
+        // a ray through a shared edge hits both faces, count the crossing once
+        hits.dedup_by(|b, a| float_eq(a.0.t, b.0.t) && a.1 == b.1);
+
+        if hits.is_empty() {
+            None
+        } else {
+            Some(hits.into_iter().map(|(x, _)| x).collect())
+        }
+    }
+
+    fn is_opaque(&self) -> bool {
+        self.material.is_opaque() && self.groups.iter().all(|(_, m)| m.is_opaque())
+    }
+
+    /// Searches the face closest to `point`, shading uses `normal_at_hit`.
+    /// A mesh without faces has no normal.
+    fn local_normal_at(&self, point: Point) -> Vector {
+        match self.face_at(point) {
+            Some((face, u, v)) => self.face_normal_at(face, u, v),
+            None => Vector::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    fn normal_at_hit(&self, hit: &Intersection, point: Point, w: Option<&World>) -> Vector {
+        let (face, u, v) = match hit.face {
+            Some((i, u, v)) => (&self.faces[i], u, v),
+            None => return self.normal_at(point, w),
+        };
+        let local_normal = self.face_normal_at(face, u, v);
+        match w {
+            Some(w) => self.normal_to_world(local_normal, w),
+            None => (self
+                .get_transform()
+                .inverse()
+                .expect("Transform should have an inverse!")
+                .as_matrix()
+                .transpose()
+                * local_normal)
+                .normalize(),
+        }
+    }
+
+    fn material_at_hit<'a>(
+        &'a self,
+        hit: &Intersection<'a>,
+        point: Point,
+        w: Option<&World>,
+    ) -> &'a Material {
+        match hit.face {
+            Some((i, _, _)) => self.face_material(&self.faces[i]),
+            None => self.material_at(point, w),
+        }
+    }
+
+    fn material_at(&self, point: Point, w: Option<&World>) -> &Material {
+        let local_point = match w {
+            Some(w) => self.world_to_object(point, w),
+            None => {
+                self.get_transform()
+                    .inverse()
+                    .expect("Transform should have an inverse!")
+                    * point
+            }
+        };
+        self.local_material_at(local_point)
+    }
+
+    fn local_uv_at(&self, point: Point) -> Option<Uv> {
+        let (face, u, v) = self.face_at(point)?;
+        // none until `set_uvs` gives the coordinates the face references
+        let [i1, i2, i3] = face.uvs?;
+        let (t1, t2, t3) = (self.uvs.get(i1)?, self.uvs.get(i2)?, self.uvs.get(i3)?);
+        let w = 1.0 - u - v;
+
+        Some(Uv::new(
+            UvFace::Side,
+            t1[0] * w + t2[0] * u + t3[0] * v,
+            t1[1] * w + t2[1] * u + t3[1] * v,
+        ))
+    }
+}
+
+impl PartialEq for Mesh {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consts::PI;
+
+    /// Two triangles folded along the x axis by 90 degrees.
+    fn fold() -> Mesh {
+        let vertices = vec![
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 0.0, -1.0),
+            Point::new(0.0, 1.0, 0.0),
+        ];
+        let faces = vec![Face::new([0, 1, 2]), Face::new([0, 1, 3])];
+        Mesh::new(vertices, Vec::new(), faces)
+    }
+
+    #[test]
+    fn intersect_mesh() {
+        let m = fold();
+        let r = Ray::new(Point::new(0.25, 5.0, -0.25), Vector::new(0.0, -1.0, 0.0));
+        let xs = m.local_intersect(&r).expect("Ray hits the floor triangle!");
+
+        assert_eq!(xs.len(), 1);
+        assert!(float_eq(xs[0].t, 5.0));
+
+        let r = Ray::new(Point::new(0.75, 5.0, -0.75), Vector::new(0.0, -1.0, 0.0));
+        assert!(m.local_intersect(&r).is_none());
+    }
+
+    #[test]
+    fn flat_normal_mesh() {
+        let m = fold();
+
+        assert_eq!(
+            m.local_normal_at(Point::new(0.25, 0.0, -0.25)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            m.local_normal_at(Point::new(0.25, 0.25, 0.0)),
+            Vector::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn smooth_normal_mesh() {
+        let mut m = fold();
+        m.smooth_normals(PI);
+
+        let n = m.local_normal_at(Point::new(0.5, 0.0, 0.0));
+        assert_eq!(n, Vector::new(0.0, 1.0, 1.0).normalize());
+        let n = m.local_normal_at(Point::new(0.0, 0.0, -1.0));
+        assert_eq!(n, Vector::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn face_hit_mesh() {
+        let mut m = fold();
+        m.smooth_normals(PI);
+        let r = Ray::new(Point::new(0.25, 5.0, -0.25), Vector::new(0.0, -1.0, 0.0));
+        let xs = m.intersect(&r).unwrap();
+
+        let (face, u, v) = xs[0].face.unwrap();
+        assert_eq!(face, 0);
+        assert!(float_eq(u, 0.25) && float_eq(v, 0.25));
+        let p = r.position(xs[0].t);
+        assert_eq!(xs[0].normal_at(p, None), m.local_normal_at(p));
+
+        // off the mesh the closest face is used
+        let n = m.local_normal_at(Point::new(5.0, -1.0, -0.5));
+        assert_eq!(n, m.local_normal_at(Point::new(1.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn uv_mesh() {
+        let mut m = fold();
+        assert!(m.local_uv_at(Point::new(0.25, 0.0, -0.25)).is_none());
+
+        m.set_uvs(vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+        m.faces[0].uvs = Some([0, 1, 2]);
+        let uv = m.local_uv_at(Point::new(0.25, 0.0, -0.5));
+
+        assert_eq!(uv, Some(Uv::new(UvFace::Side, 0.25, 0.5)));
+    }
+
+    #[test]
+    #[should_panic(expected = "texture coordinate 3, but there are only 3")]
+    fn invalid_uvs_mesh() {
+        let mut m = fold();
+        m.faces[0].uvs = Some([0, 1, 3]);
+        // the face has no coordinates before they are set
+        assert!(m.local_uv_at(Point::new(0.25, 0.0, -0.5)).is_none());
+        m.set_uvs(vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+    }
+
+    #[test]
+    fn material_groups_mesh() {
+        let mut m = fold();
+        let mut red = Material::default();
+        red.color = RGB::new(1.0, 0.0, 0.0);
+        let i = m.add_material_group("red", red);
+        m.faces[1].material = Some(i);
+        m.material_group_mut("red").unwrap().ambient = 1.0;
+
+        let floor = m.local_material_at(Point::new(0.25, 0.0, -0.25));
+        let wall = m.local_material_at(Point::new(0.25, 0.25, 0.0));
+        assert_eq!(floor, &Material::default());
+        assert_eq!(wall.color, RGB::new(1.0, 0.0, 0.0));
+        assert!(float_eq(wall.ambient, 1.0));
+        assert_eq!(m.material_groups().collect::<Vec<_>>(), vec!["red"]);
+    }
+
+    #[test]
+    fn material_groups_world() {
+        let mut m = fold();
+        let mut red = Material::default();
+        red.color = RGB::new(1.0, 0.0, 0.0);
+        red.ambient = 1.0;
+        red.diffuse = 0.0;
+        red.specular = 0.0;
+        let i = m.add_material_group("red", red);
+        m.faces[1].material = Some(i);
+        m.set_transform(Transformation::new().then_translation(0.0, 0.0, 1.0));
+
+        let mut w = World::new();
+        w.set_light(crate::PointLight::new(
+            Point::new(0.0, 0.0, -10.0),
+            crate::WHITE,
+        ));
+        w.add_object(Box::new(m));
+        let r = Ray::new(Point::new(0.25, 0.25, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert_eq!(
+            w.color_at(&r, crate::Budget::uniform(1)),
+            RGB::new(1.0, 0.0, 0.0)
+        );
+    }
+
+    /// A flat 2x2 grid of quads in the xz plane facing up.
+    fn grid() -> Mesh {
+        let mut vertices = Vec::new();
+        for z in 0..3 {
+            for x in 0..3 {
+                vertices.push(Point::new(x as Float - 1.0, 0.0, z as Float - 1.0));
+            }
+        }
+        let mut faces = Vec::new();
+        for z in 0..2 {
+            for x in 0..2 {
+                let i = z * 3 + x;
+                faces.push(Face::new([i, i + 3, i + 1]));
+                faces.push(Face::new([i + 1, i + 3, i + 4]));
+            }
+        }
+        Mesh::new(vertices, Vec::new(), faces)
+    }
+
+    #[test]
+    fn hierarchy_mesh() {
+        let mut m = grid();
+        m.displace_by(|p| (p.x * 3.0).sin() * 0.3 + p.z * 0.2, PI / 4.0);
+        assert!(m.nodes.len() > 1);
+
+        for i in 0..50 {
+            let x = (i as Float * 0.618).fract() * 2.4 - 1.2;
+            let z = (i as Float * 0.377).fract() * 2.4 - 1.2;
+            let r = Ray::new(Point::new(x, 5.0, z), Vector::new(0.1, -1.0, 0.05));
+            let brute = m
+                .faces
+                .iter()
+                .filter(|f| m.intersect_face(f, &r).is_some())
+                .count();
+            let xs = m.local_intersect(&r).map_or(0, |xs| xs.len());
+            assert_eq!(xs.min(1), brute.min(1));
+        }
+    }
+
+    #[test]
+    fn displace_mesh() {
+        let mut m = grid();
+        m.displace_by(|p| if p.x == 0.0 && p.z == 0.0 { 1.0 } else { 0.0 }, PI);
+
+        assert_eq!(m.vertices()[4], Point::new(0.0, 1.0, 0.0));
+        assert_eq!(m.vertices()[0], Point::new(-1.0, 0.0, -1.0));
+        assert_eq!(m.bounds().max, Point::new(1.0, 1.0, 1.0));
+        // the peak is smooth, the slopes lean away from it
+        assert_eq!(
+            m.local_normal_at(Point::new(0.0, 1.0, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        let r = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let xs = m.local_intersect(&r).expect("Ray hits the peak!");
+        assert!(float_eq(xs[0].t, 4.0));
+    }
+
+    #[test]
+    fn displace_pattern_mesh() {
+        let mut m = grid();
+        let mut p = crate::Gradient::gradient_pattern(crate::BLACK, crate::WHITE);
+        p.set_transform(
+            Transformation::new()
+                .then_scaling(2.0, 1.0, 1.0)
+                .then_translation(-1.0, 0.0, 0.0),
+        );
+        m.displace(&p, 2.0, 0.0);
+
+        assert_eq!(m.vertices()[0], Point::new(-1.0, 0.0, -1.0));
+        assert_eq!(m.vertices()[1], Point::new(0.0, 1.0, -1.0));
+        assert!(m.faces().iter().all(|f| f.normals.is_some()));
+    }
+
+    #[test]
+    fn crease_normal_mesh() {
+        let mut m = fold();
+        m.smooth_normals(PI / 4.0);
+
+        let n = m.local_normal_at(Point::new(0.5, 0.0, 0.0));
+        assert!(n == Vector::new(0.0, 1.0, 0.0) || n == Vector::new(0.0, 0.0, 1.0));
+        assert!(m.faces().iter().all(|f| f.normals.is_some()));
+    }
+}
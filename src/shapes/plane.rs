@@ -1,7 +1,10 @@
-use crate::{shapes::Shape, Intersection, Material, Point, Ray, Transformation, Vector, EPSILON};
+use crate::{
+    shapes::Shape, uv, BoundingBox, Float, Intersection, Material, Point, Ray, Transformation, Uv,
+    Vector, EPSILON,
+};
 use uuid::Uuid;
 
-/// A xz plan.
+/// A xz plane.
 #[derive(Debug)]
 pub struct Plane {
     uuid: Uuid,
@@ -15,12 +18,41 @@ pub struct Plane {
 impl Plane {
     pub fn new() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             transform: Transformation::new(),
             material: Material::default(),
             parent: None,
         }
     }
+
+    /// A plane of `Material::glass`.
+    pub fn glass() -> Self {
+        let mut s = Self::new();
+        s.material = Material::glass();
+        s
+    }
+
+    /// A plane of `Material::mirror`.
+    pub fn mirror() -> Self {
+        let mut s = Self::new();
+        s.material = Material::mirror();
+        s
+    }
+
+    /// Create a plane through `point` facing along `normal`.
+    /// The transformation rotating the xz plane into place is derived automatically.
+    pub fn from_point_normal(point: Point, normal: Vector) -> Self {
+        let n = normal.normalize();
+        // tilt (0,1,0) away from y, then swing it around y towards n
+        let theta = n.y.clamp(-1.0, 1.0).acos();
+        let phi = n.x.atan2(n.z);
+        let mut plane = Self::new();
+        plane.transform = Transformation::new()
+            .then_rotate_x(theta)
+            .then_rotate_y(phi)
+            .then_translation(point.x, point.y, point.z);
+        plane
+    }
 }
 
 impl Shape for Plane {
@@ -53,25 +85,44 @@ impl Shape for Plane {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point::new(Float::NEG_INFINITY, 0.0, Float::NEG_INFINITY),
+            Point::new(Float::INFINITY, 0.0, Float::INFINITY),
+        )
+    }
+
     fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection>> {
-        if ray.direction.y.abs() < EPSILON {
-            return None;
+        let mut xs = Vec::with_capacity(1);
+        self.local_intersect_into(ray, &mut xs);
+        (!xs.is_empty()).then_some(xs)
+    }
+
+    fn local_intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if ray.direction().y.abs() < EPSILON {
+            return;
         }
-        let t = -ray.origin.y / ray.direction.y;
-        Some(vec![Intersection { t, object: self }])
+        let t = -ray.origin().y / ray.direction().y;
+        xs.push(Intersection::new(t, self));
     }
 
     fn local_normal_at(&self, _point: Point) -> Vector {
         Vector::new(0.0, 1.0, 0.0)
     }
+
+    fn local_uv_at(&self, point: Point) -> Option<Uv> {
+        Some(uv::planar_map(point))
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::float_eq;
 
     #[test]
     fn normal_const_plane() {
@@ -85,6 +136,34 @@ mod test {
         assert_eq!(n3, Vector::new(0.0, 1.0, 0.0));
     }
 
+    #[test]
+    fn point_normal_plane() {
+        let normals = vec![
+            Vector::new(0.0, 1.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0, -1.0),
+            Vector::new(1.0, 1.0, 1.0).normalize(),
+        ];
+        for n in normals {
+            let p = Plane::from_point_normal(Point::new(1.0, 2.0, 3.0), n);
+
+            assert_eq!(p.normal_at(Point::new(1.0, 2.0, 3.0), None), n);
+        }
+    }
+
+    #[test]
+    fn intersect_point_normal_plane() {
+        let p = Plane::from_point_normal(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -2.0));
+        let r = Ray::new(Point::new(1.0, 1.0, 0.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = p.intersect(&r);
+        assert!(xs.is_some());
+        let xs = xs.unwrap();
+
+        assert_eq!(xs.len(), 1);
+        assert!(float_eq(xs[0].t, 5.0));
+    }
+
     #[test]
     fn intersect_parallel_plane() {
         let p = Plane::new();
@@ -119,4 +198,12 @@ mod test {
         assert_eq!(xs[0].t, 1.0);
         assert_eq!(xs[0].object.id(), p.id());
     }
+
+    #[test]
+    fn uv_plane() {
+        let p = Plane::new();
+
+        let uv = p.local_uv_at(Point::new(-0.25, 0.0, 2.5)).unwrap();
+        assert_eq!(uv, Uv::new(crate::UvFace::Side, 0.75, 0.5));
+    }
 }
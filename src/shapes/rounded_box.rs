@@ -0,0 +1,166 @@
+use crate::{
+    shapes::{sdf, Shape},
+    BoundingBox, Float, Intersection, Material, Point, Ray, Transformation, Vector, RGB,
+};
+use uuid::Uuid;
+
+/// A cube spanning [-1,1] on every axis with rounded edges and corners.
+/// Bevels catch specular highlights, which sharp cubes never do.
+#[derive(Debug)]
+pub struct RoundedBox {
+    /// Unique id.
+    uuid: Uuid,
+
+    /// Transformation matrix
+    transform: Transformation,
+
+    /// The material of a rounded box
+    material: Material,
+
+    /// Radius of the rounded edges.
+    radius: Float,
+
+    /// Parent id
+    parent: Option<Uuid>,
+}
+
+impl RoundedBox {
+    /// Create a new rounded box, `radius` is clamped to [0,1].
+    pub fn new(radius: Float) -> Self {
+        Self {
+            uuid: crate::new_id(),
+            transform: Transformation::new(),
+            material: Material::default(),
+            radius: radius.clamp(0.0, 1.0),
+            parent: None,
+        }
+    }
+
+    pub fn set_color(&mut self, color: RGB) {
+        self.material.color = color;
+    }
+
+    /// Radius of the rounded edges.
+    pub fn radius(&self) -> Float {
+        self.radius
+    }
+
+    /// Signed distance from `p` to the surface, negative inside.
+    fn sdf(&self, p: Point) -> Float {
+        let half = 1.0 - self.radius;
+        let q = Vector::new(p.x.abs() - half, p.y.abs() - half, p.z.abs() - half);
+        let outside = Vector::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).magnitude();
+        let inside = q.x.max(q.y).max(q.z).min(0.0);
+
+        outside + inside - self.radius
+    }
+}
+
+impl Shape for RoundedBox {
+    fn id(&self) -> Uuid {
+        self.uuid
+    }
+
+    fn parent_id(&self) -> Option<Uuid> {
+        self.parent
+    }
+
+    fn set_parent_id(&mut self, id: Uuid) {
+        self.parent = Some(id);
+    }
+
+    fn get_material(&self) -> &Material {
+        &self.material
+    }
+
+    fn get_material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn set_material(&mut self, m: Material) {
+        self.material = m;
+    }
+
+    fn get_transform(&self) -> Transformation {
+        self.transform
+    }
+
+    fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
+        self.transform = t;
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        let xs: Vec<Intersection> = sdf::march(|p| self.sdf(p), ray, &self.bounds())
+            .into_iter()
+            .map(|t| Intersection::new(t, self))
+            .collect();
+
+        if xs.is_empty() {
+            None
+        } else {
+            Some(xs)
+        }
+    }
+
+    fn local_normal_at(&self, point: Point) -> Vector {
+        sdf::gradient(|p| self.sdf(p), point)
+    }
+}
+
+impl PartialEq for RoundedBox {
+    fn eq(&self, other: &Self) -> bool {
+        self.uuid == other.uuid
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::float_eq;
+
+    #[test]
+    fn intersect_face_rounded_box() {
+        let b = RoundedBox::new(0.2);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = b.local_intersect(&r).expect("Ray points at the box!");
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.0));
+        assert!(float_eq(xs[1].t, 6.0));
+    }
+
+    #[test]
+    fn corner_rounded_box() {
+        let b = RoundedBox::new(0.5);
+        let corner = Ray::new(Point::new(0.9, 0.9, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let edge = Ray::new(Point::new(0.9, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+
+        assert!(b.local_intersect(&corner).is_none());
+        let xs = b
+            .local_intersect(&edge)
+            .expect("Ray hits the rounded edge!");
+        // circle of radius 0.5 around x=0.5, z=-0.5
+        let dz = (0.25 as Float - 0.16).sqrt();
+        assert!(float_eq(xs[0].t, 5.0 - 0.5 - dz));
+    }
+
+    #[test]
+    fn normal_rounded_box() {
+        let b = RoundedBox::new(0.5);
+
+        assert_eq!(
+            b.local_normal_at(Point::new(0.0, 1.0, 0.0)),
+            Vector::new(0.0, 1.0, 0.0)
+        );
+        let corner = 0.5 + 0.5 / (3.0 as Float).sqrt();
+        assert_eq!(
+            b.local_normal_at(Point::new(corner, corner, corner)),
+            Vector::new(1.0, 1.0, 1.0).normalize()
+        );
+    }
+}
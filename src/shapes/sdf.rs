@@ -0,0 +1,131 @@
+use crate::{BoundingBox, Float, Point, Ray, Vector};
+
+/// Smallest step taken while marching, keeps rays from stalling at the surface.
+const MIN_STEP: Float = 1e-5;
+
+/// Upper limit of steps per ray.
+const MAX_STEPS: usize = 1024;
+
+/// Bisection rounds used to refine a surface crossing.
+const REFINE_STEPS: usize = 32;
+
+/// Sphere trace `ray` through the signed distance field `sdf` inside `bounds`.
+/// Returns the distances of all surface crossings in ascending order, so
+/// marched shapes report entry and exit like analytic shapes do.
+/// A ray still inside once `MAX_STEPS` are used up leaves where it stopped.
+pub fn march<F: Fn(Point) -> Float>(sdf: F, ray: &Ray, bounds: &BoundingBox) -> Vec<Float> {
+    let mut xs = Vec::new();
+    let scale = ray.direction().magnitude();
+    if scale == 0.0 {
+        return xs;
+    }
+
+    let (tmin, tmax) = match bounds.intersect(ray) {
+        Some(range) => range,
+        None => return xs,
+    };
+    // faces lying on the box itself must be sampled on both sides
+    let pad = 10.0 * MIN_STEP / scale;
+    let tmax = tmax + pad;
+
+    let mut t = tmin - pad;
+    let mut d = sdf(ray.position(t));
+    for _ in 0..MAX_STEPS {
+        if t > tmax {
+            break;
+        }
+
+        let next = t + d.abs().max(MIN_STEP) / scale;
+        let next_d = sdf(ray.position(next));
+        if (d <= 0.0) != (next_d <= 0.0) {
+            xs.push(refine(&sdf, ray, t, next));
+        }
+
+        t = next;
+        d = next_d;
+    }
+    // out of steps inside the shape, close the interval
+    if d <= 0.0 {
+        xs.push(t.min(tmax));
+    }
+
+    xs
+}
+
+/// Bisect the crossing between `lo` and `hi`.
+fn refine<F: Fn(Point) -> Float>(sdf: &F, ray: &Ray, mut lo: Float, mut hi: Float) -> Float {
+    let inside = sdf(ray.position(lo)) <= 0.0;
+    for _ in 0..REFINE_STEPS {
+        let mid = (lo + hi) / 2.0;
+        if (sdf(ray.position(mid)) <= 0.0) == inside {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// Normal of the surface of `sdf` at `point` by central differences.
+pub fn gradient<F: Fn(Point) -> Float>(sdf: F, point: Point) -> Vector {
+    let dx = Vector::new(MIN_STEP, 0.0, 0.0);
+    let dy = Vector::new(0.0, MIN_STEP, 0.0);
+    let dz = Vector::new(0.0, 0.0, MIN_STEP);
+
+    Vector::new(
+        sdf(point + dx) - sdf(point - dx),
+        sdf(point + dy) - sdf(point - dy),
+        sdf(point + dz) - sdf(point - dz),
+    )
+    .normalize()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::float_eq;
+
+    fn unit_sphere(p: Point) -> Float {
+        (p - Point::new(0.0, 0.0, 0.0)).magnitude() - 1.0
+    }
+
+    #[test]
+    fn march_sphere() {
+        let bounds = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let xs = march(unit_sphere, &r, &bounds);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0], 4.0));
+        assert!(float_eq(xs[1], 6.0));
+
+        let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 0.0, 2.0));
+        let xs = march(unit_sphere, &r, &bounds);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0], -0.5));
+        assert!(float_eq(xs[1], 0.5));
+
+        let r = Ray::new(Point::new(0.0, 2.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(march(unit_sphere, &r, &bounds).is_empty());
+    }
+
+    #[test]
+    fn march_out_of_steps() {
+        let bounds = BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        // far too flat to cross the sphere in MAX_STEPS minimal steps
+        let xs = march(|p| unit_sphere(p) * 1e-6, &r, &bounds);
+
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0], 4.0));
+        assert!(xs[1] > xs[0] && xs[1] < 6.0);
+    }
+
+    #[test]
+    fn gradient_sphere() {
+        let n = gradient(unit_sphere, Point::new(0.0, 0.0, 1.0));
+
+        assert_eq!(n, Vector::new(0.0, 0.0, 1.0));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{shapes::Shape, Intersection, Material, Point, Ray, Transformation, Vector, RGB};
+use crate::{
+    shapes::Shape, uv, BoundingBox, Intersection, Material, Point, Ray, Transformation, Uv, Vector,
+    RGB,
+};
 use uuid::Uuid;
 
 /// A sphere.
@@ -21,7 +24,7 @@ impl Sphere {
     /// Create a new sphere.
     pub fn new() -> Self {
         Self {
-            uuid: Uuid::new_v4(),
+            uuid: crate::new_id(),
             transform: Transformation::new(),
             material: Material::default(),
             parent: None,
@@ -32,16 +35,22 @@ impl Sphere {
         self.material.color = color;
     }
 
+    /// A sphere of `Material::glass`.
+    pub fn glass() -> Self {
+        let mut s = Self::new();
+        s.material = Material::glass();
+        s
+    }
+
+    /// A sphere of `Material::mirror`.
+    pub fn mirror() -> Self {
+        let mut s = Self::new();
+        s.material = Material::mirror();
+        s
+    }
+
     pub fn glass_sphere() -> Self {
-        let mut m = Material::default();
-        m.transparency = 1.0;
-        m.refractive_index = 1.5;
-        Self {
-            uuid: Uuid::new_v4(),
-            transform: Transformation::new(),
-            material: m,
-            parent: None,
-        }
+        Self::glass()
     }
 }
 
@@ -75,31 +84,44 @@ impl Shape for Sphere {
     }
 
     fn set_transform(&mut self, t: Transformation) {
+        t.assert_valid();
         self.transform = t;
     }
 
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0))
+    }
+
     fn local_intersect(&self, ray: &Ray) -> Option<Vec<Intersection>> {
-        let sphere_to_ray = ray.origin - Point::new(0.0, 0.0, 0.0);
-        let a = ray.direction.dot(ray.direction);
-        let b = 2.0 * ray.direction.dot(sphere_to_ray);
+        let mut xs = Vec::with_capacity(2);
+        self.local_intersect_into(ray, &mut xs);
+        (!xs.is_empty()).then_some(xs)
+    }
+
+    fn local_intersect_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        let sphere_to_ray = ray.origin() - Point::new(0.0, 0.0, 0.0);
+        let a = ray.direction().dot(ray.direction());
+        let b = 2.0 * ray.direction().dot(sphere_to_ray);
         let c = sphere_to_ray.dot(sphere_to_ray) - 1.0;
         let discriminant = b * b - 4.0 * a * c;
 
         if discriminant < 0.0 {
-            return None;
+            return;
         }
 
         let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
         let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-        Some(vec![
-            Intersection::new(t1, self),
-            Intersection::new(t2, self),
-        ])
+        xs.push(Intersection::new(t1, self));
+        xs.push(Intersection::new(t2, self));
     }
 
     fn local_normal_at(&self, point: Point) -> Vector {
         point - Point::new(0.0, 0.0, 0.0)
     }
+
+    fn local_uv_at(&self, point: Point) -> Option<Uv> {
+        Some(uv::spherical_map(point))
+    }
 }
 
 impl PartialEq for Sphere {
@@ -110,10 +132,10 @@ impl PartialEq for Sphere {
 
 #[cfg(test)]
 mod test {
-    use std::f64::consts::PI;
+    use crate::consts::{FRAC_1_SQRT_2, PI};
 
     use super::*;
-    use crate::{Point, Ray, Transformation, Vector};
+    use crate::{Float, Point, Ray, Transformation, Vector};
 
     #[test]
     fn unique_sphere() {
@@ -200,7 +222,7 @@ mod test {
     fn intersect_scaled_sphere() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let mut s = Sphere::new();
-        s.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+        s.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
         let xs = s.intersect(&r);
         assert!(xs.is_some());
         let xs = xs.unwrap();
@@ -214,7 +236,7 @@ mod test {
     fn intersect_translated_sphere() {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let mut s = Sphere::new();
-        s.set_transform(Transformation::new().translation(5.0, 0.0, 0.0));
+        s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
         let xs = s.intersect(&r);
         assert!(xs.is_none());
     }
@@ -247,13 +269,21 @@ mod test {
     fn normal_notaxial_sphere() {
         let s = Sphere::new();
         let n = s.normal_at(
-            Point::new(3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0),
+            Point::new(
+                (3.0 as Float).sqrt() / 3.0,
+                (3.0 as Float).sqrt() / 3.0,
+                (3.0 as Float).sqrt() / 3.0,
+            ),
             None,
         );
 
         assert_eq!(
             n,
-            Vector::new(3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0)
+            Vector::new(
+                (3.0 as Float).sqrt() / 3.0,
+                (3.0 as Float).sqrt() / 3.0,
+                (3.0 as Float).sqrt() / 3.0
+            )
         );
     }
 
@@ -261,7 +291,11 @@ mod test {
     fn normal_normalize_sphere() {
         let s = Sphere::new();
         let n = s.normal_at(
-            Point::new(3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0),
+            Point::new(
+                (3.0 as Float).sqrt() / 3.0,
+                (3.0 as Float).sqrt() / 3.0,
+                (3.0 as Float).sqrt() / 3.0,
+            ),
             None,
         );
 
@@ -271,20 +305,24 @@ mod test {
     #[test]
     fn normal_translated_sphere() {
         let mut s = Sphere::new();
-        s.set_transform(Transformation::new().translation(0.0, 1.0, 0.0));
-        let n = s.normal_at(Point::new(0.0, 1.70711, -0.70711), None);
+        s.set_transform(Transformation::new().then_translation(0.0, 1.0, 0.0));
+        let n = s.normal_at(Point::new(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2), None);
 
-        assert_eq!(n, Vector::new(0.0, 0.70711, -0.70711));
+        assert_eq!(n, Vector::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
     }
 
     #[test]
     fn normal_transformed_sphere() {
         let mut s = Sphere::new();
-        let t1 = Transformation::new().scaling(1.0, 0.5, 1.0);
-        let t2 = Transformation::new().rotate_z(PI / 5.0);
+        let t1 = Transformation::new().then_scaling(1.0, 0.5, 1.0);
+        let t2 = Transformation::new().then_rotate_z(PI / 5.0);
         s.set_transform(t1 * t2);
         let n = s.normal_at(
-            Point::new(0.0, 2_f64.sqrt() / 2.0, -(2_f64.sqrt()) / 2.0),
+            Point::new(
+                0.0,
+                (2.0 as Float).sqrt() / 2.0,
+                -((2.0 as Float).sqrt()) / 2.0,
+            ),
             None,
         );
 
@@ -298,4 +336,12 @@ mod test {
         assert_eq!(s.material.transparency, 1.0);
         assert_eq!(s.material.refractive_index, 1.5);
     }
+
+    #[test]
+    fn uv_sphere() {
+        let s = Sphere::new();
+
+        let uv = s.local_uv_at(Point::new(1.0, 0.0, 0.0)).unwrap();
+        assert_eq!(uv, Uv::new(crate::UvFace::Side, 0.25, 0.5));
+    }
 }
@@ -1,34 +1,137 @@
+use crate::consts::PI;
+use crate::matrix::ParseMatrixError;
 use crate::*;
+use std::error::Error;
+use std::fmt;
 use std::ops::Mul;
+use std::str::FromStr;
+
+/// Reasons a transformation can not place an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformError {
+    /// The transformation collapses space, e.g. an axis is scaled by 0.
+    Singular,
+
+    /// An entry is NaN or infinite.
+    NonFinite,
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformError::Singular => {
+                write!(
+                    f,
+                    "Transformation is not invertible, is an axis scaled by 0?"
+                )
+            }
+            TransformError::NonFinite => write!(f, "Transformation contains NaN or infinity"),
+        }
+    }
+}
+
+impl Error for TransformError {}
 
 /// The transformation object describes a general transformation on any object.
 /// The abstraction happens since I did not implement the proper tuple as described
 /// by the book.
+///
+/// The `then_*` builder methods apply in the order they are written:
+/// `Transformation::new().then_scaling(2.0, 2.0, 2.0).then_translation(1.0, 0.0, 0.0)` first
+/// scales and then moves, every step is multiplied on the left. The book writes
+/// the same chain as `translation * scaling`, read from right to left, so the
+/// old builder names without `then_` that hid this order are deprecated. To combine
+/// whole transformations say which one comes first with `then` or `pre`
+/// instead of multiplying them.
 #[derive(Debug, Clone, Copy)]
 pub struct Transformation {
-    data: [[f64; 4]; 4],
+    data: [[Float; 4]; 4],
+
+    /// Inverse of `data`, None if singular. Every step of the builder knows its
+    /// inverse, so shapes, patterns and cameras don't pay for a generic inversion.
+    inverse: Option<[[Float; 4]; 4]>,
 }
 
 impl Transformation {
     /// Create a new Transformation object.
     pub fn new() -> Self {
         Self {
-            data: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
+            data: IDENTITY.data,
+            inverse: Some(IDENTITY.data),
         }
     }
 
-    /// Instantiate the Transformation as a Matrix
-    pub fn init(&self) -> Matrix {
+    /// Create a Transformation from a Matrix computed elsewhere,
+    /// e.g. by a physics engine or a node of a scene file.
+    pub fn from_matrix(m: Matrix) -> Self {
+        Self {
+            data: m.get_data(),
+            inverse: m.inverse(4).map(|i| i.get_data()),
+        }
+    }
+
+    /// A rotation, its inverse is the transpose.
+    fn rotation(data: [[Float; 4]; 4]) -> Self {
+        Self {
+            data,
+            inverse: Some(Matrix::new(data).transpose().get_data()),
+        }
+    }
+
+    /// Return the Transformation as a Matrix.
+    pub fn as_matrix(&self) -> Matrix {
         Matrix::new(self.data)
     }
 
+    /// Instantiate the Transformation as a Matrix, same as `as_matrix`.
+    pub fn init(&self) -> Matrix {
+        self.as_matrix()
+    }
+
+    /// Calculate the determinant of the transformation.
+    pub fn determinant(&self) -> Float {
+        self.as_matrix().determinant_4x4()
+    }
+
+    /// Calculate the inverse transformation.
+    /// Translations, rotations and shearing are always invertible,
+    /// only scaling by 0 (or a view from a point onto itself) fails with
+    /// `TransformError::Singular`, NaN or infinite entries with `TransformError::NonFinite`.
+    /// The inverse is kept along with the transformation, so this is cheap.
+    pub fn inverse(&self) -> Result<Self, TransformError> {
+        self.validate()?;
+        match self.inverse {
+            Some(inverse) => Ok(Self {
+                data: inverse,
+                inverse: Some(self.data),
+            }),
+            None => Err(TransformError::Singular),
+        }
+    }
+
+    /// Check that the transformation can place an object,
+    /// i.e. all entries are finite and it is invertible.
+    pub fn validate(&self) -> Result<(), TransformError> {
+        if !self.data.iter().flatten().all(|v| v.is_finite()) {
+            return Err(TransformError::NonFinite);
+        }
+        if self.inverse.is_none() {
+            return Err(TransformError::Singular);
+        }
+        Ok(())
+    }
+
+    /// Panic with a clear message if the transformation is degenerate.
+    /// Shapes and patterns call this when their transform is set, so a broken
+    /// scene fails while it is built and not in the middle of a render.
+    pub fn assert_valid(&self) {
+        if let Err(why) = self.validate() {
+            panic!("Invalid transformation: {}\n{}", why, self);
+        }
+    }
+
     /// A translation moves a point.
-    pub fn translation(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn then_translation(self, x: Float, y: Float, z: Float) -> Self {
         let trans = Self {
             data: [
                 [1.0, 0.0, 0.0, x],
@@ -36,13 +139,19 @@ impl Transformation {
                 [0.0, 0.0, 1.0, z],
                 [0.0, 0.0, 0.0, 1.0],
             ],
+            inverse: Some([
+                [1.0, 0.0, 0.0, -x],
+                [0.0, 1.0, 0.0, -y],
+                [0.0, 0.0, 1.0, -z],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
         };
 
         trans * self
     }
 
     /// Scales all points of an object.
-    pub fn scaling(self, x: f64, y: f64, z: f64) -> Self {
+    pub fn then_scaling(self, x: Float, y: Float, z: Float) -> Self {
         let scale = Self {
             data: [
                 [x, 0.0, 0.0, 0.0],
@@ -50,81 +159,264 @@ impl Transformation {
                 [0.0, 0.0, z, 0.0],
                 [0.0, 0.0, 0.0, 1.0],
             ],
+            inverse: (x != 0.0 && y != 0.0 && z != 0.0).then_some([
+                [1.0 / x, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / y, 0.0, 0.0],
+                [0.0, 0.0, 1.0 / z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
         };
 
         scale * self
     }
 
     /// Rotation around the x axis. Units are in radians.
-    pub fn rotate_x(self, rad: f64) -> Self {
-        let rot = Self {
-            data: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, rad.cos(), -rad.sin(), 0.0],
-                [0.0, rad.sin(), rad.cos(), 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
-        };
+    pub fn then_rotate_x(self, rad: Float) -> Self {
+        let rot = Self::rotation([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, rad.cos(), -rad.sin(), 0.0],
+            [0.0, rad.sin(), rad.cos(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
 
         rot * self
     }
 
     /// Rotation around the y axis. Units are in radians.
-    pub fn rotate_y(self, rad: f64) -> Self {
-        let rot = Self {
-            data: [
-                [rad.cos(), 0.0, rad.sin(), 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [-rad.sin(), 0.0, rad.cos(), 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
-        };
+    pub fn then_rotate_y(self, rad: Float) -> Self {
+        let rot = Self::rotation([
+            [rad.cos(), 0.0, rad.sin(), 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-rad.sin(), 0.0, rad.cos(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
 
         rot * self
     }
 
     /// Rotation around the z axis. Units are in radians.
-    pub fn rotate_z(self, rad: f64) -> Self {
-        let rot = Self {
-            data: [
-                [rad.cos(), -rad.sin(), 0.0, 0.0],
-                [rad.sin(), rad.cos(), 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
-        };
+    pub fn then_rotate_z(self, rad: Float) -> Self {
+        let rot = Self::rotation([
+            [rad.cos(), -rad.sin(), 0.0, 0.0],
+            [rad.sin(), rad.cos(), 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
 
         rot * self
     }
 
-    /// Shearing transforms an object in respect to its coordinates.
-    pub fn shearing(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Self {
-        let shear = Self {
-            data: [
-                [1.0, xy, xz, 0.0],
-                [yx, 1.0, yz, 0.0],
-                [zx, zy, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 0.0],
+    /// Rotation around an arbitrary axis through the origin (Rodrigues' formula).
+    /// Units are in radians, the axis does not need to be normalized.
+    pub fn then_rotate_axis(self, axis: Vector, rad: Float) -> Self {
+        let Vector { x, y, z } = axis.normalize();
+        let (s, c) = rad.sin_cos();
+        let t = 1.0 - c;
+        let rot = Self::rotation([
+            [t * x * x + c, t * x * y - s * z, t * x * z + s * y, 0.0],
+            [t * x * y + s * z, t * y * y + c, t * y * z - s * x, 0.0],
+            [t * x * z - s * y, t * y * z + s * x, t * z * z + c, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        rot * self
+    }
+
+    /// Shortest rotation turning the direction `from` into `to`,
+    /// e.g. `align(Vector::new(0.0, 1.0, 0.0), dir)` points a cylinder along `dir`.
+    pub fn align(self, from: Vector, to: Vector) -> Self {
+        let from = from.normalize();
+        let to = to.normalize();
+        let cos = from.dot(to).clamp(-1.0, 1.0);
+        let axis = from.cross(to);
+        if axis.magnitude() > EPSILON {
+            return self.then_rotate_axis(axis, cos.acos());
+        }
+        if cos > 0.0 {
+            return self;
+        }
+
+        // opposite directions, turn half way around any perpendicular axis
+        let other = if from.x.abs() < 0.9 {
+            Vector::new(1.0, 0.0, 0.0)
+        } else {
+            Vector::new(0.0, 1.0, 0.0)
+        };
+        self.then_rotate_axis(from.cross(other), PI)
+    }
+
+    /// Mirror across the plane through `point` with the `normal`,
+    /// e.g. for the other half of a symmetric scene.
+    /// Normals are transformed with the inverse transpose as usual, so they
+    /// still point out of the mirrored object, see `is_mirroring`.
+    pub fn reflect_across_plane(self, point: Point, normal: Vector) -> Self {
+        let n = normal.normalize();
+        let d = 2.0 * (point - Point::new(0.0, 0.0, 0.0)).dot(n);
+        let data = [
+            [
+                1.0 - 2.0 * n.x * n.x,
+                -2.0 * n.x * n.y,
+                -2.0 * n.x * n.z,
+                d * n.x,
             ],
+            [
+                -2.0 * n.y * n.x,
+                1.0 - 2.0 * n.y * n.y,
+                -2.0 * n.y * n.z,
+                d * n.y,
+            ],
+            [
+                -2.0 * n.z * n.x,
+                -2.0 * n.z * n.y,
+                1.0 - 2.0 * n.z * n.z,
+                d * n.z,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // mirroring twice changes nothing
+        let mirror = Self {
+            data,
+            inverse: Some(data),
         };
 
+        mirror * self
+    }
+
+    /// Does the transformation flip the handedness of space, i.e. mirror it?
+    /// Triangles of a mirrored mesh appear with the opposite winding order.
+    pub fn is_mirroring(&self) -> bool {
+        self.determinant() < 0.0
+    }
+
+    /// Shearing transforms an object in respect to its coordinates.
+    pub fn then_shearing(
+        self,
+        xy: Float,
+        xz: Float,
+        yx: Float,
+        yz: Float,
+        zx: Float,
+        zy: Float,
+    ) -> Self {
+        let shear = Self::from_matrix(Matrix::new([
+            [1.0, xy, xz, 0.0],
+            [yx, 1.0, yz, 0.0],
+            [zx, zy, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]));
+
         shear * self
     }
 
+    /// Axis and angle in radians of the rotation part, see `Quaternion::to_axis_angle`.
+    pub fn rotation_axis_angle(&self) -> (Vector, Float) {
+        Quaternion::from_transformation(self).to_axis_angle()
+    }
+
+    /// Angles in radians `(x, y, z)` of the rotation part, such that
+    /// `Transformation::new().then_rotate_x(x).then_rotate_y(y).then_rotate_z(z)` rotates the same.
+    /// x and z are in [-π, π] and y in [-π/2, π/2], at y = ±π/2 z is 0.
+    pub fn rotation_euler(&self) -> (Float, Float, Float) {
+        let r = Quaternion::from_transformation(self)
+            .to_transformation()
+            .as_matrix();
+        let y = (-r[2][0]).clamp(-1.0, 1.0).asin();
+        if r[2][0].abs() < 1.0 - EPSILON {
+            (r[2][1].atan2(r[2][2]), y, r[1][0].atan2(r[0][0]))
+        } else {
+            // gimbal lock, x and z turn around the same axis
+            ((-r[1][2]).atan2(r[1][1]), y, 0.0)
+        }
+    }
+
+    /// Apply `next` after this transformation, like the builder methods do.
+    /// Same as `next * self`.
+    pub fn then(self, next: Transformation) -> Self {
+        next * self
+    }
+
+    /// Apply `first` before this transformation.
+    /// Same as `self * first`, the order of the book.
+    pub fn pre(self, first: Transformation) -> Self {
+        self * first
+    }
+
+    /// Same as `then_translation`, the step is applied after the steps before it.
+    #[deprecated(
+        note = "applies after the previous steps, not like the book's `*`, use `then_translation`"
+    )]
+    pub fn translation(self, x: Float, y: Float, z: Float) -> Self {
+        self.then_translation(x, y, z)
+    }
+
+    /// Same as `then_scaling`, the step is applied after the steps before it.
+    #[deprecated(
+        note = "applies after the previous steps, not like the book's `*`, use `then_scaling`"
+    )]
+    pub fn scaling(self, x: Float, y: Float, z: Float) -> Self {
+        self.then_scaling(x, y, z)
+    }
+
+    /// Same as `then_rotate_x`, the step is applied after the steps before it.
+    #[deprecated(
+        note = "applies after the previous steps, not like the book's `*`, use `then_rotate_x`"
+    )]
+    pub fn rotate_x(self, rad: Float) -> Self {
+        self.then_rotate_x(rad)
+    }
+
+    /// Same as `then_rotate_y`, the step is applied after the steps before it.
+    #[deprecated(
+        note = "applies after the previous steps, not like the book's `*`, use `then_rotate_y`"
+    )]
+    pub fn rotate_y(self, rad: Float) -> Self {
+        self.then_rotate_y(rad)
+    }
+
+    /// Same as `then_rotate_z`, the step is applied after the steps before it.
+    #[deprecated(
+        note = "applies after the previous steps, not like the book's `*`, use `then_rotate_z`"
+    )]
+    pub fn rotate_z(self, rad: Float) -> Self {
+        self.then_rotate_z(rad)
+    }
+
+    /// Same as `then_rotate_axis`, the step is applied after the steps before it.
+    #[deprecated(
+        note = "applies after the previous steps, not like the book's `*`, use `then_rotate_axis`"
+    )]
+    pub fn rotate_axis(self, axis: Vector, rad: Float) -> Self {
+        self.then_rotate_axis(axis, rad)
+    }
+
+    /// Same as `then_shearing`, the step is applied after the steps before it.
+    #[deprecated(
+        note = "applies after the previous steps, not like the book's `*`, use `then_shearing`"
+    )]
+    pub fn shearing(
+        self,
+        xy: Float,
+        xz: Float,
+        yx: Float,
+        yz: Float,
+        zx: Float,
+        zy: Float,
+    ) -> Self {
+        self.then_shearing(xy, xz, yx, yz, zx, zy)
+    }
+
     /// Create a Transformation that orients the world relative to the camera.
     pub fn view_transformation(from: Point, to: Point, up: Vector) -> Self {
         let forward = (to - from).normalize();
         let left = forward.cross(up.normalize());
         let true_up = left.cross(forward);
-        let orientation = Transformation {
-            data: [
-                [left.x, left.y, left.z, 0.0],
-                [true_up.x, true_up.y, true_up.z, 0.0],
-                [-forward.x, -forward.y, -forward.z, 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
-        };
-        let translation = Transformation::new().translation(-from.x, -from.y, -from.z);
+        let orientation = Transformation::from_matrix(Matrix::new([
+            [left.x, left.y, left.z, 0.0],
+            [true_up.x, true_up.y, true_up.z, 0.0],
+            [-forward.x, -forward.y, -forward.z, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]));
+        let translation = Transformation::new().then_translation(-from.x, -from.y, -from.z);
 
         orientation * translation
     }
@@ -134,7 +426,25 @@ impl Mul<Transformation> for Transformation {
     type Output = Transformation;
     fn mul(self, rhs: Transformation) -> Self::Output {
         let data = (self.init() * rhs.init()).get_data();
-        Self { data }
+        let inverse = match (self.inverse, rhs.inverse) {
+            (Some(a), Some(b)) => Some((Matrix::new(b) * Matrix::new(a)).get_data()),
+            _ => None,
+        };
+        Self { data, inverse }
+    }
+}
+
+impl Mul<Point> for Transformation {
+    type Output = Point;
+    fn mul(self, rhs: Point) -> Self::Output {
+        self.as_matrix() * rhs
+    }
+}
+
+impl Mul<Vector> for Transformation {
+    type Output = Vector;
+    fn mul(self, rhs: Vector) -> Self::Output {
+        self.as_matrix() * rhs
     }
 }
 
@@ -151,6 +461,100 @@ impl PartialEq for Transformation {
     }
 }
 
+impl fmt::Display for Transformation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_matrix())
+    }
+}
+
+/// Parse the numbers within the parentheses of `op(1, 2, 3)`.
+fn parse_args(op: &str, args: &str, count: usize) -> Result<Vec<Float>, ParseMatrixError> {
+    let values: Vec<Float> = args
+        .split(',')
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| {
+            a.parse()
+                .map_err(|_| ParseMatrixError::new(format!("'{}' is not a number", a)))
+        })
+        .collect::<Result<_, _>>()?;
+
+    if values.len() != count {
+        Err(ParseMatrixError::new(format!(
+            "{} takes {} arguments, found {}",
+            op,
+            count,
+            values.len()
+        )))
+    } else {
+        Ok(values)
+    }
+}
+
+impl FromStr for Transformation {
+    type Err = ParseMatrixError;
+
+    /// Parse either a matrix (see `Matrix::from_str`) or a chain of builder
+    /// calls applied from left to right, e.g.
+    /// `rotate_x(1.5708) scaling(2, 2, 2) translation(0, 1, 0)`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.contains('(') {
+            return Ok(Transformation::from_matrix(s.parse()?));
+        }
+
+        let mut t = Transformation::new();
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            let (op, tail) = rest
+                .split_once('(')
+                .ok_or_else(|| ParseMatrixError::new(format!("expected '(' in '{}'", rest)))?;
+            let (args, tail) = tail
+                .split_once(')')
+                .ok_or_else(|| ParseMatrixError::new(format!("missing ')' after '{}'", op)))?;
+
+            let op = op.trim();
+            t = match op {
+                "translation" => {
+                    let a = parse_args(op, args, 3)?;
+                    t.then_translation(a[0], a[1], a[2])
+                }
+                "scaling" => {
+                    let a = parse_args(op, args, 3)?;
+                    t.then_scaling(a[0], a[1], a[2])
+                }
+                "rotate_x" => t.then_rotate_x(parse_args(op, args, 1)?[0]),
+                "rotate_y" => t.then_rotate_y(parse_args(op, args, 1)?[0]),
+                "rotate_z" => t.then_rotate_z(parse_args(op, args, 1)?[0]),
+                "shearing" => {
+                    let a = parse_args(op, args, 6)?;
+                    t.then_shearing(a[0], a[1], a[2], a[3], a[4], a[5])
+                }
+                _ => {
+                    return Err(ParseMatrixError::new(format!(
+                        "unknown transformation '{}'",
+                        op
+                    )))
+                }
+            };
+            rest = tail.trim_start();
+        }
+
+        Ok(t)
+    }
+}
+
+impl From<Matrix> for Transformation {
+    fn from(m: Matrix) -> Self {
+        Transformation::from_matrix(m)
+    }
+}
+
+impl From<Transformation> for Matrix {
+    fn from(t: Transformation) -> Self {
+        t.as_matrix()
+    }
+}
+
 impl Default for Transformation {
     fn default() -> Self {
         Transformation::new()
@@ -160,11 +564,12 @@ impl Default for Transformation {
 #[cfg(test)]
 mod test {
     use super::*;
-    use std::f64::consts::PI;
 
     #[test]
     fn mul_point_translation() {
-        let transform = Transformation::new().translation(5.0, -3.0, 2.0).init();
+        let transform = Transformation::new()
+            .then_translation(5.0, -3.0, 2.0)
+            .init();
         let p = Point::new(-3.0, 4.0, 5.0);
 
         assert_eq!(transform * p, Point::new(2.0, 1.0, 7.0));
@@ -172,7 +577,9 @@ mod test {
 
     #[test]
     fn mul_inv_translation() {
-        let transform = Transformation::new().translation(5.0, -3.0, 2.0).init();
+        let transform = Transformation::new()
+            .then_translation(5.0, -3.0, 2.0)
+            .init();
         let inv = transform.inverse(4).unwrap();
         let p = Point::new(-3.0, 4.0, 5.0);
 
@@ -181,7 +588,9 @@ mod test {
 
     #[test]
     fn mul_vec_translation() {
-        let transform = Transformation::new().translation(5.0, -3.0, 2.0).init();
+        let transform = Transformation::new()
+            .then_translation(5.0, -3.0, 2.0)
+            .init();
         let v = Vector::new(-3.0, 4.0, 5.0);
 
         assert_eq!(transform * v, v);
@@ -189,7 +598,7 @@ mod test {
 
     #[test]
     fn mul_point_scaling() {
-        let transform = Transformation::new().scaling(2.0, 3.0, 4.0).init();
+        let transform = Transformation::new().then_scaling(2.0, 3.0, 4.0).init();
         let p = Point::new(-4.0, 6.0, 8.0);
 
         assert_eq!(transform * p, Point::new(-8.0, 18.0, 32.0));
@@ -197,7 +606,7 @@ mod test {
 
     #[test]
     fn mul_vector_scaling() {
-        let transform = Transformation::new().scaling(2.0, 3.0, 4.0).init();
+        let transform = Transformation::new().then_scaling(2.0, 3.0, 4.0).init();
         let v = Vector::new(-4.0, 6.0, 8.0);
 
         assert_eq!(transform * v, Vector::new(-8.0, 18.0, 32.0));
@@ -205,7 +614,7 @@ mod test {
 
     #[test]
     fn mul_inv_scaling() {
-        let transform = Transformation::new().scaling(2.0, 3.0, 4.0).init();
+        let transform = Transformation::new().then_scaling(2.0, 3.0, 4.0).init();
         let inv = transform.inverse(4).unwrap();
         let v = Vector::new(-4.0, 6.0, 8.0);
 
@@ -214,7 +623,7 @@ mod test {
 
     #[test]
     fn reflection_scaling() {
-        let transform = Transformation::new().scaling(-1.0, 1.0, 1.0).init();
+        let transform = Transformation::new().then_scaling(-1.0, 1.0, 1.0).init();
         let p = Point::new(2.0, 3.0, 4.0);
 
         assert_eq!(transform * p, Point::new(-2.0, 3.0, 4.0));
@@ -223,12 +632,16 @@ mod test {
     #[test]
     fn x_rotate() {
         let p = Point::new(0.0, 1.0, 0.0);
-        let half_quarter = Transformation::new().rotate_x(PI / 4.0).init();
-        let full_quarter = Transformation::new().rotate_x(PI / 2.0).init();
+        let half_quarter = Transformation::new().then_rotate_x(PI / 4.0).init();
+        let full_quarter = Transformation::new().then_rotate_x(PI / 2.0).init();
 
         assert_eq!(
             half_quarter * p,
-            Point::new(0.0, 2.0_f64.sqrt() / 2.0, 2.0_f64.sqrt() / 2.0)
+            Point::new(
+                0.0,
+                (2.0 as Float).sqrt() / 2.0,
+                (2.0 as Float).sqrt() / 2.0
+            )
         );
         assert_eq!(full_quarter * p, Point::new(0.0, 0.0, 1.0));
     }
@@ -236,24 +649,32 @@ mod test {
     #[test]
     fn x_inv_rotate() {
         let p = Point::new(0.0, 1.0, 0.0);
-        let half_quarter = Transformation::new().rotate_x(PI / 4.0).init();
+        let half_quarter = Transformation::new().then_rotate_x(PI / 4.0).init();
         let inv = half_quarter.inverse(4).unwrap();
 
         assert_eq!(
             inv * p,
-            Point::new(0.0, 2.0_f64.sqrt() / 2.0, -(2.0_f64.sqrt()) / 2.0)
+            Point::new(
+                0.0,
+                (2.0 as Float).sqrt() / 2.0,
+                -((2.0 as Float).sqrt()) / 2.0
+            )
         );
     }
 
     #[test]
     fn y_rotate() {
         let p = Point::new(0.0, 0.0, 1.0);
-        let half_quarter = Transformation::new().rotate_y(PI / 4.0).init();
-        let full_quarter = Transformation::new().rotate_y(PI / 2.0).init();
+        let half_quarter = Transformation::new().then_rotate_y(PI / 4.0).init();
+        let full_quarter = Transformation::new().then_rotate_y(PI / 2.0).init();
 
         assert_eq!(
             half_quarter * p,
-            Point::new(2.0_f64.sqrt() / 2.0, 0.0, 2.0_f64.sqrt() / 2.0)
+            Point::new(
+                (2.0 as Float).sqrt() / 2.0,
+                0.0,
+                (2.0 as Float).sqrt() / 2.0
+            )
         );
         assert_eq!(full_quarter * p, Point::new(1.0, 0.0, 0.0));
     }
@@ -261,12 +682,16 @@ mod test {
     #[test]
     fn z_rotate() {
         let p = Point::new(0.0, 1.0, 0.0);
-        let half_quarter = Transformation::new().rotate_z(PI / 4.0).init();
-        let full_quarter = Transformation::new().rotate_z(PI / 2.0).init();
+        let half_quarter = Transformation::new().then_rotate_z(PI / 4.0).init();
+        let full_quarter = Transformation::new().then_rotate_z(PI / 2.0).init();
 
         assert_eq!(
             half_quarter * p,
-            Point::new(-(2.0_f64.sqrt()) / 2.0, 2.0_f64.sqrt() / 2.0, 0.0)
+            Point::new(
+                -((2.0 as Float).sqrt()) / 2.0,
+                (2.0 as Float).sqrt() / 2.0,
+                0.0
+            )
         );
         assert_eq!(full_quarter * p, Point::new(-1.0, 0.0, 0.0));
     }
@@ -274,7 +699,7 @@ mod test {
     #[test]
     fn xy_shearing() {
         let transform = Transformation::new()
-            .shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0)
+            .then_shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0)
             .init();
         let p = Point::new(2.0, 3.0, 4.0);
 
@@ -284,7 +709,7 @@ mod test {
     #[test]
     fn xz_shearing() {
         let transform = Transformation::new()
-            .shearing(0.0, 1.0, 0.0, 0.0, 0.0, 0.0)
+            .then_shearing(0.0, 1.0, 0.0, 0.0, 0.0, 0.0)
             .init();
         let p = Point::new(2.0, 3.0, 4.0);
 
@@ -294,7 +719,7 @@ mod test {
     #[test]
     fn yx_shearing() {
         let transform = Transformation::new()
-            .shearing(0.0, 0.0, 1.0, 0.0, 0.0, 0.0)
+            .then_shearing(0.0, 0.0, 1.0, 0.0, 0.0, 0.0)
             .init();
         let p = Point::new(2.0, 3.0, 4.0);
 
@@ -304,7 +729,7 @@ mod test {
     #[test]
     fn yz_shearing() {
         let transform = Transformation::new()
-            .shearing(0.0, 0.0, 0.0, 1.0, 0.0, 0.0)
+            .then_shearing(0.0, 0.0, 0.0, 1.0, 0.0, 0.0)
             .init();
         let p = Point::new(2.0, 3.0, 4.0);
 
@@ -314,7 +739,7 @@ mod test {
     #[test]
     fn zx_shearing() {
         let transform = Transformation::new()
-            .shearing(0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
+            .then_shearing(0.0, 0.0, 0.0, 0.0, 1.0, 0.0)
             .init();
         let p = Point::new(2.0, 3.0, 4.0);
 
@@ -324,7 +749,7 @@ mod test {
     #[test]
     fn zy_shearing() {
         let transform = Transformation::new()
-            .shearing(0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+            .then_shearing(0.0, 0.0, 0.0, 0.0, 0.0, 1.0)
             .init();
         let p = Point::new(2.0, 3.0, 4.0);
 
@@ -334,9 +759,11 @@ mod test {
     #[test]
     fn seq_transform() {
         let p = Point::new(1.0, 0.0, 1.0);
-        let a = Transformation::new().rotate_x(PI / 2.0).init();
-        let b = Transformation::new().scaling(5.0, 5.0, 5.0).init();
-        let c = Transformation::new().translation(10.0, 5.0, 7.0).init();
+        let a = Transformation::new().then_rotate_x(PI / 2.0).init();
+        let b = Transformation::new().then_scaling(5.0, 5.0, 5.0).init();
+        let c = Transformation::new()
+            .then_translation(10.0, 5.0, 7.0)
+            .init();
         let p2 = a * p;
         let p3 = b * p2;
         let p4 = c * p3;
@@ -349,9 +776,11 @@ mod test {
     #[test]
     fn chain_transform() {
         let p = Point::new(1.0, 0.0, 1.0);
-        let a = Transformation::new().rotate_x(PI / 2.0).init();
-        let b = Transformation::new().scaling(5.0, 5.0, 5.0).init();
-        let c = Transformation::new().translation(10.0, 5.0, 7.0).init();
+        let a = Transformation::new().then_rotate_x(PI / 2.0).init();
+        let b = Transformation::new().then_scaling(5.0, 5.0, 5.0).init();
+        let c = Transformation::new()
+            .then_translation(10.0, 5.0, 7.0)
+            .init();
 
         assert_eq!(c * b * a * p, Point::new(15.0, 0.0, 7.0));
     }
@@ -360,14 +789,127 @@ mod test {
     fn chain_mul_transform() {
         let p = Point::new(1.0, 0.0, 1.0);
         let a = Transformation::new()
-            .rotate_x(PI / 2.0)
-            .scaling(5.0, 5.0, 5.0)
-            .translation(10.0, 5.0, 7.0)
+            .then_rotate_x(PI / 2.0)
+            .then_scaling(5.0, 5.0, 5.0)
+            .then_translation(10.0, 5.0, 7.0)
             .init();
 
         assert_eq!(a * p, Point::new(15.0, 0.0, 7.0));
     }
 
+    #[test]
+    fn inverse_transform() {
+        let t = Transformation::new()
+            .then_rotate_y(PI / 3.0)
+            .then_scaling(2.0, 3.0, 4.0)
+            .then_translation(1.0, -2.0, 3.0);
+        let inv = t.inverse().expect("Transform is invertible!");
+        let p = Point::new(1.0, 2.0, 3.0);
+
+        assert_eq!(inv * (t * p), p);
+        assert_eq!(t * inv, Transformation::new());
+        assert!(float_eq(t.determinant(), 24.0));
+        assert_eq!(
+            Transformation::new().then_scaling(1.0, 0.0, 1.0).inverse(),
+            Err(TransformError::Singular)
+        );
+        assert_eq!(
+            Transformation::new()
+                .then_translation(Float::NAN, 0.0, 0.0)
+                .inverse(),
+            Err(TransformError::NonFinite)
+        );
+    }
+
+    #[test]
+    fn validate_transform() {
+        assert_eq!(Transformation::new().validate(), Ok(()));
+        assert_eq!(
+            Transformation::new()
+                .then_shearing(1.0, 0.0, 0.0, 0.0, 0.0, 1.0)
+                .validate(),
+            Ok(())
+        );
+        assert_eq!(
+            Transformation::new().then_scaling(0.0, 1.0, 1.0).validate(),
+            Err(TransformError::Singular)
+        );
+        assert_eq!(
+            Transformation::new()
+                .then_translation(Float::NAN, 0.0, 0.0)
+                .validate(),
+            Err(TransformError::NonFinite)
+        );
+        assert_eq!(
+            Transformation::new()
+                .then_rotate_x(Float::INFINITY)
+                .validate(),
+            Err(TransformError::NonFinite)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid transformation")]
+    fn set_degenerate_transform() {
+        let mut s = Sphere::new();
+        s.set_transform(Transformation::new().then_scaling(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn try_set_transform() {
+        let mut s = Sphere::new();
+        let t = Transformation::new().then_translation(1.0, 2.0, 3.0);
+
+        assert_eq!(
+            s.try_set_transform(Transformation::new().then_scaling(0.0, 0.0, 0.0)),
+            Err(TransformError::Singular)
+        );
+        assert_eq!(s.get_transform(), Transformation::new());
+        assert_eq!(s.try_set_transform(t), Ok(()));
+        assert_eq!(s.get_transform(), t);
+    }
+
+    #[test]
+    fn from_matrix_transform() {
+        let m = Matrix::new([
+            [1.0, 0.0, 0.0, 2.0],
+            [0.0, 0.0, -1.0, 3.0],
+            [0.0, 1.0, 0.0, 4.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        let t = Transformation::from_matrix(m);
+
+        assert_eq!(
+            t,
+            Transformation::new()
+                .then_rotate_x(PI / 2.0)
+                .then_translation(2.0, 3.0, 4.0)
+        );
+        assert_eq!(t.as_matrix(), m);
+        assert_eq!(Transformation::from(m), t);
+        assert_eq!(Matrix::from(t), m);
+    }
+
+    #[test]
+    fn parse_transform() {
+        let t: Transformation =
+            "rotate_x(1.5707963267948966) scaling(5, 5, 5) translation(10, 5, 7)"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            t,
+            Transformation::new()
+                .then_rotate_x(PI / 2.0)
+                .then_scaling(5.0, 5.0, 5.0)
+                .then_translation(10.0, 5.0, 7.0)
+        );
+        assert_eq!(t.to_string().parse::<Transformation>(), Ok(t));
+        assert!("translation(1, 2)".parse::<Transformation>().is_err());
+        assert!("explode(1)".parse::<Transformation>().is_err());
+        assert!("scaling(1, 2, 3".parse::<Transformation>().is_err());
+    }
+
     #[test]
     fn default_view_transform() {
         let from = Point::new(0.0, 0.0, 0.0);
@@ -385,7 +927,7 @@ mod test {
         let up = Vector::new(0.0, 1.0, 0.0);
         let t = Transformation::view_transformation(from, to, up);
 
-        assert_eq!(t, Transformation::new().scaling(-1.0, 1.0, -1.0));
+        assert_eq!(t, Transformation::new().then_scaling(-1.0, 1.0, -1.0));
     }
 
     #[test]
@@ -395,7 +937,7 @@ mod test {
         let up = Vector::new(0.0, 1.0, 0.0);
         let t = Transformation::view_transformation(from, to, up);
 
-        assert_eq!(t, Transformation::new().translation(0.0, 0.0, -8.0));
+        assert_eq!(t, Transformation::new().then_translation(0.0, 0.0, -8.0));
     }
 
     #[test]
@@ -404,15 +946,180 @@ mod test {
         let to = Point::new(4.0, -2.0, 8.0);
         let up = Vector::new(1.0, 1.0, 0.0);
         let t = Transformation::view_transformation(from, to, up);
-        let res = Transformation {
-            data: [
-                [-0.50709, 0.50709, 0.67612, -2.36643],
-                [0.76772, 0.60609, 0.12122, -2.82843],
-                [-0.35857, 0.59761, -0.71714, 0.00000],
-                [0.00000, 0.00000, 0.00000, 1.00000],
-            ],
-        };
+        let res = Transformation::from_matrix(Matrix::new([
+            [-0.50709, 0.50709, 0.67612, -2.36643],
+            [0.76772, 0.60609, 0.12122, -2.82843],
+            [-0.35857, 0.59761, -0.71714, 0.00000],
+            [0.00000, 0.00000, 0.00000, 1.00000],
+        ]));
 
         assert_eq!(t, res);
     }
+
+    #[test]
+    fn rotate_axis_transformation() {
+        let p = Point::new(0.0, 1.0, 0.0);
+        let x = Transformation::new().then_rotate_axis(Vector::new(2.0, 0.0, 0.0), PI / 4.0);
+        assert_eq!(x, Transformation::new().then_rotate_x(PI / 4.0));
+        assert_eq!(
+            Transformation::new().then_rotate_axis(Vector::new(0.0, 0.0, 1.0), PI / 2.0) * p,
+            Point::new(-1.0, 0.0, 0.0)
+        );
+
+        // a third turn around the diagonal cycles the axes
+        let diagonal =
+            Transformation::new().then_rotate_axis(Vector::new(1.0, 1.0, 1.0), 2.0 * PI / 3.0);
+        assert_eq!(diagonal * p, Point::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn align_transformation() {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        for to in [
+            Vector::new(1.0, 2.0, -3.0),
+            Vector::new(0.0, 5.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Vector::new(1.0, 0.0, 0.0),
+        ] {
+            let t = Transformation::new().align(up, to);
+            assert_eq!(t * up, to.normalize());
+        }
+    }
+
+    #[test]
+    fn composition_order_transformation() {
+        let p = Point::new(1.0, 0.0, 0.0);
+        let scale = Transformation::new().then_scaling(2.0, 2.0, 2.0);
+        let shift = Transformation::new().then_translation(1.0, 0.0, 0.0);
+
+        // the builder scales first, then moves
+        let built = Transformation::new()
+            .then_scaling(2.0, 2.0, 2.0)
+            .then_translation(1.0, 0.0, 0.0);
+        assert_eq!(built * p, Point::new(3.0, 0.0, 0.0));
+        assert_eq!(scale.then(shift), built);
+        assert_eq!(shift.pre(scale), built);
+        assert_eq!(shift * scale, built);
+
+        // moving first ends up somewhere else
+        assert_eq!(scale.pre(shift) * p, Point::new(4.0, 0.0, 0.0));
+        assert_eq!(shift.then(scale), scale.pre(shift));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_builder_transformation() {
+        let old = Transformation::new()
+            .scaling(2.0, 3.0, 4.0)
+            .rotate_x(0.1)
+            .rotate_y(0.2)
+            .rotate_z(0.3)
+            .rotate_axis(Vector::new(1.0, 1.0, 0.0), 0.4)
+            .shearing(0.1, 0.2, 0.3, 0.4, 0.5, 0.6)
+            .translation(1.0, 2.0, 3.0);
+        let new = Transformation::new()
+            .then_scaling(2.0, 3.0, 4.0)
+            .then_rotate_x(0.1)
+            .then_rotate_y(0.2)
+            .then_rotate_z(0.3)
+            .then_rotate_axis(Vector::new(1.0, 1.0, 0.0), 0.4)
+            .then_shearing(0.1, 0.2, 0.3, 0.4, 0.5, 0.6)
+            .then_translation(1.0, 2.0, 3.0);
+        assert_eq!(old, new);
+    }
+
+    #[test]
+    fn rotation_extraction_transformation() {
+        let t = Transformation::new()
+            .then_scaling(1.0, 2.0, 3.0)
+            .then_rotate_x(0.4)
+            .then_rotate_y(-1.1)
+            .then_rotate_z(2.5)
+            .then_translation(1.0, 2.0, 3.0);
+        let (x, y, z) = t.rotation_euler();
+        assert!(float_eq(x, 0.4) && float_eq(y, -1.1) && float_eq(z, 2.5));
+
+        let (axis, angle) = Transformation::new()
+            .then_rotate_axis(Vector::new(0.0, -2.0, 0.0), PI / 3.0)
+            .rotation_axis_angle();
+        assert_eq!(axis, Vector::new(0.0, -1.0, 0.0));
+        assert!(float_eq(angle, PI / 3.0));
+
+        let (_, angle) = Transformation::new().rotation_axis_angle();
+        assert!(float_eq(angle, 0.0));
+    }
+
+    #[test]
+    fn gimbal_lock_transformation() {
+        let t = Transformation::new()
+            .then_rotate_x(0.3)
+            .then_rotate_y(PI / 2.0)
+            .then_rotate_z(0.2);
+        let (x, y, z) = t.rotation_euler();
+
+        assert!(float_eq(y, PI / 2.0));
+        assert_eq!(
+            Transformation::new()
+                .then_rotate_x(x)
+                .then_rotate_y(y)
+                .then_rotate_z(z),
+            t
+        );
+    }
+
+    #[test]
+    fn cached_inverse_transformation() {
+        let t = Transformation::new()
+            .then_scaling(2.0, -3.0, 0.5)
+            .then_shearing(1.0, 0.0, 0.5, 0.0, 0.0, 2.0)
+            .then_rotate_x(0.3)
+            .then_rotate_axis(Vector::new(1.0, 2.0, 3.0), 1.2)
+            .then_translation(4.0, -1.0, 2.0);
+        let generic = t.as_matrix().inverse(4).unwrap();
+
+        assert_eq!(t.inverse().unwrap().as_matrix(), generic);
+        assert_eq!(t.inverse().unwrap().inverse().unwrap(), t);
+        assert!((t * t.inverse().unwrap()).as_matrix().is_identity());
+        assert_eq!(
+            t.then(Transformation::new().then_scaling(0.0, 1.0, 1.0))
+                .inverse(),
+            Err(TransformError::Singular)
+        );
+    }
+
+    #[test]
+    fn reflect_across_plane_transformation() {
+        let t = Transformation::new()
+            .reflect_across_plane(Point::new(1.0, 0.0, 0.0), Vector::new(2.0, 0.0, 0.0));
+        assert_eq!(t * Point::new(3.0, 2.0, 1.0), Point::new(-1.0, 2.0, 1.0));
+        assert_eq!(t * Vector::new(1.0, 1.0, 0.0), Vector::new(-1.0, 1.0, 0.0));
+        assert_eq!(t.inverse().unwrap(), t);
+        assert!(t.is_mirroring());
+        assert!(!t.then(t).is_mirroring());
+
+        let diagonal = Transformation::new()
+            .reflect_across_plane(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, -1.0, 0.0));
+        assert_eq!(
+            diagonal * Point::new(1.0, 0.0, 5.0),
+            Point::new(0.0, 1.0, 5.0)
+        );
+        assert_eq!(
+            diagonal.inverse().unwrap().as_matrix(),
+            diagonal.as_matrix().inverse(4).unwrap()
+        );
+    }
+
+    #[test]
+    fn mirrored_normal_transformation() {
+        let mut c = Cube::new();
+        c.set_transform(
+            Transformation::new()
+                .then_translation(2.0, 0.0, 0.0)
+                .reflect_across_plane(Point::new(0.0, 0.0, 0.0), Vector::new(1.0, 0.0, 0.0)),
+        );
+
+        // the face closest to the mirror still points out of the cube
+        let n = c.normal_at(Point::new(-1.0, 0.5, 0.5), None);
+        assert_eq!(n, Vector::new(1.0, 0.0, 0.0));
+    }
 }
@@ -0,0 +1,61 @@
+use crate::{Float, EPSILON};
+
+/// Length of one scene unit, set on a `World` with `set_units`.
+/// Tolerances and the falloff of lights are tuned for scenes modelled in meters.
+/// Scenes in other units scale them, so imported models work without retuning
+/// constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Units {
+    #[default]
+    Meters,
+    Centimeters,
+    Millimeters,
+    Inches,
+    Feet,
+}
+
+impl Units {
+    /// Length of one unit in meters.
+    pub fn meters(&self) -> Float {
+        match self {
+            Units::Meters => 1.0,
+            Units::Centimeters => 0.01,
+            Units::Millimeters => 0.001,
+            Units::Inches => 0.0254,
+            Units::Feet => 0.3048,
+        }
+    }
+
+    /// Convert a length in meters to scene units.
+    pub fn from_meters(&self, length: Float) -> Float {
+        length / self.meters()
+    }
+
+    /// Convert a length in scene units to meters.
+    pub fn to_meters(&self, length: Float) -> Float {
+        length * self.meters()
+    }
+
+    /// Distance that counts as touching, EPSILON meters in scene units.
+    pub fn epsilon(&self) -> Float {
+        self.from_meters(EPSILON)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::float_eq;
+
+    #[test]
+    fn convert_units() {
+        assert!(float_eq(Units::Centimeters.from_meters(1.5), 150.0));
+        assert!(float_eq(Units::Feet.to_meters(10.0), 3.048));
+        assert!(float_eq(
+            Units::Inches.from_meters(Units::Inches.to_meters(7.0)),
+            7.0
+        ));
+        assert_eq!(Units::default().epsilon(), EPSILON);
+        assert!(float_eq(Units::Millimeters.epsilon(), EPSILON * 1000.0));
+    }
+}
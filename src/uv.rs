@@ -0,0 +1,334 @@
+use crate::consts::PI;
+use crate::{float_eq, Float, Point, Vector};
+
+/// Part of a surface a texture coordinate lies on.
+/// Shapes with caps map each cap separately, so a texture can treat
+/// the label around a can differently from its lid and bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UvFace {
+    /// The (curved) body of the shape.
+    Side,
+    /// The cap at the maximum of the shape.
+    Top,
+    /// The cap at the minimum of the shape.
+    Bottom,
+}
+
+/// Texture coordinate on the surface of a shape, `u` and `v` are within [0,1].
+#[derive(Debug, Clone, Copy)]
+pub struct Uv {
+    /// Face the coordinate belongs to.
+    pub face: UvFace,
+    /// Horizontal coordinate.
+    pub u: Float,
+    /// Vertical coordinate.
+    pub v: Float,
+}
+
+impl Uv {
+    /// Create a new texture coordinate.
+    pub fn new(face: UvFace, u: Float, v: Float) -> Self {
+        Self { face, u, v }
+    }
+}
+
+impl PartialEq for Uv {
+    fn eq(&self, other: &Self) -> bool {
+        self.face == other.face && float_eq(self.u, other.u) && float_eq(self.v, other.v)
+    }
+}
+
+/// Wrap the body of a shape around the y axis.
+/// `u` starts at -z and passes +x after a quarter turn, `v` goes from
+/// `minimum` to `maximum`, unbounded shapes repeat `v` every unit.
+pub fn cylindrical_map(point: Point, minimum: Float, maximum: Float) -> Uv {
+    let theta = point.x.atan2(point.z);
+    let u = 1.0 - (theta / (2.0 * PI) + 0.5);
+
+    let v = if minimum.is_finite() && maximum.is_finite() && maximum > minimum {
+        (point.y - minimum) / (maximum - minimum)
+    } else {
+        point.y.rem_euclid(1.0)
+    };
+
+    Uv::new(UvFace::Side, u, v)
+}
+
+/// Map a cap of `radius` around the y axis onto the unit square.
+/// The bottom cap is mirrored, so it reads correctly when seen from below.
+pub fn cap_map(point: Point, radius: Float, face: UvFace) -> Uv {
+    let radius = if radius > 0.0 { radius } else { 1.0 };
+    let u = (point.x / radius + 1.0) / 2.0;
+    let v = match face {
+        UvFace::Bottom => (point.z / radius + 1.0) / 2.0,
+        _ => (1.0 - point.z / radius) / 2.0,
+    };
+
+    Uv::new(face, u, v)
+}
+
+/// Wrap a sphere around its center, `u` turns around the y axis like
+/// `cylindrical_map` and `v` goes from the south to the north pole.
+pub fn spherical_map(point: Point) -> Uv {
+    let theta = point.x.atan2(point.z);
+    let radius = (point - Point::new(0.0, 0.0, 0.0)).magnitude();
+    let phi = (point.y / radius).acos();
+    let u = 1.0 - (theta / (2.0 * PI) + 0.5);
+    let v = 1.0 - phi / PI;
+
+    Uv::new(UvFace::Side, u, v)
+}
+
+/// Tile the xz plane with the unit square, `u` follows x and `v` follows z.
+pub fn planar_map(point: Point) -> Uv {
+    Uv::new(
+        UvFace::Side,
+        point.x.rem_euclid(1.0),
+        point.z.rem_euclid(1.0),
+    )
+}
+
+/// Map each face of the box from `minimum` to `maximum` with outward `normal`
+/// onto the unit square. The four sides read left to right when seen from
+/// outside with y up, the top and bottom like the caps of `cap_map`.
+pub fn box_map(point: Point, normal: Vector, minimum: Point, maximum: Point) -> Uv {
+    let unit = |value: Float, min: Float, max: Float| {
+        if min.is_finite() && max.is_finite() && max > min {
+            (value - min) / (max - min)
+        } else {
+            value.rem_euclid(1.0)
+        }
+    };
+    let x = unit(point.x, minimum.x, maximum.x);
+    let y = unit(point.y, minimum.y, maximum.y);
+    let z = unit(point.z, minimum.z, maximum.z);
+
+    if normal.y > 0.5 {
+        Uv::new(UvFace::Top, x, 1.0 - z)
+    } else if normal.y < -0.5 {
+        Uv::new(UvFace::Bottom, x, z)
+    } else if normal.x > 0.5 {
+        Uv::new(UvFace::Side, 1.0 - z, y)
+    } else if normal.x < -0.5 {
+        Uv::new(UvFace::Side, z, y)
+    } else if normal.z < 0.0 {
+        Uv::new(UvFace::Side, 1.0 - x, y)
+    } else {
+        Uv::new(UvFace::Side, x, y)
+    }
+}
+
+/// Distance between the samples of `tangents`.
+const TANGENT_STEP: Float = 1e-3;
+
+/// Directions in which `u` and `v` grow at `point` on a surface with `normal`,
+/// from the texture coordinates `uv_at` of nearby points in the tangent plane.
+/// Both are unit vectors perpendicular to `normal`. None where the mapping
+/// is degenerate, e.g. at the poles of a sphere, or changes the face.
+pub fn tangents<F>(uv_at: F, point: Point, normal: Vector) -> Option<(Vector, Vector)>
+where
+    F: Fn(Point) -> Option<Uv>,
+{
+    let axis = if normal.x.abs() < 0.9 {
+        Vector::new(1.0, 0.0, 0.0)
+    } else {
+        Vector::new(0.0, 1.0, 0.0)
+    };
+    let a = normal.cross(axis).normalize();
+    let b = normal.cross(a);
+
+    let face = uv_at(point)?.face;
+    // change of u and v along `dir`, across the seam of wrapped mappings
+    let slope = |dir: Vector| -> Option<(Float, Float)> {
+        let before = uv_at(point - dir * TANGENT_STEP)?;
+        let after = uv_at(point + dir * TANGENT_STEP)?;
+        if before.face != face || after.face != face {
+            return None;
+        }
+        let wrap = |d: Float| d - d.round();
+        Some((
+            wrap(after.u - before.u) / (2.0 * TANGENT_STEP),
+            wrap(after.v - before.v) / (2.0 * TANGENT_STEP),
+        ))
+    };
+    let (du_a, dv_a) = slope(a)?;
+    let (du_b, dv_b) = slope(b)?;
+
+    let det = du_a * dv_b - du_b * dv_a;
+    if det.abs() < 1e-6 {
+        return None;
+    }
+    let dpdu = (a * dv_b - b * dv_a) / det;
+    let dpdv = (b * du_a - a * du_b) / det;
+
+    let tangent = (dpdu - normal * normal.dot(dpdu)).normalize();
+    let bitangent = normal.cross(tangent);
+    if bitangent.dot(dpdv) < 0.0 {
+        Some((tangent, -bitangent))
+    } else {
+        Some((tangent, bitangent))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn cylindrical_uv() {
+        let data = vec![
+            (Point::new(0.0, 0.0, -1.0), 0.0, 0.0),
+            (Point::new(0.0, 0.5, -1.0), 0.0, 0.5),
+            (Point::new(0.0, 1.0, -1.0), 0.0, 0.0),
+            (Point::new(FRAC_1_SQRT_2, 0.5, -FRAC_1_SQRT_2), 0.125, 0.5),
+            (Point::new(1.0, 0.5, 0.0), 0.25, 0.5),
+            (Point::new(FRAC_1_SQRT_2, 0.5, FRAC_1_SQRT_2), 0.375, 0.5),
+            (Point::new(0.0, -0.25, 1.0), 0.5, 0.75),
+            (Point::new(-1.0, 1.25, 0.0), 0.75, 0.25),
+        ];
+        for (p, u, v) in data {
+            let uv = cylindrical_map(p, Float::NEG_INFINITY, Float::INFINITY);
+
+            assert_eq!(uv.face, UvFace::Side);
+            assert!(float_eq(uv.u, u));
+            assert!(float_eq(uv.v, v));
+        }
+    }
+
+    #[test]
+    fn bounded_cylindrical_uv() {
+        let uv = cylindrical_map(Point::new(0.0, 1.5, -1.0), 1.0, 3.0);
+
+        assert!(float_eq(uv.u, 0.0));
+        assert!(float_eq(uv.v, 0.25));
+    }
+
+    #[test]
+    fn cap_uv() {
+        let top = cap_map(Point::new(0.5, 1.0, 0.5), 1.0, UvFace::Top);
+        let bottom = cap_map(Point::new(0.5, 0.0, 0.5), 1.0, UvFace::Bottom);
+        let wide = cap_map(Point::new(-2.0, 2.0, -2.0), 2.0, UvFace::Top);
+
+        assert_eq!(top, Uv::new(UvFace::Top, 0.75, 0.25));
+        assert_eq!(bottom, Uv::new(UvFace::Bottom, 0.75, 0.75));
+        assert_eq!(wide, Uv::new(UvFace::Top, 0.0, 1.0));
+    }
+
+    #[test]
+    fn spherical_uv() {
+        let data = vec![
+            (Point::new(0.0, 0.0, -1.0), 0.0, 0.5),
+            (Point::new(1.0, 0.0, 0.0), 0.25, 0.5),
+            (Point::new(0.0, 0.0, 1.0), 0.5, 0.5),
+            (Point::new(-1.0, 0.0, 0.0), 0.75, 0.5),
+            (Point::new(0.0, 1.0, 0.0), 0.5, 1.0),
+            (Point::new(0.0, -1.0, 0.0), 0.5, 0.0),
+            (Point::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0), 0.25, 0.75),
+        ];
+        for (p, u, v) in data {
+            assert_eq!(spherical_map(p), Uv::new(UvFace::Side, u, v));
+        }
+    }
+
+    #[test]
+    fn planar_uv() {
+        let data = vec![
+            (Point::new(0.25, 0.0, 0.5), 0.25, 0.5),
+            (Point::new(0.25, 0.0, -0.25), 0.25, 0.75),
+            (Point::new(0.25, 0.5, -0.25), 0.25, 0.75),
+            (Point::new(1.25, 0.0, 0.5), 0.25, 0.5),
+            (Point::new(-0.25, 0.0, -1.75), 0.75, 0.25),
+        ];
+        for (p, u, v) in data {
+            assert_eq!(planar_map(p), Uv::new(UvFace::Side, u, v));
+        }
+    }
+
+    #[test]
+    fn box_uv() {
+        let (min, max) = (Point::new(-1.0, -1.0, -1.0), Point::new(1.0, 1.0, 1.0));
+        let data = vec![
+            (
+                Point::new(-0.5, 0.5, 1.0),
+                Vector::new(0.0, 0.0, 1.0),
+                UvFace::Side,
+                0.25,
+                0.75,
+            ),
+            (
+                Point::new(0.5, -0.5, -1.0),
+                Vector::new(0.0, 0.0, -1.0),
+                UvFace::Side,
+                0.25,
+                0.25,
+            ),
+            (
+                Point::new(1.0, 0.5, -0.5),
+                Vector::new(1.0, 0.0, 0.0),
+                UvFace::Side,
+                0.75,
+                0.75,
+            ),
+            (
+                Point::new(-1.0, 0.5, -0.5),
+                Vector::new(-1.0, 0.0, 0.0),
+                UvFace::Side,
+                0.25,
+                0.75,
+            ),
+            (
+                Point::new(-0.5, 1.0, -0.5),
+                Vector::new(0.0, 1.0, 0.0),
+                UvFace::Top,
+                0.25,
+                0.75,
+            ),
+            (
+                Point::new(-0.5, -1.0, 0.5),
+                Vector::new(0.0, -1.0, 0.0),
+                UvFace::Bottom,
+                0.25,
+                0.75,
+            ),
+        ];
+        for (p, n, face, u, v) in data {
+            assert_eq!(box_map(p, n, min, max), Uv::new(face, u, v));
+        }
+
+        let wide = box_map(
+            Point::new(3.0, 2.0, 0.0),
+            Vector::new(0.0, 0.0, 1.0),
+            Point::new(-1.0, 0.0, -1.0),
+            Point::new(7.0, 4.0, 0.0),
+        );
+        assert_eq!(wide, Uv::new(UvFace::Side, 0.5, 0.5));
+    }
+
+    #[test]
+    fn tangents_uv() {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        let (t, b) = tangents(|p| Some(planar_map(p)), Point::new(0.5, 0.0, 0.5), up).unwrap();
+        assert_eq!(t, Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(b, Vector::new(0.0, 0.0, 1.0));
+
+        // across the seam of the tiling
+        let (t, b) = tangents(|p| Some(planar_map(p)), Point::new(1.0, 0.0, 2.0), up).unwrap();
+        assert_eq!(t, Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(b, Vector::new(0.0, 0.0, 1.0));
+
+        // u turns towards +x, v runs up to the north pole
+        let front = Vector::new(0.0, 0.0, -1.0);
+        let (t, b) = tangents(
+            |p| Some(spherical_map(p)),
+            Point::new(0.0, 0.0, -1.0),
+            front,
+        )
+        .unwrap();
+        assert_eq!(t, Vector::new(1.0, 0.0, 0.0));
+        assert_eq!(b, Vector::new(0.0, 1.0, 0.0));
+
+        assert!(tangents(|p| Some(spherical_map(p)), Point::new(0.0, 1.0, 0.0), up).is_none());
+        assert!(tangents(|_| None, Point::new(0.0, 0.0, 0.0), up).is_none());
+    }
+}
@@ -1,25 +1,25 @@
-use crate::float_eq;
+use crate::{float_eq, Float};
 use std::ops::{Add, Div, Mul, Neg, Sub};
 
 /// The Vector in a left-coordinate system.
 #[derive(Debug, Copy, Clone, Default)]
 pub struct Vector {
     /// Distance from origin along the X axis.
-    pub x: f64,
+    pub x: Float,
     /// Distance from origin along the Y axis.
-    pub y: f64,
+    pub y: Float,
     /// Distance from origin along the Z axis.
-    pub z: f64,
+    pub z: Float,
 }
 
 impl Vector {
     /// Creates a Vector in space.
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
         Self { x, y, z }
     }
 
     /// Calculate the Length/Magnitude of a Vector.
-    pub fn magnitude(&self) -> f64 {
+    pub fn magnitude(&self) -> Float {
         (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
     }
 
@@ -33,7 +33,7 @@ impl Vector {
     }
 
     /// Calculate the dot product of two Vectors
-    pub fn dot(&self, other: Self) -> f64 {
+    pub fn dot(&self, other: Self) -> Float {
         self.x * other.x + self.y * other.y + self.z * other.z
     }
 
@@ -85,9 +85,9 @@ impl Neg for Vector {
     }
 }
 
-impl Mul<f64> for Vector {
+impl Mul<Float> for Vector {
     type Output = Self;
-    fn mul(self, other: f64) -> Self {
+    fn mul(self, other: Float) -> Self {
         Self {
             x: self.x * other,
             y: self.y * other,
@@ -96,9 +96,9 @@ impl Mul<f64> for Vector {
     }
 }
 
-impl Div<f64> for Vector {
+impl Div<Float> for Vector {
     type Output = Self;
-    fn div(self, other: f64) -> Self {
+    fn div(self, other: Float) -> Self {
         Self {
             x: self.x / other,
             y: self.y / other,
@@ -205,14 +205,14 @@ mod test {
     fn mag_pos_vector() {
         let v = Vector::new(1.0, 2.0, 3.0);
 
-        assert_eq!(v.magnitude(), 14_f64.sqrt());
+        assert_eq!(v.magnitude(), (14.0 as Float).sqrt());
     }
 
     #[test]
     fn mag_neg_vector() {
         let v = Vector::new(-1.0, -2.0, -3.0);
 
-        assert_eq!(v.magnitude(), 14_f64.sqrt());
+        assert_eq!(v.magnitude(), (14.0 as Float).sqrt());
     }
 
     #[test]
@@ -233,7 +233,7 @@ mod test {
     fn norm_magnitude_vector() {
         let v = Vector::new(1.0, 2.0, 3.0);
 
-        assert_eq!(v.normalize().magnitude(), 1.0);
+        assert!(float_eq(v.normalize().magnitude(), 1.0));
     }
 
     #[test]
@@ -265,7 +265,11 @@ mod test {
     #[test]
     fn reflect_slanted_vector() {
         let v = Vector::new(0.0, -1.0, 0.0);
-        let n = Vector::new(2_f64.sqrt() / 2.0, 2_f64.sqrt() / 2.0, 0.0);
+        let n = Vector::new(
+            (2.0 as Float).sqrt() / 2.0,
+            (2.0 as Float).sqrt() / 2.0,
+            0.0,
+        );
         let r = v.reflect(n);
 
         assert_eq!(r, Vector::new(1.0, 0.0, 0.0));
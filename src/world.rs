@@ -1,13 +1,206 @@
+use crate::consts::PI;
 use crate::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::OnceLock;
 use uuid::Uuid;
 
+/// Per object switches to control how an object takes part in a render.
+/// Flags set on a group apply to its children, unless a child has its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectFlags {
+    /// Is the object darkened by shadows of other objects?
+    pub receive_shadows: bool,
+
+    /// Is the object hit by camera rays?
+    pub camera: bool,
+
+    /// Is the object hit by reflected and refracted rays?
+    pub reflections: bool,
+}
+
+impl Default for ObjectFlags {
+    fn default() -> Self {
+        Self {
+            receive_shadows: true,
+            camera: true,
+            reflections: true,
+        }
+    }
+}
+
+impl ObjectFlags {
+    /// Hidden from the camera but seen in reflections and refractions.
+    pub fn reflections_only() -> Self {
+        Self {
+            camera: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// Which rays are traced, to filter objects by their flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RayKind {
+    Camera,
+    Secondary,
+    Any,
+}
+
+/// Remaining recursion depth for secondary rays.
+/// Reflection and refraction are tracked separately, so glass-heavy scenes
+/// can follow long refraction chains without paying for deep reflections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    /// How many more reflected rays may be spawned.
+    pub reflection: usize,
+
+    /// How many more refracted rays may be spawned.
+    pub refraction: usize,
+}
+
+impl Budget {
+    /// Create a new budget.
+    pub fn new(reflection: usize, refraction: usize) -> Self {
+        Self {
+            reflection,
+            refraction,
+        }
+    }
+
+    /// The same depth for reflection and refraction.
+    pub fn uniform(depth: usize) -> Self {
+        Self::new(depth, depth)
+    }
+
+    /// The budget left after following a reflected ray.
+    pub fn reflected(self) -> Self {
+        Self::new(self.reflection - 1, self.refraction)
+    }
+
+    /// The budget left after following a refracted ray.
+    pub fn refracted(self) -> Self {
+        Self::new(self.reflection, self.refraction - 1)
+    }
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget::uniform(5)
+    }
+}
+
+/// The contributions to the color of a camera ray, see `World::color_terms`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorTerms {
+    /// Ambient light and emission of the visible surface, or the background of a miss.
+    pub ambient: RGB,
+
+    /// Diffuse light of the visible surface.
+    pub diffuse: RGB,
+
+    /// Highlights of the visible surface.
+    pub specular: RGB,
+
+    /// Light reflected by the visible surface.
+    pub reflection: RGB,
+
+    /// Light passing through the visible surface.
+    pub refraction: RGB,
+}
+
+impl ColorTerms {
+    /// Sum of all terms, the color of the ray.
+    pub fn total(&self) -> RGB {
+        self.ambient + self.diffuse + self.specular + self.reflection + self.refraction
+    }
+}
+
+/// Refers to an object, or a child of a group, in a `World`.
+/// Handles stay valid as long as the world exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectHandle(usize);
+
+/// Where an object sits in the scene graph.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    /// The group (or csg) containing the object, None at the top level.
+    parent: Option<ObjectHandle>,
+
+    /// Index among the children of the parent, or in the objects of the world.
+    index: usize,
+}
+
+/// Reasons a frame of external animation data can not be applied.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameError {
+    /// No object has the name.
+    UnknownName(String),
+
+    /// The transformation of the named object can't place it.
+    InvalidTransform(String, TransformError),
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::UnknownName(name) => write!(f, "No object is named '{}'", name),
+            FrameError::InvalidTransform(name, why) => {
+                write!(f, "Transformation of '{}': {}", name, why)
+            }
+        }
+    }
+}
+
+impl Error for FrameError {}
+
 /// A world holds every shape and a light source.
 pub struct World {
     /// All Shapes contain in a World.
     objects: Vec<Box<dyn Shape>>,
 
+    /// Every object and child of a group, indexed by `ObjectHandle`.
+    nodes: Vec<Node>,
+
+    /// Handle of every object by its id.
+    handles: HashMap<Uuid, ObjectHandle>,
+
+    /// Objects named after the nodes of external animation data.
+    names: HashMap<String, ObjectHandle>,
+
     /// The light source.
     light: Option<PointLight>,
+
+    /// The shader used to color every hit.
+    shader: Box<dyn Shader>,
+
+    /// Per object flag if it is inside the camera frustum, None if not culled.
+    primary_visible: Option<Vec<bool>>,
+
+    /// Bumped whenever objects may have changed.
+    generation: u64,
+
+    /// Per object flag if it blocks all light, computed by the first shadow ray.
+    opaque: OnceLock<Vec<bool>>,
+
+    /// Seen by rays that miss every object, black if None.
+    environment: Option<EnvironmentMap>,
+
+    /// Length of a scene unit, meters if None.
+    units: Option<Units>,
+
+    /// Flags of objects that differ from the default.
+    flags: HashMap<Uuid, ObjectFlags>,
+
+    /// Distance along a secondary ray in which its origin object is ignored.
+    self_exclusion: Float,
+
+    /// Multiplier of the ambient term of every material.
+    ambient: RGB,
+
+    /// Directions sampled for the ambient light of the environment, 0 for none.
+    environment_samples: usize,
 }
 
 impl World {
@@ -15,18 +208,350 @@ impl World {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
+            nodes: Vec::new(),
+            handles: HashMap::new(),
+            names: HashMap::new(),
             light: None,
+            shader: Box::new(Whitted),
+            primary_visible: None,
+            generation: 0,
+            opaque: OnceLock::new(),
+            environment: None,
+            units: None,
+            flags: HashMap::new(),
+            self_exclusion: 0.0,
+            ambient: WHITE,
+            environment_samples: 0,
         }
     }
 
+    /// Counter that changes whenever objects are added or borrowed mutably,
+    /// e.g. to call `set_transform`. Anything caching spatial data of the
+    /// world (bounding volumes, cumulative transforms) compares it with the
+    /// value it was built at to detect that it is stale.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Fingerprint of the scene, the same for the same scene in every run.
+    /// Covers the scene graph, the `Shape::fingerprint` of every object with
+    /// its parameters, transformation and material, the light and the ambient
+    /// color. Ids are left out, they differ per run, and so is the environment.
+    pub fn scene_hash(&self) -> u64 {
+        // FNV-1a, the hashers of std may change between Rust versions
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |text: String| {
+            for b in text.bytes() {
+                hash = (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+
+        for node in &self.nodes {
+            feed(format!("{:?} {};", node.parent.map(|p| p.0), node.index));
+        }
+        // children are part of the fingerprint of their container
+        for object in &self.objects {
+            feed(object.fingerprint());
+        }
+        feed(format!("{:?} {:?}", self.light, self.ambient));
+
+        hash
+    }
+
+    /// Invalidate everything derived from the objects.
+    fn touch(&mut self) {
+        self.generation += 1;
+        self.primary_visible = None;
+        self.opaque = OnceLock::new();
+    }
+
     /// Set the light source of the world.
     pub fn set_light(&mut self, light: PointLight) {
         self.light = Some(light);
     }
 
+    /// Return the light source of the world, in the units of the world.
+    pub fn get_light(&self) -> Option<PointLight> {
+        self.light
+            .map(|light| light.with_units(self.units.unwrap_or_default()))
+    }
+
+    /// Set the environment seen in the background and in reflections and refractions.
+    pub fn set_environment(&mut self, environment: EnvironmentMap) {
+        self.environment = Some(environment);
+    }
+
+    /// Return the environment of the world.
+    pub fn get_environment(&self) -> Option<&EnvironmentMap> {
+        self.environment.as_ref()
+    }
+
+    /// Color seen by a ray that hits nothing.
+    pub(crate) fn background(&self, ray: &Ray) -> RGB {
+        match &self.environment {
+            Some(environment) => environment.sample(ray.direction()),
+            None => BLACK,
+        }
+    }
+
+    /// Light from the environment reflected by a white diffuse surface at `point`,
+    /// i.e. the irradiance divided by pi, estimated with `samples` directions.
+    /// Directions are picked by the importance of the environment and are blocked
+    /// by objects, so `point` should be offset from the surface (`over_point`).
+    pub fn environment_lighting(&self, point: Point, normal: Vector, samples: usize) -> RGB {
+        let environment = match &self.environment {
+            Some(environment) => environment,
+            None => return BLACK,
+        };
+
+        let mut sum = BLACK;
+        for i in 0..samples {
+            // Hammersley points spread the samples evenly
+            let u1 = (i as Float + 0.5) / samples as Float;
+            let u2 = (i as u32).reverse_bits() as Float / (u32::MAX as Float + 1.0);
+            let (direction, pdf) = environment.sampler().sample(u1, u2);
+            let cos = direction.dot(normal);
+            if cos <= 0.0 || pdf <= 0.0 {
+                continue;
+            }
+
+            let ray = Ray::new(point, direction);
+            let xs = self.intersect_world(&ray).unwrap_or_default();
+            if Intersection::hit(&xs).is_none() {
+                sum = sum + environment.sample(direction) * (cos / (PI * pdf));
+            }
+        }
+
+        sum * (1.0 / samples.max(1) as Float)
+    }
+
+    /// Set the length of a scene unit, which scales the tolerances of the world
+    /// and the distances the falloff of the light is applied to.
+    pub fn set_units(&mut self, units: Units) {
+        self.units = Some(units);
+    }
+
+    /// Return the length of a scene unit, if set.
+    pub fn get_units(&self) -> Option<Units> {
+        self.units
+    }
+
+    /// Distance that counts as touching in this world,
+    /// e.g. to offset points from surfaces.
+    pub fn epsilon(&self) -> Float {
+        self.units.unwrap_or_default().epsilon()
+    }
+
+    /// Set the ambient light of the scene, which scales the `ambient` of
+    /// every material. WHITE (the default) keeps the materials as they are.
+    pub fn set_ambient(&mut self, ambient: RGB) {
+        self.ambient = ambient;
+    }
+
+    /// Return the ambient light of the scene.
+    pub fn get_ambient(&self) -> RGB {
+        self.ambient
+    }
+
+    /// Light the ambient term by the environment, estimated with `samples`
+    /// directions per hit. 0 (the default) keeps the uniform ambient light.
+    pub fn set_environment_samples(&mut self, samples: usize) {
+        self.environment_samples = samples;
+    }
+
+    /// Return the directions sampled for the ambient light of the environment.
+    pub fn get_environment_samples(&self) -> usize {
+        self.environment_samples
+    }
+
+    /// Ambient light reaching a surface at `point` with the normal `normal`.
+    /// The ambient light of the scene, times the light of the environment
+    /// if it is sampled, see `set_environment_samples`.
+    pub fn ambient_at(&self, point: Point, normal: Vector) -> RGB {
+        if self.environment_samples == 0 || self.environment.is_none() {
+            return self.ambient;
+        }
+        self.ambient * self.environment_lighting(point, normal, self.environment_samples)
+    }
+
+    /// Ignore the object a secondary ray starts on for the first `distance`
+    /// of the ray, which removes acne the surface offset leaves on strongly
+    /// curved glass. 0.0 (the default) turns the exclusion off.
+    pub fn set_self_exclusion(&mut self, distance: Float) {
+        self.self_exclusion = distance.max(0.0);
+    }
+
+    /// Return the distance in which the origin of a secondary ray is ignored.
+    pub fn get_self_exclusion(&self) -> Float {
+        self.self_exclusion
+    }
+
+    /// Replace the shader used to color hits.
+    pub fn set_shader(&mut self, shader: Box<dyn Shader>) {
+        self.shader = shader;
+    }
+
     /// Add objects/shapes to a world.
     pub fn add_object(&mut self, object: Box<dyn Shape>) {
         self.objects.push(object);
+        self.register(None, self.objects.len() - 1);
+        self.touch();
+    }
+
+    /// Hand out handles for an object and all of its children.
+    fn register(&mut self, parent: Option<ObjectHandle>, index: usize) {
+        let handle = ObjectHandle(self.nodes.len());
+        self.nodes.push(Node { parent, index });
+        let object = self.object(handle).expect("Registered objects exist!");
+        let id = object.id();
+        let children = (0..).take_while(|&i| object.child(i).is_some()).count();
+
+        self.handles.insert(id, handle);
+        for i in 0..children {
+            self.register(Some(handle), i);
+        }
+    }
+
+    /// Handle of the object (or child of a group) with `id`.
+    pub fn handle(&self, id: Uuid) -> Option<ObjectHandle> {
+        self.handles.get(&id).copied()
+    }
+
+    /// The object referred to by `handle`.
+    pub fn object(&self, handle: ObjectHandle) -> Option<&dyn Shape> {
+        let node = self.nodes.get(handle.0)?;
+        match node.parent {
+            Some(parent) => self.object(parent)?.child(node.index),
+            None => self.objects.get(node.index).map(|o| o.as_ref()),
+        }
+    }
+
+    /// Mutable version of `object`, the caller has to keep the bounds of groups
+    /// and anything derived from the objects up to date.
+    fn object_mut(&mut self, handle: ObjectHandle) -> Option<&mut dyn Shape> {
+        let node = *self.nodes.get(handle.0)?;
+        match node.parent {
+            Some(parent) => self.object_mut(parent)?.child_mut(node.index),
+            None => self.objects.get_mut(node.index).map(|o| o.as_mut()),
+        }
+    }
+
+    /// Name the object (or group inside a group) with `id`, e.g. after a node
+    /// of an animation. Returns false if there is no such object.
+    pub fn set_object_name(&mut self, id: Uuid, name: &str) -> bool {
+        match self.handle(id) {
+            Some(handle) => {
+                self.names.insert(name.to_string(), handle);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The object named `name`, see `set_object_name`.
+    pub fn object_by_name(&self, name: &str) -> Option<&dyn Shape> {
+        self.object(*self.names.get(name)?)
+    }
+
+    /// Set the transformation of named objects, e.g. one frame of a motion
+    /// capture or an exported animation. The transformations are relative to
+    /// the parent group like any other, the children of a named group follow it.
+    /// Nothing changes if a name is unknown or a transformation is invalid.
+    pub fn apply_frame(
+        &mut self,
+        frame: &HashMap<String, Transformation>,
+    ) -> Result<(), FrameError> {
+        let mut changes = Vec::with_capacity(frame.len());
+        for (name, t) in frame {
+            let handle = *self
+                .names
+                .get(name)
+                .ok_or_else(|| FrameError::UnknownName(name.clone()))?;
+            t.validate()
+                .map_err(|why| FrameError::InvalidTransform(name.clone(), why))?;
+            changes.push((handle, *t));
+        }
+
+        // the groups on the way to the children drop their cached bounds
+        for (handle, t) in changes {
+            self.object_mut(handle)
+                .expect("Handles of a world are valid!")
+                .set_transform(t);
+        }
+        self.touch();
+
+        Ok(())
+    }
+
+    /// Handle of the group containing the object, None at the top level.
+    pub fn parent(&self, handle: ObjectHandle) -> Option<ObjectHandle> {
+        self.nodes.get(handle.0)?.parent
+    }
+
+    /// Transform a point from world space into the space of the object.
+    pub fn world_to_object(&self, handle: ObjectHandle, point: Point) -> Point {
+        let object = self.object(handle).expect("Handles of a world are valid!");
+        let parent_point = match self.parent(handle) {
+            Some(parent) => self.world_to_object(parent, point),
+            None => point,
+        };
+
+        object.get_transform().inverse().unwrap() * parent_point
+    }
+
+    /// Transform a normal from the space of the object into world space.
+    pub fn normal_to_world(&self, handle: ObjectHandle, normal: Vector) -> Vector {
+        let object = self.object(handle).expect("Handles of a world are valid!");
+        let inv = object.get_transform().inverse().unwrap().as_matrix();
+        let parent_normal = (inv.transpose() * normal).normalize();
+
+        match self.parent(handle) {
+            Some(parent) => self.normal_to_world(parent, parent_normal),
+            None => parent_normal,
+        }
+    }
+
+    /// Mark every object outside the view of `camera`.
+    /// Culled objects are skipped by primary rays, but still cast shadows
+    /// and show up in reflections and refractions.
+    /// Changing the objects afterwards drops the culling again.
+    pub fn cull_frustum(&mut self, camera: &Camera) {
+        let visible = self
+            .objects
+            .iter()
+            .map(|o| camera.is_visible(&o.parent_space_bounds()))
+            .collect();
+        self.primary_visible = Some(visible);
+    }
+
+    /// Forget the result of `cull_frustum`.
+    pub fn clear_culling(&mut self) {
+        self.primary_visible = None;
+    }
+
+    /// Is the object at `index` hit by primary rays?
+    pub fn is_primary_visible(&self, index: usize) -> bool {
+        match &self.primary_visible {
+            Some(visible) => visible.get(index).copied().unwrap_or(true),
+            None => true,
+        }
+    }
+
+    /// Set the flags of the object (or group) with `id`.
+    pub fn set_object_flags(&mut self, id: Uuid, flags: ObjectFlags) {
+        self.flags.insert(id, flags);
+    }
+
+    /// Flags in effect for `object`, its own or those of the closest group around it.
+    pub fn object_flags(&self, object: &dyn Shape) -> ObjectFlags {
+        if let Some(flags) = self.flags.get(&object.id()) {
+            return *flags;
+        }
+        let parent = self.handle(object.id()).and_then(|h| self.parent(h));
+        match parent.and_then(|p| self.object(p)) {
+            Some(parent) => self.object_flags(parent),
+            None => ObjectFlags::default(),
+        }
     }
 
     /// Return a reference to an object inside the world identified by the index.
@@ -38,82 +563,381 @@ impl World {
     }
 
     /// Return a mut reference to an object inside the world identified by the index.
+    /// The object may be changed, so the world's generation is bumped.
     pub fn get_object_mut(&mut self, index: usize) -> Option<&mut dyn Shape> {
+        self.touch();
         match self.objects.get_mut(index) {
             Some(obj) => Some(obj.as_mut()),
             None => None,
         }
     }
 
-    /// Return a reference to a Shape.    
+    /// Return a reference to a Shape.
     pub fn get_object_by_id(&self, id: Uuid) -> Option<&dyn Shape> {
-        for s in &self.objects {
-            if s.id() == id {
-                return Some(s.as_ref());
-            }
+        self.handle(id).and_then(|h| self.object(h))
+    }
 
-            if let Some(c) = s.get_object_by_id(id) {
-                return Some(c);
-            }
+    /// Calculate the intersection of a ray with every object in this world.
+    pub fn intersect_world(&self, ray: &Ray) -> Option<Vec<Intersection>> {
+        self.intersect_objects(ray, RayKind::Any)
+    }
+
+    /// Calculate the intersection of a camera ray,
+    /// skipping culled objects and those hidden from the camera.
+    pub fn intersect_primary(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        self.intersect_objects(ray, RayKind::Camera)
+    }
+
+    /// Calculate the intersection of a reflected or refracted ray,
+    /// skipping objects hidden from reflections.
+    pub fn intersect_secondary(&self, ray: &Ray) -> Option<Vec<Intersection<'_>>> {
+        self.intersect_objects(ray, RayKind::Secondary)
+    }
+
+    /// Calculate the intersection of a reflected or refracted ray,
+    /// ignoring hits of the object `exclude` in front of the ray closer than `distance`.
+    /// Hits behind the ray are kept for the refractive indices of `prepare_computations`.
+    pub fn intersect_excluding(
+        &self,
+        ray: &Ray,
+        exclude: Uuid,
+        distance: Float,
+    ) -> Option<Vec<Intersection<'_>>> {
+        let mut xs = self.intersect_secondary(ray)?;
+        xs.retain(|x| !Self::is_excluded(x, exclude, distance));
+        if xs.is_empty() {
+            None
+        } else {
+            Some(xs)
         }
+    }
 
-        None
+    /// Is `x` a hit of the object `exclude` in front of the ray closer than `distance`?
+    fn is_excluded(x: &Intersection<'_>, exclude: Uuid, distance: Float) -> bool {
+        x.t >= 0.0 && x.t < distance && x.object.id() == exclude
     }
 
-    /// Calculate the intersection of a ray in this world.
-    pub fn intersect_world(&self, ray: &Ray) -> Option<Vec<Intersection>> {
-        let mut xs: Vec<Intersection> = Vec::new();
-        for obj in &self.objects {
-            let is = obj.intersect(ray);
-            if is.is_none() {
-                continue;
-            }
-            xs.append(&mut is.unwrap());
+    /// The hit in `xs` that is not excluded, see `set_self_exclusion`.
+    fn hit_excluding<'a>(
+        xs: &'a [Intersection<'a>],
+        exclude: Uuid,
+        distance: Float,
+    ) -> Option<&'a Intersection<'a>> {
+        xs.iter()
+            .filter(|x| x.t >= 0.0 && !Self::is_excluded(x, exclude, distance))
+            .min()
+    }
+
+    /// Replace the content of `xs` with the sorted intersections of `ray`
+    /// with every object. Reusing `xs` for many rays avoids allocations.
+    pub fn intersect_world_into<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        self.intersect_into(ray, RayKind::Any, xs)
+    }
+
+    /// The nearest hit of each ray, in the order of `rays`.
+    /// Cheaper than `intersect_world` per ray, since all rays share one buffer.
+    pub fn intersect_batch(&self, rays: &[Ray]) -> Vec<Option<Intersection<'_>>> {
+        let mut xs = Vec::new();
+        let mut hits = Vec::with_capacity(rays.len());
+        for ray in rays {
+            self.intersect_into(ray, RayKind::Any, &mut xs);
+            hits.push(xs.iter().filter(|x| x.t >= 0.0).min().copied());
         }
 
-        if xs.len() == 0 {
+        hits
+    }
+
+    fn intersect_objects(&self, ray: &Ray, kind: RayKind) -> Option<Vec<Intersection<'_>>> {
+        let mut xs = Vec::new();
+        self.intersect_into(ray, kind, &mut xs);
+        if xs.is_empty() {
             None
         } else {
-            xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
             Some(xs)
         }
     }
 
+    /// Replace the content of `xs` with the sorted intersections of `ray`.
+    fn intersect_into<'a>(&'a self, ray: &Ray, kind: RayKind, xs: &mut Vec<Intersection<'a>>) {
+        xs.clear();
+        for (index, obj) in self.objects.iter().enumerate() {
+            if kind == RayKind::Camera && !self.is_primary_visible(index) {
+                continue;
+            }
+            obj.intersect_into(ray, xs);
+        }
+        if !self.flags.is_empty() {
+            xs.retain(|x| match kind {
+                RayKind::Camera => self.object_flags(x.object).camera,
+                RayKind::Secondary => self.object_flags(x.object).reflections,
+                RayKind::Any => true,
+            });
+        }
+
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    }
+
+    /// Find the nearest object hit by `ray`.
+    /// Returns its id together with the distance, the point and the normal of the hit,
+    /// which is all editors or collision tests need.
+    pub fn pick(&self, ray: &Ray) -> Option<(Uuid, Float, Point, Vector)> {
+        let xs = self.intersect_world(ray)?;
+        let hit = Intersection::hit(&xs)?;
+        let point = ray.position(hit.t);
+        let normal = hit.normal_at(point, Some(self));
+
+        Some((hit.object.id(), hit.t, point, normal))
+    }
+
     /// Compute the color at the intersection.
-    pub fn shade_hit(&self, comps: &Computation, remaining: usize) -> RGB {
-        let shadowed = self.is_shadowed(comps.over_point);
-        let surface = comps.object.get_material().lightning(
-            comps.object,
-            self.light.expect("World has no light!"),
-            comps.over_point,
-            comps.eyev,
-            comps.normalv,
-            shadowed,
-        );
-        let reflected = self.reflected_color(&comps, remaining);
-        let refracted = self.refracted_color(comps, remaining);
+    /// A shader set on the material takes precedence over the one of the world.
+    pub fn shade_hit(&self, comps: &Computation, remaining: Budget) -> RGB {
+        self.trace_shading(self.shading(comps, remaining))
+    }
+
+    /// Shade a hit with the shader in charge, without tracing secondary rays.
+    fn shading(&self, comps: &Computation, remaining: Budget) -> Shading {
+        match comps.material.shader.as_ref() {
+            Some(shader) => shader.shade(self, comps, remaining),
+            None => self.shader.shade(self, comps, remaining),
+        }
+    }
+
+    /// Add up the color of a shaded hit and all rays it spawns.
+    /// Secondary rays are kept on an explicit stack together with their
+    /// accumulated weight, so the depth of the budget never limits the call stack.
+    pub fn trace_shading(&self, shading: Shading) -> RGB {
+        let mut color = shading.color;
+        let mut stack = shading.secondary;
+        // one buffer for the intersections of all rays
+        let mut xs = Vec::new();
+
+        while let Some(secondary) = stack.pop() {
+            self.intersect_into(&secondary.ray, RayKind::Secondary, &mut xs);
+            // excluded hits still count for the refractive indices
+            let hit = match secondary.origin {
+                Some(id) => Self::hit_excluding(&xs, id, self.self_exclusion),
+                None => Intersection::hit(&xs),
+            };
+            let hit = match hit {
+                Some(hit) => hit,
+                None => {
+                    color = color + self.background(&secondary.ray) * secondary.weight;
+                    continue;
+                }
+            };
+
+            let comps = hit.prepare_computations(&secondary.ray, &xs, Some(self));
+            let shading = self.shading(&comps, secondary.remaining);
+            color = color + shading.color * secondary.weight;
+            for mut next in shading.secondary {
+                next.weight = next.weight * secondary.weight;
+                stack.push(next);
+            }
+        }
 
-        let material = comps.object.get_material();
-        if material.reflective > 0.0 && material.transparency > 0.0 {
+        color
+    }
+
+    /// Split the color of a camera ray into the lighting terms of the visible
+    /// surface and what its reflection and refraction add, as shaded by `Whitted`.
+    /// Shaders set on the world or on materials are ignored.
+    pub fn color_terms(&self, ray: &Ray, remaining: Budget) -> ColorTerms {
+        let xs = self.intersect_primary(ray).unwrap_or_default();
+        let hit = match Intersection::hit(&xs) {
+            Some(hit) => hit,
+            None => {
+                return ColorTerms {
+                    ambient: self.background(ray),
+                    diffuse: BLACK,
+                    specular: BLACK,
+                    reflection: BLACK,
+                    refraction: BLACK,
+                }
+            }
+        };
+
+        let comps = hit.prepare_computations(ray, &xs, Some(self));
+        let light = self.get_light().expect("World has no light!");
+        let ctx = LightingContext::new(light, comps.over_point, comps.eyev, comps.normalv)
+            .with_transmission(self.hit_transmission(&comps))
+            .with_ambient(self.ambient_at(comps.over_point, comps.normalv));
+        let terms = lighting_terms(comps.material, comps.object, &ctx);
+
+        let reflective = comps.material.reflective_at(comps.object, comps.over_point);
+        let transparency = comps
+            .material
+            .transparency_at(comps.object, comps.over_point);
+        let (reflect_weight, refract_weight) = if reflective > 0.0 && transparency > 0.0 {
             let reflectance = comps.schlick();
-            surface + reflected * reflectance + refracted * (1.0 - reflectance)
+            (reflectance, 1.0 - reflectance)
         } else {
-            surface + reflected + refracted
+            (1.0, 1.0)
+        };
+
+        ColorTerms {
+            ambient: terms.ambient + terms.emission,
+            diffuse: terms.diffuse,
+            specular: terms.specular,
+            reflection: self.reflected_color(&comps, remaining) * reflect_weight,
+            refraction: self.refracted_color(&comps, remaining) * refract_weight,
         }
     }
 
-    /// Compute the Color of a Ray.
-    pub fn color_at(&self, ray: &Ray, remaining: usize) -> RGB {
-        match self.intersect_world(ray) {
-            Some(xs) => match Intersection::hit(&xs) {
-                Some(i) => {
-                    let comps = i.prepare_computations(&ray, &xs, None);
-                    self.shade_hit(&comps, remaining)
-                }
-                None => BLACK,
-            },
-            None => BLACK,
+    /// Compute the Color of a reflected or refracted Ray.
+    pub fn color_at(&self, ray: &Ray, remaining: Budget) -> RGB {
+        self.color_of(ray, self.intersect_secondary(ray), remaining)
+    }
+
+    /// Compute the Color of a camera Ray, skipping culled objects.
+    pub fn color_at_primary(&self, ray: &Ray, remaining: Budget) -> RGB {
+        self.color_of(ray, self.intersect_primary(ray), remaining)
+    }
+
+    /// Compute the Color of many reflected or refracted Rays, in the order of `rays`.
+    /// The intersections of all rays share one buffer, which makes this
+    /// faster than calling `color_at` in a loop.
+    pub fn color_at_batch(&self, rays: &[Ray], remaining: Budget) -> Vec<RGB> {
+        let mut xs = Vec::new();
+        rays.iter()
+            .map(|ray| {
+                self.intersect_into(ray, RayKind::Secondary, &mut xs);
+                self.color_of_hits(ray, &xs, remaining)
+            })
+            .collect()
+    }
+
+    /// Compute the Color of many camera Rays, in the order of `rays`,
+    /// see `color_at_batch`.
+    pub fn color_at_primary_batch(&self, rays: &[Ray], remaining: Budget) -> Vec<RGB> {
+        let mut xs = Vec::new();
+        rays.iter()
+            .map(|ray| {
+                self.intersect_into(ray, RayKind::Camera, &mut xs);
+                self.color_of_hits(ray, &xs, remaining)
+            })
+            .collect()
+    }
+
+    fn color_of(&self, ray: &Ray, xs: Option<Vec<Intersection>>, remaining: Budget) -> RGB {
+        self.color_of_hits(ray, &xs.unwrap_or_default(), remaining)
+    }
+
+    fn color_of_hits(&self, ray: &Ray, xs: &Vec<Intersection>, remaining: Budget) -> RGB {
+        match Intersection::hit(xs) {
+            Some(i) => {
+                let comps = i.prepare_computations(ray, xs, Some(self));
+                self.shade_hit(&comps, remaining)
+            }
+            None => self.background(ray),
+        }
+    }
+
+    /// Test if the hit of `comps` is in shadows, unless its object ignores them.
+    pub fn is_hit_shadowed(&self, comps: &Computation) -> bool {
+        self.object_flags(comps.object).receive_shadows
+            && self.is_shadowed_at(comps.over_point, comps.normalv)
+    }
+
+    /// Fraction of the light reaching the hit of `comps`, see `transmission`.
+    /// Objects that don't receive shadows are always fully lit. Light behind
+    /// the surface only reaches it through subsurface scattering, which the
+    /// object itself doesn't block.
+    pub fn hit_transmission(&self, comps: &Computation) -> RGB {
+        if !self.object_flags(comps.object).receive_shadows {
+            WHITE
+        } else if self.faces_away(comps.over_point, comps.normalv) {
+            if comps.material.subsurface > 0.0 {
+                self.transmission_past(comps.over_point, Some(comps.object.id()))
+            } else {
+                BLACK
+            }
+        } else {
+            self.transmission(comps.over_point)
+        }
+    }
+
+    /// Is the light behind the surface at `p` with the normal `normalv`?
+    fn faces_away(&self, p: Point, normalv: Vector) -> bool {
+        let lightv = self.light.expect("World has no light!").get_position() - p;
+        lightv.dot(normalv) <= 0.0
+    }
+
+    /// Fraction of the light reaching `p` per channel, WHITE if nothing is in the way.
+    /// Opaque objects block all light, transparent ones let `transparency` of it
+    /// through, tinted by their color if the material has `shadow_tint` set.
+    /// Each object filters the light once, whether the ray passes through it,
+    /// starts inside of it or crosses a single surface like a plane.
+    pub fn transmission(&self, p: Point) -> RGB {
+        self.transmission_past(p, None)
+    }
+
+    /// `transmission`, but the shape with the id `ignored` lets all light pass.
+    fn transmission_past(&self, p: Point, ignored: Option<Uuid>) -> RGB {
+        let v = self.light.expect("World has no light!").get_position() - p;
+        let distance = v.magnitude();
+        let r = Ray::new(p, v.normalize());
+
+        // opaque blockers settle it without collecting intersections,
+        // unless the ignored shape could be one of them or inside of one
+        let opaque = self
+            .opaque
+            .get_or_init(|| self.objects.iter().map(|o| o.is_opaque()).collect());
+        let objects = self.objects.iter().zip(opaque);
+        let skip_opaque = ignored.is_none();
+        if skip_opaque
+            && objects
+                .clone()
+                .any(|(o, &opaque)| opaque && o.intersects_before(&r, distance))
+        {
+            return BLACK;
+        }
+
+        let mut xs = Vec::new();
+        for (o, _) in objects.filter(|(_, &opaque)| !(skip_opaque && opaque)) {
+            o.intersect_into(&r, &mut xs);
+        }
+        xs.retain(|x| x.t >= 0.0 && x.t < distance && Some(x.object.id()) != ignored);
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mut transmission = WHITE;
+        let mut crossed: Vec<&Intersection> = Vec::new();
+        for x in &xs {
+            if crossed.iter().any(|c| c.same_shape(x)) {
+                continue;
+            }
+            crossed.push(x);
+
+            let point = r.position(x.t);
+            let material = x.material_at(point, Some(self));
+            let transparency = material.transparency_at(x.object, point);
+            if transparency <= 0.0 {
+                return BLACK;
+            }
+
+            let mut filter = WHITE * transparency.min(1.0);
+            if material.shadow_tint {
+                let c = material.color_at(x.object, point);
+                filter = filter
+                    * RGB::new(
+                        c.red.clamp(0.0, 1.0),
+                        c.green.clamp(0.0, 1.0),
+                        c.blue.clamp(0.0, 1.0),
+                    );
+            }
+            transmission = transmission * filter;
+            if transmission == BLACK {
+                return BLACK;
+            }
         }
+
+        transmission
+    }
+
+    /// Test if a point on a surface with the normal `normalv` is in shadows.
+    /// A light behind the surface can't reach it, so no shadow ray is needed.
+    pub fn is_shadowed_at(&self, p: Point, normalv: Vector) -> bool {
+        self.faces_away(p, normalv) || self.is_shadowed(p)
     }
 
     /// Test if a point is in shadows.
@@ -123,77 +947,213 @@ impl World {
         let direction = v.normalize();
 
         let r = Ray::new(p, direction);
-        if let Some(intersections) = self.intersect_world(&r) {
-            if let Some(h) = Intersection::hit(&intersections) {
-                if h.t < distance {
-                    return true;
-                }
-            }
-        }
+        self.intersect_shadow(&r, distance)
+    }
 
-        false
+    /// Is there any object along `ray` closer than `max_t`?
+    /// Stops at the first blocker instead of collecting every intersection.
+    pub fn intersect_shadow(&self, ray: &Ray, max_t: Float) -> bool {
+        self.objects.iter().any(|o| o.intersects_before(ray, max_t))
     }
 
     /// Compute the reflected color.
-    pub fn reflected_color(&self, comps: &Computation, remaining: usize) -> RGB {
-        if float_eq(comps.object.get_material().reflective, 0.0) || remaining == 0 {
+    pub fn reflected_color(&self, comps: &Computation, remaining: Budget) -> RGB {
+        let reflective = comps.material.reflective_at(comps.object, comps.over_point);
+        if float_eq(reflective, 0.0) || remaining.reflection == 0 {
             return BLACK;
         }
 
         let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
-        let color = self.color_at(&reflect_ray, remaining - 1);
+        let color = self.color_at(&reflect_ray, remaining.reflected());
 
-        color * comps.object.get_material().reflective
+        color * reflective
     }
 
     /// Compute the refracted color.
-    pub fn refracted_color(&self, comps: &Computation, remaining: usize) -> RGB {
-        if float_eq(comps.object.get_material().transparency, 0.0) || remaining == 0 {
+    pub fn refracted_color(&self, comps: &Computation, remaining: Budget) -> RGB {
+        let transparency = comps
+            .material
+            .transparency_at(comps.object, comps.over_point);
+        if float_eq(transparency, 0.0) || remaining.refraction == 0 {
             return BLACK;
         }
 
+        if comps.is_dispersive() {
+            // every channel bends by its own index and is traced separately
+            let mut rgb = [0.0; 3];
+            for (c, channel) in rgb.iter_mut().enumerate() {
+                if let Some(refract_ray) =
+                    Self::refract_ray(comps, comps.n1_rgb[c], comps.n2_rgb[c])
+                {
+                    let color = self.color_at(&refract_ray, remaining.refracted());
+                    *channel = [color.red, color.green, color.blue][c];
+                }
+            }
+            return RGB::new(rgb[0], rgb[1], rgb[2]) * transparency;
+        }
+
+        match Self::refract_ray(comps, comps.n1, comps.n2) {
+            Some(refract_ray) => self.color_at(&refract_ray, remaining.refracted()) * transparency,
+            None => BLACK,
+        }
+    }
+
+    /// The ray refracted at the intersection, None on total internal reflection.
+    pub fn refract_ray(comps: &Computation, n1: Float, n2: Float) -> Option<Ray> {
         // Check for total internal reflection
-        let n_ratio = comps.n1 / comps.n2;
+        let n_ratio = n1 / n2;
         let cos_i = comps.eyev.dot(comps.normalv);
         let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
         if sin2_t > 1.0 {
-            return BLACK;
+            return None;
         }
 
-        let cos_t = (1.0 - sin2_t).sqrt();
-        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
-        let refract_ray = Ray::new(comps.under_point, direction);
-        let color =
-            self.color_at(&&refract_ray, remaining - 1) * comps.object.get_material().transparency;
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+        Some(Ray::new(comps.under_point, direction))
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        let mut w = World::new();
+
+        w.light = Some(PointLight::new(Point::new(-10.0, 10.0, -10.0), WHITE));
+        let mut s1 = Sphere::new();
+        let mut m1 = Material::default();
+        m1.color = RGB::new(0.8, 1.0, 0.6);
+        m1.diffuse = 0.7;
+        m1.specular = 0.2;
+        s1.set_material(m1);
+        add_object!(w, s1);
+
+        let mut s2 = Sphere::new();
+        let t2 = Transformation::new().then_scaling(0.5, 0.5, 0.5);
+        s2.set_transform(t2);
+        add_object!(w, s2);
+
+        w
+    }
+}
+
+/// Declare the lights, objects and camera of a scene in one expression.
+///
+/// ```
+/// use rtracer::*;
+///
+/// let (world, camera) = WorldBuilder::new()
+///     .light(PointLight::new(Point::new(-10.0, 10.0, -10.0), WHITE))
+///     .object(Plane::new())
+///     .object(
+///         Sphere::new()
+///             .with_transform(Transformation::new().then_translation(0.0, 1.0, 0.0))
+///             .with_material(presets::chrome()),
+///     )
+///     .camera(CameraBuilder::new(40, 20).look_at(
+///         Point::new(0.0, 1.5, -5.0),
+///         Point::new(0.0, 1.0, 0.0),
+///         Vector::new(0.0, 1.0, 0.0),
+///     ))
+///     .build()
+///     .unwrap();
+/// let canvas = camera.render(&world);
+/// ```
+pub struct WorldBuilder {
+    world: World,
+    camera: CameraBuilder,
+    library: Library,
+}
+
+impl WorldBuilder {
+    /// An empty world without light, seen by a camera looking down -z.
+    pub fn new() -> Self {
+        Self {
+            world: World::new(),
+            camera: CameraBuilder::new(100, 100),
+            library: Library::new(),
+        }
+    }
+
+    /// The light of the world.
+    pub fn light(mut self, light: PointLight) -> Self {
+        self.world.set_light(light);
+        self
+    }
+
+    /// Add an object.
+    pub fn object<S: Shape>(mut self, object: S) -> Self {
+        self.world.add_object(Box::new(object));
+        self
+    }
+
+    /// Presets objects can refer to by name, see `object_with_material`.
+    /// Layered on top of the presets added before.
+    pub fn library(mut self, library: Library) -> Self {
+        self.library.merge(library);
+        self
+    }
+
+    /// Add an object with the material preset `name` of the library.
+    pub fn object_with_material<S: Shape>(
+        self,
+        mut object: S,
+        name: &str,
+    ) -> Result<Self, LibraryError> {
+        let material = self
+            .library
+            .material(name)
+            .ok_or_else(|| LibraryError::UnknownMaterial(name.to_string()))?;
+        object.set_material(material);
+        Ok(self.object(object))
+    }
+
+    /// Add an object with flags that differ from the default.
+    pub fn object_with_flags<S: Shape>(mut self, object: S, flags: ObjectFlags) -> Self {
+        self.world.set_object_flags(object.id(), flags);
+        self.object(object)
+    }
+
+    /// Shader of every hit, see `World::set_shader`.
+    pub fn shader(mut self, shader: Box<dyn Shader>) -> Self {
+        self.world.set_shader(shader);
+        self
+    }
 
-        color
+    /// Ambient light of the scene, see `World::set_ambient`.
+    pub fn ambient(mut self, ambient: RGB) -> Self {
+        self.world.set_ambient(ambient);
+        self
     }
-}
 
-impl Default for World {
-    fn default() -> Self {
-        let mut w = World::new();
+    /// Environment seen in the background and in reflections, see `World::set_environment`.
+    pub fn environment(mut self, environment: EnvironmentMap) -> Self {
+        self.world.set_environment(environment);
+        self
+    }
 
-        w.light = Some(PointLight::new(Point::new(-10.0, 10.0, -10.0), WHITE));
-        let mut s1 = Sphere::new();
-        let mut m1 = Material::default();
-        m1.color = RGB::new(0.8, 1.0, 0.6);
-        m1.diffuse = 0.7;
-        m1.specular = 0.2;
-        s1.set_material(m1);
-        add_object!(w, s1);
+    /// Light the ambient term by the environment, see `World::set_environment_samples`.
+    pub fn environment_samples(mut self, samples: usize) -> Self {
+        self.world.set_environment_samples(samples);
+        self
+    }
 
-        let mut s2 = Sphere::new();
-        let t2 = Transformation::new().scaling(0.5, 0.5, 0.5);
-        s2.set_transform(t2);
-        add_object!(w, s2);
+    /// The camera looking at the world.
+    pub fn camera(mut self, camera: CameraBuilder) -> Self {
+        self.camera = camera;
+        self
+    }
 
-        w
+    /// The world and the camera, or why the camera can't see it.
+    pub fn build(self) -> Result<(World, Camera), CameraError> {
+        Ok((self.world, self.camera.build()?))
     }
 }
 
-unsafe impl Send for World {}
-unsafe impl Sync for World {}
+impl Default for WorldBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -221,7 +1181,7 @@ mod test {
         s1.set_material(m1);
 
         let mut s2 = Sphere::new();
-        let t2 = Transformation::new().scaling(0.5, 0.5, 0.5);
+        let t2 = Transformation::new().then_scaling(0.5, 0.5, 0.5);
         s2.set_transform(t2);
 
         assert_eq!(w.objects.len(), 2);
@@ -270,7 +1230,7 @@ mod test {
         let i = Intersection::new(4.0, shape);
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
-        let c = w.shade_hit(&comps, 0);
+        let c = w.shade_hit(&comps, Budget::uniform(0));
 
         assert_eq!(c, RGB::new(0.38066, 0.47583, 0.2855));
     }
@@ -286,7 +1246,7 @@ mod test {
         let i = Intersection::new(0.5, shape);
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
-        let c = w.shade_hit(&comps, 0);
+        let c = w.shade_hit(&comps, Budget::uniform(0));
 
         assert_eq!(c, RGB::new(0.90498, 0.90498, 0.90498));
     }
@@ -295,7 +1255,7 @@ mod test {
     fn color_miss_world() {
         let w = World::default();
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
-        let c = w.color_at(&r, 0);
+        let c = w.color_at(&r, Budget::uniform(0));
 
         assert_eq!(c, BLACK);
     }
@@ -304,7 +1264,7 @@ mod test {
     fn color_hit_world() {
         let w = World::default();
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
-        let c = w.color_at(&r, 0);
+        let c = w.color_at(&r, Budget::uniform(0));
 
         assert_eq!(c, RGB::new(0.38066, 0.47583, 0.2855));
     }
@@ -326,11 +1286,192 @@ mod test {
             .get_object(1)
             .expect("First object must exists in default world!");
         let r = Ray::new(Point::new(0.0, 0.0, 0.75), Vector::new(0.0, 0.0, -1.0));
-        let c = w.color_at(&r, 0);
+        let c = w.color_at(&r, Budget::uniform(0));
 
         assert_eq!(c, inner.get_material().color);
     }
 
+    #[test]
+    fn pick_world() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let (id, t, point, normal) = w.pick(&r).expect("The ray hits the outer sphere");
+
+        assert_eq!(
+            id,
+            w.get_object(0).expect("Default world has two shapes").id()
+        );
+        assert_eq!(t, 4.0);
+        assert_eq!(point, Point::new(0.0, 0.0, -1.0));
+        assert_eq!(normal, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn pick_miss_world() {
+        let w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+
+        assert!(w.pick(&r).is_none());
+    }
+
+    #[test]
+    fn pick_group_child_world() {
+        let mut w = World::new();
+        let mut g = Group::new();
+        g.set_transform(Transformation::new().then_translation(0.0, 0.0, 5.0));
+        let s = Sphere::new();
+        let s_id = s.id();
+        g.add_object(Box::new(s));
+        add_object!(w, g);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let (id, t, point, normal) = w.pick(&r).expect("The ray hits the sphere");
+
+        assert_eq!(id, s_id);
+        assert_eq!(t, 9.0);
+        assert_eq!(point, Point::new(0.0, 0.0, 4.0));
+        assert_eq!(normal, Vector::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn handles_world() {
+        let mut w = World::new();
+        let mut outer = Group::new();
+        outer.set_transform(Transformation::new().then_rotate_y(PI / 2.0));
+        let mut inner = Group::new();
+        inner.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
+        let mut s = Sphere::new();
+        s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
+        let (inner_id, s_id) = (inner.id(), s.id());
+        inner.add_object(Box::new(s));
+        outer.add_object(Box::new(inner));
+        add_object!(w, outer);
+
+        let s = w.handle(s_id).unwrap();
+        let inner = w.handle(inner_id).unwrap();
+        assert_eq!(w.parent(s), Some(inner));
+        assert_eq!(w.parent(w.parent(inner).unwrap()), None);
+        assert_eq!(w.object(s).unwrap().id(), s_id);
+        assert!(w.handle(Uuid::new_v4()).is_none());
+
+        assert_eq!(
+            w.world_to_object(s, Point::new(-2.0, 0.0, -10.0)),
+            Point::new(0.0, 0.0, -1.0)
+        );
+        let v = (3.0 as Float).sqrt() / 3.0;
+        assert_eq!(
+            w.normal_to_world(s, Vector::new(v, v, v)),
+            Vector::new(v, v, -v)
+        );
+    }
+
+    #[test]
+    fn apply_frame_world() {
+        let mut w = World::new();
+        let mut body = Group::new();
+        let mut arm = Group::new();
+        arm.add_object(Box::new(Sphere::new()));
+        let (body_id, arm_id) = (body.id(), arm.id());
+        body.add_object(Box::new(arm));
+        add_object!(w, body);
+        assert!(w.set_object_name(body_id, "body"));
+        assert!(w.set_object_name(arm_id, "arm"));
+        assert!(!w.set_object_name(Uuid::new_v4(), "leg"));
+
+        let r = Ray::new(Point::new(0.0, 5.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(w.intersect_world(&r).is_none());
+
+        let generation = w.generation();
+        let mut frame = HashMap::new();
+        frame.insert(
+            "arm".to_string(),
+            Transformation::new().then_translation(0.0, 5.0, 0.0),
+        );
+        w.apply_frame(&frame).unwrap();
+        assert!(w.generation() > generation);
+        assert_eq!(
+            w.object_by_name("arm").unwrap().get_transform(),
+            Transformation::new().then_translation(0.0, 5.0, 0.0)
+        );
+        // the cached bounds of the body follow the arm
+        assert_eq!(w.intersect_world(&r).unwrap().len(), 2);
+
+        frame.insert(
+            "leg".to_string(),
+            Transformation::new().then_translation(0.0, 9.0, 0.0),
+        );
+        assert_eq!(
+            w.apply_frame(&frame),
+            Err(FrameError::UnknownName("leg".to_string()))
+        );
+        let mut frame = HashMap::new();
+        frame.insert(
+            "body".to_string(),
+            Transformation::new().then_scaling(0.0, 1.0, 1.0),
+        );
+        assert!(matches!(
+            w.apply_frame(&frame),
+            Err(FrameError::InvalidTransform(_, TransformError::Singular))
+        ));
+        assert_eq!(
+            w.object_by_name("body").unwrap().get_transform(),
+            Transformation::new()
+        );
+    }
+
+    #[test]
+    fn cull_frustum_world() {
+        let mut w = World::default();
+        let mut behind = Sphere::new();
+        behind.set_transform(Transformation::new().then_translation(0.0, 0.0, -10.0));
+        add_object!(w, behind);
+        let mut c = Camera::new(11, 11, crate::consts::PI / 2.0);
+        c.transform = Transformation::view_transformation(
+            Point::new(0.0, 0.0, -5.0),
+            Point::new(0.0, 0.0, 0.0),
+            Vector::new(0.0, 1.0, 0.0),
+        );
+        w.cull_frustum(&c);
+
+        assert!(w.is_primary_visible(0));
+        assert!(w.is_primary_visible(1));
+        assert!(!w.is_primary_visible(2));
+
+        let r = Ray::new(Point::new(0.0, 0.0, -20.0), Vector::new(0.0, 0.0, 1.0));
+        let primary = w
+            .intersect_primary(&r)
+            .expect("The default spheres are hit");
+        let all = w.intersect_world(&r).expect("All spheres are hit");
+
+        assert_eq!(primary.len(), 4);
+        assert_eq!(all.len(), 6);
+
+        w.clear_culling();
+        assert!(w.is_primary_visible(2));
+    }
+
+    #[test]
+    fn generation_world() {
+        let mut w = World::default();
+        let c = Camera::new(11, 11, crate::consts::PI / 2.0);
+        let start = w.generation();
+        w.cull_frustum(&c);
+
+        assert_eq!(w.generation(), start);
+        let _ = w.get_object(0);
+        assert_eq!(w.generation(), start);
+
+        w.get_object_mut(0)
+            .expect("Default world has two shapes!")
+            .set_transform(Transformation::new().then_translation(0.0, 0.0, -10.0));
+        assert!(w.generation() > start);
+        // the stale culling is dropped
+        assert!(w.is_primary_visible(0));
+
+        let before = w.generation();
+        add_object!(w, Sphere::new());
+        assert!(w.generation() > before);
+    }
+
     #[test]
     fn point_collinear_light_world() {
         let w = World::default();
@@ -370,13 +1511,13 @@ mod test {
         let s1 = Sphere::new();
         add_object!(w, s1);
         let mut s2 = Sphere::new();
-        s2.set_transform(Transformation::new().translation(0.0, 0.0, -10.0));
+        s2.set_transform(Transformation::new().then_translation(0.0, 0.0, -10.0));
         add_object!(w, s2);
         let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
         let i = Intersection::new(4.0, w.get_object(1).expect("Where is it?"));
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
-        let c = w.shade_hit(&comps, 0);
+        let c = w.shade_hit(&comps, Budget::uniform(0));
 
         assert_eq!(c, RGB::new(0.1, 0.1, 0.1));
     }
@@ -392,7 +1533,7 @@ mod test {
         let i = Intersection::new(1.0, w.get_object(1).expect("Default world has 2 spheres"));
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
-        let color = w.reflected_color(&comps, 0);
+        let color = w.reflected_color(&comps, Budget::uniform(0));
 
         assert_eq!(color, BLACK);
     }
@@ -402,19 +1543,23 @@ mod test {
         let mut w = World::default();
         let mut shape = Plane::new();
         shape.get_material_mut().reflective = 0.5;
-        shape.set_transform(Transformation::new().translation(0.0, -1.0, 0.0));
+        shape.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
         add_object!(w, shape);
         let r = Ray::new(
             Point::new(0.0, 0.0, -3.0),
-            Vector::new(0.0, -(2_f64.sqrt() / 2.0), 2_f64.sqrt() / 2.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt() / 2.0),
+                (2.0 as Float).sqrt() / 2.0,
+            ),
         );
         let i = Intersection::new(
-            2_f64.sqrt(),
+            (2.0 as Float).sqrt(),
             w.get_object(2).expect("I just added this plane?"),
         );
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
-        let color = w.reflected_color(&comps, 4);
+        let color = w.reflected_color(&comps, Budget::uniform(4));
 
         assert_eq!(color, RGB::new(0.19032, 0.2379, 0.14274));
     }
@@ -424,19 +1569,23 @@ mod test {
         let mut w = World::default();
         let mut shape = Plane::new();
         shape.get_material_mut().reflective = 0.5;
-        shape.set_transform(Transformation::new().translation(0.0, -1.0, 0.0));
+        shape.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
         add_object!(w, shape);
         let r = Ray::new(
             Point::new(0.0, 0.0, -3.0),
-            Vector::new(0.0, -(2_f64.sqrt() / 2.0), 2_f64.sqrt() / 2.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt() / 2.0),
+                (2.0 as Float).sqrt() / 2.0,
+            ),
         );
         let i = Intersection::new(
-            2_f64.sqrt(),
+            (2.0 as Float).sqrt(),
             w.get_object(2).expect("I just added this plane?"),
         );
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
-        let color = w.shade_hit(&comps, 4);
+        let color = w.shade_hit(&comps, Budget::uniform(4));
 
         assert_eq!(color, RGB::new(0.87677, 0.92436, 0.82918));
     }
@@ -447,15 +1596,15 @@ mod test {
         w.set_light(PointLight::new(Point::new(0.0, 0.0, 0.0), WHITE));
         let mut lower = Plane::new();
         lower.get_material_mut().reflective = 1.0;
-        lower.set_transform(Transformation::new().translation(0.0, -1.0, 0.0));
+        lower.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
         add_object!(w, lower);
         let mut upper = Plane::new();
         upper.get_material_mut().reflective = 1.0;
-        upper.set_transform(Transformation::new().translation(0.0, 1.0, 0.0));
+        upper.set_transform(Transformation::new().then_translation(0.0, 1.0, 0.0));
         add_object!(w, upper);
         let r = Ray::new(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0));
 
-        w.color_at(&r, 4);
+        w.color_at(&r, Budget::uniform(4));
     }
 
     #[test]
@@ -463,19 +1612,23 @@ mod test {
         let mut w = World::default();
         let mut shape = Plane::new();
         shape.get_material_mut().reflective = 0.5;
-        shape.set_transform(Transformation::new().translation(0.0, -1.0, 0.0));
+        shape.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
         add_object!(w, shape);
         let r = Ray::new(
             Point::new(0.0, 0.0, -3.0),
-            Vector::new(0.0, -(2_f64.sqrt() / 2.0), 2_f64.sqrt() / 2.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt() / 2.0),
+                (2.0 as Float).sqrt() / 2.0,
+            ),
         );
         let i = Intersection::new(
-            2_f64.sqrt(),
+            (2.0 as Float).sqrt(),
             w.get_object(2).expect("I just added this plane?"),
         );
         let xs = &vec![i];
         let comps = i.prepare_computations(&r, xs, None);
-        let color = w.reflected_color(&comps, 0);
+        let color = w.reflected_color(&comps, Budget::uniform(0));
 
         assert_eq!(color, BLACK);
     }
@@ -487,7 +1640,7 @@ mod test {
         let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
         let xs = vec![Intersection::new(4.0, shape), Intersection::new(6.0, shape)];
         let comps = xs[0].prepare_computations(&r, &xs, None);
-        let c = w.refracted_color(&comps, 5);
+        let c = w.refracted_color(&comps, Budget::uniform(5));
 
         assert_eq!(c, BLACK);
     }
@@ -506,7 +1659,7 @@ mod test {
             Intersection::new(6.0, w.get_object(0).expect("where")),
         ];
         let comps = xs[0].prepare_computations(&r, &xs, None);
-        let c = w.refracted_color(&comps, 0);
+        let c = w.refracted_color(&comps, Budget::uniform(0));
 
         assert_eq!(c, BLACK);
     }
@@ -520,15 +1673,15 @@ mod test {
             shape.get_material_mut().refractive_index = 1.5;
         }
         let r = Ray::new(
-            Point::new(0.0, 0.0, 2_f64.sqrt() / 2.0),
+            Point::new(0.0, 0.0, (2.0 as Float).sqrt() / 2.0),
             Vector::new(0.0, 1.0, 0.0),
         );
         let xs = vec![
-            Intersection::new(-2_f64.sqrt() / 2.0, w.get_object(0).expect("how")),
-            Intersection::new(2_f64.sqrt() / 2.0, w.get_object(0).expect("where")),
+            Intersection::new(-(2.0 as Float).sqrt() / 2.0, w.get_object(0).expect("how")),
+            Intersection::new((2.0 as Float).sqrt() / 2.0, w.get_object(0).expect("where")),
         ];
         let comps = xs[1].prepare_computations(&r, &xs, None);
-        let c = w.refracted_color(&comps, 5);
+        let c = w.refracted_color(&comps, Budget::uniform(5));
 
         assert_eq!(c, BLACK);
     }
@@ -552,16 +1705,47 @@ mod test {
             Intersection::new(0.9899, w.get_object(0).expect("how")),
         ];
         let comps = xs[2].prepare_computations(&r, &xs, None);
-        let c = w.refracted_color(&comps, 5);
+        let c = w.refracted_color(&comps, Budget::uniform(5));
+
+        // the refracted ray starts one surface bias below the hit, which is
+        // coarser in single precision
+        let expected = RGB::new(0.0, 0.99888, 0.04725);
+        assert!((c.green - expected.green).abs() < 2.0 * EPSILON);
+        assert!((c.blue - expected.blue).abs() < 2.0 * EPSILON);
+        assert_eq!(c.red, 0.0);
+    }
+
+    #[test]
+    fn refracted_color_dispersion() {
+        let mut w = World::default();
+        {
+            let a = w.get_object_mut(0).expect("Must be here");
+            a.get_material_mut().ambient = 1.0;
+            set_pattern!(a, TestPattern::new());
+            let b = w.get_object_mut(1).expect("Must be here");
+            b.get_material_mut().transparency = 1.0;
+            b.get_material_mut().refractive_index = 1.5;
+            b.get_material_mut().dispersion = Some([1.5, 1.2, 1.5]);
+        }
+        let r = Ray::new(Point::new(0.0, 0.0, 0.1), Vector::new(0.0, 1.0, 0.0));
+        let xs = vec![
+            Intersection::new(-0.9899, w.get_object(0).expect("how")),
+            Intersection::new(-0.4899, w.get_object(1).expect("how")),
+            Intersection::new(0.4899, w.get_object(1).expect("how")),
+            Intersection::new(0.9899, w.get_object(0).expect("how")),
+        ];
+        let comps = xs[2].prepare_computations(&r, &xs, None);
+        let c = w.refracted_color(&comps, Budget::uniform(5));
 
-        assert_eq!(c, RGB::new(0.0, 0.99888, 0.04725));
+        assert!(float_eq(c.blue, 0.04725));
+        assert!(!float_eq(c.green, 0.99888));
     }
 
     #[test]
     fn transparent_shade_hit() {
         let mut w = World::default();
         let mut floor = Plane::new();
-        floor.set_transform(Transformation::new().translation(0.0, -1.0, 0.0));
+        floor.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
         floor.get_material_mut().reflective = 0.5;
         floor.get_material_mut().transparency = 0.5;
         floor.get_material_mut().refractive_index = 1.5;
@@ -569,19 +1753,538 @@ mod test {
         let mut ball = Sphere::new();
         ball.get_material_mut().color = RED;
         ball.get_material_mut().ambient = 0.5;
-        ball.set_transform(Transformation::new().translation(0.0, -3.5, -0.5));
+        ball.set_transform(Transformation::new().then_translation(0.0, -3.5, -0.5));
         add_object!(w, ball);
         let r = Ray::new(
             Point::new(0.0, 0.0, -3.0),
-            Vector::new(0.0, -(2_f64.sqrt() / 2.0), 2_f64.sqrt() / 2.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt() / 2.0),
+                (2.0 as Float).sqrt() / 2.0,
+            ),
+        );
+        let xs = vec![Intersection::new(
+            (2.0 as Float).sqrt(),
+            w.get_object(2).expect("how"),
+        )];
+        let comps = xs[0].prepare_computations(&r, &xs, None);
+        let c = w.shade_hit(&comps, Budget::uniform(5));
+
+        // half of the light reaches the ball through the floor,
+        // with an opaque shadow the red channel would be 0.93391
+        assert_eq!(c, RGB::new(1.11500, 0.69643, 0.69243));
+    }
+
+    #[test]
+    fn separate_budgets_world() {
+        let mut w = World::default();
+        let mut floor = Plane::new();
+        floor.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
+        floor.get_material_mut().reflective = 0.5;
+        floor.get_material_mut().transparency = 0.5;
+        floor.get_material_mut().refractive_index = 1.5;
+        add_object!(w, floor);
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -3.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt() / 2.0),
+                (2.0 as Float).sqrt() / 2.0,
+            ),
         );
         let xs = vec![Intersection::new(
-            2_f64.sqrt(),
+            (2.0 as Float).sqrt(),
             w.get_object(2).expect("how"),
         )];
         let comps = xs[0].prepare_computations(&r, &xs, None);
-        let c = w.shade_hit(&comps, 5);
 
-        assert_eq!(c, RGB::new(0.93391, 0.69643, 0.69243));
+        assert_eq!(w.reflected_color(&comps, Budget::new(0, 5)), BLACK);
+        assert_ne!(w.reflected_color(&comps, Budget::new(5, 0)), BLACK);
+        assert_eq!(w.refracted_color(&comps, Budget::new(5, 0)), BLACK);
+        assert_eq!(Budget::new(2, 3).reflected(), Budget::new(1, 3));
+        assert_eq!(Budget::new(2, 3).refracted(), Budget::new(2, 2));
+    }
+
+    #[test]
+    fn environment_world() {
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(&r, Budget::default()), BLACK);
+
+        w.set_environment(EnvironmentMap::new(1, 1, vec![RGB::new(0.2, 0.4, 0.8)]));
+        assert_eq!(w.color_at(&r, Budget::default()), RGB::new(0.2, 0.4, 0.8));
+
+        // a mirror in an empty world shows the environment
+        let mut w = World::new();
+        w.set_light(PointLight::new(Point::new(0.0, 0.0, -5.0), BLACK));
+        w.set_environment(EnvironmentMap::new(1, 1, vec![WHITE]));
+        let mut p = Plane::new();
+        p.get_material_mut().reflective = 0.5;
+        p.get_material_mut().ambient = 0.0;
+        p.set_transform(Transformation::new().then_rotate_x(PI / 2.0));
+        add_object!(w, p);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, Budget::default()), RGB::new(0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn environment_lighting_world() {
+        let mut w = World::new();
+        let up = Vector::new(0.0, 1.0, 0.0);
+        assert_eq!(
+            w.environment_lighting(Point::new(0.0, 0.0, 0.0), up, 16),
+            BLACK
+        );
+
+        // a uniform white sky lights a white surface fully
+        w.set_environment(EnvironmentMap::new(64, 32, vec![WHITE; 64 * 32]));
+        let c = w.environment_lighting(Point::new(0.0, 0.0, 0.0), up, 1024);
+        assert!((c.red - 1.0).abs() < 0.02);
+
+        // a small sun gets every sample
+        let mut pixels = vec![BLACK; 64 * 32];
+        pixels[32 + 64 * 4] = RGB::new(1000.0, 1000.0, 1000.0);
+        let map = EnvironmentMap::new(64, 32, pixels);
+        let (sun, _) = map.sampler().sample(0.5, 0.5);
+        w.set_environment(map);
+        let lit = w.environment_lighting(Point::new(0.0, 0.0, 0.0), up, 16);
+        assert!(lit.red > 0.0);
+        assert!(float_eq(lit.red, lit.blue));
+
+        // which an object can block
+        let mut s = Sphere::new();
+        let center = Point::new(0.0, 0.0, 0.0) + sun * 3.0;
+        s.set_transform(Transformation::new().then_translation(center.x, center.y, center.z));
+        add_object!(w, s);
+        assert_eq!(
+            w.environment_lighting(Point::new(0.0, 0.0, 0.0), up, 16),
+            BLACK
+        );
+    }
+
+    #[test]
+    fn environment_ambient_world() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(Point::new(0.0, 10.0, 0.0), WHITE));
+        w.set_environment(EnvironmentMap::new(
+            64,
+            32,
+            vec![RGB::new(0.5, 0.5, 0.5); 64 * 32],
+        ));
+        let mut floor = Plane::new();
+        floor.get_material_mut().ambient = 1.0;
+        floor.get_material_mut().diffuse = 0.0;
+        floor.get_material_mut().specular = 0.0;
+        add_object!(w, floor);
+        let r = Ray::new(Point::new(0.0, 1.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        assert_eq!(w.color_at(&r, Budget::uniform(0)), WHITE);
+
+        // the gray sky lights the floor from above
+        w.set_environment_samples(256);
+        let c = w.color_at(&r, Budget::uniform(0));
+        assert!((c.red - 0.5).abs() < 0.02);
+        assert!(float_eq(c.red, c.blue));
+    }
+
+    #[test]
+    fn units_world() {
+        let mut w = World::new();
+        assert_eq!(w.get_units(), None);
+        assert_eq!(w.epsilon(), EPSILON);
+
+        w.set_units(Units::Centimeters);
+        assert_eq!(w.get_units(), Some(Units::Centimeters));
+        assert!(float_eq(w.epsilon(), EPSILON * 100.0));
+
+        w.set_light(PointLight::new(Point::new(0.0, 0.0, 0.0), WHITE));
+        assert_eq!(w.get_light().unwrap().get_units(), Units::Centimeters);
+    }
+
+    #[test]
+    fn receive_shadows_world() {
+        let mut w = World::new();
+        w.light = Some(PointLight::new(Point::new(0.0, 0.0, -10.0), WHITE));
+        add_object!(w, Sphere::new());
+        let mut s2 = Sphere::new();
+        s2.set_transform(Transformation::new().then_translation(0.0, 0.0, 10.0));
+        let id = s2.id();
+        add_object!(w, s2);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let shade = |w: &World| {
+            let i = Intersection::new(14.0, w.get_object(1).unwrap());
+            let comps = i.prepare_computations(&r, &vec![i], Some(w));
+            (
+                w.is_hit_shadowed(&comps),
+                w.shade_hit(&comps, Budget::uniform(0)),
+            )
+        };
+        assert_eq!(shade(&w), (true, RGB::new(0.1, 0.1, 0.1)));
+
+        w.set_object_flags(
+            id,
+            ObjectFlags {
+                receive_shadows: false,
+                ..ObjectFlags::default()
+            },
+        );
+        assert_eq!(shade(&w), (false, RGB::new(1.9, 1.9, 1.9)));
+    }
+
+    #[test]
+    fn camera_flag_world() {
+        let mut w = World::default();
+        let outer = w.get_object(0).unwrap().id();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        w.set_object_flags(outer, ObjectFlags::reflections_only());
+
+        // camera rays see the inner sphere, others still hit the outer one
+        let xs = w.intersect_primary(&r).unwrap();
+        assert_eq!(xs.len(), 2);
+        assert!(float_eq(xs[0].t, 4.5));
+        assert_eq!(w.intersect_secondary(&r).unwrap().len(), 4);
+        assert_eq!(w.intersect_world(&r).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn reflections_flag_world() {
+        let mut w = World::default();
+        let mut floor = Plane::new();
+        floor.get_material_mut().reflective = 0.5;
+        floor.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
+        add_object!(w, floor);
+        let r = Ray::new(
+            Point::new(0.0, 0.0, -3.0),
+            Vector::new(
+                0.0,
+                -((2.0 as Float).sqrt() / 2.0),
+                (2.0 as Float).sqrt() / 2.0,
+            ),
+        );
+        let mirrored = |w: &World| {
+            let i = Intersection::new((2.0 as Float).sqrt(), w.get_object(2).unwrap());
+            let comps = i.prepare_computations(&r, &vec![i], Some(w));
+            w.reflected_color(&comps, Budget::default())
+        };
+        assert_ne!(mirrored(&w), BLACK);
+
+        // neither sphere shows up in the mirror, but both for the camera
+        for index in 0..2 {
+            let id = w.get_object(index).unwrap().id();
+            w.set_object_flags(
+                id,
+                ObjectFlags {
+                    reflections: false,
+                    ..ObjectFlags::default()
+                },
+            );
+        }
+        assert_eq!(mirrored(&w), BLACK);
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(w.intersect_primary(&r).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn transmission_world() {
+        let mut w = World::new();
+        w.set_light(PointLight::new(Point::new(0.0, 10.0, 0.0), WHITE));
+        let p = Point::new(0.0, -5.0, 0.0);
+        assert_eq!(w.transmission(p), WHITE);
+
+        let mut glass = Sphere::glass();
+        glass.get_material_mut().transparency = 0.5;
+        glass.get_material_mut().color = RGB::new(1.0, 0.25, 0.0);
+        let id = glass.id();
+        add_object!(w, glass);
+        assert_eq!(w.transmission(p), RGB::new(0.5, 0.5, 0.5));
+
+        w.get_object_mut(0).unwrap().get_material_mut().shadow_tint = true;
+        assert_eq!(w.transmission(p), RGB::new(0.5, 0.125, 0.0));
+        // starting inside of the glass the light passes it once, too
+        let center = Point::new(0.0, 0.0, 0.0);
+        assert_eq!(w.transmission(center), RGB::new(0.5, 0.125, 0.0));
+
+        // a single surface filters as much as a closed object
+        let mut floor = Plane::new();
+        floor.set_transform(Transformation::new().then_translation(0.0, -2.0, 0.0));
+        floor.get_material_mut().transparency = 0.5;
+        add_object!(w, floor);
+        assert_eq!(w.transmission(p), RGB::new(0.25, 0.0625, 0.0));
+
+        // an opaque object blocks everything
+        let mut wall = Cube::new();
+        wall.set_transform(Transformation::new().then_translation(0.0, 5.0, 0.0));
+        add_object!(w, wall);
+        assert_eq!(w.transmission(p), BLACK);
+        assert!(w.get_object_by_id(id).is_some());
+    }
+
+    #[test]
+    fn batch_world() {
+        let w = World::default();
+        let rays = [
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 1.0, 0.0)),
+            Ray::new(Point::new(0.0, 0.0, 0.75), Vector::new(0.0, 0.0, -1.0)),
+        ];
+        let colors = w.color_at_batch(&rays, Budget::uniform(5));
+        let expected: Vec<RGB> = rays
+            .iter()
+            .map(|r| w.color_at(r, Budget::uniform(5)))
+            .collect();
+        assert_eq!(colors, expected);
+
+        let hits = w.intersect_batch(&rays);
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].unwrap().t, 4.0);
+        assert!(hits[1].is_none());
+        assert_eq!(hits[2].unwrap().object.id(), w.get_object(1).unwrap().id());
+    }
+
+    #[test]
+    fn self_exclusion_world() {
+        let mut w = World::default();
+        assert_eq!(w.get_self_exclusion(), 0.0);
+        w.set_self_exclusion(-1.0);
+        assert_eq!(w.get_self_exclusion(), 0.0);
+
+        let outer = w.get_object(0).unwrap().id();
+        let inner = w.get_object(1).unwrap().id();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        let ts = |xs: Option<Vec<Intersection>>| -> Vec<Float> {
+            xs.unwrap_or_default().iter().map(|x| x.t).collect()
+        };
+
+        assert_eq!(
+            ts(w.intersect_excluding(&r, outer, 5.0)),
+            vec![4.5, 5.5, 6.0]
+        );
+        assert_eq!(
+            ts(w.intersect_excluding(&r, inner, 5.0)),
+            vec![4.0, 5.5, 6.0]
+        );
+    }
+
+    #[test]
+    fn self_exclusion_refraction_world() {
+        let mut w = World::new();
+        let mut glass = Sphere::new();
+        glass.set_material(Material::glass());
+        let id = glass.id();
+        add_object!(w, glass);
+        w.set_self_exclusion(0.01);
+
+        // a ray refracted into the glass starts just behind or in front of the surface
+        for z in [-0.9995, -1.0005] {
+            let r = Ray::new(Point::new(0.0, 0.0, z), Vector::new(0.0, 0.0, 1.0));
+            let xs = w.intersect_secondary(&r).unwrap();
+            let hit = World::hit_excluding(&xs, id, w.get_self_exclusion()).unwrap();
+            assert!(float_eq(hit.t, 1.0 - z));
+            let comps = hit.prepare_computations(&r, &xs, Some(&w));
+            assert_eq!((comps.n1, comps.n2), (1.5, 1.0));
+        }
+
+        let r = Ray::new(Point::new(0.0, 0.0, -0.9995), Vector::new(0.0, 0.0, 1.0));
+        let xs = w.intersect_excluding(&r, id, 0.01).unwrap();
+        assert_eq!(xs.len(), 2);
+        let comps = xs[1].prepare_computations(&r, &xs, Some(&w));
+        assert_eq!((comps.n1, comps.n2), (1.5, 1.0));
+    }
+
+    #[test]
+    fn intersect_shadow_world() {
+        let mut w = World::default();
+        let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(w.intersect_shadow(&r, 4.5));
+        assert!(!w.intersect_shadow(&r, 4.0));
+        let away = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(!w.intersect_shadow(&away, 100.0));
+
+        let mut g = Group::new();
+        let mut s = Sphere::new();
+        s.set_transform(Transformation::new().then_translation(0.0, 5.0, 0.0));
+        g.add_object(Box::new(s));
+        g.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
+        add_object!(w, g);
+        let r = Ray::new(Point::new(0.0, 10.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(w.intersect_shadow(&r, 3.5));
+        assert!(!w.intersect_shadow(&r, 2.5));
+    }
+
+    #[test]
+    fn shadowed_at_world() {
+        let w = World::default();
+        let p = Point::new(0.0, 10.0, 0.0);
+
+        assert!(!w.is_shadowed_at(p, Vector::new(0.0, 0.0, -1.0)));
+        // the light at (-10, 10, -10) is behind the surface
+        assert!(w.is_shadowed_at(p, Vector::new(1.0, 0.0, 0.0)));
+        assert!(!w.is_shadowed(p));
+    }
+
+    #[test]
+    fn ambient_world() {
+        let mut w = World::default();
+        assert_eq!(w.get_ambient(), WHITE);
+        // the hit is on the far side of the outer sphere, only ambient light reaches it
+        let r = Ray::new(Point::new(0.0, 0.0, 5.0), Vector::new(0.0, 0.0, -1.0));
+        let lit = w.color_at(&r, Budget::uniform(5));
+
+        w.set_ambient(RGB::new(0.5, 0.0, 2.0));
+        let c = w.color_at(&r, Budget::uniform(5));
+        assert_eq!(c, RGB::new(lit.red * 0.5, 0.0, lit.blue * 2.0));
+    }
+
+    #[test]
+    fn color_terms_world() {
+        let mut w = World::default();
+        let mut floor = Plane::new();
+        floor.set_transform(Transformation::new().then_translation(0.0, -1.0, 0.0));
+        floor.get_material_mut().reflective = 0.5;
+        floor.get_material_mut().transparency = 0.5;
+        floor.get_material_mut().refractive_index = 1.5;
+        add_object!(w, floor);
+
+        let rays = [
+            Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0)),
+            Ray::new(
+                Point::new(0.0, 0.0, -3.0),
+                Vector::new(
+                    0.0,
+                    -(2.0 as Float).sqrt() / 2.0,
+                    (2.0 as Float).sqrt() / 2.0,
+                ),
+            ),
+        ];
+        for r in rays {
+            let terms = w.color_terms(&r, Budget::uniform(5));
+            assert_eq!(terms.total(), w.color_at_primary(&r, Budget::uniform(5)));
+        }
+
+        let terms = w.color_terms(&rays[0], Budget::uniform(5));
+        assert_eq!(terms.ambient, RGB::new(0.08, 0.1, 0.06));
+        assert_eq!(terms.reflection, BLACK);
+        let terms = w.color_terms(&rays[1], Budget::uniform(5));
+        assert_ne!(terms.reflection, BLACK);
+        // the refracted ray leaves the scene below the floor
+        assert_eq!(terms.refraction, BLACK);
+    }
+
+    #[test]
+    fn world_builder() {
+        let hidden =
+            Sphere::new().with_transform(Transformation::new().then_translation(0.0, 0.0, 3.0));
+        let hidden_id = hidden.id();
+        let (w, c) = WorldBuilder::new()
+            .light(PointLight::new(Point::new(-10.0, 10.0, -10.0), WHITE))
+            .object(Sphere::new().with_material(Material::mirror()))
+            .object_with_flags(hidden, ObjectFlags::reflections_only())
+            .ambient(RGB::new(0.5, 0.5, 0.5))
+            .camera(CameraBuilder::new(20, 10).fov(PI / 2.0))
+            .build()
+            .unwrap();
+
+        assert!(w.get_light().is_some());
+        assert_eq!(w.get_object(0).unwrap().get_material().reflective, 1.0);
+        assert_eq!(
+            w.get_object(1).unwrap().get_transform(),
+            Transformation::new().then_translation(0.0, 0.0, 3.0)
+        );
+        assert_eq!(
+            w.object_flags(w.get_object_by_id(hidden_id).unwrap()),
+            ObjectFlags::reflections_only()
+        );
+        assert_eq!(w.get_ambient(), RGB::new(0.5, 0.5, 0.5));
+        assert_eq!((c.hsize, c.vsize), (20, 10));
+
+        assert!(WorldBuilder::new()
+            .camera(CameraBuilder::new(0, 10))
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn scene_hash_world() {
+        let a = World::default();
+        let mut b = World::default();
+        // same scene, different ids
+        assert_eq!(a.scene_hash(), b.scene_hash());
+
+        b.get_object_mut(1)
+            .unwrap()
+            .set_transform(Transformation::new().then_scaling(0.4, 0.5, 0.5));
+        assert_ne!(a.scene_hash(), b.scene_hash());
+        assert_ne!(World::new().scene_hash(), a.scene_hash());
+    }
+
+    #[test]
+    fn subsurface_shadow_world() {
+        // lit from behind, the wax glows past the terminator
+        let mut w = World::new();
+        w.set_light(PointLight::new(Point::new(0.0, 0.0, 10.0), WHITE));
+        let mut wax = Sphere::new();
+        let m = wax.get_material_mut();
+        m.ambient = 0.0;
+        m.subsurface = 1.0;
+        m.scatter_distance = 1.0;
+        add_object!(w, wax);
+        let r = Ray::new(Point::new(0.9, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
+        assert!(w.color_at(&r, Budget::uniform(0)).red > 0.1);
+
+        // unless something else blocks the light
+        let mut blocker = Sphere::new();
+        blocker.set_transform(
+            Transformation::new()
+                .then_scaling(3.0, 3.0, 3.0)
+                .then_translation(0.0, 0.0, 6.0),
+        );
+        add_object!(w, blocker);
+        assert_eq!(w.color_at(&r, Budget::uniform(0)), BLACK);
+    }
+
+    #[test]
+    fn scene_hash_shapes_world() {
+        let mut sphere = World::new();
+        add_object!(sphere, Sphere::new());
+        let mut cube = World::new();
+        add_object!(cube, Cube::new());
+        assert_ne!(sphere.scene_hash(), cube.scene_hash());
+
+        let cylinders = |maximum: Float| {
+            let mut w = World::new();
+            let mut c = Cylinder::new();
+            c.set_cuts(0.0, maximum);
+            add_object!(w, c);
+            w.scene_hash()
+        };
+        assert_eq!(cylinders(1.0), cylinders(1.0));
+        assert_ne!(cylinders(1.0), cylinders(2.0));
+
+        // patterns and maps of the material count too
+        let striped = |color: RGB| {
+            let mut w = World::new();
+            let mut s = Sphere::new();
+            s.get_material_mut().specular_map =
+                Some(Box::new(Stripes::stripe_pattern(color, BLACK)));
+            add_object!(w, s);
+            w.scene_hash()
+        };
+        assert_eq!(striped(WHITE), striped(WHITE));
+        assert_ne!(striped(WHITE), striped(RED));
+
+        // instances of groups list their parts in no particular order
+        let instanced = || {
+            let mut parts = Group::new();
+            for x in 0..8 {
+                let mut s = Sphere::new();
+                s.set_transform(Transformation::new().then_translation(x as Float, 0.0, 0.0));
+                parts.add_object(Box::new(s));
+            }
+            let mut group = Group::new();
+            group.add_object(Box::new(Instance::new(std::sync::Arc::new(parts))));
+            let mut w = World::new();
+            add_object!(w, group);
+            w.scene_hash()
+        };
+        assert_eq!(instanced(), instanced());
     }
 }
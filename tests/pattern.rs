@@ -46,18 +46,18 @@ fn default_transformation_pattern() {
 #[test]
 fn set_transform_pattern() {
     let mut pattern = TestPattern::new();
-    pattern.set_transform(Transformation::new().translation(1.0, 2.0, 3.0));
+    pattern.set_transform(Transformation::new().then_translation(1.0, 2.0, 3.0));
 
     assert_eq!(
         pattern.get_transform(),
-        Transformation::new().translation(1.0, 2.0, 3.0)
+        Transformation::new().then_translation(1.0, 2.0, 3.0)
     );
 }
 
 #[test]
 fn object_transform_pattern() {
     let mut shape = Sphere::new();
-    shape.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+    shape.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
     let pattern = TestPattern::new();
     let c = pattern.pattern_at_shape(&shape, Point::new(2.0, 3.0, 4.0));
 
@@ -68,7 +68,7 @@ fn object_transform_pattern() {
 fn pattern_transform_pattern() {
     let shape = Sphere::new();
     let mut pattern = TestPattern::new();
-    pattern.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+    pattern.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
     let c = pattern.pattern_at_shape(&shape, Point::new(2.0, 3.0, 4.0));
 
     assert_eq!(c, RGB::new(1.0, 1.5, 2.0));
@@ -77,9 +77,9 @@ fn pattern_transform_pattern() {
 #[test]
 fn pattern_object_transform_pattern() {
     let mut shape = Sphere::new();
-    shape.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+    shape.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
     let mut pattern = TestPattern::new();
-    pattern.set_transform(Transformation::new().translation(0.5, 1.0, 1.5));
+    pattern.set_transform(Transformation::new().then_translation(0.5, 1.0, 1.5));
     let c = pattern.pattern_at_shape(&shape, Point::new(2.5, 3.0, 3.5));
 
     assert_eq!(c, RGB::new(0.75, 0.5, 0.25));
@@ -1,4 +1,4 @@
-use std::f64::consts::PI;
+use rtracer::consts::{FRAC_1_SQRT_2, PI};
 
 use rtracer::*;
 use uuid::Uuid;
@@ -11,18 +11,18 @@ struct TestShape {
     parent: Option<Uuid>,
 }
 
-static mut SAVE_RAY: Ray = Ray {
-    origin: Point {
+static mut SAVE_RAY: Ray = Ray::new(
+    Point {
         x: 0.0,
         y: 0.0,
         z: 0.0,
     },
-    direction: Vector {
+    Vector {
         x: 0.0,
         y: 0.0,
         z: 0.0,
     },
-};
+);
 
 impl Shape for TestShape {
     fn id(&self) -> Uuid {
@@ -88,11 +88,11 @@ fn default_transformation() {
 #[test]
 fn set_transform() {
     let mut s = TestShape::default();
-    s.set_transform(Transformation::new().translation(2.0, 3.0, 4.0));
+    s.set_transform(Transformation::new().then_translation(2.0, 3.0, 4.0));
 
     assert_eq!(
         s.transform,
-        Transformation::new().translation(2.0, 3.0, 4.0)
+        Transformation::new().then_translation(2.0, 3.0, 4.0)
     );
 }
 
@@ -100,12 +100,12 @@ fn set_transform() {
 fn intersect_scaled_shape_ray() {
     let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
     let mut s = TestShape::default();
-    s.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+    s.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
     let _xs = s.intersect(&r);
 
     unsafe {
-        assert_eq!(SAVE_RAY.origin, Point::new(0.0, 0.0, -2.5));
-        assert_eq!(SAVE_RAY.direction, Vector::new(0.0, 0.0, 0.5));
+        assert_eq!(SAVE_RAY.origin(), Point::new(0.0, 0.0, -2.5));
+        assert_eq!(SAVE_RAY.direction(), Vector::new(0.0, 0.0, 0.5));
     }
 }
 
@@ -113,33 +113,37 @@ fn intersect_scaled_shape_ray() {
 fn intersect_translated_shape_ray() {
     let r = Ray::new(Point::new(0.0, 0.0, -5.0), Vector::new(0.0, 0.0, 1.0));
     let mut s = TestShape::default();
-    s.set_transform(Transformation::new().translation(5.0, 0.0, 0.0));
+    s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
     let _xs = s.intersect(&r);
 
     unsafe {
-        assert_eq!(SAVE_RAY.origin, Point::new(-5.0, 0.0, -5.0));
-        assert_eq!(SAVE_RAY.direction, Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(SAVE_RAY.origin(), Point::new(-5.0, 0.0, -5.0));
+        assert_eq!(SAVE_RAY.direction(), Vector::new(0.0, 0.0, 1.0));
     }
 }
 
 #[test]
 fn normal_translated_shape() {
     let mut s = TestShape::default();
-    s.set_transform(Transformation::new().translation(0.0, 1.0, 0.0));
-    let n = s.normal_at(Point::new(0.0, 1.70711, -0.70711), None);
+    s.set_transform(Transformation::new().then_translation(0.0, 1.0, 0.0));
+    let n = s.normal_at(Point::new(0.0, 1.0 + FRAC_1_SQRT_2, -FRAC_1_SQRT_2), None);
 
-    assert_eq!(n, Vector::new(0.0, 0.70711, -0.70711));
+    assert_eq!(n, Vector::new(0.0, FRAC_1_SQRT_2, -FRAC_1_SQRT_2));
 }
 
 #[test]
 fn normal_transformed_shape() {
     let mut s = TestShape::default();
     let m = Transformation::new()
-        .rotate_z(PI / 5.0)
-        .scaling(1.0, 0.5, 1.0);
+        .then_rotate_z(PI / 5.0)
+        .then_scaling(1.0, 0.5, 1.0);
     s.set_transform(m);
     let n = s.normal_at(
-        Point::new(0.0, 2_f64.sqrt() / 2.0, -(2_f64.sqrt() / 2.0)),
+        Point::new(
+            0.0,
+            (2.0 as Float).sqrt() / 2.0,
+            -((2.0 as Float).sqrt() / 2.0),
+        ),
         None,
     );
 
@@ -158,14 +162,14 @@ fn convert_object_space() {
     let mut w = World::new();
 
     let mut g1 = Group::new();
-    g1.set_transform(Transformation::new().rotate_y(PI / 2.0));
+    g1.set_transform(Transformation::new().then_rotate_y(PI / 2.0));
 
     let mut g2 = Group::new();
-    g2.set_transform(Transformation::new().scaling(2.0, 2.0, 2.0));
+    g2.set_transform(Transformation::new().then_scaling(2.0, 2.0, 2.0));
 
     let mut s = Sphere::new();
     let s_id = s.id();
-    s.set_transform(Transformation::new().translation(5.0, 0.0, 0.0));
+    s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
 
     g2.add_object(Box::new(s));
     g1.add_object(Box::new(g2));
@@ -182,14 +186,14 @@ fn convert_normal_object_world_space() {
     let mut w = World::new();
 
     let mut g1 = Group::new();
-    g1.set_transform(Transformation::new().rotate_y(PI / 2.0));
+    g1.set_transform(Transformation::new().then_rotate_y(PI / 2.0));
 
     let mut g2 = Group::new();
-    g2.set_transform(Transformation::new().scaling(1.0, 2.0, 3.0));
+    g2.set_transform(Transformation::new().then_scaling(1.0, 2.0, 3.0));
 
     let mut s = Sphere::new();
     let s_id = s.id();
-    s.set_transform(Transformation::new().translation(5.0, 0.0, 0.0));
+    s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
 
     g2.add_object(Box::new(s));
     g1.add_object(Box::new(g2));
@@ -198,7 +202,11 @@ fn convert_normal_object_world_space() {
     let s = w.get_object_by_id(s_id).unwrap();
 
     let p = s.normal_to_world(
-        Vector::new(3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0, 3_f64.sqrt() / 3.0),
+        Vector::new(
+            (3.0 as Float).sqrt() / 3.0,
+            (3.0 as Float).sqrt() / 3.0,
+            (3.0 as Float).sqrt() / 3.0,
+        ),
         &w,
     );
 
@@ -210,14 +218,14 @@ fn find_normal_child_object() {
     let mut w = World::new();
 
     let mut g1 = Group::new();
-    g1.set_transform(Transformation::new().rotate_y(PI / 2.0));
+    g1.set_transform(Transformation::new().then_rotate_y(PI / 2.0));
 
     let mut g2 = Group::new();
-    g2.set_transform(Transformation::new().scaling(1.0, 2.0, 3.0));
+    g2.set_transform(Transformation::new().then_scaling(1.0, 2.0, 3.0));
 
     let mut s = Sphere::new();
     let s_id = s.id();
-    s.set_transform(Transformation::new().translation(5.0, 0.0, 0.0));
+    s.set_transform(Transformation::new().then_translation(5.0, 0.0, 0.0));
 
     g2.add_object(Box::new(s));
     g1.add_object(Box::new(g2));
@@ -229,3 +237,85 @@ fn find_normal_child_object() {
 
     assert_eq!(p, Vector::new(0.2857, 0.4286, -0.8571));
 }
+
+/// A world with `shape` inside of a red shell, lit only by its ambient color.
+fn red_shell_world(shape: Box<dyn Shape>) -> World {
+    let mut w = World::new();
+    w.set_light(PointLight::new(Point::new(-5.0, 5.0, -5.0), WHITE));
+    let mut shell = Sphere::new();
+    shell.set_transform(Transformation::new().then_scaling(20.0, 20.0, 20.0));
+    shell.set_material(Material {
+        color: RED,
+        ambient: 1.0,
+        diffuse: 0.0,
+        specular: 0.0,
+        ..Material::default()
+    });
+    w.add_object(Box::new(shell));
+    w.add_object(shape);
+    w
+}
+
+#[test]
+fn glass_mirror_shapes() {
+    let cut = |mut cone: Cone| {
+        cone.set_cuts(0.0, 1.0);
+        Box::new(cone)
+    };
+    let shapes: [(&str, Box<dyn Shape>, Box<dyn Shape>); 5] = [
+        (
+            "sphere",
+            Box::new(Sphere::glass()),
+            Box::new(Sphere::mirror()),
+        ),
+        ("cube", Box::new(Cube::glass()), Box::new(Cube::mirror())),
+        (
+            "cylinder",
+            Box::new(Cylinder::glass()),
+            Box::new(Cylinder::mirror()),
+        ),
+        ("cone", cut(Cone::glass()), cut(Cone::mirror())),
+        ("plane", Box::new(Plane::glass()), Box::new(Plane::mirror())),
+    ];
+    // hits every shape at a slant from the outside
+    let r = Ray::new(
+        Point::new(0.1, 2.5, -5.0),
+        Vector::new(0.0, -2.0, 4.8).normalize(),
+    );
+
+    for (name, glass, mirror) in shapes {
+        let w = red_shell_world(glass);
+        let xs = w.intersect_world(&r).unwrap();
+        let comps = Intersection::hit(&xs)
+            .unwrap()
+            .prepare_computations(&r, &xs, Some(&w));
+        assert!(!comps.object.is_opaque(), "{}", name);
+        assert_eq!((comps.n1, comps.n2), (1.0, 1.5), "{}", name);
+        // bent towards the normal as Snell's law says
+        let refracted = World::refract_ray(&comps, comps.n1, comps.n2).unwrap();
+        let sin_i = (1.0 - comps.eyev.dot(comps.normalv).powi(2)).sqrt();
+        let sin_t = (1.0 - refracted.direction().dot(comps.normalv).powi(2)).sqrt();
+        assert!(sin_i > 0.1, "{}", name);
+        assert!(float_eq(sin_i, 1.5 * sin_t), "{}", name);
+
+        let w = red_shell_world(mirror);
+        let xs = w.intersect_world(&r).unwrap();
+        let comps = Intersection::hit(&xs)
+            .unwrap()
+            .prepare_computations(&r, &xs, Some(&w));
+        assert!(comps.object.is_opaque(), "{}", name);
+        // all the color is the reflected shell
+        assert_eq!(
+            w.reflected_color(&comps, Budget::uniform(1)),
+            RED,
+            "{}",
+            name
+        );
+        assert_eq!(
+            w.refracted_color(&comps, Budget::uniform(1)),
+            BLACK,
+            "{}",
+            name
+        );
+    }
+}